@@ -0,0 +1,36 @@
+//! Cool down a simulated Sunpower CryoTel GT cryocooler, fully offline.
+//!
+//! Run with `cargo run --example simulator-cryotel --features simulator`.
+
+use instrumentrs::{CryoTelGtSimulator, InstrumentInterface, SimulatorInterface};
+
+/// Send a command to the cooler, check the echo, and return the value.
+fn query(intf: &mut SimulatorInterface<CryoTelGtSimulator>, cmd: &str) -> String {
+    intf.write(&format!("{cmd}\r")).unwrap();
+    assert_eq!(cmd, intf.read_until_terminator().unwrap());
+    intf.read_until_terminator().unwrap()
+}
+
+fn main() {
+    // The cooler relaxes by 20% toward the target per temperature query.
+    let sim = CryoTelGtSimulator::new().with_relaxation(0.2);
+    let mut intf = SimulatorInterface::new(sim);
+    intf.set_terminator("\r\n");
+
+    // Set the target temperature and start the cooler.
+    println!("Target: {} K", query(&mut intf, "SET TTARGET=80"));
+    query(&mut intf, "SET SSTOP=0");
+
+    // Watch it cool down.
+    for _ in 0..10 {
+        let temperature = query(&mut intf, "TC");
+        let power = query(&mut intf, "P");
+        println!("Temperature: {temperature} K, power: {power} W");
+    }
+
+    // Stop the cooler and let it warm up again.
+    query(&mut intf, "SET SSTOP=1");
+    for _ in 0..5 {
+        println!("Temperature: {} K", query(&mut intf, "TC"));
+    }
+}
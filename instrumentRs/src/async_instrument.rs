@@ -0,0 +1,78 @@
+//! This module provides the main implementation for the Async Instrument Interface trait.
+//!
+//! This module is only available when the `async` feature is enabled. It can be called with any
+//! type that implements [`tokio::io::AsyncRead`] and [`tokio::io::AsyncWrite`], such as
+//! [`tokio::net::TcpStream`].
+
+#![cfg(feature = "async")]
+
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{AsyncInstrumentInterface, InstrumentError};
+
+/// A general asynchronous instrument interface that can be built with any interface that
+/// implements [`tokio::io::AsyncRead`] and [`tokio::io::AsyncWrite`].
+///
+/// This is the asynchronous counterpart of [`crate::Instrument`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use instrumentrs::AsyncInstrument;
+/// use tokio::net::TcpStream;
+///
+/// # async fn example() {
+/// let my_interface = TcpStream::connect("192.168.10.1:8000").await.unwrap();
+/// let inst_interface = AsyncInstrument::new(my_interface, Duration::from_secs(3));
+/// # }
+/// ```
+pub struct AsyncInstrument<P: AsyncRead + AsyncWrite + Unpin + Send> {
+    port: P,
+    terminator: String,
+    timeout: Duration,
+}
+
+impl<P: AsyncRead + AsyncWrite + Unpin + Send> AsyncInstrument<P> {
+    /// Create a new instance of [`AsyncInstrument`] with a given interface.
+    pub fn new(port: P, timeout: Duration) -> Self {
+        Self {
+            port,
+            terminator: "\n".to_string(),
+            timeout,
+        }
+    }
+}
+
+impl<P: AsyncRead + AsyncWrite + Unpin + Send> AsyncInstrumentInterface for AsyncInstrument<P> {
+    async fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        match tokio::time::timeout(self.timeout, self.port.read_exact(buf)).await {
+            Ok(res) => {
+                res?;
+                Ok(())
+            }
+            Err(_) => Err(InstrumentError::Timeout(self.timeout)),
+        }
+    }
+
+    fn get_terminator(&self) -> &str {
+        self.terminator.as_str()
+    }
+
+    fn set_terminator(&mut self, terminator: &str) {
+        self.terminator = terminator.to_string();
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    async fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        self.port.write_all(data).await?;
+        self.port.flush().await?;
+        Ok(())
+    }
+}
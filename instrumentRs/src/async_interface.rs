@@ -0,0 +1,177 @@
+//! This module provides the asynchronous counterpart of the [`crate::InstrumentInterface`] trait.
+//!
+//! This module is only available when the `async` feature is enabled. It uses the [`tokio`]
+//! runtime, i.e., all timeouts are enforced using [`tokio::time::timeout`]. The methods mirror the
+//! ones of the blocking trait, such that drivers can be written in the same way.
+
+#![cfg(feature = "async")]
+
+use std::{future::Future, time::Duration};
+
+use crate::InstrumentError;
+
+/// The [`AsyncInstrumentInterface`] trait defines the asynchronous interface for controlling
+/// instruments.
+///
+/// It mirrors the blocking [`crate::InstrumentInterface`] trait. Only `read_exact` and `write_raw`
+/// must be implemented, all other methods have default implementations that build on top of these
+/// two methods. All futures returned by this trait are [`Send`], such that they can be spawned on
+/// a multi-threaded [`tokio`] runtime.
+pub trait AsyncInstrumentInterface: Send {
+    /// Check if an acknowledgment is received from the instrument.
+    ///
+    /// If no acknowledgment is received, it returns an [`InstrumentError::NotAcknowledged`] error
+    /// with the incorrect response received in the error message.
+    ///
+    /// # Arguments:
+    /// - `ack` - A string slice that contains the expected acknowledgment response.
+    fn check_acknowledgment(
+        &mut self,
+        ack: &str,
+    ) -> impl Future<Output = Result<(), InstrumentError>> + Send {
+        async move {
+            let response = self.read_until_terminator().await?;
+            if response == ack {
+                Ok(())
+            } else {
+                Err(InstrumentError::NotAcknowledged(response))
+            }
+        }
+    }
+
+    /// Query the instrument with a command and return the response as a String.
+    ///
+    /// If no terminator is received within the timeout of the interface, an
+    /// [`InstrumentError::TimeoutQuery`] error is returned.
+    ///
+    /// # Arguments
+    /// * `cmd` - The command to send to the instrument for which we expect a response.
+    fn query(&mut self, cmd: &str) -> impl Future<Output = Result<String, InstrumentError>> + Send {
+        async move {
+            self.sendcmd(cmd).await?;
+            self.read_until_terminator()
+                .await
+                .map_err(|e| crate::timeout_to_query_error(e, cmd))
+        }
+    }
+
+    /// Read an exact number of bytes from the instrument.
+    ///
+    /// You must provide a mutable buffer that this function will read into. The function will
+    /// read as many bytes as the buffer can hold.
+    fn read_exact(
+        &mut self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = Result<(), InstrumentError>> + Send;
+
+    /// Read until the terminator is found or the timeout is reached.
+    ///
+    /// The whole read is wrapped in a [`tokio::time::timeout`], such that an
+    /// [`InstrumentError::Timeout`] error is returned even if the interface does not send a single
+    /// byte. If some bytes were received before the timeout, they are returned in an
+    /// [`InstrumentError::TimeoutPartial`] error. Only the terminator is removed from the response.
+    /// Invalid UTF-8 data is replaced with the replacement character.
+    fn read_until_terminator(
+        &mut self,
+    ) -> impl Future<Output = Result<String, InstrumentError>> + Send {
+        async move {
+            let timeout = self.get_timeout();
+            let terminator = self.get_terminator_bytes().to_vec();
+
+            // the response lives outside of the reader, such that it is kept on a timeout
+            let mut response = Vec::new();
+            let reader = async {
+                let mut single_buf = [0u8];
+                while !response.ends_with(&terminator) {
+                    self.read_exact(&mut single_buf).await?;
+                    response.push(single_buf[0]);
+                }
+                Ok::<(), InstrumentError>(())
+            };
+
+            match tokio::time::timeout(timeout, reader).await {
+                Ok(Ok(())) => {
+                    response.truncate(response.len() - terminator.len());
+                    Ok(String::from_utf8_lossy(&response).into_owned())
+                }
+                Ok(Err(e)) => Err(e),
+                Err(_) if response.is_empty() => Err(InstrumentError::Timeout(timeout)),
+                Err(_) => Err(InstrumentError::TimeoutPartial {
+                    timeout,
+                    partial: response,
+                }),
+            }
+        }
+    }
+
+    /// Read until the terminator is found and remove leading and trailing whitespace.
+    ///
+    /// This function behaves like `read_until_terminator`, however, the response is trimmed.
+    fn read_line_trimmed(
+        &mut self,
+    ) -> impl Future<Output = Result<String, InstrumentError>> + Send {
+        async move { Ok(self.read_until_terminator().await?.trim().to_string()) }
+    }
+
+    /// Send a command to the instrument.
+    ///
+    /// This function takes the command, appends the terminator, and writes it to the instrument.
+    ///
+    /// # Arguments:
+    /// - `cmd` - A string slice that will be sent to the instrument.
+    fn sendcmd(&mut self, cmd: &str) -> impl Future<Output = Result<(), InstrumentError>> + Send {
+        async move {
+            let mut data = cmd.as_bytes().to_vec();
+            data.extend_from_slice(self.get_terminator_bytes());
+            self.write_raw(&data).await
+        }
+    }
+
+    /// Get the current terminator of the interface.
+    ///
+    /// If not implemented, this function will return a default value of `"\n"`.
+    fn get_terminator(&self) -> &str {
+        "\n"
+    }
+
+    /// Set the terminator of an interface from a `&str`.
+    ///
+    /// # Arguments:
+    /// - `_terminator` - A string slice that will be used as the terminator for commands
+    fn set_terminator(&mut self, _terminator: &str) {}
+
+    /// Get the current terminator of the interface as a byte slice.
+    ///
+    /// If not implemented, this function returns the bytes of `get_terminator`. The default
+    /// implementations of reading and sending commands use this terminator.
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.get_terminator().as_bytes()
+    }
+
+    /// Get the current timeout of the interface.
+    ///
+    /// The default timeout, if not implemented, is set to three seconds.
+    fn get_timeout(&self) -> Duration {
+        Duration::from_secs(3)
+    }
+
+    /// Write a string to the instrument.
+    ///
+    /// This function does NOT append the terminator. If you prefer a command that appends the
+    /// terminator, use `sendcmd`.
+    ///
+    /// # Arguments:
+    /// - `data` - A string slice that will be written to the instrument.
+    fn write(&mut self, data: &str) -> impl Future<Output = Result<(), InstrumentError>> + Send {
+        async move { self.write_raw(data.as_bytes()).await }
+    }
+
+    /// Write a byte slice to the instrument and flush it after.
+    ///
+    /// This function does NOT append the terminator. After writing, the interface should be
+    /// flushed.
+    fn write_raw(
+        &mut self,
+        data: &[u8],
+    ) -> impl Future<Output = Result<(), InstrumentError>> + Send;
+}
@@ -0,0 +1,65 @@
+//! This module provides implementations generating an asynchronous serial port interface.
+//!
+//! This module is only available when the `serial-async` feature is enabled. It uses the
+//! [`tokio_serial`] crate in order to create an asynchronous connection to the specified port.
+
+#![cfg(feature = "serial-async")]
+
+use std::time::Duration;
+
+use tokio_serial::{SerialPort, SerialPortBuilder, SerialStream};
+
+use crate::{AsyncInstrument, InstrumentError};
+
+/// An asynchronous serial port implementation using the [`tokio_serial`] crate.
+///
+/// This is the asynchronous counterpart of [`crate::SerialInterface`]. You have the possibility
+/// to create an instrument interface from a simple serial port configuration (port and baud rate)
+/// or a full featured serial port configuration using a [`serialport::SerialPortBuilder`]
+/// structure.
+///
+/// # Returns
+/// Returns a [`Result`] containing an [`AsyncInstrument`] with the serial interface if
+/// successful, or an [`InstrumentError`] if there was an error opening the port.
+#[derive(Debug)]
+pub struct AsyncSerialInterface {}
+
+impl AsyncSerialInterface {
+    /// Try to create an asynchronous Instrument interface with a simple serial port configuration.
+    ///
+    /// The timeout is by default set to 3 seconds.
+    ///
+    /// # Arguments
+    /// * `port` - The name of the serial port, e.g., `"/dev/ttyUSB0"` or `"COM3"`.
+    /// * `baud` - The baud rate for the serial communication, e.g., `9600`.
+    pub fn simple(port: &str, baud: u32) -> Result<AsyncInstrument<SerialStream>, InstrumentError> {
+        let timeout = Duration::from_secs(3);
+        Self::full(tokio_serial::new(port, baud).timeout(timeout))
+    }
+
+    /// Try to create a new asynchronous Instrument interface with a full featured serial port
+    /// interface.
+    ///
+    /// You have to specify the timeout inside your [`serialport::SerialPortBuilder`] structure.
+    /// This timeout is then passed on to the [`AsyncInstrument`] interface, just as for
+    /// [`crate::SerialInterface::full`].
+    ///
+    /// # Arguments
+    /// * `builder` - A [`serialport::SerialPortBuilder`] that contains all the parameters for the
+    ///   connection to the serial port.
+    pub fn full(
+        builder: SerialPortBuilder,
+    ) -> Result<AsyncInstrument<SerialStream>, InstrumentError> {
+        // Asynchronous ports do not report the timeout of the builder, the native port does.
+        let native = builder.open_native()?;
+        let timeout = native.timeout();
+        #[cfg(unix)]
+        let port = SerialStream::try_from(native)?;
+        #[cfg(not(unix))]
+        let port = {
+            drop(native);
+            SerialStream::open(&builder)?
+        };
+        Ok(AsyncInstrument::new(port, timeout))
+    }
+}
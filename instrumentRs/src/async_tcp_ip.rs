@@ -0,0 +1,58 @@
+//! This module provides the implementation for an instrument controlled asynchronously via TCP/IP.
+//!
+//! This module is only available when the `async` feature is enabled. It uses the
+//! [`tokio::net::TcpStream`] struct.
+
+#![cfg(feature = "async")]
+
+use std::time::Duration;
+
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use crate::{AsyncInstrument, InstrumentError};
+
+/// An asynchronous TCP/IP implementation using [`tokio::net::TcpStream`].
+///
+/// This is the asynchronous counterpart of [`crate::TcpIpInterface`]. You have the possibility to
+/// create an instrument interface from a simple socket address, or to pass the `full` method an
+/// open [`TcpStream`].
+///
+/// # Returns
+/// Returns a [`Result`] containing an [`AsyncInstrument`] with the TCP/IP interface if
+/// successful, or an [`InstrumentError`] if there was an error opening the port.
+#[derive(Debug)]
+pub struct AsyncTcpIpInterface {}
+
+impl AsyncTcpIpInterface {
+    /// Try to create a new asynchronous Instrument interface with a TCP/IP interface.
+    ///
+    /// The timeout in the simple implementation is set to 3 seconds. It is used for establishing
+    /// the connection as well as for reading.
+    ///
+    /// # Arguments
+    /// * `sock_addr` - Socket address.
+    pub async fn simple<A: ToSocketAddrs>(
+        sock_addr: A,
+    ) -> Result<AsyncInstrument<TcpStream>, InstrumentError> {
+        let timeout = Duration::from_secs(3);
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(sock_addr))
+            .await
+            .map_err(|_| InstrumentError::Timeout(timeout))??;
+        Ok(AsyncInstrument::new(stream, timeout))
+    }
+
+    /// Try to create a new asynchronous Instrument interface from an open TCP/IP stream.
+    ///
+    /// As a [`tokio::net::TcpStream`] does not carry any timeout itself, the timeout for the
+    /// [`AsyncInstrument`] must be given explicitly.
+    ///
+    /// # Arguments
+    /// * `stream` - An already open [`TcpStream`].
+    /// * `timeout` - The timeout that is used for reading from the instrument.
+    pub fn full(
+        stream: TcpStream,
+        timeout: Duration,
+    ) -> Result<AsyncInstrument<TcpStream>, InstrumentError> {
+        Ok(AsyncInstrument::new(stream, timeout))
+    }
+}
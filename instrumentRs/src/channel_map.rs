@@ -0,0 +1,130 @@
+//! This module provides a helper for instrument drivers that have multiple channels.
+//!
+//! Drivers of instruments with channels usually hand out channel structs for a given index. The
+//! [`ChannelMap`] keeps track of the number of channels, validates indices, and checks that the
+//! number of channels can only be set within the range that the instrument family supports.
+
+use alloc::format;
+use core::ops::{Range, RangeInclusive};
+
+use crate::InstrumentError;
+
+/// Number of channels and index validation for instruments with multiple channels.
+///
+/// Channels are zero-indexed, i.e., valid indices are `0..num_channels`.
+///
+/// # Example
+///
+/// ```
+/// use instrumentrs::ChannelMap;
+///
+/// // A controller that comes in a version with one and a version with two channels.
+/// let mut channels = ChannelMap::new(2).with_allowed_range(1..=2).unwrap();
+/// assert_eq!(1, channels.check_index(1).unwrap());
+/// assert!(channels.check_index(2).is_err());
+///
+/// channels.set_num_channels(1).unwrap();
+/// assert!(channels.set_num_channels(3).is_err());
+/// assert_eq!(vec![0], channels.indices().collect::<Vec<_>>());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelMap {
+    num_channels: usize,
+    allowed: RangeInclusive<usize>,
+}
+
+impl ChannelMap {
+    /// Create a new channel map with the given number of channels.
+    ///
+    /// By default, any number of channels larger than zero can be set later on.
+    ///
+    /// # Arguments
+    /// * `num_channels` - The number of channels.
+    pub fn new(num_channels: usize) -> Self {
+        ChannelMap {
+            num_channels,
+            allowed: 1..=usize::MAX,
+        }
+    }
+
+    /// Set the range of the allowed number of channels and return the channel map.
+    ///
+    /// The number of channels that the map was created with must be within this range, otherwise
+    /// an [`InstrumentError::IntValueOutOfRange`] error is returned.
+    ///
+    /// # Arguments
+    /// * `allowed` - The range of the allowed number of channels.
+    pub fn with_allowed_range(
+        mut self,
+        allowed: RangeInclusive<usize>,
+    ) -> Result<Self, InstrumentError> {
+        self.allowed = allowed;
+        self.check_num_channels(self.num_channels)?;
+        Ok(self)
+    }
+
+    /// Get the number of channels.
+    pub fn get_num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// Set the number of channels.
+    ///
+    /// If the number is not within the allowed range, an [`InstrumentError::IntValueOutOfRange`]
+    /// error is returned and the number of channels is not changed. Without an allowed range,
+    /// setting zero channels returns an [`InstrumentError::InvalidArgument`] error instead.
+    ///
+    /// # Arguments
+    /// * `num` - The new number of channels.
+    pub fn set_num_channels(&mut self, num: usize) -> Result<(), InstrumentError> {
+        self.check_num_channels(num)?;
+        self.num_channels = num;
+        Ok(())
+    }
+
+    /// Check that a channel index is valid and return it.
+    ///
+    /// If the index is out of range, an [`InstrumentError::ChannelIndexOutOfRange`] error is
+    /// returned.
+    ///
+    /// # Arguments
+    /// * `idx` - The zero-based index of the channel.
+    pub fn check_index(&self, idx: usize) -> Result<usize, InstrumentError> {
+        if idx >= self.num_channels {
+            return Err(InstrumentError::ChannelIndexOutOfRange {
+                idx,
+                nof_channels: self.num_channels,
+            });
+        }
+        Ok(idx)
+    }
+
+    /// Get an iterator over all valid channel indices.
+    pub fn indices(&self) -> Range<usize> {
+        0..self.num_channels
+    }
+
+    /// Check that a number of channels is within the allowed range.
+    fn check_num_channels(&self, num: usize) -> Result<(), InstrumentError> {
+        if self.allowed.contains(&num) {
+            return Ok(());
+        }
+        let (min, max) = (*self.allowed.start(), *self.allowed.end());
+        if max == usize::MAX {
+            return Err(InstrumentError::InvalidArgument(format!(
+                "Number of channels must be greater than {}",
+                min - 1
+            )));
+        }
+        Err(InstrumentError::IntValueOutOfRange {
+            value: to_i64(num),
+            min: to_i64(min),
+            max: to_i64(max),
+        })
+    }
+}
+
+/// Convert a number of channels into an `i64` for error messages, saturating at `i64::MAX`.
+fn to_i64(num: usize) -> i64 {
+    num.try_into().unwrap_or(i64::MAX)
+}
@@ -0,0 +1,69 @@
+//! This module provides the time source that the default methods of the interface trait use.
+//!
+//! The default implementations of [`crate::InstrumentInterface`] measure timeouts and wait between
+//! chunks of a write. With the standard library, the [`StdClock`] is used for this. On embedded
+//! hosts without the standard library, interfaces provide their own [`Clock`], e.g., based on the
+//! timer of the microcontroller.
+
+use core::time::Duration;
+
+/// A monotonic time source.
+///
+/// # Example
+///
+/// A clock for an embedded host that counts microseconds in a hardware timer could look as
+/// follows:
+///
+/// ```
+/// use core::time::Duration;
+///
+/// use instrumentrs::Clock;
+///
+/// struct TimerClock;
+///
+/// impl Clock for TimerClock {
+///     fn now(&self) -> Duration {
+///         let ticks_us: u64 = 0; // read the hardware timer here
+///         Duration::from_micros(ticks_us)
+///     }
+/// }
+/// ```
+pub trait Clock {
+    /// Get the time that passed since an arbitrary, but fixed, point in time.
+    ///
+    /// The returned time must never decrease.
+    fn now(&self) -> Duration;
+
+    /// Get the time that passed since the given time, which was returned by `now`.
+    fn elapsed(&self, since: Duration) -> Duration {
+        self.now().saturating_sub(since)
+    }
+
+    /// Wait for the given duration.
+    ///
+    /// The default implementation spins until the duration has passed. Clocks that can put the
+    /// host to sleep should override it.
+    fn sleep(&self, duration: Duration) {
+        let start = self.now();
+        while self.elapsed(start) < duration {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// A [`Clock`] that uses [`std::time::Instant`] and sleeps with [`std::thread::sleep`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdClock;
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        EPOCH.get_or_init(std::time::Instant::now).elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
@@ -0,0 +1,259 @@
+//! This module provides the error type that all interfaces and instrument drivers return.
+//!
+//! The error type is part of the `no_std` compatible core of this crate. Variants that wrap errors
+//! of the standard library or of optional dependencies are only available with the respective
+//! features.
+
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::time::Duration;
+
+use thiserror::Error;
+
+use crate::ModbusException;
+
+/// The error enum for all instruments.
+///
+/// For any command sending or querying, your instrument should return either an empty result or a
+/// result with the query where this Error is the alternative. [`InstrumentError`] makes it easy to
+/// propagate all the sending commands, querying errors forward with the `?` operator such that
+/// errors propagate nicely. If this is not possible, it is considered a bug and should be
+/// reported.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum InstrumentError {
+    /// The instrument did not acknowledge the command that was sent. The response received is
+    /// returned in the error as a String.
+    #[error("Instrument did not acknowledge the command sent, but responded with: {0}")]
+    NotAcknowledged(String),
+    /// The channel index requested is out of range. The error contains the index requested and
+    /// the number of channels that are currently configured.
+    #[error(
+        "Channel with index {idx} is out of range. Number of channels available: {nof_channels}"
+    )]
+    ChannelIndexOutOfRange {
+        /// Index of the channel that is out of range.
+        idx: usize,
+        /// Total number of channels.
+        nof_channels: usize,
+    },
+    /// A given float value is out of the specified range. The error contains the value that was
+    /// sent, the minimum value that is allowed, and the maximum value that is allowed.
+    #[error("Float value {value} is out of range. Allowed range is [{min}, {max}]")]
+    FloatValueOutOfRange {
+        /// The value that is out of range.
+        value: f64,
+        /// The minimum value that is allowed.
+        min: f64,
+        /// The maximum value that is allowed.
+        max: f64,
+    },
+    /// A given integer value is out of the specified range. The error contains the value that was
+    /// sent, the minimum value that is allowed, and the maximum value that is allowed.
+    #[error("Integer value {value} is out of range. Allowed range is [{min}, {max}]")]
+    IntValueOutOfRange {
+        /// The value that is out of range.
+        value: i64,
+        /// The minimum value that is allowed.
+        min: i64,
+        /// The maximum value that is allowed.
+        max: i64,
+    },
+    /// Error when an invalid argument is passed to a function. This error contains only an error
+    /// message, but no arguments. It is intended for the user.
+    #[error("{0}")]
+    InvalidArgument(String),
+    /// The shared interface is unusable, as another thread panicked while it was using the
+    /// interface. The state of the interface is unknown, e.g., a response might be half read.
+    #[error("Interface is poisoned, as another thread panicked while using it.")]
+    InterfacePoisoned,
+    /// Data received from the instrument is not valid UTF-8. The error contains the raw data that
+    /// was received.
+    #[error("Received invalid UTF-8 data: {0:?}")]
+    InvalidData(Vec<u8>),
+    /// A response exceeded the maximum length without a terminator. The error contains the
+    /// maximum length and the data that was received up to this length.
+    #[error(
+        "Response exceeded the maximum length of {limit} bytes without a terminator. Start of the response: {:?}",
+        String::from_utf8_lossy(&got[..got.len().min(32)])
+    )]
+    ResponseTooLong {
+        /// The maximum length of a response.
+        limit: usize,
+        /// The data that was received, truncated to the maximum length.
+        got: Vec<u8>,
+    },
+    /// A Modbus server responded with an exception. The error contains the function code of the
+    /// request and the exception that was returned.
+    #[error("Modbus exception for function code {function:#04X}: {exception}")]
+    Modbus {
+        /// Function code of the request.
+        function: u8,
+        /// The exception that the server returned.
+        exception: ModbusException,
+    },
+    #[cfg(feature = "std")]
+    /// Error when reading from/writing to an interface. See [`std::io::Error`] for more details.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Instrument status is not okay, e.g., a response from the instrument did not succeed with a
+    /// given error message. This error contains a string with the error message that is intended
+    /// to be displayed for the user, i.e., "Sensor not calibrated". Note that the string is
+    /// directly displayed without any further formatting, so you need to ensure that it is
+    /// descriptive enough for the user.
+    #[error("{0}")]
+    InstrumentStatus(String),
+    /// Instrument response could not be parsed becuase it was unexpected by the driver. This error
+    /// contains the response that was received from the instrument.
+    #[error("Response from instrument could not be parsed. Response was: {0}")]
+    ResponseParseError(String),
+    /// All attempts of a [`crate::RetryPolicy`] failed. The error contains the number of attempts
+    /// that were made and the error of the last attempt.
+    #[error("Operation failed after {attempts} attempts. Last error: {source}")]
+    RetriesExhausted {
+        /// Number of attempts that were made.
+        attempts: usize,
+        /// The error of the last attempt.
+        source: Box<InstrumentError>,
+    },
+    #[cfg(feature = "serial")]
+    /// Serial port errors can occur when opening a serial interface. See the [`serialport::Error`]
+    /// documentation for more information.
+    #[error(transparent)]
+    Serialport(#[from] serialport::Error),
+    /// A sensor error occurred. This error is returned if the instrument reports a sensor error.
+    /// The error string contains should contain further information about the sensor error.
+    #[error("Sensor error: {0}")]
+    SensorError(String),
+    #[cfg(feature = "recording")]
+    /// A transcript could not be serialized or deserialized. The error contains the message of the
+    /// underlying serialization library.
+    #[error("Transcript error: {0}")]
+    Transcript(String),
+    /// Timeout occurred while waiting for a response from the instrument. The error contains the
+    /// timeout that was exceeded.
+    #[error(
+        "Timeout occured while waiting for a response from the instrument. Timeout was set to {0:?}."
+    )]
+    Timeout(Duration),
+    /// Timeout occurred while writing to the instrument, e.g., because the port did not accept any
+    /// more data. The error contains the timeout that was exceeded.
+    #[error("Timeout occured while writing to the instrument. Timeout was set to {0:?}.")]
+    TimeoutWrite(Duration),
+    /// Timeout occurred while waiting for the terminator, but a partial response was received.
+    /// The error contains the timeout that was exceeded and the data received so far.
+    #[error(
+        "Timeout occured while waiting for the terminator. Timeout was set to {timeout:?}.{}",
+        fmt_partial(partial)
+    )]
+    TimeoutPartial {
+        /// The timeout that was set.
+        timeout: Duration,
+        /// The partial response that was received before the timeout.
+        partial: Vec<u8>,
+    },
+    /// Timeout occurred while waiting for a response to a query. The error contains the query
+    /// that was sent, the timeout that was exceeded, and the partial response received so far,
+    /// which is empty if nothing was received.
+    #[error(
+        "Timeout occured while waiting for a response to query: {query}. Timeout was set to {timeout:?}.{}",
+        fmt_partial(partial)
+    )]
+    TimeoutQuery {
+        /// The query that timed out.
+        query: String,
+        /// The timeout that was set.
+        timeout: Duration,
+        /// The partial response that was received before the timeout.
+        partial: Vec<u8>,
+    },
+    /// Timeout occurred while waiting for one line of the response to a multi-line query. The
+    /// error contains the query that was sent, the index of the line that timed out (starting at
+    /// zero), the timeout that was exceeded, and the partial data of this line.
+    #[error(
+        "Timeout occured while waiting for line {line} of the response to query: {query}. Timeout was set to {timeout:?}.{}",
+        fmt_partial(partial)
+    )]
+    TimeoutQueryLine {
+        /// The query that timed out.
+        query: String,
+        /// The index of the line that timed out, starting at zero.
+        line: usize,
+        /// The timeout that was set.
+        timeout: Duration,
+        /// The partial data of the line that timed out.
+        partial: Vec<u8>,
+    },
+    #[cfg(feature = "visa")]
+    /// The VISA library returned an error status. The error contains the VISA status code and the
+    /// description of the status as reported by the VISA library.
+    #[error("VISA error {status:#010X}: {description}")]
+    Visa {
+        /// The VISA status code.
+        status: i32,
+        /// The description of the status code.
+        description: String,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl InstrumentError {
+    /// Get the name of the error variant, e.g., `"Timeout"`.
+    fn kind(&self) -> &'static str {
+        match self {
+            InstrumentError::NotAcknowledged(_) => "NotAcknowledged",
+            InstrumentError::ChannelIndexOutOfRange { .. } => "ChannelIndexOutOfRange",
+            InstrumentError::FloatValueOutOfRange { .. } => "FloatValueOutOfRange",
+            InstrumentError::IntValueOutOfRange { .. } => "IntValueOutOfRange",
+            InstrumentError::InvalidArgument(_) => "InvalidArgument",
+            InstrumentError::InterfacePoisoned => "InterfacePoisoned",
+            InstrumentError::InvalidData(_) => "InvalidData",
+            InstrumentError::ResponseTooLong { .. } => "ResponseTooLong",
+            InstrumentError::Modbus { .. } => "Modbus",
+            #[cfg(feature = "std")]
+            InstrumentError::Io(_) => "Io",
+            InstrumentError::InstrumentStatus(_) => "InstrumentStatus",
+            InstrumentError::ResponseParseError(_) => "ResponseParseError",
+            InstrumentError::RetriesExhausted { .. } => "RetriesExhausted",
+            #[cfg(feature = "serial")]
+            InstrumentError::Serialport(_) => "Serialport",
+            InstrumentError::SensorError(_) => "SensorError",
+            #[cfg(feature = "recording")]
+            InstrumentError::Transcript(_) => "Transcript",
+            InstrumentError::Timeout(_) => "Timeout",
+            InstrumentError::TimeoutWrite(_) => "TimeoutWrite",
+            InstrumentError::TimeoutPartial { .. } => "TimeoutPartial",
+            InstrumentError::TimeoutQuery { .. } => "TimeoutQuery",
+            InstrumentError::TimeoutQueryLine { .. } => "TimeoutQueryLine",
+            #[cfg(feature = "visa")]
+            InstrumentError::Visa { .. } => "Visa",
+        }
+    }
+}
+
+/// Errors are serialized as a struct with the name of the variant (`kind`) and the displayed
+/// error message (`message`), e.g., for error reports in a log. The data of the variants is not
+/// serialized separately, as some of it, e.g., a [`std::io::Error`], is not serializable.
+#[cfg(feature = "serde")]
+impl serde::Serialize for InstrumentError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use alloc::string::ToString;
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("InstrumentError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// Format a partial response for the display of timeout errors.
+fn fmt_partial(partial: &[u8]) -> String {
+    if partial.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " Partial response received: {:?}",
+            String::from_utf8_lossy(partial)
+        )
+    }
+}
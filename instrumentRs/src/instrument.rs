@@ -3,11 +3,16 @@
 //! It can be called with any type that implements [`std::io::Read`] and [`std::io::Write`],
 //! such as [`std::net::TcpStream`] or [`serialport::SerialPort`].
 
-use std::time::Duration;
+#![cfg(feature = "std")]
 
-use thiserror::Error;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
-use crate::InstrumentInterface;
+use crate::{
+    InstrumentError, InstrumentInterface, check_chunk_size, decode_response, timeout_to_query_error,
+};
 
 /// A general instrument interface that can be built with any interface that implements
 /// [`std::io::Read`] and [`std::io::Write`].
@@ -16,6 +21,9 @@ use crate::InstrumentInterface;
 /// shortcuts for creating various interfaces are provides as well. However, this general
 /// implementation can also be used with any other types that are not provided by `InstrumentRs`.
 ///
+/// Data is read from the port in larger chunks and buffered internally. Bytes that were received
+/// after a terminator are kept in the buffer and returned by the next read.
+///
 /// # Example
 ///
 /// The following shows a simple example on how to create an [`Instrument`] interface from your own
@@ -33,135 +41,855 @@ use crate::InstrumentInterface;
 /// ```
 pub struct Instrument<P: std::io::Read + std::io::Write> {
     port: P,
-    terminator: String,
+    terminator: Vec<u8>,
     timeout: Duration,
+    write_timeout: Option<Duration>,
+    read_buf: VecDeque<u8>,
+    set_port_timeout: Option<fn(&mut P, Duration) -> std::io::Result<()>>,
+    set_port_write_timeout: Option<fn(&mut P, Duration) -> std::io::Result<()>>,
+    check_port: Option<fn(&mut P) -> bool>,
+    name: Option<String>,
+    inter_command_delay: Option<Duration>,
+    last_write: Option<Instant>,
+    set_port_rts: Option<fn(&mut P, bool) -> std::io::Result<()>>,
+    turnaround: Duration,
+    turnaround_start: Option<Instant>,
+    strict_utf8: bool,
+    echo: bool,
+    write_chunking: Option<(usize, Duration)>,
+    max_response_len: usize,
 }
 
+/// Number of bytes that are requested from the port at once when filling the read buffer.
+const READ_CHUNK_SIZE: usize = 1024;
+
+/// Read timeout of the port while draining the input, see [`Instrument::drain_input`].
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// Default maximum length of a response, see [`Instrument::set_max_response_len`].
+pub const DEFAULT_MAX_RESPONSE_LEN: usize = 64 * 1024;
+
+/// Interval to poll ports that would block until data is available or the timeout is reached.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 impl<P: std::io::Read + std::io::Write> Instrument<P> {
     /// Try to create a new instance of [`Instrument`] with a given interface.
     pub fn new(port: P, timeout: Duration) -> Self {
         Self {
             port,
-            terminator: "\n".to_string(),
+            terminator: b"\n".to_vec(),
             timeout,
+            write_timeout: None,
+            read_buf: VecDeque::new(),
+            set_port_timeout: None,
+            set_port_write_timeout: None,
+            check_port: None,
+            name: None,
+            inter_command_delay: None,
+            last_write: None,
+            set_port_rts: None,
+            turnaround: Duration::ZERO,
+            turnaround_start: None,
+            strict_utf8: false,
+            echo: false,
+            write_chunking: None,
+            max_response_len: DEFAULT_MAX_RESPONSE_LEN,
+        }
+    }
+
+    /// Create a builder to configure a new [`Instrument`] in one expression.
+    ///
+    /// By default, the terminator is `"\n"`, the timeout is 3 seconds for reading and writing,
+    /// and no name is set.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::{net::TcpStream, time::Duration};
+    ///
+    /// use instrumentrs::Instrument;
+    ///
+    /// let my_interface = TcpStream::connect("192.168.10.1:8000").unwrap();
+    /// let inst_interface = Instrument::builder(my_interface)
+    ///     .terminator("\r")
+    ///     .timeout(Duration::from_secs(1))
+    ///     .name("tpg-roughing")
+    ///     .build();
+    /// ```
+    ///
+    /// # Arguments
+    /// * `port` - The port to communicate with the instrument.
+    pub fn builder(port: P) -> InstrumentBuilder<P> {
+        InstrumentBuilder {
+            inst: Self::new(port, Duration::from_secs(3)),
+        }
+    }
+
+    /// Get the write timeout of the interface.
+    ///
+    /// Unless a separate write timeout is set, this is the timeout of the interface.
+    pub fn get_write_timeout(&self) -> Duration {
+        self.write_timeout.unwrap_or(self.timeout)
+    }
+
+    /// Set a write timeout that differs from the timeout of the interface.
+    ///
+    /// Every write to the port has to finish within the write timeout, otherwise an
+    /// [`InstrumentError::TimeoutWrite`] error is returned. If the port supports it, see
+    /// [`Instrument::with_port_write_timeout`], the write timeout of the port is set as well.
+    /// Errors when setting the timeout of the port are ignored, as they would surface with the
+    /// next write.
+    ///
+    /// # Arguments
+    /// * `timeout` - The write timeout or `None` to use the timeout of the interface.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+        let write_timeout = self.get_write_timeout();
+        if let Some(set_port_write_timeout) = self.set_port_write_timeout {
+            let _ = set_port_write_timeout(&mut self.port, write_timeout);
+        }
+    }
+
+    /// Set if invalid UTF-8 data in responses should result in an error.
+    ///
+    /// By default, invalid UTF-8 data in a response that is read until the terminator is
+    /// replaced with the Unicode replacement character `U+FFFD`. In strict mode, an
+    /// [`InstrumentError::InvalidData`] error that contains the raw response is returned instead.
+    ///
+    /// # Arguments
+    /// * `strict` - If `true`, return an error for invalid UTF-8 data.
+    pub fn set_strict_utf8(&mut self, strict: bool) {
+        self.strict_utf8 = strict;
+    }
+
+    /// Enable the suppression of the command echo and return the [`Instrument`].
+    ///
+    /// See [`Instrument::set_echo`] for details.
+    pub fn with_echo(mut self) -> Self {
+        self.set_echo(true);
+        self
+    }
+
+    /// Get if the instrument echoes every command that is sent to it.
+    pub fn get_echo(&self) -> bool {
+        self.echo
+    }
+
+    /// Set if the instrument echoes every command that is sent to it.
+    ///
+    /// Instruments with local echo enabled, e.g., terminal-style RS-232 devices, send back every
+    /// command before the actual response. If the echo is set, `sendcmd` reads one line after
+    /// sending a command and checks that it matches the command. This also applies to `query` and
+    /// all other methods that send a command. If the line does not match, an
+    /// [`InstrumentError::NotAcknowledged`] error with the received line is returned. Commands
+    /// sent with `write_raw` are not affected.
+    ///
+    /// # Arguments
+    /// * `echo` - If `true`, read and verify the echo of every command.
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Set a minimum delay between two commands and return the [`Instrument`].
+    ///
+    /// See [`Instrument::set_inter_command_delay`] for details.
+    ///
+    /// # Arguments
+    /// * `delay` - The minimum delay between two commands.
+    pub fn with_inter_command_delay(mut self, delay: Duration) -> Self {
+        self.set_inter_command_delay(Some(delay));
+        self
+    }
+
+    /// Get the minimum delay between two commands, if one is set.
+    pub fn get_inter_command_delay(&self) -> Option<Duration> {
+        self.inter_command_delay
+    }
+
+    /// Set a minimum delay between two commands.
+    ///
+    /// Some instruments drop commands that arrive too quickly after each other. If a delay is
+    /// set, every write to the instrument waits until at least this delay has passed since the
+    /// previous write. This applies to all writes, i.e., `sendcmd`, `query`, and `write_raw`.
+    ///
+    /// # Arguments
+    /// * `delay` - The minimum delay between two commands or `None` to disable the delay.
+    pub fn set_inter_command_delay(&mut self, delay: Option<Duration>) {
+        self.inter_command_delay = delay;
+    }
+
+    /// Send all commands in chunks and return the [`Instrument`].
+    ///
+    /// See [`Instrument::set_write_chunking`] for details.
+    ///
+    /// # Arguments
+    /// * `chunk_size` - The maximum number of bytes per chunk.
+    /// * `delay` - The delay between two chunks.
+    pub fn with_write_chunking(mut self, chunk_size: usize, delay: Duration) -> Self {
+        self.set_write_chunking(Some((chunk_size, delay)));
+        self
+    }
+
+    /// Get the chunk size and the delay between chunks for commands, if chunking is set.
+    pub fn get_write_chunking(&self) -> Option<(usize, Duration)> {
+        self.write_chunking
+    }
+
+    /// Set if commands are sent in chunks with a delay between them.
+    ///
+    /// Instruments with small input buffers might lose data if a long command is sent at once.
+    /// If chunking is set, `sendcmd` and `query` send every command and its terminator like
+    /// [`InstrumentInterface::write_raw_chunked`]. The inter-command delay applies to the command
+    /// as a whole and not to every chunk. Data sent with `write_raw` is not chunked. If the chunk
+    /// size is zero, sending a command returns an [`InstrumentError::InvalidArgument`] error.
+    ///
+    /// # Arguments
+    /// * `chunking` - The chunk size and the delay between chunks or `None` to disable chunking.
+    pub fn set_write_chunking(&mut self, chunking: Option<(usize, Duration)>) {
+        self.write_chunking = chunking;
+    }
+
+    /// Set the maximum length of a response and return the [`Instrument`].
+    ///
+    /// See [`Instrument::set_max_response_len`] for details.
+    ///
+    /// # Arguments
+    /// * `max_len` - The maximum number of bytes of a response, without the terminator.
+    pub fn with_max_response_len(mut self, max_len: usize) -> Self {
+        self.set_max_response_len(max_len);
+        self
+    }
+
+    /// Get the maximum length of a response.
+    pub fn get_max_response_len(&self) -> usize {
+        self.max_response_len
+    }
+
+    /// Set the maximum length of a response that is read until the terminator.
+    ///
+    /// An instrument that streams data without ever sending the terminator would otherwise fill
+    /// the read buffer until the timeout is reached. If more bytes than the maximum length are
+    /// received without a terminator, the read is aborted with an
+    /// [`InstrumentError::ResponseTooLong`] error that contains the first `max_len` bytes. The
+    /// following data stays in the read buffer and can be discarded with `drain_input`. The limit
+    /// defaults to [`DEFAULT_MAX_RESPONSE_LEN`]. To read a single large response, use
+    /// [`Instrument::read_until_terminator_with_limit`] instead.
+    ///
+    /// # Arguments
+    /// * `max_len` - The maximum number of bytes of a response, without the terminator.
+    pub fn set_max_response_len(&mut self, max_len: usize) {
+        self.max_response_len = max_len;
+    }
+
+    /// Read until the terminator is found with a maximum response length for this call only.
+    ///
+    /// This function behaves like `read_until_terminator`, however, the given limit replaces the
+    /// maximum length of a response, see [`Instrument::set_max_response_len`].
+    ///
+    /// # Arguments
+    /// * `max_len` - The maximum number of bytes of the response, without the terminator.
+    pub fn read_until_terminator_with_limit(
+        &mut self,
+        max_len: usize,
+    ) -> Result<String, InstrumentError> {
+        let timeout = self.timeout;
+        let ret = self.read_until_terminator_buffered(timeout, max_len);
+        self.reset_port_timeout()?;
+        ret
+    }
+
+    /// Get the name of the interface, if one was set.
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Set a name for the interface.
+    ///
+    /// The name is used to distinguish the traffic of multiple instruments if the `tracing`
+    /// feature is enabled. Interfaces created with [`crate::TcpIpInterface`] and
+    /// [`crate::SerialInterface`] are named after their address or port by default.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the interface.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string());
+    }
+
+    /// Emit a trace event for data that was transferred through the port.
+    #[cfg(feature = "tracing")]
+    fn trace_traffic(&self, direction: &str, data: &[u8], tic: Instant) {
+        tracing::trace!(
+            interface = self.get_name().unwrap_or_default(),
+            direction,
+            data = %crate::trace::HexAscii(data),
+            elapsed = ?tic.elapsed(),
+        );
+    }
+
+    /// Set a function that sets the read timeout of the underlying port.
+    ///
+    /// This allows the [`Instrument`] to enforce its timeout while waiting for data from the port,
+    /// and to temporarily change the read timeout, e.g., when draining the input. Interfaces
+    /// created with [`crate::TcpIpInterface`] and [`crate::SerialInterface`] set this function
+    /// already. If you create an [`Instrument`] from a port that blocks on reading, you should set
+    /// this function, as the [`Instrument`] cannot interrupt a blocking read otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::{net::TcpStream, time::Duration};
+    ///
+    /// use instrumentrs::Instrument;
+    ///
+    /// let my_interface = TcpStream::connect("192.168.10.1:8000").unwrap();
+    /// let inst_interface = Instrument::new(my_interface, Duration::from_secs(3))
+    ///     .with_port_timeout(|stream, timeout| stream.set_read_timeout(Some(timeout)));
+    /// ```
+    ///
+    /// # Arguments
+    /// * `set_port_timeout` - Function that sets the read timeout of the port.
+    pub fn with_port_timeout(
+        mut self,
+        set_port_timeout: fn(&mut P, Duration) -> std::io::Result<()>,
+    ) -> Self {
+        self.set_port_timeout = Some(set_port_timeout);
+        self
+    }
+
+    /// Set a function that sets the write timeout of the underlying port.
+    ///
+    /// Every write to the port has to finish within the write timeout of the [`Instrument`], see
+    /// [`Instrument::get_write_timeout`], otherwise an [`InstrumentError::TimeoutWrite`] error is
+    /// returned. Ports that time out or would block on writing are retried until the timeout is
+    /// reached. If this function is set, the write timeout of the port is set right away and
+    /// whenever the timeout changes, and to the time remaining until the deadline before every
+    /// write.
+    /// Interfaces created with [`crate::TcpIpInterface`] and [`crate::SerialInterface`] set this
+    /// function already. If you create an [`Instrument`] from a port that blocks on writing, you
+    /// should set this function, as the [`Instrument`] cannot interrupt a blocking write otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::{net::TcpStream, time::Duration};
+    ///
+    /// use instrumentrs::Instrument;
+    ///
+    /// let my_interface = TcpStream::connect("192.168.10.1:8000").unwrap();
+    /// let inst_interface = Instrument::new(my_interface, Duration::from_secs(3))
+    ///     .with_port_write_timeout(|stream, timeout| stream.set_write_timeout(Some(timeout)));
+    /// ```
+    ///
+    /// # Arguments
+    /// * `set_port_write_timeout` - Function that sets the write timeout of the port.
+    pub fn with_port_write_timeout(
+        mut self,
+        set_port_write_timeout: fn(&mut P, Duration) -> std::io::Result<()>,
+    ) -> Self {
+        // Errors would surface with the next write, which sets the timeout again.
+        let write_timeout = self.get_write_timeout();
+        let _ = set_port_write_timeout(&mut self.port, write_timeout);
+        self.set_port_write_timeout = Some(set_port_write_timeout);
+        self
+    }
+
+    /// Get a reference to the underlying port.
+    pub fn get_ref(&self) -> &P {
+        &self.port
+    }
+
+    /// Get a mutable reference to the underlying port.
+    ///
+    /// This allows to change settings of the port, e.g., socket options or control lines, after
+    /// the [`Instrument`] was created. Note that data that was already received is kept in the
+    /// read buffer of the [`Instrument`]. Reading from or writing to the port directly bypasses
+    /// this buffer, and keeping both consistent is the responsibility of the caller.
+    pub fn get_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    /// Consume the [`Instrument`] and return the underlying port.
+    ///
+    /// Data that was received but not read yet is discarded with the read buffer.
+    pub fn into_inner(self) -> P {
+        self.port
+    }
+
+    /// Set a function that checks if the underlying port is still usable.
+    ///
+    /// The function must not consume any data from the port. See [`Instrument::is_alive`].
+    pub(crate) fn with_port_check(mut self, check_port: fn(&mut P) -> bool) -> Self {
+        self.check_port = Some(check_port);
+        self
+    }
+
+    /// Configure half-duplex operation of the port.
+    ///
+    /// If a function to set the RTS line is given, RTS is asserted while writing and cleared
+    /// after flushing. Before the first read after a write, the given turnaround delay is
+    /// awaited, starting from the end of the write.
+    pub(crate) fn set_half_duplex(
+        &mut self,
+        set_port_rts: Option<fn(&mut P, bool) -> std::io::Result<()>>,
+        turnaround: Duration,
+    ) {
+        self.set_port_rts = set_port_rts;
+        self.turnaround = turnaround;
+        self.turnaround_start = None;
+    }
+
+    /// Write data to the port, optionally in chunks with a delay between them.
+    ///
+    /// The inter-command delay and the turnaround apply to the data as a whole.
+    fn write_paced(
+        &mut self,
+        data: &[u8],
+        chunking: Option<(usize, Duration)>,
+    ) -> Result<(), InstrumentError> {
+        if let Some((chunk_size, _)) = chunking {
+            check_chunk_size(chunk_size)?;
+        }
+        if let (Some(delay), Some(last_write)) = (self.inter_command_delay, self.last_write) {
+            std::thread::sleep(delay.saturating_sub(last_write.elapsed()));
+        }
+
+        #[cfg(feature = "tracing")]
+        let tic = Instant::now();
+
+        match chunking {
+            Some((chunk_size, delay)) => {
+                for (idx, chunk) in data.chunks(chunk_size).enumerate() {
+                    if idx > 0 {
+                        std::thread::sleep(delay);
+                    }
+                    self.write_port(chunk)?;
+                }
+            }
+            None => self.write_port(data)?,
+        }
+        if self.inter_command_delay.is_some() {
+            self.last_write = Some(Instant::now());
+        }
+        if !self.turnaround.is_zero() {
+            self.turnaround_start = Some(Instant::now());
+        }
+
+        #[cfg(feature = "tracing")]
+        self.trace_traffic("write", data, tic);
+        Ok(())
+    }
+
+    /// Write data to the port and flush it, asserting RTS during the write if configured.
+    fn write_port(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        let Some(set_port_rts) = self.set_port_rts else {
+            return self.write_port_with_timeout(data);
+        };
+        set_port_rts(&mut self.port, true)?;
+        let ret = self.write_port_with_timeout(data);
+        // Always release the bus, even if writing failed, but report the write error first.
+        let released = set_port_rts(&mut self.port, false);
+        ret?;
+        released?;
+        Ok(())
+    }
+
+    /// Write all data to the port and flush it within the write timeout.
+    ///
+    /// If the port supports it, its write timeout is reset to the write timeout afterwards, see
+    /// [`Instrument::poll_port_write`].
+    fn write_port_with_timeout(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        let deadline = Instant::now() + self.get_write_timeout();
+        let mut written = 0;
+        let ret = self
+            .poll_port_write(deadline, |port| {
+                if written < data.len() {
+                    match port.write(&data[written..])? {
+                        0 => return Err(std::io::ErrorKind::WriteZero.into()),
+                        n => written += n,
+                    }
+                }
+                Ok(written == data.len())
+            })
+            .and_then(|_| self.poll_port_write(deadline, |port| port.flush().map(|_| true)));
+        let write_timeout = self.get_write_timeout();
+        if let Some(set_port_write_timeout) = self.set_port_write_timeout {
+            set_port_write_timeout(&mut self.port, write_timeout)?;
+        }
+        ret
+    }
+
+    /// Call a write operation on the port until it reports that it is done or the deadline has
+    /// passed.
+    ///
+    /// The operation is called at least once, even if the deadline has already passed. If the
+    /// port supports it, its write timeout is set to the time remaining until the deadline before
+    /// every call. Ports that time out or would block are polled until the deadline, after which
+    /// an [`InstrumentError::TimeoutWrite`] error is returned.
+    fn poll_port_write<F>(&mut self, deadline: Instant, mut op: F) -> Result<(), InstrumentError>
+    where
+        F: FnMut(&mut P) -> std::io::Result<bool>,
+    {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if let Some(set_port_write_timeout) = self.set_port_write_timeout
+                && !remaining.is_zero()
+            {
+                set_port_write_timeout(&mut self.port, remaining)?;
+            }
+            match op(&mut self.port) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    if Instant::now() >= deadline {
+                        return Err(InstrumentError::TimeoutWrite(self.get_write_timeout()));
+                    }
+                    std::thread::sleep(POLL_INTERVAL.min(remaining));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Read and discard everything from the port until nothing more arrives.
+    ///
+    /// Returns the number of bytes that were read.
+    fn drain_port(&mut self) -> Result<usize, InstrumentError> {
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        let mut drained = 0;
+        let tic = Instant::now();
+        // Stop after the timeout in case the instrument keeps on sending data.
+        while (Instant::now() - tic) < self.timeout {
+            match self.port.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => drained += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(drained)
+    }
+
+    /// Read the next chunk of data from the port and append it to the read buffer.
+    ///
+    /// This blocks until at least one byte is available or the deadline has passed. Returns
+    /// `false` if the deadline passed without receiving any data. If the port supports it, its
+    /// read timeout is set to the time remaining until the deadline before reading. Ports that
+    /// time out or would block are polled until the deadline. If the port reports the end of the
+    /// stream, an [`std::io::ErrorKind::UnexpectedEof`] error is returned, just as
+    /// [`std::io::Read::read_exact`] would.
+    fn fill_read_buf(&mut self, deadline: Instant) -> Result<bool, InstrumentError> {
+        if let Some(turnaround_start) = self.turnaround_start.take() {
+            std::thread::sleep(self.turnaround.saturating_sub(turnaround_start.elapsed()));
+        }
+
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(false);
+            }
+            if let Some(set_port_timeout) = self.set_port_timeout {
+                set_port_timeout(&mut self.port, remaining)?;
+            }
+            match self.port.read(&mut chunk) {
+                Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => {
+                    self.read_buf.extend(&chunk[..n]);
+                    return Ok(true);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    std::thread::sleep(POLL_INTERVAL.min(remaining));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Reset the read timeout of the port to the timeout of the interface, if supported.
+    fn reset_port_timeout(&mut self) -> Result<(), InstrumentError> {
+        if let Some(set_port_timeout) = self.set_port_timeout {
+            set_port_timeout(&mut self.port, self.timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Read into the buffer from the buffered port, waiting at most for the given timeout.
+    fn read_exact_buffered(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(), InstrumentError> {
+        let deadline = Instant::now() + timeout;
+        while self.read_buf.len() < buf.len() {
+            if !self.fill_read_buf(deadline)? {
+                return Err(InstrumentError::Timeout(timeout));
+            }
+        }
+        let len = buf.len();
+        for (byte, val) in buf.iter_mut().zip(self.read_buf.drain(..len)) {
+            *byte = val;
+        }
+        Ok(())
+    }
+
+    /// Read from the buffered port until the terminator is found or the timeout is reached.
+    ///
+    /// Bytes after the terminator stay in the read buffer for the next read. On a timeout, the
+    /// buffered bytes are returned in the error. If more than `max_len` bytes are received
+    /// without a terminator, these bytes are returned in an error.
+    fn read_until_terminator_buffered(
+        &mut self,
+        timeout: Duration,
+        max_len: usize,
+    ) -> Result<String, InstrumentError> {
+        let tic = Instant::now();
+        let deadline = tic + timeout;
+        let term_len = self.terminator.len();
+        // The response can end earliest after the first byte, as for the unbuffered implementation.
+        let mut end = term_len.max(1);
+
+        // The terminator must end within this many bytes for the response to be short enough.
+        let max_end = max_len.saturating_add(term_len);
+
+        // The buffer is searched before the deadline is checked, such that responses that are
+        // already buffered or arrive right at the deadline are returned.
+        loop {
+            let buf = self.read_buf.make_contiguous();
+            let term = self.terminator.as_slice();
+            let search_end = buf.len().min(max_end);
+            if let Some(found) = (end..=search_end).find(|&idx| &buf[idx - term_len..idx] == term) {
+                let mut response: Vec<u8> = self.read_buf.drain(..found).collect();
+                #[cfg(feature = "tracing")]
+                self.trace_traffic("read", &response, tic);
+                response.truncate(found - term_len);
+                return decode_response(response, self.strict_utf8);
+            }
+            if buf.len() >= max_end {
+                return Err(InstrumentError::ResponseTooLong {
+                    limit: max_len,
+                    got: self.read_buf.drain(..max_len).collect(),
+                });
+            }
+            end = end.max(buf.len() + 1);
+            if Instant::now() >= deadline || !self.fill_read_buf(deadline)? {
+                break;
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            interface = self.name.as_deref().unwrap_or_default(),
+            buffered = %crate::trace::HexAscii(self.read_buf.make_contiguous()),
+            ?timeout,
+            "timeout while waiting for terminator",
+        );
+        // Complete responses must stay buffered for the next read and never end up in the error.
+        let term = self.terminator.as_slice();
+        let complete = !term.is_empty()
+            && self
+                .read_buf
+                .make_contiguous()
+                .windows(term.len())
+                .any(|w| w == term);
+        if self.read_buf.is_empty() || complete {
+            Err(InstrumentError::Timeout(timeout))
+        } else {
+            Err(InstrumentError::TimeoutPartial {
+                timeout,
+                partial: self.read_buf.drain(..).collect(),
+            })
         }
     }
 }
 
 impl<P: std::io::Read + std::io::Write> InstrumentInterface for Instrument<P> {
     fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
-        self.port.read_exact(buf)?;
+        #[cfg(feature = "tracing")]
+        let tic = Instant::now();
+
+        let reads_port = self.read_buf.len() < buf.len();
+        let ret = self.read_exact_buffered(buf, self.timeout);
+        if reads_port {
+            // Reading from the port changes its timeout.
+            self.reset_port_timeout()?;
+        }
+        ret?;
+
+        #[cfg(feature = "tracing")]
+        self.trace_traffic("read", buf, tic);
         Ok(())
     }
 
-    fn get_terminator(&self) -> &str {
-        self.terminator.as_str()
+    /// Send a command to the instrument.
+    ///
+    /// If the echo is set, see [`Instrument::set_echo`], the echo of the command is read and
+    /// verified.
+    fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
+        let mut data = cmd.as_bytes().to_vec();
+        data.extend_from_slice(self.get_terminator_bytes());
+        self.write_paced(&data, self.write_chunking)?;
+        if self.echo {
+            let echo = self
+                .read_until_terminator()
+                .map_err(|e| timeout_to_query_error(e, cmd))?;
+            if echo != cmd {
+                return Err(InstrumentError::NotAcknowledged(echo));
+            }
+        }
+        Ok(())
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminator.as_slice()
     }
 
-    fn set_terminator(&mut self, terminator: &str) {
-        self.terminator = terminator.to_string();
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.terminator = terminator.to_vec();
     }
 
     fn get_timeout(&self) -> Duration {
         self.timeout
     }
 
+    /// Discard all buffered data and all data that is waiting on the port.
+    ///
+    /// If the port supports it, e.g., for interfaces created with [`crate::TcpIpInterface`] or
+    /// [`crate::SerialInterface`], the read timeout of the port is set to a very short value
+    /// while draining and restored afterwards. Note that for other ports that block on reading
+    /// without any timeout, this function will block as well.
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        let buffered = self.read_buf.len();
+        self.read_buf.clear();
+
+        let Some(set_port_timeout) = self.set_port_timeout else {
+            return Ok(buffered + self.drain_port()?);
+        };
+        set_port_timeout(&mut self.port, DRAIN_TIMEOUT)?;
+        let drained = self.drain_port();
+        set_port_timeout(&mut self.port, self.timeout)?;
+        Ok(buffered + drained?)
+    }
+
+    /// Check if the port is still usable.
+    ///
+    /// If the port supports it, e.g., for interfaces created with [`crate::TcpIpInterface`] or
+    /// [`crate::SerialInterface`], the port is checked without consuming any data. Otherwise, the
+    /// port is assumed to be usable.
+    fn is_alive(&mut self) -> bool {
+        match self.check_port {
+            Some(check_port) => check_port(&mut self.port),
+            None => true,
+        }
+    }
+
+    fn read_until_terminator_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        // The given timeout replaces the interface timeout for this call only. Reading changes
+        // the read timeout of the port, which is restored afterwards.
+        let ret = self.read_until_terminator_buffered(timeout, self.max_response_len);
+        self.reset_port_timeout()?;
+        ret
+    }
+
+    /// Set the timeout of the interface.
+    ///
+    /// The timeout applies to reading and, unless a separate write timeout is set, to writing. If
+    /// the port supports it, e.g., for interfaces created with [`crate::TcpIpInterface`] or
+    /// [`crate::SerialInterface`], the read and write timeouts of the port are set as well. Errors
+    /// when setting the timeouts of the port are ignored, as they would surface with the next
+    /// read or write.
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+        if let Some(set_port_timeout) = self.set_port_timeout {
+            let _ = set_port_timeout(&mut self.port, timeout);
+        }
+        let write_timeout = self.get_write_timeout();
+        if let Some(set_port_write_timeout) = self.set_port_write_timeout {
+            let _ = set_port_write_timeout(&mut self.port, write_timeout);
+        }
+    }
+
     fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
-        self.port.write_all(data)?;
-        self.port.flush()?;
-        Ok(())
+        self.write_paced(data, None)
+    }
+
+    fn write_raw_chunked(
+        &mut self,
+        data: &[u8],
+        chunk_size: usize,
+        delay: Duration,
+    ) -> Result<(), InstrumentError> {
+        self.write_paced(data, Some((chunk_size, delay)))
     }
 }
 
-/// The error enum for all instruments.
-///
-/// For any command sending or querying, your instrument should return either an empty result or a
-/// result with the query where this Error is the alternative. [`InstrumentError`] makes it easy to
-/// propagate all the sending commands, querying errors forward with the `?` operator such that
-/// errors propagate nicely. If this is not possible, it is considered a bug and should be
-/// reported.
-#[derive(Debug, Error)]
-#[non_exhaustive]
-pub enum InstrumentError {
-    /// The instrument did not acknowledge the command that was sent. The response received is
-    /// returned in the error as a String.
-    #[error("Instrument did not acknowledge the command sent, but responded with: {0}")]
-    NotAcknowledged(String),
-    /// The channel index requested is out of range. The error contains the index requested and
-    /// the number of channels that are currently configured.
-    #[error(
-        "Channel with index {idx} is out of range. Number of channels available: {nof_channels}"
-    )]
-    ChannelIndexOutOfRange {
-        /// Index of the channel that is out of range.
-        idx: usize,
-        /// Total number of channels.
-        nof_channels: usize,
-    },
-    /// A given float value is out of the specified range. The error contains the value that was
-    /// sent, the minimum value that is allowed, and the maximum value that is allowed.
-    #[error("Float value {value} is out of range. Allowed range is [{min}, {max}]")]
-    FloatValueOutOfRange {
-        /// The value that is out of range.
-        value: f64,
-        /// The minimum value that is allowed.
-        min: f64,
-        /// The maximum value that is allowed.
-        max: f64,
-    },
-    /// A given integer value is out of the specified range. The error contains the value that was
-    /// sent, the minimum value that is allowed, and the maximum value that is allowed.
-    #[error("Integer value {value} is out of range. Allowed range is [{min}, {max}]")]
-    IntValueOutOfRange {
-        /// The value that is out of range.
-        value: i64,
-        /// The minimum value that is allowed.
-        min: i64,
-        /// The maximum value that is allowed.
-        max: i64,
-    },
-    /// Error when an invalid argument is passed to a function. This error contains only an error
-    /// message, but no arguments. It is intended for the user.
-    #[error("{0}")]
-    InvalidArgument(String),
-    /// Error when reading from/writing to an interface. See [`std::io::Error`] for more details.
-    #[error(transparent)]
-    Io(#[from] std::io::Error),
-    /// Instrument status is not okay, e.g., a response from the instrument did not succeed with a
-    /// given error message. This error contains a string with the error message that is intended
-    /// to be displayed for the user, i.e., "Sensor not calibrated". Note that the string is
-    /// directly displayed without any further formatting, so you need to ensure that it is
-    /// descriptive enough for the user.
-    #[error("{0}")]
-    InstrumentStatus(String),
-    /// Instrument response could not be parsed becuase it was unexpected by the driver. This error
-    /// contains the response that was received from the instrument.
-    #[error("Response from instrument could not be parsed. Response was: {0}")]
-    ResponseParseError(String),
-    #[cfg(feature = "serial")]
-    /// Serial port errors can occur when opening a serial interface. See the [`serialport::Error`]
-    /// documentation for more information.
-    #[error(transparent)]
-    Serialport(#[from] serialport::Error),
-    /// A sensor error occurred. This error is returned if the instrument reports a sensor error.
-    /// The error string contains should contain further information about the sensor error.
-    #[error("Sensor error: {0}")]
-    SensorError(String),
-    /// Timeout occurred while waiting for a response from the instrument. The error contains the
-    /// timeout that was exceeded.
-    #[error(
-        "Timeout occured while waiting for a response from the instrument. Timeout was set to {0:?}."
-    )]
-    Timeout(Duration),
-    /// Timeout occurred while waiting for a response to a query. The error contains the query
-    /// that was sent and the timeout that was exceeded.
-    #[error(
-        "Timeout occured while waiting for a response to query: {query}. Timeout was set to {timeout:?}."
-    )]
-    TimeoutQuery {
-        /// The query that timed out.
-        query: String,
-        /// The timeout that was set.
-        timeout: Duration,
-    },
+/// A builder for an [`Instrument`], see [`Instrument::builder`].
+pub struct InstrumentBuilder<P: std::io::Read + std::io::Write> {
+    inst: Instrument<P>,
+}
+
+impl<P: std::io::Read + std::io::Write> InstrumentBuilder<P> {
+    /// Set the terminator, defaults to `"\n"`.
+    pub fn terminator(mut self, terminator: &str) -> Self {
+        self.inst.set_terminator(terminator);
+        self
+    }
+
+    /// Set the timeout for reading and writing, defaults to 3 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.inst.timeout = timeout;
+        self
+    }
+
+    /// Set a write timeout that differs from the timeout, see [`Instrument::set_write_timeout`].
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.inst.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum length of a response, see [`Instrument::set_max_response_len`].
+    pub fn max_response_len(mut self, max_len: usize) -> Self {
+        self.inst.max_response_len = max_len;
+        self
+    }
+
+    /// Set the name of the interface, see [`Instrument::set_name`].
+    pub fn name(mut self, name: &str) -> Self {
+        self.inst.set_name(name);
+        self
+    }
+
+    /// Create the [`Instrument`] and apply the timeouts to the port, if supported.
+    pub fn build(mut self) -> Instrument<P> {
+        let timeout = self.inst.timeout;
+        self.inst.set_timeout(timeout);
+        self.inst
+    }
+
+    /// Wrap an existing [`Instrument`] to change its settings with the builder.
+    pub(crate) fn from_instrument(inst: Instrument<P>) -> Self {
+        Self { inst }
+    }
 }
@@ -0,0 +1,209 @@
+//! This module provides a declarative configuration for instrument interfaces.
+//!
+//! Deployment tooling often describes the connection of every instrument in a configuration file.
+//! An [`InterfaceConfig`] holds such a description and builds the interface from it. With the
+//! `"serde"` feature, it can be deserialized, e.g., from a TOML file.
+
+#![cfg(feature = "std")]
+
+use std::time::Duration;
+
+use crate::{InstrumentError, InstrumentInterface, TcpIpInterface};
+
+/// Configuration of an instrument interface.
+///
+/// With the `"serde"` feature, the configuration can be (de)serialized. The type of the interface
+/// is given by the `type` field, and the timeout is given in seconds. Unknown fields are
+/// rejected. The serial interface is only available with the `"serial"` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use instrumentrs::{InstrumentInterface, InterfaceConfig};
+///
+/// let config = InterfaceConfig::from_toml(
+///     r#"
+///     type = "tcp"
+///     addr = "192.168.1.10:8000"
+///     timeout = 1.5
+///     "#,
+/// )
+/// .unwrap();
+/// let mut intf = config.build().unwrap();
+/// let name = intf.query("*IDN?").unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)
+)]
+pub enum InterfaceConfig {
+    /// A TCP/IP interface, see [`TcpIpInterface::simple`].
+    Tcp {
+        /// The socket address, e.g., `"192.168.1.10:8000"`.
+        addr: String,
+        /// The timeout, defaults to 3 seconds.
+        #[cfg_attr(
+            feature = "serde",
+            serde(
+                default,
+                skip_serializing_if = "Option::is_none",
+                with = "timeout_secs"
+            )
+        )]
+        timeout: Option<Duration>,
+    },
+    /// A serial interface, see [`crate::SerialInterface::builder`].
+    #[cfg(feature = "serial")]
+    Serial {
+        /// The name of the serial port, e.g., `"/dev/ttyUSB0"`.
+        port: String,
+        /// The baud rate.
+        baud: u32,
+        /// The number of data bits, must be between 5 and 8. Defaults to 8.
+        #[cfg_attr(feature = "serde", serde(default = "default_data_bits"))]
+        data_bits: u8,
+        /// The parity. Defaults to [`ConfigParity::None`].
+        #[cfg_attr(feature = "serde", serde(default))]
+        parity: ConfigParity,
+        /// The number of stop bits, must be 1 or 2. Defaults to 1.
+        #[cfg_attr(feature = "serde", serde(default = "default_stop_bits"))]
+        stop_bits: u8,
+        /// The timeout, defaults to 3 seconds.
+        #[cfg_attr(
+            feature = "serde",
+            serde(
+                default,
+                skip_serializing_if = "Option::is_none",
+                with = "timeout_secs"
+            )
+        )]
+        timeout: Option<Duration>,
+    },
+}
+
+/// Parity of a serial interface in an [`InterfaceConfig`].
+///
+/// With the `"serde"` feature, it is (de)serialized as `"none"`, `"odd"`, or `"even"`.
+#[cfg(feature = "serial")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ConfigParity {
+    /// No parity bit.
+    #[default]
+    None,
+    /// Odd parity.
+    Odd,
+    /// Even parity.
+    Even,
+}
+
+#[cfg(feature = "serial")]
+impl From<ConfigParity> for crate::Parity {
+    fn from(parity: ConfigParity) -> Self {
+        match parity {
+            ConfigParity::None => crate::Parity::None,
+            ConfigParity::Odd => crate::Parity::Odd,
+            ConfigParity::Even => crate::Parity::Even,
+        }
+    }
+}
+
+impl InterfaceConfig {
+    /// Parse a configuration from a TOML string.
+    ///
+    /// If the TOML is invalid, contains unknown fields, or has invalid values, an
+    /// [`InstrumentError::InvalidArgument`] error with the reason is returned.
+    ///
+    /// # Arguments
+    /// * `toml` - The TOML string.
+    #[cfg(feature = "serde")]
+    pub fn from_toml(toml: &str) -> Result<Self, InstrumentError> {
+        toml::from_str(toml).map_err(|err| {
+            InstrumentError::InvalidArgument(format!("Invalid interface configuration: {err}"))
+        })
+    }
+
+    /// Open the interface that is described by the configuration.
+    ///
+    /// If a value of the configuration is invalid, an [`InstrumentError::InvalidArgument`] error
+    /// is returned. Errors that occur while opening the interface are returned as well.
+    pub fn build(&self) -> Result<Box<dyn InstrumentInterface + Send>, InstrumentError> {
+        match self {
+            InterfaceConfig::Tcp { addr, timeout } => {
+                let mut intf = TcpIpInterface::simple(addr.as_str())?;
+                if let Some(timeout) = timeout {
+                    intf.set_timeout(*timeout);
+                }
+                Ok(Box::new(intf))
+            }
+            #[cfg(feature = "serial")]
+            InterfaceConfig::Serial {
+                port,
+                baud,
+                data_bits,
+                parity,
+                stop_bits,
+                timeout,
+            } => {
+                let mut builder = crate::SerialInterface::builder(port, *baud)
+                    .data_bits(*data_bits)
+                    .parity((*parity).into())
+                    .stop_bits(*stop_bits);
+                if let Some(timeout) = timeout {
+                    builder = builder.timeout(*timeout);
+                }
+                Ok(Box::new(builder.open()?))
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "serial"))]
+fn default_data_bits() -> u8 {
+    8
+}
+
+#[cfg(all(feature = "serde", feature = "serial"))]
+fn default_stop_bits() -> u8 {
+    1
+}
+
+/// (De)serialize an optional timeout as a number of seconds.
+#[cfg(feature = "serde")]
+mod timeout_secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub(super) fn serialize<S: Serializer>(
+        timeout: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match timeout {
+            Some(timeout) => serializer.serialize_f64(timeout.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        let Some(secs) = Option::<f64>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        Duration::try_from_secs_f64(secs).map(Some).map_err(|_| {
+            D::Error::custom(format!(
+                "invalid timeout: {secs}. The timeout must be a positive number of seconds."
+            ))
+        })
+    }
+}
@@ -0,0 +1,116 @@
+//! This module provides a keep-alive helper for instruments with a communication watchdog.
+//!
+//! Some instruments, and some serial to Ethernet converters, drop the session or trip a watchdog
+//! if they do not receive any traffic for a while. The [`KeepAlive`] sends a harmless command,
+//! e.g., a status query, at a fixed interval from a background thread.
+
+#![cfg(feature = "std")]
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Sender,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{InstrumentError, poller::spawn_periodic};
+
+/// A keep-alive that periodically sends a command to an instrument from a background thread.
+///
+/// The keep-alive takes ownership of a target, usually a clone of a driver or channel that shares
+/// its interface with the other clones. Since every command locks the shared interface, a beat
+/// waits while another clone is in the middle of a transaction and is sent right after it, such
+/// that the keep-alive never interleaves with other traffic.
+///
+/// A beat is missed if the closure returns an error. Every missed beat is reported to the
+/// callback together with the number of consecutive missed beats, and the keep-alive continues.
+///
+/// When the keep-alive is dropped or stopped with [`KeepAlive::stop`], the thread finishes the
+/// beat that is currently running, if any, and exits. Dropping the keep-alive waits for this.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use instrumentrs::{InstrumentInterface, KeepAlive, SharedInterface, TcpIpInterface};
+///
+/// let inst_interface = TcpIpInterface::simple("192.168.1.10:8000").unwrap();
+/// let mut intf = SharedInterface::new(inst_interface);
+///
+/// let keep_alive = KeepAlive::new(
+///     intf.clone(),
+///     Duration::from_secs(1),
+///     |intf| intf.query("*STB?").map(|_| ()),
+///     |err, consecutive| eprintln!("Missed {consecutive} beats: {err}"),
+/// );
+///
+/// // The interface can be used as usual in the meantime.
+/// let name = intf.query("*IDN?").unwrap();
+/// ```
+pub struct KeepAlive<T> {
+    missed: Arc<AtomicUsize>,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<T>>,
+}
+
+impl<T: Send + 'static> KeepAlive<T> {
+    /// Create a new keep-alive and start sending beats in a background thread.
+    ///
+    /// The first beat is sent immediately.
+    ///
+    /// # Arguments
+    /// * `target` - The driver, channel, or interface to keep alive.
+    /// * `interval` - The interval between the starts of two beats.
+    /// * `beat` - The closure that sends a command to the target.
+    /// * `on_missed` - The callback for missed beats, which gets the error and the number of
+    ///   consecutive missed beats.
+    pub fn new<F, M>(target: T, interval: Duration, mut beat: F, mut on_missed: M) -> Self
+    where
+        F: FnMut(&mut T) -> Result<(), InstrumentError> + Send + 'static,
+        M: FnMut(&InstrumentError, usize) + Send + 'static,
+    {
+        let missed = Arc::new(AtomicUsize::new(0));
+        let thread_missed = Arc::clone(&missed);
+        let mut consecutive = 0;
+        let (stop, handle) = spawn_periodic(target, interval, move |target| {
+            if let Err(err) = beat(target) {
+                consecutive += 1;
+                thread_missed.fetch_add(1, Ordering::SeqCst);
+                on_missed(&err, consecutive);
+            } else {
+                consecutive = 0;
+            }
+        });
+        KeepAlive {
+            missed,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Get the total number of missed beats.
+    pub fn missed_beats(&self) -> usize {
+        self.missed.load(Ordering::SeqCst)
+    }
+
+    /// Stop sending beats and return the target.
+    ///
+    /// Returns `None` if the closure or the callback panicked, in which case the target is lost.
+    pub fn stop(mut self) -> Option<T> {
+        self.stop.take();
+        self.handle.take().and_then(|handle| handle.join().ok())
+    }
+}
+
+impl<T> Drop for KeepAlive<T> {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -8,9 +8,38 @@
 //! simplified access to the following interfaces:
 //!
 //! - TCP/IP (blocking) using the [`std::net`] module.
+//! - Remote serial ports according to RFC 2217 (blocking) using the [`std::net`] module.
 //! - Serial (blocking) using the [`serialport`] crate (feature `"serial"`).
+//! - TCP/IP (async) using the [`tokio`] crate (feature `"async"`).
+//! - Serial (async) using the [`tokio_serial`] crate (feature `"serial-async"`).
+//! - USBTMC (blocking) using the [`rusb`] crate (feature `"usbtmc"`).
+//! - Any VISA resource (blocking) using an installed VISA library (feature `"visa"`).
 //!
-//! We are planning in the future to also support asynchronous interfaces.
+//! Asynchronous interfaces implement the [`AsyncInstrumentInterface`] trait, which mirrors the
+//! blocking [`InstrumentInterface`] trait.
+//!
+//! To turn a session with real hardware into a test, the [`RecordingInterface`] records all
+//! traffic of an interface and saves it as JSON or TOML file (feature `"recording"`). The
+//! [`ReplayInterface`] then replays such a file in your tests. To test the full TCP/IP path of a
+//! driver, the [`SimulatedTcpInstrument`] answers commands on a local port (feature
+//! `"test-server"`). Stateful simulators of instruments are provided by the
+//! [`SimulatorInterface`] (feature `"simulator"`). Expected commands of the
+//! [`LoopbackInterface`] can be regular expressions with the `"regex"` feature.
+//!
+//! Conversions between pressure units that instruments report and the types of the
+//! [`measurements`] crate are provided in the [`units`] module (feature `"measurements"`).
+//!
+//! # `no_std` support
+//!
+//! The [`InstrumentInterface`] trait, the [`InstrumentError`] type, the [`ModbusClient`]s, and the
+//! [`LoopbackInterface`] only need `alloc` and are available without the `"std"` feature,
+//! which is enabled by default. This allows to reuse drivers on embedded hosts, e.g., a gateway
+//! based on a microcontroller. Interfaces then implement `read_exact` and `write_raw` on top of
+//! the peripherals of the host and provide a [`Clock`] that measures timeouts. All other
+//! interfaces and helpers require the `"std"` feature.
+//!
+//! If the `"tracing"` feature is enabled, all traffic of an [`Instrument`] is emitted as events
+//! using the [`tracing`] crate, such that protocol issues can be debugged with any subscriber.
 //!
 //! # Example
 //!
@@ -43,7 +72,7 @@
 //! repository on GitHub in order to get your driver added here. This means that we will take
 //! over maintainership of the driver and release them as bugs get squished, etc. In order for this
 //! to work, all functionality of your instrument driver must be tested with hardware, but also
-//! with tests using the provided [`LoopbackInterfaceString`].
+//! with tests using the provided [`LoopbackInterface`].
 //!
 //! # Inspiration
 //!
@@ -73,21 +102,114 @@
 //! for inclusion in the work by you, as defined in the Apache-2.0 license, shall be
 //! dual licensed as above, without any additional terms or conditions.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(warnings, missing_docs)]
 
+extern crate alloc;
+
+mod async_instrument;
+mod async_interface;
+mod async_serial;
+mod async_tcp_ip;
+mod channel_map;
+mod clock;
+mod error;
 mod instrument;
+mod interface_config;
+mod keep_alive;
 mod loopback;
+mod loopback_script;
+mod modbus;
+mod poller;
+mod recording;
+mod replay;
+mod retry;
+mod rfc2217;
 mod serial;
+mod shared_bus;
+mod shared_interface;
+mod simulator;
 mod tcp_ip;
+mod telnet;
+mod test_server;
+mod trace;
+pub mod units;
+mod usbtmc;
+mod visa;
 
-use std::time::{Duration, Instant};
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::time::Duration;
 
-pub use instrument::{Instrument, InstrumentError};
-pub use loopback::LoopbackInterfaceString;
+pub use channel_map::ChannelMap;
+pub use clock::Clock;
+pub use error::InstrumentError;
+pub use loopback::{LoopbackCommand, LoopbackEntry, LoopbackInterface, LoopbackPayload};
+#[allow(deprecated)]
+pub use loopback::{LoopbackInterfaceBytes, LoopbackInterfaceString};
+pub use loopback_script::LoopbackScript;
+pub use modbus::{ModbusClient, ModbusException, ModbusRtuClient, ModbusTcpClient};
+
+#[cfg(feature = "std")]
+pub use clock::StdClock;
+#[cfg(feature = "std")]
+pub use instrument::{DEFAULT_MAX_RESPONSE_LEN, Instrument, InstrumentBuilder};
+#[cfg(feature = "serial")]
+pub use interface_config::ConfigParity;
+#[cfg(feature = "std")]
+pub use interface_config::InterfaceConfig;
+#[cfg(feature = "std")]
+pub use keep_alive::KeepAlive;
+#[cfg(feature = "std")]
+pub use poller::{PollEvent, Poller};
+#[cfg(feature = "std")]
+pub use retry::{Backoff, RetryPolicy};
+#[cfg(feature = "std")]
+pub use rfc2217::{Rfc2217Config, Rfc2217Interface, Rfc2217Parity, Rfc2217Port, Rfc2217StopBits};
+#[cfg(feature = "std")]
+pub use shared_bus::{BusHandle, SharedBus};
+#[cfg(feature = "std")]
+pub use shared_interface::{SharedInterface, lock_interface};
+#[cfg(feature = "std")]
 pub use tcp_ip::TcpIpInterface;
+#[cfg(feature = "std")]
+pub use telnet::TelnetFilter;
+
+#[cfg(feature = "async")]
+pub use async_instrument::AsyncInstrument;
+#[cfg(feature = "async")]
+pub use async_interface::AsyncInstrumentInterface;
+#[cfg(feature = "serial-async")]
+pub use async_serial::AsyncSerialInterface;
+#[cfg(feature = "async")]
+pub use async_tcp_ip::AsyncTcpIpInterface;
 
+#[cfg(feature = "recording")]
+pub use recording::{Direction, RecordingInterface, Transcript, TranscriptEntry};
+#[cfg(feature = "recording")]
+pub use replay::ReplayInterface;
+
+#[cfg(feature = "serial")]
+pub use serial::{Rs485Config, SerialInterface, SerialInterfaceBuilder, SerialPortDescriptor};
 #[cfg(feature = "serial")]
-pub use serial::SerialInterface;
+pub use serialport::{FlowControl, Parity, SerialPort};
+
+#[cfg(feature = "simulator")]
+pub use simulator::{CryoTelGtSimulator, SimulatedInstrument, SimulatorInterface, Tpg36xSimulator};
+
+#[cfg(feature = "test-server")]
+pub use test_server::{SimulatedTcpInstrument, SimulatedTcpInstrumentBuilder};
+
+#[cfg(feature = "usbtmc")]
+pub use usbtmc::UsbTmcInterface;
+
+#[cfg(feature = "visa")]
+pub use visa::VisaInterface;
 
 /// The [`InstrumentInterface`] trait defines the interface for controlling instruments.
 ///
@@ -107,20 +229,41 @@ pub trait InstrumentInterface {
     /// # Arguments:
     /// - `_ack` - A string slice that contains the expected acknowledgment response.
     fn check_acknowledgment(&mut self, ack: &str) -> Result<(), InstrumentError> {
-        let response = self.read_until_terminator()?;
-        if response == ack {
-            Ok(())
-        } else {
-            Err(InstrumentError::NotAcknowledged(response))
-        }
+        check_acknowledgment_with(self, &|response| response == ack)
+    }
+
+    /// Check if an acknowledgment that starts with the given prefix is received.
+    ///
+    /// This function behaves like `check_acknowledgment`, however, the response only has to start
+    /// with the prefix, e.g., for instruments that append variable content to the acknowledgment.
+    ///
+    /// # Arguments:
+    /// - `prefix` - The expected start of the acknowledgment response.
+    fn check_acknowledgment_prefix(&mut self, prefix: &str) -> Result<(), InstrumentError> {
+        check_acknowledgment_with(self, &|response| response.starts_with(prefix))
+    }
+
+    /// Check if an acknowledgment is received that is accepted by the given predicate.
+    ///
+    /// One line is read from the instrument and passed to the predicate. If the predicate returns
+    /// `false`, an [`InstrumentError::NotAcknowledged`] error with the response received is
+    /// returned.
+    ///
+    /// # Arguments:
+    /// - `f` - The predicate that returns `true` if the response is a valid acknowledgment.
+    fn check_acknowledgment_with(&mut self, f: impl Fn(&str) -> bool) -> Result<(), InstrumentError>
+    where
+        Self: Sized,
+    {
+        check_acknowledgment_with(self, &f)
     }
 
     /// Query the instrument with a command and return the response as a String.
     ///
     /// This function uses `sendcmd` to send the command and then reads the response character by
     /// character until the response string ends with the terminator. If no terminator is
-    /// encountered, the function will block until the timeout is reached. If a non-UTF-8 byte is
-    /// received, an error is printed to `stderr` and the byte is skipped.
+    /// encountered, the function will block until the timeout is reached. Invalid UTF-8 data in
+    /// the response is handled as described in `read_until_terminator`.
     ///
     /// This function has a default implementation, as it uses other interface specific methods in
     /// order to query the instrument.
@@ -128,15 +271,171 @@ pub trait InstrumentInterface {
     /// # Arguments
     /// * `_cmd` - The command to send to the instrument for which we expect a response.
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
+        self.query_with_timeout(cmd, self.get_timeout())
+    }
+
+    /// Query the instrument with a command and a timeout that only applies to this call.
+    ///
+    /// This function behaves like `query`, however, the given timeout replaces the timeout of the
+    /// interface while waiting for the response. This is useful for commands that take
+    /// significantly longer than usual, e.g., a factory reset, without changing the timeout for
+    /// all other commands.
+    ///
+    /// # Arguments
+    /// * `cmd` - The command to send to the instrument for which we expect a response.
+    /// * `timeout` - The timeout to use for this query only.
+    fn query_with_timeout(
+        &mut self,
+        cmd: &str,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
         self.sendcmd(cmd)?;
-        match self.read_until_terminator() {
-            Ok(response) => Ok(response),
-            Err(InstrumentError::Timeout(tout)) => Err(InstrumentError::TimeoutQuery {
-                query: cmd.to_string(),
-                timeout: tout,
-            }),
-            Err(e) => Err(e),
+        self.read_until_terminator_with_timeout(timeout)
+            .map_err(|e| timeout_to_query_error(e, cmd))
+    }
+
+    /// Query the instrument with a command that returns multiple lines.
+    ///
+    /// The command is sent once, then `nlines` lines are read with `read_until_terminator`. The
+    /// timeout of the interface applies to every line separately. If a line is not received in
+    /// time, an [`InstrumentError::TimeoutQueryLine`] error is returned, which contains the index
+    /// of the line that timed out.
+    ///
+    /// # Arguments
+    /// * `cmd` - The command to send to the instrument for which we expect a response.
+    /// * `nlines` - Number of lines to read.
+    fn query_multiline(
+        &mut self,
+        cmd: &str,
+        nlines: usize,
+    ) -> Result<Vec<String>, InstrumentError> {
+        self.sendcmd(cmd)?;
+        let mut lines = Vec::with_capacity(nlines);
+        for line in 0..nlines {
+            let response = self.read_until_terminator().map_err(|e| match e {
+                InstrumentError::Timeout(timeout) => InstrumentError::TimeoutQueryLine {
+                    query: cmd.to_string(),
+                    line,
+                    timeout,
+                    partial: Vec::new(),
+                },
+                InstrumentError::TimeoutPartial { timeout, partial } => {
+                    InstrumentError::TimeoutQueryLine {
+                        query: cmd.to_string(),
+                        line,
+                        timeout,
+                        partial,
+                    }
+                }
+                e => e,
+            })?;
+            lines.push(response);
+        }
+        Ok(lines)
+    }
+
+    /// Query the instrument with raw bytes and return a response with a fixed length.
+    ///
+    /// The data is written as is, i.e., no terminator is appended. Then, exactly `response_len`
+    /// bytes are read from the instrument. If the response is not received within the timeout of
+    /// the interface, an [`InstrumentError::TimeoutQuery`] error is returned, just as for `query`.
+    /// It contains the bytes of a response that was too short.
+    ///
+    /// # Arguments
+    /// * `data` - The raw bytes to send to the instrument.
+    /// * `response_len` - Number of bytes to read as the response.
+    fn query_raw(&mut self, data: &[u8], response_len: usize) -> Result<Vec<u8>, InstrumentError> {
+        self.write_raw(data)?;
+
+        let timeout = self.get_timeout();
+        let mut response = Vec::with_capacity(response_len);
+        let mut single_buf = [0u8];
+        let tic = self.clock().now();
+        while response.len() < response_len {
+            let ret = if self.clock().elapsed(tic) >= timeout {
+                Err(InstrumentError::Timeout(timeout))
+            } else {
+                self.read_exact(&mut single_buf)
+            };
+            if let Err(e) = ret {
+                let e = with_partial(e, core::mem::take(&mut response));
+                return Err(timeout_to_query_error(e, &String::from_utf8_lossy(data)));
+            }
+            response.push(single_buf[0]);
         }
+        Ok(response)
+    }
+
+    /// Query the instrument with raw bytes and read the response until a delimiter byte.
+    ///
+    /// The data is written as is, i.e., no terminator is appended. The response is read with
+    /// `read_until_byte` and includes the delimiter. If the delimiter is not received within the
+    /// timeout of the interface, an [`InstrumentError::TimeoutQuery`] error is returned, just as
+    /// for `query`.
+    ///
+    /// # Arguments
+    /// * `data` - The raw bytes to send to the instrument.
+    /// * `delim` - The delimiter byte that ends the response.
+    fn query_raw_until(&mut self, data: &[u8], delim: u8) -> Result<Vec<u8>, InstrumentError> {
+        self.query_raw_until_byte(data, delim, 0)
+    }
+
+    /// Query the instrument with raw bytes and read a frame that ends with a delimiter byte and a
+    /// given number of extra bytes.
+    ///
+    /// This function behaves like `query_raw_until`, however, `extra_bytes` bytes are read after
+    /// the delimiter as well, e.g., a checksum, see `read_until_byte`. The full frame is returned.
+    ///
+    /// # Arguments
+    /// * `data` - The raw bytes to send to the instrument.
+    /// * `delim` - The delimiter byte that ends the frame.
+    /// * `extra_bytes` - Number of bytes to read after the delimiter.
+    fn query_raw_until_byte(
+        &mut self,
+        data: &[u8],
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        self.write_raw(data)?;
+        self.read_until_byte(delim, extra_bytes)
+            .map_err(|e| timeout_to_query_error(e, &String::from_utf8_lossy(data)))
+    }
+
+    /// Query the instrument with a command and retry according to the given retry policy.
+    ///
+    /// If an attempt fails with an error that the policy considers worth retrying (by default
+    /// timeouts and I/O errors), the input is drained and the query is sent again after the
+    /// backoff of the policy, see [`RetryPolicy::run`]. If all attempts fail, an
+    /// [`InstrumentError::RetriesExhausted`] error is returned.
+    ///
+    /// # Arguments
+    /// * `cmd` - The command to send to the instrument for which we expect a response.
+    /// * `policy` - The [`RetryPolicy`] to use.
+    #[cfg(feature = "std")]
+    fn query_with_retry(
+        &mut self,
+        cmd: &str,
+        policy: &RetryPolicy,
+    ) -> Result<String, InstrumentError> {
+        policy.run(self, |intf| intf.query(cmd))
+    }
+
+    /// Discard all data that was received from the instrument but not read yet.
+    ///
+    /// This is useful after a timeout or a reboot of the instrument, when stale data would
+    /// otherwise be parsed as the response to the next query. Returns the number of bytes that
+    /// were discarded. The default implementation does nothing and returns zero.
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        Ok(0)
+    }
+
+    /// Check if the interface is still usable.
+    ///
+    /// This is a cheap check that does not send any instrument specific command and does not
+    /// consume any data that is waiting to be read. It is intended, e.g., for long-running
+    /// monitoring applications. The default implementation always returns `true`.
+    fn is_alive(&mut self) -> bool {
+        true
     }
 
     /// Read an exact number of bytes from the instrument.
@@ -145,37 +444,129 @@ pub trait InstrumentInterface {
     /// read as many bytes as the buffer can hold.
     fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError>;
 
+    /// Read until a delimiter byte is found, then read a given number of extra bytes.
+    ///
+    /// This is useful for binary framed protocols, where a frame ends with a delimiter, e.g., ETX
+    /// (`0x03`), that is followed by a fixed number of bytes, e.g., a checksum. The extra bytes
+    /// are read as is, even if they contain the delimiter. The full raw frame, including the
+    /// delimiter and the extra bytes, is returned. If the frame is not received within the
+    /// timeout of the interface, an [`InstrumentError::Timeout`] error is returned, or an
+    /// [`InstrumentError::TimeoutPartial`] error with the bytes received so far.
+    ///
+    /// # Arguments
+    /// * `delim` - The delimiter byte that ends the frame.
+    /// * `extra_bytes` - Number of bytes to read after the delimiter.
+    fn read_until_byte(
+        &mut self,
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        let timeout = self.get_timeout();
+        let mut frame = Vec::new();
+        let mut single_buf = [0u8];
+
+        let tic = self.clock().now();
+        loop {
+            if self.clock().elapsed(tic) >= timeout {
+                return Err(with_partial(InstrumentError::Timeout(timeout), frame));
+            }
+            if let Err(e) = self.read_exact(&mut single_buf) {
+                return Err(with_partial(e, frame));
+            }
+            frame.push(single_buf[0]);
+            if single_buf[0] == delim {
+                break;
+            }
+        }
+
+        let mut extra = vec![0u8; extra_bytes];
+        if let Err(e) = self.read_exact(&mut extra) {
+            return Err(with_partial(e, frame));
+        }
+        frame.extend(extra);
+        Ok(frame)
+    }
+
     /// Read until the terminator is found or the timeout is reached.
     ///
     /// This function reads from the instrument until the terminator is found or the timeout is
-    /// reached and returns the read data as a String.
+    /// reached and returns the read data without the terminator as a String. The terminator is
+    /// matched on the raw bytes, the data is only converted into a String at the end. Only the
+    /// terminator is removed, whitespace in the response is kept as it was received. Use
+    /// `read_line_trimmed` to remove leading and trailing whitespace as well. If the timeout is
+    /// reached after some data was received, this data is returned in an
+    /// [`InstrumentError::TimeoutPartial`] error.
+    ///
+    /// Invalid UTF-8 data, e.g., a stray `0xFF` byte on a noisy serial line, is replaced with the
+    /// Unicode replacement character `U+FFFD`. An [`Instrument`] can be configured to return an
+    /// [`InstrumentError::InvalidData`] error instead, see [`Instrument::set_strict_utf8`].
     fn read_until_terminator(&mut self) -> Result<String, InstrumentError> {
-        let mut response = String::new();
+        self.read_until_terminator_with_timeout(self.get_timeout())
+    }
+
+    /// Read until the terminator is found and remove leading and trailing whitespace.
+    ///
+    /// This function behaves like `read_until_terminator`, however, the response is trimmed. This
+    /// is useful for instruments that pad their responses or send additional line breaks.
+    fn read_line_trimmed(&mut self) -> Result<String, InstrumentError> {
+        Ok(self.read_until_terminator()?.trim().to_string())
+    }
+
+    /// Read until the terminator is found or the timeout is reached, keeping partial responses.
+    ///
+    /// This function behaves like `read_until_terminator`, however, a timeout is not an error.
+    /// Instead, the data that was received until the timeout is returned. The boolean is `true`
+    /// if the terminator was found and `false` if the timeout was reached. This is useful to
+    /// diagnose instruments that respond with an unexpected terminator.
+    fn read_until_terminator_lossy(&mut self) -> Result<(String, bool), InstrumentError> {
+        match self.read_until_terminator() {
+            Ok(response) => Ok((response, true)),
+            Err(InstrumentError::TimeoutPartial { partial, .. }) => {
+                Ok((decode_response(partial, false)?, false))
+            }
+            Err(InstrumentError::Timeout(_)) => Ok((String::new(), false)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read until the terminator is found or the given timeout is reached.
+    ///
+    /// This function behaves like `read_until_terminator`, however, the given timeout replaces
+    /// the timeout of the interface for this call only.
+    ///
+    /// # Arguments
+    /// * `timeout` - The timeout to use for this read only.
+    fn read_until_terminator_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        let mut response = Vec::new();
         let mut single_buf = [0u8];
 
-        let tic = Instant::now();
+        let tic = self.clock().now();
         let mut timeout_occured = true;
 
-        while (Instant::now() - tic) < self.get_timeout() {
-            self.read_exact(&mut single_buf)?;
-            if let Ok(val) = str::from_utf8(&single_buf) {
-                response.push_str(val);
-            } else {
-                panic!(
-                    "Received invalid UTF-8 data: {single_buf:?}. This should be unreachable, as read exact always returns a `u8`. Please report this as a bug."
-                );
+        while self.clock().elapsed(tic) < timeout {
+            if let Err(e) = self.read_exact(&mut single_buf) {
+                return Err(with_partial(e, response));
             }
-            if response.ends_with(&self.get_terminator()) {
+            response.push(single_buf[0]);
+            if response.ends_with(self.get_terminator_bytes()) {
                 timeout_occured = false;
                 break;
             }
         }
 
-        if timeout_occured {
-            Err(InstrumentError::Timeout(self.get_timeout()))
+        if timeout_occured && response.is_empty() {
+            Err(InstrumentError::Timeout(timeout))
+        } else if timeout_occured {
+            Err(InstrumentError::TimeoutPartial {
+                timeout,
+                partial: response,
+            })
         } else {
-            let retval = response.trim();
-            Ok(retval.to_string())
+            response.truncate(response.len() - self.get_terminator_bytes().len());
+            decode_response(response, false)
         }
     }
 
@@ -191,23 +582,44 @@ pub trait InstrumentInterface {
     /// # Arguments:
     /// - `_cmd` - A string slice that will be sent to the instrument.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        let cmd = format!("{}{}", cmd, self.get_terminator());
-        self.write(&cmd)
+        let mut data = cmd.as_bytes().to_vec();
+        data.extend_from_slice(self.get_terminator_bytes());
+        self.write_raw(&data)
     }
 
+    /// Get the current terminator of the interface as a string.
     ///
-    /// Get the current terminator of the interface.
-    ///
-    /// If not implemented, this function will return a default value of `"\n"`.
-    fn get_terminator(&self) -> &str {
-        "\n"
+    /// This is a thin wrapper around `get_terminator_bytes`. Invalid UTF-8 data in the terminator
+    /// is replaced with the replacement character, use `get_terminator_bytes` for interfaces that
+    /// use non-UTF-8 terminators.
+    fn get_terminator(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.get_terminator_bytes())
     }
 
     /// Set the terminator of an interface from a `&str`.
     ///
+    /// This is a thin wrapper around `set_terminator_bytes`.
+    ///
+    /// # Arguments:
+    /// - `terminator` - A string slice that will be used as the terminator for commands
+    fn set_terminator(&mut self, terminator: &str) {
+        self.set_terminator_bytes(terminator.as_bytes());
+    }
+
+    /// Get the current terminator of the interface as a byte slice.
+    ///
+    /// If not implemented, this function will return a default value of `b"\n"`.
+    fn get_terminator_bytes(&self) -> &[u8] {
+        b"\n"
+    }
+
+    /// Set the terminator of an interface from a byte slice.
+    ///
+    /// This allows to use terminators that are not valid UTF-8, e.g., ETX (`0x03`).
+    ///
     /// # Arguments:
-    /// - `_terminator` - A string slice that will be used as the terminator for commands
-    fn set_terminator(&mut self, _terminator: &str) {}
+    /// - `_terminator` - A byte slice that will be used as the terminator for commands
+    fn set_terminator_bytes(&mut self, _terminator: &[u8]) {}
 
     /// Get the current timeout of the interface.
     ///
@@ -217,6 +629,14 @@ pub trait InstrumentInterface {
         Duration::from_secs(3)
     }
 
+    /// Set the timeout of the interface.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// # Arguments:
+    /// - `_timeout` - The new timeout of the interface.
+    fn set_timeout(&mut self, _timeout: Duration) {}
+
     /// Write a string to the instrument.
     ///
     /// This function takes a string slice and writes it to the instrument. It does NOT append the
@@ -233,4 +653,225 @@ pub trait InstrumentInterface {
     /// This function takes a byte slice and writes it to the interface. It does NOT append the
     /// terminator. After writing, the interface should be flushed.
     fn write_raw(&mut self, _data: &[u8]) -> Result<(), InstrumentError>;
+
+    /// Write a byte slice to the instrument in chunks with a delay between them.
+    ///
+    /// Instruments with small input buffers, e.g., behind a slow UART, might lose data if a long
+    /// payload is sent at once. This function writes the data in chunks of at most `chunk_size`
+    /// bytes with `write_raw`, which flushes every chunk, and waits for `delay` between two
+    /// chunks. The last chunk might be shorter. It does NOT append the terminator.
+    ///
+    /// If `chunk_size` is zero, an [`InstrumentError::InvalidArgument`] error is returned.
+    ///
+    /// # Arguments:
+    /// - `data` - The data to write.
+    /// - `chunk_size` - The maximum number of bytes per chunk.
+    /// - `delay` - The delay between two chunks.
+    fn write_raw_chunked(
+        &mut self,
+        data: &[u8],
+        chunk_size: usize,
+        delay: Duration,
+    ) -> Result<(), InstrumentError> {
+        check_chunk_size(chunk_size)?;
+        for (idx, chunk) in data.chunks(chunk_size).enumerate() {
+            if idx > 0 {
+                self.clock().sleep(delay);
+            }
+            self.write_raw(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Get the clock that the default methods use to measure timeouts and delays.
+    ///
+    /// With the `"std"` feature, the default implementation returns the [`StdClock`].
+    #[cfg(feature = "std")]
+    fn clock(&self) -> &dyn Clock {
+        &StdClock
+    }
+
+    /// Get the clock that the default methods use to measure timeouts and delays.
+    ///
+    /// Without the `"std"` feature, every interface has to provide its own clock.
+    #[cfg(not(feature = "std"))]
+    fn clock(&self) -> &dyn Clock;
+}
+
+/// Boxed interfaces, e.g., a `Box<dyn InstrumentInterface + Send>` that was built with
+/// [`InterfaceConfig::build`], can be passed to instrument drivers like any other interface.
+impl<T: InstrumentInterface + ?Sized> InstrumentInterface for Box<T> {
+    fn check_acknowledgment(&mut self, ack: &str) -> Result<(), InstrumentError> {
+        (**self).check_acknowledgment(ack)
+    }
+
+    fn check_acknowledgment_prefix(&mut self, prefix: &str) -> Result<(), InstrumentError> {
+        (**self).check_acknowledgment_prefix(prefix)
+    }
+
+    fn query_with_timeout(
+        &mut self,
+        cmd: &str,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        (**self).query_with_timeout(cmd, timeout)
+    }
+
+    fn query_multiline(
+        &mut self,
+        cmd: &str,
+        nlines: usize,
+    ) -> Result<Vec<String>, InstrumentError> {
+        (**self).query_multiline(cmd, nlines)
+    }
+
+    fn query_raw(&mut self, data: &[u8], response_len: usize) -> Result<Vec<u8>, InstrumentError> {
+        (**self).query_raw(data, response_len)
+    }
+
+    fn query_raw_until(&mut self, data: &[u8], delim: u8) -> Result<Vec<u8>, InstrumentError> {
+        (**self).query_raw_until(data, delim)
+    }
+
+    fn query_raw_until_byte(
+        &mut self,
+        data: &[u8],
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        (**self).query_raw_until_byte(data, delim, extra_bytes)
+    }
+
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        (**self).drain_input()
+    }
+
+    fn is_alive(&mut self) -> bool {
+        (**self).is_alive()
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        (**self).read_exact(buf)
+    }
+
+    fn read_until_byte(
+        &mut self,
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        (**self).read_until_byte(delim, extra_bytes)
+    }
+
+    fn read_until_terminator_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        (**self).read_until_terminator_with_timeout(timeout)
+    }
+
+    fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
+        (**self).sendcmd(cmd)
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        (**self).get_terminator_bytes()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        (**self).set_terminator_bytes(terminator)
+    }
+
+    fn get_timeout(&self) -> Duration {
+        (**self).get_timeout()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        (**self).set_timeout(timeout)
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        (**self).write_raw(data)
+    }
+
+    fn write_raw_chunked(
+        &mut self,
+        data: &[u8],
+        chunk_size: usize,
+        delay: Duration,
+    ) -> Result<(), InstrumentError> {
+        (**self).write_raw_chunked(data, chunk_size, delay)
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        (**self).clock()
+    }
+}
+
+/// Read one line and check it with the predicate, see
+/// [`InstrumentInterface::check_acknowledgment_with`].
+///
+/// This is a free function such that the interface stays dyn compatible.
+fn check_acknowledgment_with<T: InstrumentInterface + ?Sized>(
+    intf: &mut T,
+    f: &dyn Fn(&str) -> bool,
+) -> Result<(), InstrumentError> {
+    let response = intf.read_until_terminator()?;
+    if f(&response) {
+        Ok(())
+    } else {
+        Err(InstrumentError::NotAcknowledged(response))
+    }
+}
+
+/// Check that the chunk size of a chunked write is not zero.
+pub(crate) fn check_chunk_size(chunk_size: usize) -> Result<(), InstrumentError> {
+    if chunk_size == 0 {
+        return Err(InstrumentError::InvalidArgument(
+            "Chunk size of a chunked write must be greater than 0".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Convert a [`InstrumentError::Timeout`] or [`InstrumentError::TimeoutPartial`] into a
+/// [`InstrumentError::TimeoutQuery`] error.
+///
+/// All other errors are returned unchanged.
+pub(crate) fn timeout_to_query_error(err: InstrumentError, query: &str) -> InstrumentError {
+    match err {
+        InstrumentError::Timeout(timeout) => InstrumentError::TimeoutQuery {
+            query: query.to_string(),
+            timeout,
+            partial: Vec::new(),
+        },
+        InstrumentError::TimeoutPartial { timeout, partial } => InstrumentError::TimeoutQuery {
+            query: query.to_string(),
+            timeout,
+            partial,
+        },
+        e => e,
+    }
+}
+
+/// Convert a [`InstrumentError::Timeout`] into a [`InstrumentError::TimeoutPartial`] error if a
+/// partial response was received.
+fn with_partial(err: InstrumentError, partial: Vec<u8>) -> InstrumentError {
+    match err {
+        InstrumentError::Timeout(timeout) if !partial.is_empty() => {
+            InstrumentError::TimeoutPartial { timeout, partial }
+        }
+        e => e,
+    }
+}
+
+/// Convert a response into a String.
+///
+/// In strict mode, invalid UTF-8 data results in an [`InstrumentError::InvalidData`] error that
+/// contains the raw response. Otherwise, invalid data is replaced with `U+FFFD`.
+pub(crate) fn decode_response(response: Vec<u8>, strict: bool) -> Result<String, InstrumentError> {
+    match String::from_utf8(response) {
+        Ok(val) => Ok(val),
+        Err(e) if strict => Err(InstrumentError::InvalidData(e.into_bytes())),
+        Err(e) => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+    }
 }
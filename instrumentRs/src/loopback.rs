@@ -1,17 +1,527 @@
 //! The loopback module provides an instrument simulator for testing purposes.
 //!
-//! The [`LoopbackInterfaceString`] allows to test instruments drivers that communicate using
-//! strings (which are then encoded as bytes of course) and have a fixed terminator to declare the
-//! end of a line.
+//! The [`LoopbackInterface`] allows to test instruments drivers that have a fixed terminator to
+//! declare the end of a line. Its script consists of [`LoopbackPayload`]s, i.e., either strings
+//! for drivers that communicate using text (which is then encoded as bytes of course), or byte
+//! vectors for drivers of binary protocols. Both payloads behave identically, as the loopback
+//! interface only handles bytes internally.
 //!
-//! Check out the [`LoopbackInterfaceString`] for more details and examples on how to use it. You can
+//! If the `async` feature is enabled, the [`LoopbackInterface`] also implements the
+//! [`crate::AsyncInstrumentInterface`] trait, such that asynchronous drivers can be tested in the
+//! same way.
+//!
+//! Expected commands from host to instrument can also be given as patterns using
+//! [`LoopbackCommand`], e.g., to not depend on the precision that a driver uses to format floats.
+//!
+//! Check out the [`LoopbackInterface`] for more details and examples on how to use it. You can
 //! also find simple and more advanced test examples that use the loopback interface in the
 //! instrument drivers that are available in the GitHub repository of this project.
 
-use std::collections::VecDeque;
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, marker::PhantomData, time::Duration};
 
 use crate::{InstrumentError, InstrumentInterface};
 
+/// A clock that never advances, for the loopback interface without the `"std"` feature.
+///
+/// The loopback interface answers every read immediately or panics, so it does not need to
+/// measure timeouts. Delays between chunks of a write are skipped.
+#[cfg(not(feature = "std"))]
+struct StoppedClock;
+
+#[cfg(not(feature = "std"))]
+impl crate::Clock for StoppedClock {
+    fn now(&self) -> core::time::Duration {
+        core::time::Duration::ZERO
+    }
+
+    fn sleep(&self, _duration: core::time::Duration) {}
+}
+
+/// The payload of the script of a [`LoopbackInterface`].
+///
+/// The payload is the type of the commands and responses that are given to
+/// [`LoopbackInterface::new`] and [`LoopbackInterface::unordered`]. It is implemented for
+/// [`String`], for drivers that communicate using text, and for `Vec<u8>`, for drivers of binary
+/// protocols.
+pub trait LoopbackPayload: Into<LoopbackCommand> + Into<LoopbackEntry> {}
+
+impl LoopbackPayload for String {}
+
+impl LoopbackPayload for Vec<u8> {}
+
+/// A command that the [`LoopbackInterface`] expects from host to instrument.
+///
+/// Commands are either exact strings or patterns. Glob patterns match any sequence of characters
+/// with `*` and any single character with `?`. Regular expressions are available with the
+/// `"regex"` feature and must match the full command. The parts of the command that the wildcards
+/// or the groups of the regular expression matched are called captures and can be used to pick
+/// the response with [`LoopbackCommand::respond_with`].
+///
+/// Exact commands can simply be converted from strings:
+///
+/// ```
+/// use instrumentrs::{InstrumentInterface, LoopbackCommand, LoopbackInterface};
+///
+/// let from_host = vec![
+///     "*IDN?".into(),
+///     LoopbackCommand::glob("SET TTARGET=*"),
+///     LoopbackCommand::glob("TTARGET?").respond_with(|_| "100.00".to_string()),
+/// ];
+/// let from_inst = vec!["CRYOTEL".into()];
+/// let mut lbk = LoopbackInterface::<String>::with_commands(from_host, from_inst, "\n");
+///
+/// assert_eq!("CRYOTEL", lbk.query("*IDN?").unwrap());
+/// lbk.sendcmd("SET TTARGET=100.0").unwrap();
+/// assert_eq!("100.00", lbk.query("TTARGET?").unwrap());
+/// ```
+pub struct LoopbackCommand {
+    pattern: Pattern,
+    responder: Option<CaptureResponder>,
+    failures: Failures,
+    repeat: Repeat,
+    matched: usize,
+}
+
+/// How often a [`LoopbackCommand`] is expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repeat {
+    Times(usize),
+    Forever,
+}
+
+/// A function that picks a response from the captures of a matched command.
+type CaptureResponder = Box<dyn FnMut(&[&str]) -> Vec<u8> + Send>;
+
+/// A function that creates an error that the loopback interface injects.
+type ErrorFactory = Box<dyn Fn() -> InstrumentError + Send>;
+
+/// Errors that are returned a number of times before an entry of the script is handled.
+#[derive(Default)]
+struct Failures {
+    remaining: usize,
+    error: Option<ErrorFactory>,
+}
+
+impl Failures {
+    fn new(times: usize, error: impl Fn() -> InstrumentError + Send + 'static) -> Self {
+        Failures {
+            remaining: times,
+            error: Some(Box::new(error)),
+        }
+    }
+
+    /// Get the next injected error, if any are remaining.
+    fn next(&mut self) -> Option<InstrumentError> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.error.as_ref().map(|error| error())
+    }
+}
+
+/// A function that answers a command instead of the script of the loopback interface.
+type Responder = Box<dyn FnMut(&str) -> Option<String> + Send>;
+
+/// The pattern that a [`LoopbackCommand`] matches.
+enum Pattern {
+    Exact(Vec<u8>),
+    Raw(Vec<u8>),
+    Glob(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl LoopbackCommand {
+    /// Expect exactly the given command.
+    pub fn exact(cmd: &str) -> Self {
+        Self::from_pattern(Pattern::Exact(cmd.as_bytes().to_vec()))
+    }
+
+    /// Expect exactly the given data, without the expected terminator.
+    ///
+    /// This is useful for protocols where some commands are sent without a terminator, e.g., the
+    /// `ENQ` character that Pfeiffer gauge controllers use to request the response of a command.
+    pub fn raw(data: &str) -> Self {
+        Self::from_pattern(Pattern::Raw(data.as_bytes().to_vec()))
+    }
+
+    /// Expect a command that matches the given glob pattern.
+    ///
+    /// A `*` matches any sequence of characters, a `?` any single character. Every wildcard is a
+    /// capture.
+    pub fn glob(pattern: &str) -> Self {
+        Self::from_pattern(Pattern::Glob(pattern.to_string()))
+    }
+
+    /// Expect a command that matches the given regular expression.
+    ///
+    /// The expression must match the full command. Every group of the expression is a capture,
+    /// groups that did not participate in the match are captured as empty strings.
+    ///
+    /// # Panics
+    /// Panics if the regular expression is invalid.
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> Self {
+        let regex = regex::Regex::new(&format!("^(?:{pattern})$"))
+            .unwrap_or_else(|err| panic!("Invalid regular expression '{pattern}': {err}"));
+        Self::from_pattern(Pattern::Regex(regex))
+    }
+
+    /// Answer the command with the response that the given function returns.
+    ///
+    /// The function gets the captures of the match. Its response is read by the host before any
+    /// of the remaining responses from instrument to host.
+    pub fn respond_with(
+        self,
+        mut responder: impl FnMut(&[&str]) -> String + Send + 'static,
+    ) -> Self {
+        self.respond_with_bytes(move |captures| responder(captures).into_bytes())
+    }
+
+    /// Answer the command with the binary response that the given function returns.
+    ///
+    /// This works like [`LoopbackCommand::respond_with`], but the response can contain arbitrary
+    /// bytes, e.g., for drivers of binary protocols.
+    pub fn respond_with_bytes(
+        mut self,
+        responder: impl FnMut(&[&str]) -> Vec<u8> + Send + 'static,
+    ) -> Self {
+        self.responder = Some(Box::new(responder));
+        self
+    }
+
+    /// Fail the write of this command a number of times before it is accepted.
+    ///
+    /// The first `times` writes that expect this command return the error that the given
+    /// function creates, without checking the command. Afterwards, the command is matched as
+    /// usual. This allows to test retry and reconnect logic of drivers.
+    ///
+    /// ```
+    /// use std::io::{Error, ErrorKind};
+    /// use instrumentrs::{InstrumentError, InstrumentInterface, LoopbackCommand, LoopbackInterface};
+    ///
+    /// let broken_pipe = || Error::from(ErrorKind::BrokenPipe).into();
+    /// let from_host = vec![LoopbackCommand::exact("ALLOFF").failing(1, broken_pipe)];
+    /// let mut lbk = LoopbackInterface::<String>::with_commands(from_host, vec![], "\n");
+    ///
+    /// assert!(matches!(lbk.sendcmd("ALLOFF"), Err(InstrumentError::Io(_))));
+    /// lbk.sendcmd("ALLOFF").unwrap();
+    /// ```
+    pub fn failing(
+        mut self,
+        times: usize,
+        error: impl Fn() -> InstrumentError + Send + 'static,
+    ) -> Self {
+        self.failures = Failures::new(times, error);
+        self
+    }
+
+    /// Expect this command the given number of times in a row.
+    ///
+    /// The command is expected exactly `times` times before the next command of the script is
+    /// expected. Combine it with [`LoopbackCommand::respond_with`] to answer every repetition.
+    ///
+    /// # Panics
+    /// Panics if `times` is zero.
+    pub fn times(mut self, times: usize) -> Self {
+        assert!(times > 0, "A command must be expected at least once.");
+        self.repeat = Repeat::Times(times);
+        self
+    }
+
+    /// Expect this command any number of times in a row, including not at all.
+    ///
+    /// The script only advances to the next command once a different command arrives. This is
+    /// useful for polling, where a driver sends the same query over and over again. Combine it
+    /// with [`LoopbackCommand::respond_with`] to answer every repetition.
+    pub fn forever(mut self) -> Self {
+        self.repeat = Repeat::Forever;
+        self
+    }
+
+    fn from_pattern(pattern: Pattern) -> Self {
+        LoopbackCommand {
+            pattern,
+            responder: None,
+            failures: Failures::default(),
+            repeat: Repeat::Times(1),
+            matched: 0,
+        }
+    }
+
+    /// Record a match of this command, returns `true` if the command is not expected anymore.
+    fn record_match(&mut self) -> bool {
+        self.matched += 1;
+        match self.repeat {
+            Repeat::Times(times) => self.matched >= times,
+            Repeat::Forever => false,
+        }
+    }
+
+    /// Check if the command can be skipped, as it was matched often enough.
+    fn is_satisfied(&self) -> bool {
+        match self.repeat {
+            Repeat::Times(times) => self.matched >= times,
+            Repeat::Forever => true,
+        }
+    }
+
+    /// Match the command including the terminator and return the captures.
+    fn captures<'a>(&self, cmd: &'a [u8], terminator: &str) -> Option<Vec<&'a str>> {
+        match &self.pattern {
+            Pattern::Exact(exp) => {
+                let cmd = cmd.strip_suffix(terminator.as_bytes());
+                return (cmd == Some(exp.as_slice())).then(Vec::new);
+            }
+            Pattern::Raw(exp) => return (exp == cmd).then(Vec::new),
+            _ => {}
+        }
+        let cmd = str::from_utf8(cmd.strip_suffix(terminator.as_bytes())?).ok()?;
+        match &self.pattern {
+            Pattern::Exact(_) | Pattern::Raw(_) => None,
+            Pattern::Glob(pattern) => {
+                let mut captures = Vec::new();
+                glob_match(pattern, cmd, &mut captures).then_some(captures)
+            }
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => regex.captures(cmd).map(|caps| {
+                caps.iter()
+                    .skip(1)
+                    .map(|m| m.map_or("", |m| m.as_str()))
+                    .collect()
+            }),
+        }
+    }
+
+    /// Match the command and return the response of the responder, if any.
+    fn respond(&mut self, cmd: &[u8], terminator: &str) -> Option<Option<Vec<u8>>> {
+        let captures = self.captures(cmd, terminator)?;
+        Some(self.responder.as_mut().map(|f| f(&captures)))
+    }
+}
+
+impl From<&str> for LoopbackCommand {
+    fn from(cmd: &str) -> Self {
+        Self::exact(cmd)
+    }
+}
+
+impl From<String> for LoopbackCommand {
+    fn from(cmd: String) -> Self {
+        Self::from_pattern(Pattern::Exact(cmd.into_bytes()))
+    }
+}
+
+impl From<Vec<u8>> for LoopbackCommand {
+    fn from(cmd: Vec<u8>) -> Self {
+        Self::from_pattern(Pattern::Exact(cmd))
+    }
+}
+
+impl fmt::Display for LoopbackCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pattern {
+            Pattern::Exact(cmd) => write!(f, "{}", display_payload(cmd)),
+            Pattern::Raw(data) => match str::from_utf8(data) {
+                Ok(data) => write!(f, "raw {}", data.escape_debug()),
+                Err(_) => write!(f, "raw {}", escape_bytes(data)),
+            },
+            Pattern::Glob(pattern) => write!(f, "glob {pattern}"),
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => {
+                let pattern = regex.as_str();
+                write!(f, "regex {}", &pattern[4..pattern.len() - 2])
+            }
+        }
+    }
+}
+
+/// Match a text against a glob pattern and collect what the wildcards matched.
+fn glob_match<'a>(pattern: &str, text: &'a str, captures: &mut Vec<&'a str>) -> bool {
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => text.is_empty(),
+        Some('*') => {
+            for end in (0..=text.len()).filter(|&end| text.is_char_boundary(end)) {
+                captures.push(&text[..end]);
+                if glob_match(chars.as_str(), &text[end..], captures) {
+                    return true;
+                }
+                captures.pop();
+            }
+            false
+        }
+        Some('?') => {
+            let Some(first) = text.chars().next() else {
+                return false;
+            };
+            let len = first.len_utf8();
+            captures.push(&text[..len]);
+            if glob_match(chars.as_str(), &text[len..], captures) {
+                return true;
+            }
+            captures.pop();
+            false
+        }
+        Some(c) => text
+            .strip_prefix(c)
+            .is_some_and(|text| glob_match(chars.as_str(), text, captures)),
+    }
+}
+
+/// A response that the [`LoopbackInterface`] sends from instrument to host.
+///
+/// Responses can be delayed or missing altogether, which allows to test how drivers handle slow
+/// or unresponsive instruments. Responses without a delay can simply be converted from strings.
+///
+/// ```
+/// use std::time::Duration;
+/// use instrumentrs::{InstrumentError, InstrumentInterface, LoopbackEntry, LoopbackInterface};
+///
+/// let from_host = vec!["TC".into(), "TC".into()];
+/// let from_inst = vec![LoopbackEntry::no_response(), "77.00".into()];
+/// let mut lbk = LoopbackInterface::<String>::with_commands(from_host, from_inst, "\n")
+///     .with_timeout(Duration::from_millis(10));
+///
+/// assert!(matches!(lbk.query("TC"), Err(InstrumentError::TimeoutQuery { .. })));
+/// assert_eq!("77.00", lbk.query("TC").unwrap());
+/// ```
+pub struct LoopbackEntry {
+    response: Option<Vec<u8>>,
+    delay: Duration,
+    failures: Failures,
+}
+
+impl LoopbackEntry {
+    /// Send the given response immediately.
+    pub fn response(response: &str) -> Self {
+        Self::delayed(response, Duration::ZERO)
+    }
+
+    /// Send the given response after a delay.
+    ///
+    /// If the delay is longer than the timeout of the loopback interface, the read times out with
+    /// an [`InstrumentError::Timeout`] error after the timeout elapsed. The response then arrives
+    /// at a later read, delayed by the remaining time.
+    pub fn delayed(response: &str, delay: Duration) -> Self {
+        LoopbackEntry {
+            response: Some(response.as_bytes().to_vec()),
+            delay,
+            failures: Failures::default(),
+        }
+    }
+
+    /// Do not respond at all.
+    ///
+    /// The read that expects this response blocks until the timeout of the loopback interface
+    /// elapsed and then fails with an [`InstrumentError::Timeout`] error. Subsequent reads get the
+    /// next response of the script.
+    pub fn no_response() -> Self {
+        LoopbackEntry {
+            response: None,
+            delay: Duration::ZERO,
+            failures: Failures::default(),
+        }
+    }
+
+    /// Fail the read of this response a number of times before it is sent.
+    ///
+    /// The first `times` reads that expect this response return the error that the given
+    /// function creates. Afterwards, the response is sent as usual. Together with the ordered
+    /// script, this allows to test that drivers recover from errors, e.g., that the first read
+    /// fails with an I/O error and the second one returns the real response.
+    pub fn failing(
+        mut self,
+        times: usize,
+        error: impl Fn() -> InstrumentError + Send + 'static,
+    ) -> Self {
+        self.failures = Failures::new(times, error);
+        self
+    }
+
+    /// Get the response, dropping the delay and the failures.
+    pub(crate) fn into_response(self) -> Option<Vec<u8>> {
+        self.response
+    }
+}
+
+impl From<&str> for LoopbackEntry {
+    fn from(response: &str) -> Self {
+        Self::response(response)
+    }
+}
+
+impl From<String> for LoopbackEntry {
+    fn from(response: String) -> Self {
+        Self::from(response.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for LoopbackEntry {
+    fn from(response: Vec<u8>) -> Self {
+        LoopbackEntry {
+            response: Some(response),
+            delay: Duration::ZERO,
+            failures: Failures::default(),
+        }
+    }
+}
+
+impl fmt::Display for LoopbackEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.response {
+            Some(response) => write!(f, "{}", display_payload(response)),
+            None => write!(f, "<no response>"),
+        }
+    }
+}
+
+/// Format a payload as text if it is valid UTF-8, otherwise as an escaped byte string.
+fn display_payload(bytes: &[u8]) -> String {
+    match str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => escape_bytes(bytes),
+    }
+}
+
+/// Format bytes as an escaped byte string, e.g., `b"TC\r\n"`.
+fn escape_bytes(bytes: &[u8]) -> String {
+    let escaped: String = bytes
+        .iter()
+        .flat_map(|byte| core::ascii::escape_default(*byte))
+        .map(char::from)
+        .collect();
+    format!("b\"{escaped}\"")
+}
+
+/// Describe the difference between expected and received bytes.
+fn mismatch_diff(expected: &[u8], received: &[u8]) -> String {
+    let pos = expected
+        .iter()
+        .zip(received)
+        .position(|(exp, rec)| exp != rec)
+        .unwrap_or(expected.len().min(received.len()));
+    let difference = match (expected.get(pos), received.get(pos)) {
+        (Some(exp), Some(rec)) => format!("expected {exp:#04x}, received {rec:#04x}"),
+        (Some(exp), None) => format!("expected {exp:#04x}, received end of command"),
+        (None, Some(rec)) => format!("expected end of command, received {rec:#04x}"),
+        (None, None) => "none".to_string(),
+    };
+    format!(
+        "  expected: {0}\n  received: {1}\n  first difference at byte {pos}: {difference}",
+        escape_bytes(expected),
+        escape_bytes(received)
+    )
+}
+
 /// A self-incrementing index structure that by default starts at 0 and increments whenever `next`
 /// is called.
 #[derive(Debug, Default)]
@@ -32,12 +542,15 @@ impl IncrIndex {
 /// # Example
 ///
 /// Let us build a simple instrument that would send a `"*IDN?"` command to an instrument and get
-/// back a string and then write a test for it using the [`LoopbackInterfaceString`]. The instrument itself
+/// back a string and then write a test for it using the [`LoopbackInterface`]. The instrument itself
 /// would take any interface that implements the [`InstrumentInterface`] trait.
 ///
+/// Drivers of binary protocols can use a `LoopbackInterface<Vec<u8>>` instead, which expects and
+/// responds with byte vectors, see [`LoopbackPayload`].
+///
 /// ```
 /// use std::sync::{Arc, Mutex};
-/// use instrumentrs::{InstrumentInterface, InstrumentError, LoopbackInterfaceString};
+/// use instrumentrs::{InstrumentInterface, InstrumentError, LoopbackInterface};
 ///
 /// struct MyInstrument<T: InstrumentInterface> {
 ///    interface: Arc<Mutex<T>>,
@@ -66,7 +579,7 @@ impl IncrIndex {
 ///        let terminator = "\n";  // the default terminator
 ///        
 ///        // Create the loopback interface with the expected commands.
-///        let loopback = LoopbackInterfaceString::new(host2inst, inst2host, terminator);
+///        let loopback = LoopbackInterface::new(host2inst, inst2host, terminator);
 ///
 ///        // Create the instrument
 ///        let mut inst= MyInstrument::new(loopback);
@@ -82,7 +595,7 @@ impl IncrIndex {
 ///        let inst2host = vec!["MyInstrument,1.0,1234"];
 ///
 ///        // Create the loopback interface with the expected commands.
-///        let loopback = LoopbackInterfaceString::new(host2inst, inst2host, "\n");
+///        let loopback = LoopbackInterface::new(host2inst, inst2host, "\n");
 ///
 ///        // Create the instrument
 ///        let mut inst = MyInstrument::new(loopback);
@@ -98,7 +611,7 @@ impl IncrIndex {
 ///        let inst2host = vec!["MyInstrument,1.0,1234"];
 ///
 ///        // Create the loopback interface with the expected commands.
-///        let loopback = LoopbackInterfaceString::new(host2inst, inst2host, "\n");
+///        let loopback = LoopbackInterface::new(host2inst, inst2host, "\n");
 ///
 ///        // Create the instrument
 ///        let mut inst = MyInstrument::new(loopback);
@@ -108,27 +621,46 @@ impl IncrIndex {
 ///     }
 /// }
 /// ```
-pub struct LoopbackInterfaceString {
-    from_host: Vec<String>,
-    from_inst: Vec<String>,
+#[allow(clippy::test_attr_in_doctest)] // the example shows how tests for a driver would look like
+pub struct LoopbackInterface<T: LoopbackPayload = String> {
+    from_host: Vec<LoopbackCommand>,
+    from_inst: Vec<LoopbackEntry>,
     terminator_exp: String,
     from_host_index: IncrIndex,
     from_inst_index: IncrIndex,
     curr_bytes: VecDeque<u8>,
-    terminator: String,
+    terminator: Vec<u8>,
+    responses: VecDeque<Vec<u8>>,
+    unordered: Option<Vec<LoopbackCommand>>,
+    responder: Option<Responder>,
+    timeout: Duration,
+    fragment_size: usize,
+    fragment_left: usize,
+    allow_empty_reads: bool,
+    strict_terminator: bool,
+    terminator_checked: bool,
+    payload: PhantomData<fn() -> T>,
 }
 
-impl LoopbackInterfaceString {
+/// A loopback interface for drivers that communicate using strings.
+#[deprecated(note = "use `LoopbackInterface<String>` instead")]
+pub type LoopbackInterfaceString = LoopbackInterface<String>;
+
+/// A loopback interface for drivers that communicate using bytes.
+#[deprecated(note = "use `LoopbackInterface<Vec<u8>>` instead")]
+pub type LoopbackInterfaceBytes = LoopbackInterface<Vec<u8>>;
+
+impl<T: LoopbackPayload> LoopbackInterface<T> {
     /// Create a new loopback instrument with given commands to and from instrument.
     ///
     /// The main purpose of this interface is to provide a simple loopback interface for testing of
     /// instrument drivers. To do so, you can provide a list of commands that are expected to go from
     /// the host to the instrument, and a list of commands that are expected to go from the
     /// instrument to the host. The commands are read in order. At the end, when the
-    /// [`LoopbackInterfaceString`] is dropped, a `finalize` function is called that checks if all
+    /// [`LoopbackInterface`] is dropped, a `finalize` function is called that checks if all
     /// commands that you have provided have been used. If not, a the program panics. During
     /// instrument calls, whenever something is sent to the instrument that is not expected, the
-    /// [`LoopbackInterfaceString`] will panic as well. This way, your tests can ensure easily that all
+    /// [`LoopbackInterface`] will panic as well. This way, your tests can ensure easily that all
     /// commands that you have provided are used in the correct order.
     ///
     /// # Arguments:
@@ -136,105 +668,504 @@ impl LoopbackInterfaceString {
     /// * `from_inst` - Commands from instrument to host.
     /// * `terminator_exp` - The expected terminator. This is required for every instantiation of
     ///   the loopback interface.
-    pub fn new(from_host: Vec<String>, from_inst: Vec<String>, terminator_exp: &str) -> Self {
-        LoopbackInterfaceString {
+    pub fn new(from_host: Vec<T>, from_inst: Vec<T>, terminator_exp: &str) -> Self {
+        let from_host = from_host.into_iter().map(Into::into).collect();
+        let from_inst = from_inst.into_iter().map(Into::into).collect();
+        Self::with_commands(from_host, from_inst, terminator_exp)
+    }
+
+    /// Create a new loopback instrument where the commands from host to instrument can be patterns.
+    ///
+    /// This works like [`LoopbackInterface::new`], however, every expected command is a
+    /// [`LoopbackCommand`], such that exact commands and patterns can be mixed in one script.
+    /// Responses of matched commands with a responder are read before the remaining responses in
+    /// `from_inst`. Every response is a [`LoopbackEntry`], which allows to delay responses or to
+    /// not respond at all.
+    ///
+    /// # Arguments:
+    /// * `from_host` - Commands or patterns from host to instrument.
+    /// * `from_inst` - Responses from instrument to host.
+    /// * `terminator_exp` - The expected terminator. This is required for every instantiation of
+    ///   the loopback interface.
+    pub fn with_commands(
+        from_host: Vec<LoopbackCommand>,
+        from_inst: Vec<LoopbackEntry>,
+        terminator_exp: &str,
+    ) -> Self {
+        LoopbackInterface {
             from_host,
             from_inst,
             terminator_exp: terminator_exp.to_string(), // the expected terminator
             from_host_index: IncrIndex::default(),
             from_inst_index: IncrIndex::default(),
             curr_bytes: VecDeque::new(),
-            terminator: "\n".to_string(), // default terminator, as interfaces
+            terminator: b"\n".to_vec(), // default terminator, as interfaces
+            responses: VecDeque::new(),
+            unordered: None,
+            responder: None,
+            timeout: Duration::from_secs(3), // default timeout, as interfaces
+            fragment_size: usize::MAX,
+            fragment_left: 0,
+            allow_empty_reads: false,
+            strict_terminator: false,
+            terminator_checked: false,
+            payload: PhantomData,
         }
     }
 
-    /// This command panics if not all commands in the [`LoopbackInterfaceString`] have been used.
+    /// Set the timeout of the loopback interface.
     ///
-    /// It is automatically called when the [`LoopbackInterfaceString`] is dropped, but you can also call
-    /// it manually to ensure that all commands have been used.
+    /// Reads of delayed or missing responses time out after this duration. The default is three
+    /// seconds, as for the other interfaces.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Time out reads after all responses were read instead of panicking.
+    ///
+    /// By default, reading from a loopback interface without any remaining responses panics, as
+    /// the test script is missing a response. With this flag, such reads wait for the timeout of
+    /// the interface and then fail with an [`InstrumentError::Timeout`] error, just as a real
+    /// instrument that does not respond. This allows to test how drivers handle silent
+    /// instruments, e.g., with a zero timeout such that the tests do not wait.
+    pub fn allow_empty_reads(mut self) -> Self {
+        self.allow_empty_reads = true;
+        self
+    }
+
+    /// Verify that the driver configures the expected terminator.
+    ///
+    /// By default, the expected terminator of the script is independent of the terminator that
+    /// the driver configures with `set_terminator`, such that tests pass even if a driver forgets
+    /// to configure its terminator. In strict mode, the first write to the loopback interface
+    /// panics if the configured terminator differs from the expected one. Responses are then
+    /// terminated with the configured terminator instead of the expected one.
+    ///
+    /// ```should_panic
+    /// use instrumentrs::{InstrumentInterface, LoopbackScript};
+    ///
+    /// let mut lbk = LoopbackScript::new("\r").cmd("OUT 1").build().strict_terminator();
+    ///
+    /// // the driver forgot to call `lbk.set_terminator("\r")`
+    /// lbk.write("OUT 1\r").unwrap();
+    /// ```
+    pub fn strict_terminator(mut self) -> Self {
+        self.strict_terminator = true;
+        self
+    }
+
+    /// Deliver responses in fragments of at most the given number of bytes.
+    ///
+    /// Real instruments, e.g., on a serial port, deliver responses in arbitrary chunks. With the
+    /// `"std"` feature, the loopback interface implements [`std::io::Read`], such that it can be
+    /// used as the port of an [`crate::Instrument`]. Every call to `read` then returns at most
+    /// one fragment, with the boundaries counted from the start of each response, i.e., also
+    /// inside the terminator. This allows to test the buffering of responses, e.g., in
+    /// `read_until_terminator`. The `read_exact` function of the [`InstrumentInterface`] always
+    /// fills the whole buffer and is not affected.
+    ///
+    /// # Panics
+    /// Panics if the fragment size is zero.
+    pub fn fragment_responses(mut self, max_len: usize) -> Self {
+        assert!(max_len > 0, "Fragments must contain at least one byte.");
+        self.fragment_size = max_len;
+        self
+    }
+
+    /// Answer commands with a function before checking them against the script.
+    ///
+    /// The function is called with every command that is sent to the instrument, without the
+    /// expected terminator. If it returns a response, this response (plus terminator) is read by the
+    /// host next and the command is not checked against the script. If it returns `None`, the
+    /// command must match the script as usual. This allows to simulate stateful instruments, e.g.,
+    /// a cooler that reports the setpoint it was just given.
+    ///
+    /// ```
+    /// use instrumentrs::{InstrumentInterface, LoopbackInterface};
+    ///
+    /// let mut pressure = 0.0;
+    /// let lbk = LoopbackInterface::<String>::new(vec![], vec![], "\n");
+    /// let mut lbk = lbk.with_responder(move |cmd| {
+    ///     (cmd == "PR1").then(|| {
+    ///         pressure += 1.0;
+    ///         format!("{pressure:.1}")
+    ///     })
+    /// });
+    ///
+    /// assert_eq!("1.0", lbk.query("PR1").unwrap());
+    /// assert_eq!("2.0", lbk.query("PR1").unwrap());
+    /// ```
+    pub fn with_responder(
+        mut self,
+        responder: impl FnMut(&str) -> Option<String> + Send + 'static,
+    ) -> Self {
+        self.responder = Some(Box::new(responder));
+        self
+    }
+
+    /// Create a new loopback instrument that accepts the expected commands in any order.
+    ///
+    /// Every command that is sent to the instrument is matched against the set of remaining
+    /// expected commands from host to instrument. The matched command is removed from the set and,
+    /// if it is paired with a response, this response is returned by the next read. This allows to
+    /// test drivers that are used from multiple threads, where commands legitimately interleave.
+    /// When the [`LoopbackInterface`] is dropped, `finalize` checks that all expected
+    /// commands were sent and all responses were read.
+    ///
+    /// # Arguments:
+    /// * `exchanges` - Commands from host to instrument, each paired with an optional response.
+    /// * `terminator_exp` - The expected terminator. This is required for every instantiation of
+    ///   the loopback interface.
+    pub fn unordered(exchanges: Vec<(T, Option<T>)>, terminator_exp: &str) -> Self {
+        let exchanges = exchanges
+            .into_iter()
+            .map(|(cmd, resp)| {
+                let cmd: LoopbackCommand = cmd.into();
+                match resp.and_then(|resp| LoopbackEntry::into_response(resp.into())) {
+                    Some(resp) => cmd.respond_with_bytes(move |_| resp.clone()),
+                    None => cmd,
+                }
+            })
+            .collect();
+        let mut lbk = Self::new(Vec::new(), Vec::new(), terminator_exp);
+        lbk.unordered = Some(exchanges);
+        lbk
+    }
+
+    /// This command panics if not all commands in the [`LoopbackInterface`] have been used.
+    ///
+    /// It is automatically called when the [`LoopbackInterface`] is dropped, but you can also call
+    /// it manually to ensure that all commands have been used. The panic message reports how many
+    /// commands of each script were consumed. If the thread is already panicking, e.g., because of
+    /// a mismatched command, the checks are skipped such that the original panic is reported.
     pub fn finalize(&mut self) {
-        let from_host_leftover = self.from_host.get(self.from_host_index.next());
-        let from_inst_leftover = self.from_inst.get(self.from_inst_index.next());
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+        while self
+            .from_host
+            .get(self.from_host_index.index)
+            .is_some_and(LoopbackCommand::is_satisfied)
+        {
+            self.from_host_index.next();
+        }
+        let consumed = self.consumed();
+        let from_host_leftover = self.from_host.get(self.from_host_index.index);
+        let from_inst_leftover = self.from_inst.get(self.from_inst_index.index);
         if let Some(fil) = from_host_leftover {
-            panic!("Leftover expected commands found from host to instrument: {fil}");
+            panic!("Leftover expected commands found from host to instrument: {fil} ({consumed})");
         }
         if let Some(fil) = from_inst_leftover {
-            panic!("Leftover expected commands found from instrument to host: {fil}");
+            panic!("Leftover expected commands found from instrument to host: {fil} ({consumed})");
+        }
+        if let Some(exchanges) = self.unordered.as_mut() {
+            exchanges.retain(|exp| !exp.is_satisfied());
+        }
+        if let Some(exchanges) = self.unordered.as_ref().filter(|ex| !ex.is_empty()) {
+            let remaining: Vec<String> = exchanges.iter().map(|exp| exp.to_string()).collect();
+            panic!("Leftover expected commands found from host to instrument: {remaining:?}");
+        }
+        if let Some(fil) = self.responses.front() {
+            let fil = display_payload(fil);
+            panic!("Leftover expected commands found from instrument to host: {fil} ({consumed})");
         }
     }
 
-    /// Get the next command from host to instrument, or panic.
-    fn get_next_from_host(&mut self) -> &str {
-        self.from_host
-            .get(self.from_host_index.next())
-            .expect("No more commands were expected from host to instrument.")
+    /// Describe how many commands of each script were consumed.
+    fn consumed(&self) -> String {
+        format!(
+            "consumed {0} of {1} commands from host to instrument and {2} of {3} commands from \
+             instrument to host",
+            self.from_host_index.index.min(self.from_host.len()),
+            self.from_host.len(),
+            self.from_inst_index.index.min(self.from_inst.len()),
+            self.from_inst.len()
+        )
     }
 
-    /// Get the next command from instrument to host, or panic.
-    fn get_next_from_inst(&mut self) -> &str {
-        self.from_inst
-            .get(self.from_inst_index.next())
-            .expect("No more commands were expected from instrument to host.")
+    /// Match a command against the remaining unordered exchanges and queue its response.
+    ///
+    /// Panics if the command does not match any of the remaining expected commands.
+    fn match_unordered(&mut self, cmd: &[u8]) {
+        let exchanges = self.unordered.as_mut().expect("Loopback is not unordered.");
+        let matched = exchanges.iter_mut().enumerate().find_map(|(pos, exp)| {
+            exp.respond(cmd, &self.terminator_exp)
+                .map(|resp| (pos, resp))
+        });
+        match matched {
+            Some((pos, resp)) => {
+                if exchanges[pos].record_match() {
+                    exchanges.remove(pos);
+                }
+                self.responses.extend(resp);
+            }
+            None => {
+                let remaining: Vec<String> = exchanges.iter().map(|exp| exp.to_string()).collect();
+                panic!(
+                    "Expected one of sendcmd {remaining:?}, got {0}",
+                    escape_bytes(cmd)
+                );
+            }
+        }
     }
 
-    /// Get the next command from host to instrument as a string including the terminator.
-    fn get_next_from_host_with_terminator(&mut self) -> String {
-        let cmd = self.get_next_from_host().to_string();
-        format!("{cmd}{}", self.terminator_exp)
+    /// Match a command against the next command from host to instrument, or panic.
+    ///
+    /// The panic message contains the index of the expected command in the script and, for exact
+    /// commands, where the received bytes differ from the expected ones.
+    fn match_next_from_host(&mut self, cmd: &[u8]) {
+        // skip repeated commands that were matched often enough if a different command arrives
+        let idx = loop {
+            let idx = self.from_host_index.index;
+            let skip = self.from_host.get(idx).is_some_and(|exp| {
+                exp.is_satisfied() && exp.captures(cmd, &self.terminator_exp).is_none()
+            });
+            if !skip {
+                break idx;
+            }
+            self.from_host_index.next();
+        };
+        let Some(exp) = self.from_host.get_mut(idx) else {
+            panic!(
+                "No more commands were expected from host to instrument, got command #{idx}: {0}",
+                escape_bytes(cmd)
+            );
+        };
+        if exp.record_match() {
+            self.from_host_index.next();
+        }
+        match exp.respond(cmd, &self.terminator_exp) {
+            Some(resp) => self.responses.extend(resp),
+            None => match &exp.pattern {
+                pattern @ (Pattern::Exact(exp) | Pattern::Raw(exp)) => {
+                    let mut exp = exp.clone();
+                    if let Pattern::Exact(_) = pattern {
+                        exp.extend_from_slice(self.terminator_exp.as_bytes());
+                    }
+                    panic!(
+                        "Expected sendcmd #{idx} '{0}', got '{1:?}'\n{2}",
+                        String::from_utf8_lossy(&exp),
+                        str::from_utf8(cmd),
+                        mismatch_diff(&exp, cmd)
+                    )
+                }
+                _ => panic!(
+                    "Expected sendcmd #{idx} matching '{exp}', got {0}",
+                    escape_bytes(cmd)
+                ),
+            },
+        }
     }
 
-    /// Get the next command from instrument to host as a string including the terminator.
-    fn get_next_from_inst_with_terminator(&mut self) -> String {
-        let cmd = self.get_next_from_inst().to_string();
-        format!("{cmd}{}", self.terminator_exp)
+    /// Get the next response from instrument to host, waiting for its delay, or panic.
+    ///
+    /// Missing responses and responses that are delayed longer than the timeout return an
+    /// [`InstrumentError::Timeout`] error after the timeout elapsed.
+    fn get_next_from_inst(&mut self) -> Result<Vec<u8>, InstrumentError> {
+        let timeout = self.timeout;
+        let Some(entry) = self.from_inst.get_mut(self.from_inst_index.index) else {
+            if self.allow_empty_reads {
+                self.clock().sleep(timeout);
+                return Err(InstrumentError::Timeout(timeout));
+            }
+            panic!("No more commands were expected from instrument to host.");
+        };
+        if let Some(err) = entry.failures.next() {
+            return Err(err);
+        }
+        let response = entry.response.clone();
+        let delay = entry.delay;
+        entry.delay = delay.saturating_sub(timeout);
+
+        match response {
+            Some(response) if delay <= timeout => {
+                self.from_inst_index.next();
+                self.clock().sleep(delay);
+                Ok(response)
+            }
+            response => {
+                if response.is_none() {
+                    self.from_inst_index.next();
+                }
+                self.clock().sleep(timeout);
+                Err(InstrumentError::Timeout(timeout))
+            }
+        }
     }
 
-    /// Function to read exactly one byte from the next command from the instrument.
+    /// Load the next response from the instrument if the current one was read completely.
     ///
-    /// This just panics if there are no more commands. If there are no more commands but one is
-    /// required, the panic is justified as this is a test interface.
-    fn read_one_byte(&mut self) -> u8 {
-        match self.curr_bytes.pop_front() {
-            Some(byte) => byte,
-            None => {
-                let next_cmd = self.get_next_from_inst_with_terminator();
-                self.curr_bytes = next_cmd.as_bytes().iter().copied().collect();
-                self.read_one_byte()
+    /// This just panics if there are no more commands, unless empty reads are allowed. If there
+    /// are no more commands but one is required, the panic is justified as this is a test
+    /// interface.
+    fn load_next_response(&mut self) -> Result<(), InstrumentError> {
+        if self.curr_bytes.is_empty() {
+            let mut next_cmd = match self.responses.pop_front() {
+                Some(resp) => resp,
+                None => self.get_next_from_inst()?,
+            };
+            match self.strict_terminator {
+                true => next_cmd.extend_from_slice(&self.terminator),
+                false => next_cmd.extend_from_slice(self.terminator_exp.as_bytes()),
             }
+            self.curr_bytes = next_cmd.into();
+            self.fragment_left = self.fragment_size;
         }
+        Ok(())
+    }
+
+    /// Function to read exactly one byte from the next command from the instrument.
+    fn read_one_byte(&mut self) -> Result<u8, InstrumentError> {
+        self.load_next_response()?;
+        self.fragment_left = self.fragment_left.saturating_sub(1);
+        Ok(self
+            .curr_bytes
+            .pop_front()
+            .expect("Responses always contain the terminator."))
     }
 }
 
-impl InstrumentInterface for LoopbackInterfaceString {
+impl<T: LoopbackPayload> InstrumentInterface for LoopbackInterface<T> {
+    /// Discard the remaining bytes of the current response from the instrument.
+    ///
+    /// Responses that were not started to be read yet are not discarded.
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        let drained = self.curr_bytes.len();
+        self.curr_bytes.clear();
+        Ok(drained)
+    }
+
     fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
         for byte in buf.iter_mut() {
-            *byte = self.read_one_byte();
+            *byte = self.read_one_byte()?;
+        }
+        Ok(())
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminator.as_slice()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.terminator = terminator.to_vec();
+    }
+
+    fn write_raw(&mut self, cmd: &[u8]) -> Result<(), InstrumentError> {
+        if self.strict_terminator && !self.terminator_checked {
+            self.terminator_checked = true;
+            assert!(
+                self.terminator == self.terminator_exp.as_bytes(),
+                "The driver configured the terminator {0}, but the script expects {1}.",
+                escape_bytes(&self.terminator),
+                escape_bytes(self.terminator_exp.as_bytes())
+            );
         }
+        if let Some(responder) = self.responder.as_mut() {
+            let cmd = cmd
+                .strip_suffix(self.terminator_exp.as_bytes())
+                .unwrap_or(cmd);
+            if let Some(resp) = responder(&String::from_utf8_lossy(cmd)) {
+                self.responses.push_back(resp.into_bytes());
+                return Ok(());
+            }
+        }
+        if self.unordered.is_some() {
+            self.match_unordered(cmd);
+            return Ok(());
+        }
+        let next = self.from_host.get_mut(self.from_host_index.index);
+        if let Some(err) = next.and_then(|exp| exp.failures.next()) {
+            return Err(err);
+        }
+        self.match_next_from_host(cmd);
         Ok(())
     }
 
+    #[cfg(not(feature = "std"))]
+    fn clock(&self) -> &dyn crate::Clock {
+        &StoppedClock
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: LoopbackPayload> crate::AsyncInstrumentInterface for LoopbackInterface<T> {
+    async fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        InstrumentInterface::read_exact(self, buf)
+    }
+
+    /// Get the terminator as a string slice.
+    ///
+    /// Returns an empty string if the terminator is not valid UTF-8, see `get_terminator_bytes`.
     fn get_terminator(&self) -> &str {
-        self.terminator.as_str()
+        core::str::from_utf8(InstrumentInterface::get_terminator_bytes(self)).unwrap_or_default()
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        InstrumentInterface::get_terminator_bytes(self)
     }
 
     fn set_terminator(&mut self, terminator: &str) {
-        self.terminator = terminator.to_string();
+        InstrumentInterface::set_terminator(self, terminator);
     }
 
-    fn write_raw(&mut self, cmd: &[u8]) -> Result<(), InstrumentError> {
-        let exp = self.get_next_from_host_with_terminator();
-        assert_eq!(
-            exp.as_bytes(),
-            cmd,
-            "Expected sendcmd '{0}', got '{1:?}'",
-            exp,
-            str::from_utf8(cmd)
-        );
+    async fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        InstrumentInterface::write_raw(self, data)
+    }
+}
+
+/// Read responses in fragments, such that the loopback interface can be the port of an
+/// [`crate::Instrument`].
+#[cfg(feature = "std")]
+impl<T: LoopbackPayload> std::io::Read for LoopbackInterface<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.load_next_response().map_err(|err| match err {
+            InstrumentError::Io(err) => err,
+            InstrumentError::Timeout(_) => std::io::ErrorKind::TimedOut.into(),
+            err => std::io::Error::other(err),
+        })?;
+        if self.fragment_left == 0 {
+            self.fragment_left = self.fragment_size;
+        }
+        let len = buf.len().min(self.curr_bytes.len()).min(self.fragment_left);
+        for (byte, val) in buf.iter_mut().zip(self.curr_bytes.drain(..len)) {
+            *byte = val;
+        }
+        self.fragment_left -= len;
+        Ok(len)
+    }
+}
+
+/// Write commands as a whole, such that the loopback interface can be the port of an
+/// [`crate::Instrument`].
+#[cfg(feature = "std")]
+impl<T: LoopbackPayload> std::io::Write for LoopbackInterface<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        InstrumentInterface::write_raw(self, buf).map_err(|err| match err {
+            InstrumentError::Io(err) => err,
+            err => std::io::Error::other(err),
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
         Ok(())
     }
 }
 
-impl Drop for LoopbackInterfaceString {
+impl<T: LoopbackPayload> Drop for LoopbackInterface<T> {
     fn drop(&mut self) {
         self.finalize();
     }
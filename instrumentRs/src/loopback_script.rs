@@ -0,0 +1,213 @@
+//! This module provides a builder for the scripts of the [`LoopbackInterface`].
+//!
+//! Building the commands from host to instrument and the responses from instrument to host as
+//! two separate vectors is error-prone, as it is easy to pair a command with the wrong response.
+//! The [`LoopbackScript`] builds both from one list of exchanges instead.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{LoopbackCommand, LoopbackEntry, LoopbackInterface};
+
+/// A builder for the script of a [`LoopbackInterface`].
+///
+/// Every method adds one exchange with the instrument to the script, such that commands and their
+/// responses are always correctly interleaved. The [`LoopbackInterface`] is then created
+/// with [`LoopbackScript::build`].
+///
+/// Instruments that acknowledge commands, e.g., Pfeiffer gauge controllers, are supported with
+/// [`LoopbackScript::ack`] and [`LoopbackScript::ack_then`]. The acknowledgment and the enquiry
+/// of the response must be configured with [`LoopbackScript::with_ack_enq`] or
+/// [`LoopbackScript::pfeiffer_ack_enq`] first.
+///
+/// # Example
+///
+/// ```
+/// use instrumentrs::{InstrumentInterface, LoopbackScript};
+///
+/// let mut lbk = LoopbackScript::new("\n")
+///     .query("*IDN?", "MyInstrument,1.0,1234")
+///     .cmd("OUT 1")
+///     .build();
+///
+/// assert_eq!("MyInstrument,1.0,1234", lbk.query("*IDN?").unwrap());
+/// lbk.sendcmd("OUT 1").unwrap();
+/// ```
+///
+/// A script for a Pfeiffer TPG36x, where every command is acknowledged and the response of a
+/// query is enquired with `ENQ`:
+///
+/// ```
+/// use instrumentrs::{InstrumentInterface, LoopbackScript};
+///
+/// let mut lbk = LoopbackScript::new("\r\n")
+///     .pfeiffer_ack_enq()
+///     .ack_then("PR1", "0,1.2E-5")
+///     .ack("SEN,0,1")
+///     .build();
+/// lbk.set_terminator("\r\n"); // as the driver does
+///
+/// lbk.sendcmd("PR1").unwrap();
+/// lbk.check_acknowledgment("\u{6}").unwrap();
+/// lbk.write("\u{5}").unwrap();
+/// assert_eq!("0,1.2E-5", lbk.read_until_terminator().unwrap());
+/// lbk.sendcmd("SEN,0,1").unwrap();
+/// lbk.check_acknowledgment("\u{6}").unwrap();
+/// ```
+pub struct LoopbackScript {
+    terminator: String,
+    ack_enq: Option<(String, String)>,
+    from_host: Vec<LoopbackCommand>,
+    from_inst: Vec<LoopbackEntry>,
+    last_expect: usize,
+}
+
+impl LoopbackScript {
+    /// Create a new, empty script with the given terminator.
+    ///
+    /// The terminator is appended to every command and every response, just as the terminator
+    /// of [`LoopbackInterface::new`].
+    pub fn new(terminator: &str) -> Self {
+        LoopbackScript {
+            terminator: terminator.to_string(),
+            ack_enq: None,
+            from_host: Vec::new(),
+            from_inst: Vec::new(),
+            last_expect: 0,
+        }
+    }
+
+    /// Configure the acknowledgment of commands and the enquiry of responses.
+    ///
+    /// # Arguments
+    /// * `ack` - The response that acknowledges a command, sent with the terminator.
+    /// * `enq` - The data that enquires the response of a command, sent without terminator.
+    pub fn with_ack_enq(mut self, ack: &str, enq: &str) -> Self {
+        self.ack_enq = Some((ack.to_string(), enq.to_string()));
+        self
+    }
+
+    /// Configure the acknowledgment (`ACK`) and enquiry (`ENQ`) of Pfeiffer gauge controllers.
+    pub fn pfeiffer_ack_enq(self) -> Self {
+        self.with_ack_enq("\u{6}", "\u{5}")
+    }
+
+    /// Expect a command that the instrument does not respond to.
+    pub fn cmd(self, cmd: &str) -> Self {
+        self.expect(cmd)
+    }
+
+    /// Expect a query and respond to it.
+    pub fn query(self, cmd: &str, response: &str) -> Self {
+        self.expect(cmd).respond(response)
+    }
+
+    /// Expect a command that the instrument acknowledges.
+    ///
+    /// # Panics
+    /// Panics if the acknowledgment was not configured.
+    pub fn ack(self, cmd: &str) -> Self {
+        let (ack, _) = self.get_ack_enq();
+        self.expect(cmd).respond(ack)
+    }
+
+    /// Expect a command that the instrument acknowledges, then the enquiry and respond to it.
+    ///
+    /// # Panics
+    /// Panics if the acknowledgment and enquiry were not configured.
+    pub fn ack_then(self, cmd: &str, response: &str) -> Self {
+        let (ack, enq) = self.get_ack_enq();
+        self.expect(cmd)
+            .respond(ack)
+            .expect(LoopbackCommand::raw(&enq))
+            .respond(response)
+    }
+
+    /// Expect the given command, which can also be a pattern.
+    pub fn expect(mut self, cmd: impl Into<LoopbackCommand>) -> Self {
+        self.from_host.push(cmd.into());
+        self.last_expect = self.from_inst.len();
+        self
+    }
+
+    /// Repeat the last exchange the given number of times in a row.
+    ///
+    /// The last expected command and its response, if any, are expected `times` times before the
+    /// rest of the script. Only the text of the response is repeated, delays and failures are not.
+    ///
+    /// # Panics
+    /// Panics if `times` is zero, if no command was expected yet, or if the last command has more
+    /// than one response.
+    pub fn times(self, times: usize) -> Self {
+        self.repeat_last(|cmd| cmd.times(times))
+    }
+
+    /// Repeat the last exchange any number of times in a row, including not at all.
+    ///
+    /// This is useful to test polling loops: the last expected command is answered with its
+    /// response until a different command arrives. Only the text of the response is repeated,
+    /// delays and failures are not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use instrumentrs::{InstrumentInterface, LoopbackScript};
+    ///
+    /// let mut lbk = LoopbackScript::new("\n")
+    ///     .query("TEMP?", "42.0")
+    ///     .forever()
+    ///     .cmd("STOP")
+    ///     .build();
+    ///
+    /// for _ in 0..10 {
+    ///     assert_eq!("42.0", lbk.query("TEMP?").unwrap());
+    /// }
+    /// lbk.sendcmd("STOP").unwrap();
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if no command was expected yet, or if the last command has more than one response.
+    pub fn forever(self) -> Self {
+        self.repeat_last(LoopbackCommand::forever)
+    }
+
+    /// Respond with the given response, which can also be delayed or missing.
+    pub fn respond(mut self, response: impl Into<LoopbackEntry>) -> Self {
+        self.from_inst.push(response.into());
+        self
+    }
+
+    /// Create the [`LoopbackInterface`] with this script.
+    pub fn build(self) -> LoopbackInterface<String> {
+        LoopbackInterface::with_commands(self.from_host, self.from_inst, &self.terminator)
+    }
+
+    /// Move the response of the last command into its responder and set its repetition.
+    fn repeat_last(mut self, repeat: impl FnOnce(LoopbackCommand) -> LoopbackCommand) -> Self {
+        let cmd = self
+            .from_host
+            .pop()
+            .expect("A command must be expected before it can be repeated.");
+        let mut responses = self.from_inst.split_off(self.last_expect);
+        assert!(
+            responses.len() <= 1,
+            "Only commands with at most one response can be repeated."
+        );
+        let cmd = match responses.pop().and_then(LoopbackEntry::into_response) {
+            Some(resp) => cmd.respond_with_bytes(move |_| resp.clone()),
+            None => cmd,
+        };
+        self.from_host.push(repeat(cmd));
+        self
+    }
+
+    /// Get the configured acknowledgment and enquiry, or panic.
+    fn get_ack_enq(&self) -> (String, String) {
+        self.ack_enq.clone().expect(
+            "Acknowledgment and enquiry must be configured before they are used, see \
+             `LoopbackScript::with_ack_enq`.",
+        )
+    }
+}
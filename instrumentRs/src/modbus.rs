@@ -0,0 +1,431 @@
+//! This module provides Modbus RTU and Modbus TCP clients on top of instrument interfaces.
+//!
+//! Modbus requests are sent with the raw byte methods of any [`InstrumentInterface`], such that
+//! the [`ModbusRtuClient`] and the [`ModbusTcpClient`] work with serial and TCP/IP interfaces
+//! alike. Both clients implement the [`ModbusClient`] trait, which provides the functions to read
+//! and write registers. Exception responses of the server are returned as an
+//! [`InstrumentError::Modbus`] error that contains the [`ModbusException`].
+
+use alloc::{format, vec, vec::Vec};
+use core::fmt;
+
+use crate::{InstrumentError, InstrumentInterface};
+
+/// Function code to read holding registers.
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+/// Function code to read input registers.
+const READ_INPUT_REGISTERS: u8 = 0x04;
+/// Function code to write a single register.
+const WRITE_SINGLE_REGISTER: u8 = 0x06;
+/// Function code to write multiple registers.
+const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+/// Bit that is set in the function code of an exception response.
+const EXCEPTION_BIT: u8 = 0x80;
+
+/// Maximum number of registers that can be read with one request.
+const MAX_READ_COUNT: u16 = 125;
+/// Maximum number of registers that can be written with one request.
+const MAX_WRITE_COUNT: u16 = 123;
+
+/// An exception code that a Modbus server returned instead of a regular response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModbusException {
+    /// The function code is not supported by the server.
+    IllegalFunction,
+    /// The data address is not valid for the server.
+    IllegalDataAddress,
+    /// A value in the request is not valid for the server.
+    IllegalDataValue,
+    /// An unrecoverable error occurred in the server.
+    ServerDeviceFailure,
+    /// The server accepted the request, but needs a long time to process it.
+    Acknowledge,
+    /// The server is busy processing a long-duration command.
+    ServerDeviceBusy,
+    /// The server detected a parity error in its memory.
+    MemoryParityError,
+    /// A gateway could not allocate a path to the target device.
+    GatewayPathUnavailable,
+    /// A gateway did not get a response from the target device.
+    GatewayTargetFailedToRespond,
+    /// An exception code that is not defined by the Modbus specification.
+    Other(u8),
+}
+
+impl From<u8> for ModbusException {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => ModbusException::IllegalFunction,
+            0x02 => ModbusException::IllegalDataAddress,
+            0x03 => ModbusException::IllegalDataValue,
+            0x04 => ModbusException::ServerDeviceFailure,
+            0x05 => ModbusException::Acknowledge,
+            0x06 => ModbusException::ServerDeviceBusy,
+            0x08 => ModbusException::MemoryParityError,
+            0x0A => ModbusException::GatewayPathUnavailable,
+            0x0B => ModbusException::GatewayTargetFailedToRespond,
+            code => ModbusException::Other(code),
+        }
+    }
+}
+
+impl fmt::Display for ModbusException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModbusException::IllegalFunction => write!(f, "Illegal function"),
+            ModbusException::IllegalDataAddress => write!(f, "Illegal data address"),
+            ModbusException::IllegalDataValue => write!(f, "Illegal data value"),
+            ModbusException::ServerDeviceFailure => write!(f, "Server device failure"),
+            ModbusException::Acknowledge => write!(f, "Acknowledge"),
+            ModbusException::ServerDeviceBusy => write!(f, "Server device busy"),
+            ModbusException::MemoryParityError => write!(f, "Memory parity error"),
+            ModbusException::GatewayPathUnavailable => write!(f, "Gateway path unavailable"),
+            ModbusException::GatewayTargetFailedToRespond => {
+                write!(f, "Gateway target device failed to respond")
+            }
+            ModbusException::Other(code) => write!(f, "Unknown exception code {code:#04X}"),
+        }
+    }
+}
+
+/// A Modbus client that reads and writes registers of a Modbus server.
+///
+/// The register functions are provided by this trait and use `transact` to send a request and
+/// receive the response, which is implemented by the [`ModbusRtuClient`] and the
+/// [`ModbusTcpClient`] for their respective framing.
+pub trait ModbusClient {
+    /// Send a request and return the response.
+    ///
+    /// The request and the response are protocol data units (PDUs), i.e., they start with the
+    /// function code and do not contain any addressing or checksum. Exception responses are
+    /// returned as [`InstrumentError::Modbus`] error.
+    ///
+    /// # Arguments
+    /// * `request` - The request PDU.
+    fn transact(&mut self, request: &[u8]) -> Result<Vec<u8>, InstrumentError>;
+
+    /// Read holding registers (function code `0x03`).
+    ///
+    /// # Arguments
+    /// * `address` - Address of the first register.
+    /// * `count` - Number of registers to read, between 1 and 125.
+    fn read_holding_registers(
+        &mut self,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, InstrumentError> {
+        read_registers(self, READ_HOLDING_REGISTERS, address, count)
+    }
+
+    /// Read input registers (function code `0x04`).
+    ///
+    /// # Arguments
+    /// * `address` - Address of the first register.
+    /// * `count` - Number of registers to read, between 1 and 125.
+    fn read_input_registers(
+        &mut self,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, InstrumentError> {
+        read_registers(self, READ_INPUT_REGISTERS, address, count)
+    }
+
+    /// Write a single register (function code `0x06`).
+    ///
+    /// # Arguments
+    /// * `address` - Address of the register.
+    /// * `value` - Value to write.
+    fn write_single_register(&mut self, address: u16, value: u16) -> Result<(), InstrumentError> {
+        let mut request = vec![WRITE_SINGLE_REGISTER];
+        request.extend(address.to_be_bytes());
+        request.extend(value.to_be_bytes());
+        let response = self.transact(&request)?;
+        if response != request {
+            return Err(invalid_response(&response));
+        }
+        Ok(())
+    }
+
+    /// Write multiple registers (function code `0x10`).
+    ///
+    /// # Arguments
+    /// * `address` - Address of the first register.
+    /// * `values` - Values to write, between 1 and 123 registers.
+    fn write_multiple_registers(
+        &mut self,
+        address: u16,
+        values: &[u16],
+    ) -> Result<(), InstrumentError> {
+        let count = check_count(values.len(), MAX_WRITE_COUNT)?;
+        let mut request = vec![WRITE_MULTIPLE_REGISTERS];
+        request.extend(address.to_be_bytes());
+        request.extend(count.to_be_bytes());
+        request.push((2 * count) as u8);
+        for value in values {
+            request.extend(value.to_be_bytes());
+        }
+        let response = self.transact(&request)?;
+        if response != request[..5] {
+            return Err(invalid_response(&response));
+        }
+        Ok(())
+    }
+}
+
+/// A Modbus RTU client, e.g., for a serial interface.
+///
+/// Every frame consists of the unit ID of the server, the PDU, and a CRC16 checksum.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{ModbusClient, ModbusRtuClient, TcpIpInterface};
+///
+/// let inst_interface = TcpIpInterface::simple("192.168.1.10:4001").unwrap();
+/// let mut client = ModbusRtuClient::new(inst_interface, 1);
+/// let registers = client.read_holding_registers(0x0000, 10).unwrap();
+/// ```
+pub struct ModbusRtuClient<T: InstrumentInterface> {
+    interface: T,
+    unit_id: u8,
+}
+
+impl<T: InstrumentInterface> ModbusRtuClient<T> {
+    /// Create a new Modbus RTU client.
+    ///
+    /// # Arguments
+    /// * `interface` - The interface to the Modbus server.
+    /// * `unit_id` - The unit ID of the server.
+    pub fn new(interface: T, unit_id: u8) -> Self {
+        ModbusRtuClient { interface, unit_id }
+    }
+
+    /// Get the unit ID of the server.
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    /// Set the unit ID of the server.
+    pub fn set_unit_id(&mut self, unit_id: u8) {
+        self.unit_id = unit_id;
+    }
+
+    /// Consume the client and return the interface.
+    pub fn into_inner(self) -> T {
+        self.interface
+    }
+}
+
+impl<T: InstrumentInterface> ModbusClient for ModbusRtuClient<T> {
+    fn transact(&mut self, request: &[u8]) -> Result<Vec<u8>, InstrumentError> {
+        let mut frame = vec![self.unit_id];
+        frame.extend_from_slice(request);
+        frame.extend(crc16(&frame).to_le_bytes());
+        self.interface.write_raw(&frame)?;
+
+        // unit ID and function code, then the length depends on the function
+        let mut response = vec![0u8; 2];
+        self.interface.read_exact(&mut response)?;
+        let len = if response[1] & EXCEPTION_BIT != 0 {
+            1
+        } else {
+            match response[1] {
+                READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS => {
+                    let mut byte_count = [0u8];
+                    self.interface.read_exact(&mut byte_count)?;
+                    response.push(byte_count[0]);
+                    byte_count[0] as usize
+                }
+                WRITE_SINGLE_REGISTER | WRITE_MULTIPLE_REGISTERS => 4,
+                _ => return Err(invalid_response(&response)),
+            }
+        };
+        let mut rest = vec![0u8; len + 2];
+        self.interface.read_exact(&mut rest)?;
+        response.extend(rest);
+
+        let (data, crc) = response.split_at(response.len() - 2);
+        if crc16(data).to_le_bytes() != crc {
+            return Err(InstrumentError::ResponseParseError(format!(
+                "{response:02X?} (CRC mismatch)"
+            )));
+        }
+        if data[0] != self.unit_id {
+            return Err(invalid_response(&response));
+        }
+        check_pdu(request, data[1..].to_vec())
+    }
+}
+
+/// A Modbus TCP client.
+///
+/// Every frame consists of an MBAP header, which contains a transaction ID, the length of the
+/// frame, and the unit ID of the server, followed by the PDU.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{ModbusClient, ModbusTcpClient, TcpIpInterface};
+///
+/// let inst_interface = TcpIpInterface::simple("192.168.1.10:502").unwrap();
+/// let mut client = ModbusTcpClient::new(inst_interface, 1);
+/// client.write_single_register(0x0010, 42).unwrap();
+/// ```
+pub struct ModbusTcpClient<T: InstrumentInterface> {
+    interface: T,
+    unit_id: u8,
+    transaction_id: u16,
+}
+
+impl<T: InstrumentInterface> ModbusTcpClient<T> {
+    /// Create a new Modbus TCP client.
+    ///
+    /// # Arguments
+    /// * `interface` - The interface to the Modbus server.
+    /// * `unit_id` - The unit ID of the server, usually `0xFF` or `1` if the server is not a
+    ///   gateway.
+    pub fn new(interface: T, unit_id: u8) -> Self {
+        ModbusTcpClient {
+            interface,
+            unit_id,
+            transaction_id: 0,
+        }
+    }
+
+    /// Get the unit ID of the server.
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    /// Set the unit ID of the server.
+    pub fn set_unit_id(&mut self, unit_id: u8) {
+        self.unit_id = unit_id;
+    }
+
+    /// Consume the client and return the interface.
+    pub fn into_inner(self) -> T {
+        self.interface
+    }
+}
+
+impl<T: InstrumentInterface> ModbusClient for ModbusTcpClient<T> {
+    fn transact(&mut self, request: &[u8]) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let mut frame = Vec::with_capacity(request.len() + 7);
+        frame.extend(self.transaction_id.to_be_bytes());
+        frame.extend([0x00, 0x00]); // protocol ID
+        frame.extend((request.len() as u16 + 1).to_be_bytes());
+        frame.push(self.unit_id);
+        frame.extend_from_slice(request);
+        self.interface.write_raw(&frame)?;
+
+        let mut header = [0u8; 7];
+        self.interface.read_exact(&mut header)?;
+        let len = u16::from_be_bytes([header[4], header[5]]) as usize;
+        if header[..2] != self.transaction_id.to_be_bytes()
+            || header[2..4] != [0x00, 0x00]
+            || header[6] != self.unit_id
+            || len < 2
+        {
+            // discard the rest of the frame, such that it does not end up in the next response
+            let mut rest = vec![0u8; len.saturating_sub(1)];
+            self.interface.read_exact(&mut rest)?;
+            return Err(invalid_response(&header));
+        }
+        let mut response = vec![0u8; len - 1];
+        self.interface.read_exact(&mut response)?;
+        check_pdu(request, response)
+    }
+}
+
+/// Read registers with the given function code.
+fn read_registers<C: ModbusClient + ?Sized>(
+    client: &mut C,
+    function: u8,
+    address: u16,
+    count: u16,
+) -> Result<Vec<u16>, InstrumentError> {
+    check_count(count as usize, MAX_READ_COUNT)?;
+    let mut request = vec![function];
+    request.extend(address.to_be_bytes());
+    request.extend(count.to_be_bytes());
+    let response = client.transact(&request)?;
+    if response.len() != 2 + 2 * count as usize || response[1] as usize != 2 * count as usize {
+        return Err(invalid_response(&response));
+    }
+    Ok(response[2..]
+        .chunks_exact(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]))
+        .collect())
+}
+
+/// Check that a number of registers is between 1 and the given maximum.
+fn check_count(count: usize, max: u16) -> Result<u16, InstrumentError> {
+    if count == 0 || count > max as usize {
+        return Err(InstrumentError::IntValueOutOfRange {
+            value: count as i64,
+            min: 1,
+            max: max as i64,
+        });
+    }
+    Ok(count as u16)
+}
+
+/// Check the function code of a response PDU and convert exception responses into errors.
+fn check_pdu(request: &[u8], response: Vec<u8>) -> Result<Vec<u8>, InstrumentError> {
+    match response.first() {
+        Some(&function) if function == request[0] => Ok(response),
+        Some(&function) if function == request[0] | EXCEPTION_BIT && response.len() == 2 => {
+            Err(InstrumentError::Modbus {
+                function: request[0],
+                exception: response[1].into(),
+            })
+        }
+        _ => Err(invalid_response(&response)),
+    }
+}
+
+/// Error for a response that does not match the request.
+fn invalid_response(response: &[u8]) -> InstrumentError {
+    InstrumentError::ResponseParseError(format!("{response:02X?}"))
+}
+
+/// Calculate the CRC16 checksum of a Modbus RTU frame.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A], 0xCDC5)]
+    #[case(&[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03], 0x8776)]
+    #[case(&[], 0xFFFF)]
+    fn test_crc16(#[case] data: &[u8], #[case] exp: u16) {
+        assert_eq!(exp, crc16(data));
+    }
+
+    #[rstest]
+    #[case(0x02, ModbusException::IllegalDataAddress)]
+    #[case(0x0B, ModbusException::GatewayTargetFailedToRespond)]
+    #[case(0x42, ModbusException::Other(0x42))]
+    fn test_exception_from_code(#[case] code: u8, #[case] exp: ModbusException) {
+        assert_eq!(exp, ModbusException::from(code));
+    }
+}
@@ -0,0 +1,208 @@
+//! This module provides a background poller that caches the latest value of an instrument.
+//!
+//! Dashboards and user interfaces often want to show the latest reading of an instrument at a
+//! fixed rate without every thread talking to the instrument itself. The [`Poller`] owns a driver
+//! or channel, queries it in a background thread at a fixed interval, and caches the latest
+//! value. Updates and errors can additionally be received with [`Poller::subscribe`].
+
+#![cfg(feature = "std")]
+
+use std::{
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::InstrumentError;
+
+/// An update of a [`Poller`] that is sent to all subscribers.
+#[derive(Debug, Clone)]
+pub enum PollEvent<V> {
+    /// A new value was read at the given instant.
+    Value(Instant, V),
+    /// Reading a value failed.
+    Error {
+        /// The error that was returned.
+        error: Arc<InstrumentError>,
+        /// Number of consecutive errors, including this one.
+        consecutive: usize,
+    },
+}
+
+/// State that is shared between the [`Poller`] and its thread.
+struct PollState<V> {
+    latest: Mutex<Option<(Instant, V)>>,
+    consecutive_errors: AtomicUsize,
+    subscribers: Mutex<Vec<Sender<PollEvent<V>>>>,
+}
+
+impl<V: Clone> PollState<V> {
+    /// Store a new value and send it to all subscribers.
+    fn update(&self, value: V) {
+        let now = Instant::now();
+        self.consecutive_errors.store(0, Ordering::SeqCst);
+        *self.latest.lock().unwrap_or_else(PoisonError::into_inner) = Some((now, value.clone()));
+        self.publish(PollEvent::Value(now, value));
+    }
+
+    /// Count an error and send it to all subscribers.
+    fn error(&self, error: InstrumentError) {
+        let consecutive = self.consecutive_errors.fetch_add(1, Ordering::SeqCst) + 1;
+        self.publish(PollEvent::Error {
+            error: Arc::new(error),
+            consecutive,
+        });
+    }
+
+    /// Send an event to all subscribers and forget the ones that went away.
+    fn publish(&self, event: PollEvent<V>) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+/// A background poller that repeatedly reads a value from an instrument and caches it.
+///
+/// The poller takes ownership of a target, e.g., a cloned driver or channel, and calls the given
+/// closure with it in a background thread once per interval. The latest successful value is
+/// available with [`Poller::latest`], and failed reads are counted until the next successful one,
+/// see [`Poller::consecutive_errors`]. Polling continues after errors.
+///
+/// When the poller is dropped or stopped with [`Poller::stop`], the thread finishes the read
+/// that is currently running, if any, and exits. Dropping the poller waits for this.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use instrumentrs::{InstrumentInterface, Poller, TcpIpInterface};
+///
+/// let inst_interface = TcpIpInterface::simple("192.168.1.10:8000").unwrap();
+/// let poller = Poller::new(inst_interface, Duration::from_millis(100), |intf| {
+///     intf.query("TEMP?")
+/// });
+///
+/// // Any thread can now get the latest value without talking to the instrument.
+/// if let Some((time, temperature)) = poller.latest() {
+///     println!("{temperature} read {:?} ago", time.elapsed());
+/// }
+/// ```
+pub struct Poller<T, V> {
+    state: Arc<PollState<V>>,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<T>>,
+}
+
+impl<T: Send + 'static, V: Clone + Send + 'static> Poller<T, V> {
+    /// Create a new poller and start polling in a background thread.
+    ///
+    /// The first read happens immediately. If a read takes longer than the interval, the next
+    /// one starts right after it.
+    ///
+    /// # Arguments
+    /// * `target` - The driver, channel, or interface to poll.
+    /// * `interval` - The interval between the starts of two reads.
+    /// * `f` - The closure that reads a value from the target.
+    pub fn new<F>(target: T, interval: Duration, mut f: F) -> Self
+    where
+        F: FnMut(&mut T) -> Result<V, InstrumentError> + Send + 'static,
+    {
+        let state = Arc::new(PollState {
+            latest: Mutex::new(None),
+            consecutive_errors: AtomicUsize::new(0),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        let thread_state = Arc::clone(&state);
+        let (stop, handle) = spawn_periodic(target, interval, move |target| match f(target) {
+            Ok(value) => thread_state.update(value),
+            Err(err) => thread_state.error(err),
+        });
+        Poller {
+            state,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Get the latest value and the instant at which it was read.
+    ///
+    /// Returns `None` if no value was read successfully yet.
+    pub fn latest(&self) -> Option<(Instant, V)> {
+        self.state
+            .latest
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Get the number of reads that failed since the last successful one.
+    pub fn consecutive_errors(&self) -> usize {
+        self.state.consecutive_errors.load(Ordering::SeqCst)
+    }
+
+    /// Subscribe to all values and errors that are read from now on.
+    ///
+    /// Every subscriber receives all events. Dropping the receiver ends the subscription.
+    pub fn subscribe(&self) -> Receiver<PollEvent<V>> {
+        let (tx, rx) = mpsc::channel();
+        self.state
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(tx);
+        rx
+    }
+
+    /// Stop polling and return the target.
+    ///
+    /// Returns `None` if the closure panicked, in which case the target is lost.
+    pub fn stop(mut self) -> Option<T> {
+        self.stop.take();
+        self.handle.take().and_then(|handle| handle.join().ok())
+    }
+}
+
+impl<T, V> Drop for Poller<T, V> {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Call `f` with the target in a new thread once per interval until the returned sender is
+/// dropped, then return the target from the thread.
+///
+/// If a call takes longer than the interval, the next one starts right after it.
+pub(crate) fn spawn_periodic<T, F>(
+    mut target: T,
+    interval: Duration,
+    mut f: F,
+) -> (Sender<()>, JoinHandle<T>)
+where
+    T: Send + 'static,
+    F: FnMut(&mut T) + Send + 'static,
+{
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        loop {
+            let start = Instant::now();
+            f(&mut target);
+            // waiting on the channel returns immediately when the sender is dropped
+            match stop_rx.recv_timeout(interval.saturating_sub(start.elapsed())) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => break,
+            }
+        }
+        target
+    });
+    (stop_tx, handle)
+}
@@ -0,0 +1,260 @@
+//! This module provides a wrapper to record the traffic of an instrument interface.
+//!
+//! The [`RecordingInterface`] forwards all calls to an inner interface and keeps a [`Transcript`]
+//! of all bytes that were written to and read from the instrument. The transcript can be saved as
+//! JSON or TOML file, e.g., to turn a session with real hardware into a test.
+//!
+//! This module is only available when the `recording` feature is enabled.
+//!
+//! # Transcript schema
+//!
+//! A transcript contains a list of `entries`. Every entry has the following fields:
+//!
+//! - `time`: Time in seconds since the recording was started, as a float.
+//! - `direction`: Either `"write"` (host to instrument) or `"read"` (instrument to host).
+//! - `text` or `bytes`: The data that was transferred, including terminators. If the data is valid
+//!   UTF-8, it is stored as a string in `text`, otherwise as a list of integers in `bytes`.
+//!
+//! Every call to write data creates a new entry, consecutive reads are combined into one entry.
+//! A transcript with a query to an instrument would look as following in TOML format:
+//!
+//! ```toml
+//! [[entries]]
+//! time = 0.0
+//! direction = "write"
+//! text = "*IDN?\n"
+//!
+//! [[entries]]
+//! time = 0.0123
+//! direction = "read"
+//! text = "MyInstrument,1.0,1234\n"
+//! ```
+
+#![cfg(feature = "recording")]
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{InstrumentError, InstrumentInterface};
+
+/// The direction of the traffic in a [`TranscriptEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Data written from the host to the instrument.
+    Write,
+    /// Data read by the host from the instrument.
+    Read,
+}
+
+/// A single entry of a [`Transcript`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "EntryRepr", into = "EntryRepr")]
+pub struct TranscriptEntry {
+    /// Time in seconds since the recording was started.
+    pub time: f64,
+    /// Direction of the traffic.
+    pub direction: Direction,
+    /// The data that was transferred, including terminators.
+    pub data: Vec<u8>,
+}
+
+/// Serialized representation of a [`TranscriptEntry`].
+#[derive(Serialize, Deserialize)]
+struct EntryRepr {
+    time: f64,
+    direction: Direction,
+    #[serde(flatten)]
+    data: DataRepr,
+}
+
+/// Serialized representation of the data: text if it is valid UTF-8, bytes otherwise.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum DataRepr {
+    Text { text: String },
+    Bytes { bytes: Vec<u8> },
+}
+
+impl From<EntryRepr> for TranscriptEntry {
+    fn from(repr: EntryRepr) -> Self {
+        let data = match repr.data {
+            DataRepr::Text { text } => text.into_bytes(),
+            DataRepr::Bytes { bytes } => bytes,
+        };
+        TranscriptEntry {
+            time: repr.time,
+            direction: repr.direction,
+            data,
+        }
+    }
+}
+
+impl From<TranscriptEntry> for EntryRepr {
+    fn from(entry: TranscriptEntry) -> Self {
+        let data = match String::from_utf8(entry.data) {
+            Ok(text) => DataRepr::Text { text },
+            Err(err) => DataRepr::Bytes {
+                bytes: err.into_bytes(),
+            },
+        };
+        EntryRepr {
+            time: entry.time,
+            direction: entry.direction,
+            data,
+        }
+    }
+}
+
+/// A transcript of the traffic with an instrument.
+///
+/// See the [module documentation](crate::recording) for the schema of the serialized transcript.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    /// All entries of the transcript in chronological order.
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    /// Serialize the transcript to a JSON string.
+    pub fn to_json(&self) -> Result<String, InstrumentError> {
+        serde_json::to_string_pretty(self).map_err(|e| InstrumentError::Transcript(e.to_string()))
+    }
+
+    /// Serialize the transcript to a TOML string.
+    pub fn to_toml(&self) -> Result<String, InstrumentError> {
+        toml::to_string(self).map_err(|e| InstrumentError::Transcript(e.to_string()))
+    }
+
+    /// Deserialize a transcript from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, InstrumentError> {
+        serde_json::from_str(json).map_err(|e| InstrumentError::Transcript(e.to_string()))
+    }
+
+    /// Deserialize a transcript from a TOML string.
+    pub fn from_toml(toml: &str) -> Result<Self, InstrumentError> {
+        toml::from_str(toml).map_err(|e| InstrumentError::Transcript(e.to_string()))
+    }
+}
+
+/// An interface that records all traffic of an inner interface.
+///
+/// All calls are forwarded to the inner interface. Bytes that are written to or read from the
+/// inner interface are appended to a [`Transcript`], exactly as they were transferred. Data that
+/// is discarded with [`InstrumentInterface::drain_input`] is not recorded.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{InstrumentInterface, RecordingInterface, TcpIpInterface};
+///
+/// let inst_interface = TcpIpInterface::simple("192.168.1.10:8000").unwrap();
+/// let mut recorder = RecordingInterface::new(inst_interface);
+/// let name = recorder.query("*IDN?").unwrap();
+/// recorder.save_toml("idn_session.toml").unwrap();
+/// ```
+pub struct RecordingInterface<T: InstrumentInterface> {
+    inner: T,
+    start: Instant,
+    transcript: Transcript,
+}
+
+impl<T: InstrumentInterface> RecordingInterface<T> {
+    /// Create a new recording interface that wraps the given interface.
+    ///
+    /// The time of all entries is measured from the moment this function is called.
+    pub fn new(inner: T) -> Self {
+        RecordingInterface {
+            inner,
+            start: Instant::now(),
+            transcript: Transcript::default(),
+        }
+    }
+
+    /// Get the transcript that was recorded so far.
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    /// Consume the recording interface and return the inner interface.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Save the transcript that was recorded so far as a JSON file.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file to write. An existing file is overwritten.
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<(), InstrumentError> {
+        fs::write(path, self.transcript.to_json()?)?;
+        Ok(())
+    }
+
+    /// Save the transcript that was recorded so far as a TOML file.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file to write. An existing file is overwritten.
+    pub fn save_toml<P: AsRef<Path>>(&self, path: P) -> Result<(), InstrumentError> {
+        fs::write(path, self.transcript.to_toml()?)?;
+        Ok(())
+    }
+
+    /// Append data to the transcript. Consecutive reads are combined into one entry.
+    fn record(&mut self, direction: Direction, data: &[u8]) {
+        if direction == Direction::Read
+            && let Some(last) = self.transcript.entries.last_mut()
+            && last.direction == Direction::Read
+        {
+            last.data.extend_from_slice(data);
+            return;
+        }
+        self.transcript.entries.push(TranscriptEntry {
+            time: self.start.elapsed().as_secs_f64(),
+            direction,
+            data: data.to_vec(),
+        });
+    }
+}
+
+impl<T: InstrumentInterface> InstrumentInterface for RecordingInterface<T> {
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        self.inner.drain_input()
+    }
+
+    fn is_alive(&mut self) -> bool {
+        self.inner.is_alive()
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        self.inner.read_exact(buf)?;
+        self.record(Direction::Read, buf);
+        Ok(())
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.inner.get_terminator_bytes()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.inner.set_terminator_bytes(terminator);
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.inner.get_timeout()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.inner.set_timeout(timeout);
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        self.inner.write_raw(data)?;
+        self.record(Direction::Write, data);
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+//! This module provides an interface that replays recorded transcripts for testing purposes.
+//!
+//! The [`ReplayInterface`] is the counterpart of the [`crate::RecordingInterface`]: It loads a
+//! [`Transcript`] and behaves like the [`crate::LoopbackInterface`], i.e., it checks that
+//! all data written matches the recording and returns the recorded responses in order.
+//!
+//! This module is only available when the `recording` feature is enabled.
+
+#![cfg(feature = "recording")]
+
+use std::{collections::VecDeque, fs, path::Path};
+
+use crate::{Direction, InstrumentError, InstrumentInterface, Transcript};
+
+/// An interface that replays a recorded [`Transcript`] to test instrument drivers.
+///
+/// Every write to the interface must match the next recorded write exactly, including the
+/// terminator. Reads return the recorded responses in order. If the data written does not match
+/// the recording, the interface panics and shows the expected and the received data. When the
+/// [`ReplayInterface`] is dropped, [`ReplayInterface::finalize`] checks that the whole transcript
+/// was used.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{InstrumentInterface, ReplayInterface};
+///
+/// // Transcript that was recorded with a `RecordingInterface` while querying "*IDN?".
+/// let mut replay = ReplayInterface::from_file("idn_session.toml").unwrap();
+/// assert_eq!("MyInstrument,1.0,1234", replay.query("*IDN?").unwrap());
+/// ```
+pub struct ReplayInterface {
+    transcript: Transcript,
+    index: usize,
+    curr_bytes: VecDeque<u8>,
+    terminator: Vec<u8>,
+}
+
+impl ReplayInterface {
+    /// Create a new replay interface from a transcript.
+    pub fn new(transcript: Transcript) -> Self {
+        ReplayInterface {
+            transcript,
+            index: 0,
+            curr_bytes: VecDeque::new(),
+            terminator: b"\n".to_vec(), // default terminator, as interfaces
+        }
+    }
+
+    /// Load a transcript from a JSON or TOML file and create a new replay interface.
+    ///
+    /// The format is selected by the file extension, which must be `json` or `toml`.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the transcript file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, InstrumentError> {
+        let path = path.as_ref();
+        let transcript = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Transcript::from_json(&fs::read_to_string(path)?)?,
+            Some("toml") => Transcript::from_toml(&fs::read_to_string(path)?)?,
+            _ => {
+                return Err(InstrumentError::InvalidArgument(format!(
+                    "Transcript file {} must have a json or toml extension.",
+                    path.display()
+                )));
+            }
+        };
+        Ok(Self::new(transcript))
+    }
+
+    /// This command panics if not all entries of the transcript have been used.
+    ///
+    /// It is automatically called when the [`ReplayInterface`] is dropped, but you can also call
+    /// it manually to ensure that the whole transcript has been used.
+    pub fn finalize(&mut self) {
+        if let Some(entry) = self.transcript.entries.get(self.index) {
+            let direction = match entry.direction {
+                Direction::Write => "from host to instrument",
+                Direction::Read => "from instrument to host",
+            };
+            panic!(
+                "Leftover entry {} of transcript found {direction}: {}",
+                self.index,
+                fmt_data(&entry.data)
+            );
+        }
+    }
+
+    /// Get the data of the next entry, which must have the given direction, or panic.
+    fn next_entry(&mut self, direction: Direction) -> Vec<u8> {
+        let idx = self.index;
+        let entry = match self.transcript.entries.get(idx) {
+            Some(entry) => entry,
+            None => panic!("Transcript has no more entries, but a {direction:?} was requested."),
+        };
+        if entry.direction != direction {
+            panic!(
+                "Transcript entry {idx} is a {:?} of {}, but a {direction:?} was requested.",
+                entry.direction,
+                fmt_data(&entry.data)
+            );
+        }
+        self.index += 1;
+        entry.data.clone()
+    }
+}
+
+impl InstrumentInterface for ReplayInterface {
+    /// Discard the remaining bytes of the current recorded response.
+    ///
+    /// Responses that were not started to be read yet are not discarded.
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        let drained = self.curr_bytes.len();
+        self.curr_bytes.clear();
+        Ok(drained)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        for byte in buf.iter_mut() {
+            while self.curr_bytes.is_empty() {
+                self.curr_bytes = self.next_entry(Direction::Read).into();
+            }
+            *byte = self.curr_bytes.pop_front().unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminator.as_slice()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.terminator = terminator.to_vec();
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        let idx = self.index;
+        let exp = self.next_entry(Direction::Write);
+        assert!(
+            exp == data,
+            "Data written does not match transcript entry {idx}.\n  expected: {}\n       got: {}",
+            fmt_data(&exp),
+            fmt_data(data)
+        );
+        Ok(())
+    }
+}
+
+impl Drop for ReplayInterface {
+    fn drop(&mut self) {
+        // Do not panic again if a mismatch already panicked, as this would abort the test.
+        if !std::thread::panicking() {
+            self.finalize();
+        }
+    }
+}
+
+/// Format data as string if it is valid UTF-8, otherwise as bytes.
+fn fmt_data(data: &[u8]) -> String {
+    match str::from_utf8(data) {
+        Ok(text) => format!("{text:?}"),
+        Err(_) => format!("{data:?}"),
+    }
+}
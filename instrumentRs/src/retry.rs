@@ -0,0 +1,188 @@
+//! This module provides a configurable retry policy for communicating with instruments.
+//!
+//! Flaky links, e.g., RS-232 connections in electrically noisy environments, can make single-shot
+//! queries unreliable. A [`RetryPolicy`] defines how often and with which delay an operation is
+//! retried, and which errors are considered worth retrying.
+
+#![cfg(feature = "std")]
+
+use std::{thread, time::Duration};
+
+use crate::{InstrumentError, InstrumentInterface};
+
+/// The delay between two attempts of a [`RetryPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backoff {
+    /// Wait the same duration between all attempts.
+    Fixed(Duration),
+    /// Start with the `initial` duration and double it after every attempt, but never wait
+    /// longer than `max`.
+    Exponential {
+        /// Delay after the first failed attempt.
+        initial: Duration,
+        /// Maximum delay between two attempts.
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// Get the delay after the given failed attempt (one-indexed).
+    pub fn delay(&self, attempt: usize) -> Duration {
+        match self {
+            Backoff::Fixed(delay) => *delay,
+            Backoff::Exponential { initial, max } => {
+                let exp = u32::try_from(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+                let factor = 2u32.checked_pow(exp).unwrap_or(u32::MAX);
+                initial.saturating_mul(factor).min(*max)
+            }
+        }
+    }
+}
+
+/// A retry policy that defines how operations on an instrument are retried.
+///
+/// By default, an operation is tried three times with a fixed delay of 100 ms between the
+/// attempts. Only timeouts and I/O errors are retried, see [`RetryPolicy::is_transient`]. If all
+/// attempts fail, an [`InstrumentError::RetriesExhausted`] error is returned that contains the
+/// number of attempts and the last error.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use instrumentrs::{Backoff, InstrumentInterface, RetryPolicy, TcpIpInterface};
+///
+/// let mut inst_interface = TcpIpInterface::simple("192.168.1.10:8000").unwrap();
+/// let policy = RetryPolicy::new(5).with_backoff(Backoff::Exponential {
+///     initial: Duration::from_millis(50),
+///     max: Duration::from_secs(1),
+/// });
+/// let name = inst_interface.query_with_retry("*IDN?", &policy).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: usize,
+    /// Delay between two attempts.
+    pub backoff: Backoff,
+    /// Function that decides if an error should be retried.
+    pub retry_on: fn(&InstrumentError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Backoff::Fixed(Duration::from_millis(100)),
+            retry_on: Self::is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a new retry policy with a given maximum number of attempts.
+    ///
+    /// All other settings are taken from the default policy.
+    ///
+    /// # Arguments
+    /// * `max_attempts` - Maximum number of attempts, including the first one. A value of zero is
+    ///   treated as one attempt.
+    pub fn new(max_attempts: usize) -> Self {
+        Self {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Set the backoff between two attempts.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the function that decides if an error should be retried.
+    pub fn with_retry_on(mut self, retry_on: fn(&InstrumentError) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Default selection of errors to retry: timeouts and I/O errors.
+    pub fn is_transient(err: &InstrumentError) -> bool {
+        matches!(
+            err,
+            InstrumentError::Timeout(_)
+                | InstrumentError::TimeoutPartial { .. }
+                | InstrumentError::TimeoutQuery { .. }
+                | InstrumentError::TimeoutQueryLine { .. }
+                | InstrumentError::TimeoutWrite(_)
+                | InstrumentError::Io(_)
+        )
+    }
+
+    /// Run an operation on an interface according to this retry policy.
+    ///
+    /// Errors that should not be retried are returned immediately. If the last attempt fails, an
+    /// [`InstrumentError::RetriesExhausted`] error is returned. Before every retry, the input of
+    /// the interface is drained, such that a late response to a failed attempt is not taken as
+    /// the response to the retry. Errors while draining are ignored, as they would surface with
+    /// the retry.
+    ///
+    /// # Arguments
+    /// * `intf` - The interface to run the operation on.
+    /// * `op` - The operation to run.
+    pub fn run<I, T, F>(&self, intf: &mut I, mut op: F) -> Result<T, InstrumentError>
+    where
+        I: InstrumentInterface + ?Sized,
+        F: FnMut(&mut I) -> Result<T, InstrumentError>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            if attempt > 1 {
+                let _ = intf.drain_input();
+            }
+            match op(intf) {
+                Ok(val) => return Ok(val),
+                Err(err) if !(self.retry_on)(&err) => return Err(err),
+                Err(err) if attempt >= max_attempts => {
+                    return Err(InstrumentError::RetriesExhausted {
+                        attempts: attempt,
+                        source: Box::new(err),
+                    });
+                }
+                Err(_) => {
+                    thread::sleep(self.backoff.delay(attempt));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(1, 10)]
+    #[case(2, 20)]
+    #[case(3, 40)]
+    #[case(4, 50)]
+    #[case(100, 50)]
+    fn test_backoff_exponential(#[case] attempt: usize, #[case] exp_ms: u64) {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(50),
+        };
+        assert_eq!(Duration::from_millis(exp_ms), backoff.delay(attempt));
+    }
+
+    #[rstest]
+    fn test_backoff_fixed() {
+        let backoff = Backoff::Fixed(Duration::from_millis(10));
+        assert_eq!(backoff.delay(1), backoff.delay(5));
+    }
+}
@@ -0,0 +1,325 @@
+//! This module provides an instrument interface for remote serial ports according to RFC 2217.
+//!
+//! Serial to Ethernet converters, e.g., Moxa NPort servers in RFC 2217 mode, allow the client to
+//! configure the serial port over the network using the Telnet COM-PORT-OPTION. The
+//! [`Rfc2217Interface`] connects to such a server, negotiates the option, sets the serial
+//! parameters from an [`Rfc2217Config`], and returns a regular [`Instrument`].
+
+#![cfg(feature = "std")]
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use crate::{Instrument, InstrumentError, TelnetFilter};
+
+/// Telnet option code of the COM-PORT-OPTION.
+const COM_PORT_OPTION: u8 = 44;
+
+/// Client to server command to set the baud rate.
+const SET_BAUDRATE: u8 = 1;
+/// Client to server command to set the number of data bits.
+const SET_DATASIZE: u8 = 2;
+/// Client to server command to set the parity.
+const SET_PARITY: u8 = 3;
+/// Client to server command to set the number of stop bits.
+const SET_STOPSIZE: u8 = 4;
+/// Client to server command to set flow control and control lines.
+const SET_CONTROL: u8 = 5;
+
+/// `SET_CONTROL` value to use no flow control.
+const CONTROL_NO_FLOW_CONTROL: u8 = 1;
+/// `SET_CONTROL` value to set the DTR line.
+const CONTROL_DTR_ON: u8 = 8;
+/// `SET_CONTROL` value to clear the DTR line.
+const CONTROL_DTR_OFF: u8 = 9;
+/// `SET_CONTROL` value to set the RTS line.
+const CONTROL_RTS_ON: u8 = 11;
+/// `SET_CONTROL` value to clear the RTS line.
+const CONTROL_RTS_OFF: u8 = 12;
+
+/// Parity of a remote serial port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rfc2217Parity {
+    /// No parity bit.
+    #[default]
+    None,
+    /// Odd parity.
+    Odd,
+    /// Even parity.
+    Even,
+    /// Parity bit is always set.
+    Mark,
+    /// Parity bit is always cleared.
+    Space,
+}
+
+/// Number of stop bits of a remote serial port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rfc2217StopBits {
+    /// One stop bit.
+    #[default]
+    One,
+    /// Two stop bits.
+    Two,
+    /// One and a half stop bits.
+    OnePointFive,
+}
+
+/// Serial settings of a remote serial port.
+///
+/// The default settings are 9600 baud, 8 data bits, no parity, and one stop bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rfc2217Config {
+    /// Baud rate of the serial port.
+    pub baud: u32,
+    /// Number of data bits, must be between 5 and 8.
+    pub data_bits: u8,
+    /// Parity of the serial port.
+    pub parity: Rfc2217Parity,
+    /// Number of stop bits of the serial port.
+    pub stop_bits: Rfc2217StopBits,
+}
+
+impl Default for Rfc2217Config {
+    fn default() -> Self {
+        Rfc2217Config {
+            baud: 9600,
+            data_bits: 8,
+            parity: Rfc2217Parity::None,
+            stop_bits: Rfc2217StopBits::One,
+        }
+    }
+}
+
+/// A remote serial port that is controlled with the COM-PORT-OPTION.
+///
+/// This port is used by the [`Instrument`] that is returned by the [`Rfc2217Interface`]. All data
+/// is sent through a [`TelnetFilter`].
+#[derive(Debug)]
+pub struct Rfc2217Port {
+    filter: TelnetFilter<TcpStream>,
+}
+
+impl Rfc2217Port {
+    /// Set the RTS line of the remote serial port.
+    pub fn set_rts(&mut self, level: bool) -> io::Result<()> {
+        let value = if level {
+            CONTROL_RTS_ON
+        } else {
+            CONTROL_RTS_OFF
+        };
+        self.send_command(SET_CONTROL, &[value])
+    }
+
+    /// Set the DTR line of the remote serial port.
+    pub fn set_dtr(&mut self, level: bool) -> io::Result<()> {
+        let value = if level {
+            CONTROL_DTR_ON
+        } else {
+            CONTROL_DTR_OFF
+        };
+        self.send_command(SET_CONTROL, &[value])
+    }
+
+    /// Negotiate the COM-PORT-OPTION and configure the serial port.
+    fn configure(
+        &mut self,
+        config: &Rfc2217Config,
+        timeout: Duration,
+    ) -> Result<(), InstrumentError> {
+        let data_bits = match config.data_bits {
+            5..=8 => config.data_bits,
+            val => {
+                return Err(InstrumentError::InvalidArgument(format!(
+                    "Invalid number of data bits: {val}. Allowed values are 5, 6, 7, and 8."
+                )));
+            }
+        };
+        let parity = match config.parity {
+            Rfc2217Parity::None => 1,
+            Rfc2217Parity::Odd => 2,
+            Rfc2217Parity::Even => 3,
+            Rfc2217Parity::Mark => 4,
+            Rfc2217Parity::Space => 5,
+        };
+        let stop_bits = match config.stop_bits {
+            Rfc2217StopBits::One => 1,
+            Rfc2217StopBits::Two => 2,
+            Rfc2217StopBits::OnePointFive => 3,
+        };
+
+        self.filter.offer_option(COM_PORT_OPTION)?;
+        let tic = Instant::now();
+        loop {
+            match self.filter.option_state(COM_PORT_OPTION) {
+                Some(true) => break,
+                Some(false) => {
+                    return Err(InstrumentError::Io(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "Server refused the RFC 2217 COM-PORT-OPTION.",
+                    )));
+                }
+                None if tic.elapsed() >= timeout => return Err(InstrumentError::Timeout(timeout)),
+                None => match self.filter.process_negotiation() {
+                    Ok(()) => {}
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) => {}
+                    Err(e) => return Err(e.into()),
+                },
+            }
+        }
+
+        self.send_command(SET_BAUDRATE, &config.baud.to_be_bytes())?;
+        self.send_command(SET_DATASIZE, &[data_bits])?;
+        self.send_command(SET_PARITY, &[parity])?;
+        self.send_command(SET_STOPSIZE, &[stop_bits])?;
+        self.send_command(SET_CONTROL, &[CONTROL_NO_FLOW_CONTROL])?;
+        Ok(())
+    }
+
+    /// Send a COM-PORT-OPTION subnegotiation with the given command and value.
+    fn send_command(&mut self, cmd: u8, value: &[u8]) -> io::Result<()> {
+        let mut frame = vec![0xFF, 0xFA, COM_PORT_OPTION, cmd];
+        for &byte in value {
+            if byte == 0xFF {
+                frame.push(0xFF);
+            }
+            frame.push(byte);
+        }
+        frame.extend([0xFF, 0xF0]);
+        let stream = self.filter.get_mut();
+        stream.write_all(&frame)?;
+        stream.flush()
+    }
+}
+
+impl Read for Rfc2217Port {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.filter.read(buf)
+    }
+}
+
+impl Write for Rfc2217Port {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.filter.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.filter.flush()
+    }
+}
+
+/// A blocking interface to a remote serial port according to RFC 2217.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{InstrumentInterface, Rfc2217Config, Rfc2217Interface, Rfc2217Parity};
+///
+/// let config = Rfc2217Config {
+///     baud: 57600,
+///     data_bits: 7,
+///     parity: Rfc2217Parity::Odd,
+///     ..Default::default()
+/// };
+/// let mut inst_interface = Rfc2217Interface::simple("192.168.1.10:4001", &config).unwrap();
+/// let name = inst_interface.query("*IDN?").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Rfc2217Interface {}
+
+impl Rfc2217Interface {
+    /// Try to connect to a remote serial port and configure it.
+    ///
+    /// The timeout is set to 3 seconds for both reading and writing.
+    ///
+    /// # Arguments
+    /// * `sock_addr` - Socket address of the RFC 2217 server.
+    /// * `config` - Serial settings of the remote serial port.
+    pub fn simple<A: ToSocketAddrs>(
+        sock_addr: A,
+        config: &Rfc2217Config,
+    ) -> Result<Instrument<Rfc2217Port>, InstrumentError> {
+        let stream = TcpStream::connect(sock_addr)?;
+        let timeout = Duration::from_secs(3);
+        stream.set_write_timeout(Some(timeout))?;
+        stream.set_read_timeout(Some(timeout))?;
+        Self::full(stream, config)
+    }
+
+    /// Try to configure a remote serial port from an open TCP/IP stream.
+    ///
+    /// For the internal [`Instrument`] timeout, we will use the `read_timeout` of the
+    /// [`TcpStream`]. If this is `None`, we will use a default timeout of 3 seconds. Data that
+    /// is received before the server accepted the COM-PORT-OPTION is discarded.
+    ///
+    /// # Arguments
+    /// * `stream` - An already open [`TcpStream`] to the RFC 2217 server.
+    /// * `config` - Serial settings of the remote serial port.
+    pub fn full(
+        stream: TcpStream,
+        config: &Rfc2217Config,
+    ) -> Result<Instrument<Rfc2217Port>, InstrumentError> {
+        let timeout = stream.read_timeout()?.unwrap_or(Duration::from_secs(3));
+        let peer_addr = stream.peer_addr().ok();
+        stream.set_read_timeout(Some(timeout))?;
+
+        let mut port = Rfc2217Port {
+            filter: TelnetFilter::new(stream),
+        };
+        port.configure(config, timeout)?;
+
+        let mut inst = Instrument::new(port, timeout)
+            .with_port_timeout(|port, timeout| {
+                port.filter.get_mut().set_read_timeout(Some(timeout))
+            })
+            .with_port_write_timeout(|port, timeout| {
+                port.filter.get_mut().set_write_timeout(Some(timeout))
+            });
+        if let Some(addr) = peer_addr {
+            inst.set_name(&addr.to_string());
+        }
+        Ok(inst)
+    }
+}
+
+impl Instrument<Rfc2217Port> {
+    /// Set the RTS line of the remote serial port.
+    ///
+    /// # Arguments
+    /// * `level` - `true` to set the line, `false` to clear it.
+    pub fn set_rts(&mut self, level: bool) -> Result<(), InstrumentError> {
+        Ok(self.get_mut().set_rts(level)?)
+    }
+
+    /// Set the DTR line of the remote serial port.
+    ///
+    /// # Arguments
+    /// * `level` - `true` to set the line, `false` to clear it.
+    pub fn set_dtr(&mut self, level: bool) -> Result<(), InstrumentError> {
+        Ok(self.get_mut().set_dtr(level)?)
+    }
+
+    /// Control the direction of an RS-485 transceiver with the RTS line.
+    ///
+    /// If a turnaround delay is given, RTS is set for every write and cleared after the data was
+    /// flushed. Before the first read after a write, the turnaround delay is awaited. Note that
+    /// the remote server processes the control commands asynchronously to the data, so the
+    /// turnaround delay should account for the latency of the network.
+    ///
+    /// # Arguments
+    /// * `turnaround` - The turnaround delay or `None` to disable the direction control.
+    pub fn set_rts_on_send(&mut self, turnaround: Option<Duration>) {
+        let set_rts: fn(&mut Rfc2217Port, bool) -> io::Result<()> = Rfc2217Port::set_rts;
+        self.set_half_duplex(turnaround.map(|_| set_rts), turnaround.unwrap_or_default());
+    }
+}
@@ -7,15 +7,19 @@
 
 use std::time::Duration;
 
-use serialport::{SerialPort, SerialPortBuilder};
+use serialport::{
+    DataBits, FlowControl, Parity, SerialPort, SerialPortBuilder, SerialPortInfo, SerialPortType,
+    StopBits,
+};
 
-use crate::{Instrument, InstrumentError};
+use crate::{Instrument, InstrumentBuilder, InstrumentError, InstrumentInterface};
 
 /// A blocking serial port implementation using the [`serialport`] crate.
 ///
 /// You have the possibility to create an instrument interface from a simple serial port
-/// configuration (port and baud rate) or a full featured serial port configuration using a
-/// [`serialport::SerialPortBuilder`] structure.
+/// configuration (port and baud rate), with the [`SerialInterface::builder`] for other settings,
+/// or a full featured serial port configuration using a [`serialport::SerialPortBuilder`]
+/// structure.
 ///
 /// # Returns
 /// Returns a [`Result`] containing an [`Instrument`] with the serial interface if successful,
@@ -38,7 +42,112 @@ impl SerialInterface {
     ) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError> {
         let timeout = Duration::from_secs(3);
         let port = serialport::new(port, baud).timeout(timeout).open()?;
-        Ok(Instrument::new(port, timeout))
+        Ok(instrument(port, timeout))
+    }
+
+    /// Try to create a Instrument interface with a simple serial port configuration and custom
+    /// settings of the interface.
+    ///
+    /// The port is opened as for the `simple` method. The given closure then gets an
+    /// [`InstrumentBuilder`] with these settings, which allows to change, e.g., the terminator,
+    /// the timeouts, or the name before the interface is created.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use instrumentrs::SerialInterface;
+    ///
+    /// let inst_interface = SerialInterface::simple_with("/dev/ttyUSB0", 9600, |builder| {
+    ///     builder.terminator("\r").name("tpg-roughing")
+    /// })
+    /// .unwrap();
+    /// ```
+    ///
+    /// # Arguments
+    /// * `port` - The name of the serial port, e.g., `"/dev/ttyUSB0"` or `"COM3"`.
+    /// * `baud` - The baud rate for the serial communication, e.g., `9600`.
+    /// * `f` - Closure that configures the builder.
+    pub fn simple_with<F>(
+        port: &str,
+        baud: u32,
+        f: F,
+    ) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError>
+    where
+        F: FnOnce(InstrumentBuilder<Box<dyn SerialPort>>) -> InstrumentBuilder<Box<dyn SerialPort>>,
+    {
+        let inst = Self::simple(port, baud)?;
+        Ok(f(InstrumentBuilder::from_instrument(inst)).build())
+    }
+
+    /// Try to create a Instrument interface with a simple serial port configuration and run a
+    /// handshake.
+    ///
+    /// The port is opened as for the `simple` method. Right after, the `on_connect` closure runs
+    /// with the interface, e.g., to send a password or to switch the instrument to remote mode.
+    /// If the closure returns an error, the port is closed and the error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use instrumentrs::SerialInterface;
+    ///
+    /// let inst_interface = SerialInterface::simple_on_connect("/dev/ttyUSB0", 9600, |intf| {
+    ///     intf.sendcmd("REMOTE")?;
+    ///     intf.check_acknowledgment("OK")
+    /// })
+    /// .unwrap();
+    /// ```
+    ///
+    /// # Arguments
+    /// * `port` - The name of the serial port, e.g., `"/dev/ttyUSB0"` or `"COM3"`.
+    /// * `baud` - The baud rate for the serial communication, e.g., `9600`.
+    /// * `on_connect` - Closure that runs the handshake with the interface.
+    pub fn simple_on_connect<F>(
+        port: &str,
+        baud: u32,
+        on_connect: F,
+    ) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError>
+    where
+        F: FnOnce(&mut dyn InstrumentInterface) -> Result<(), InstrumentError>,
+    {
+        let mut inst = Self::simple(port, baud)?;
+        on_connect(&mut inst)?;
+        Ok(inst)
+    }
+
+    /// Create a builder to configure a serial port without using [`serialport`] types directly.
+    ///
+    /// By default, the port uses 8 data bits, no parity, one stop bit, no flow control, and a
+    /// timeout of 3 seconds, i.e., the same settings as [`SerialInterface::simple`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use instrumentrs::{Parity, SerialInterface};
+    ///
+    /// let inst_interface = SerialInterface::builder("/dev/ttyUSB0", 57600)
+    ///     .parity(Parity::Odd)
+    ///     .data_bits(7)
+    ///     .stop_bits(1)
+    ///     .open()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Arguments
+    /// * `port` - The name of the serial port, e.g., `"/dev/ttyUSB0"` or `"COM3"`.
+    /// * `baud` - The baud rate for the serial communication, e.g., `9600`.
+    pub fn builder(port: &str, baud: u32) -> SerialInterfaceBuilder {
+        SerialInterfaceBuilder {
+            port: port.to_string(),
+            baud,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+            flow_control: FlowControl::None,
+            timeout: Duration::from_secs(3),
+        }
     }
 
     /// Try to create a new Instrument interface with a full featured serial port interface.
@@ -58,6 +167,409 @@ impl SerialInterface {
     ) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError> {
         let port = builder.open()?;
         let timeout = port.timeout();
-        Ok(Instrument::new(port, timeout))
+        Ok(instrument(port, timeout))
+    }
+
+    /// List all serial ports that are available on this system.
+    ///
+    /// USB information, e.g., the vendor and product ID, is only available for USB serial ports.
+    pub fn list_ports() -> Result<Vec<SerialPortDescriptor>, InstrumentError> {
+        Ok(serialport::available_ports()?
+            .into_iter()
+            .map(SerialPortDescriptor::from)
+            .collect())
+    }
+
+    /// Find the USB serial port with a given serial number and open it.
+    ///
+    /// The port is opened with the same defaults as [`SerialInterface::simple`]. If no port or
+    /// more than one port with the given serial number is found, an
+    /// [`InstrumentError::InvalidArgument`] error is returned.
+    ///
+    /// # Arguments
+    /// * `serial_number` - The serial number of the USB serial port.
+    /// * `baud` - The baud rate for the serial communication, e.g., `9600`.
+    pub fn find_by_serial_number(
+        serial_number: &str,
+        baud: u32,
+    ) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError> {
+        let ports = Self::list_ports()?;
+        let port = find_port(&ports, &format!("serial number {serial_number}"), |port| {
+            port.serial_number.as_deref() == Some(serial_number)
+        })?;
+        Self::simple(&port.port_name, baud)
+    }
+
+    /// Find the USB serial port with a given vendor and product ID and open it.
+    ///
+    /// The port is opened with the same defaults as [`SerialInterface::simple`]. If no port or
+    /// more than one port with the given IDs is found, an [`InstrumentError::InvalidArgument`]
+    /// error is returned.
+    ///
+    /// # Arguments
+    /// * `vid` - The USB vendor ID.
+    /// * `pid` - The USB product ID.
+    /// * `baud` - The baud rate for the serial communication, e.g., `9600`.
+    pub fn find_by_vid_pid(
+        vid: u16,
+        pid: u16,
+        baud: u32,
+    ) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError> {
+        let ports = Self::list_ports()?;
+        let port = find_port(&ports, &format!("VID:PID {vid:04X}:{pid:04X}"), |port| {
+            port.vid == Some(vid) && port.pid == Some(pid)
+        })?;
+        Self::simple(&port.port_name, baud)
+    }
+}
+
+/// A builder for a serial [`Instrument`] interface, see [`SerialInterface::builder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialInterfaceBuilder {
+    port: String,
+    baud: u32,
+    data_bits: u8,
+    parity: Parity,
+    stop_bits: u8,
+    flow_control: FlowControl,
+    timeout: Duration,
+}
+
+impl SerialInterfaceBuilder {
+    /// Set the number of data bits, which must be between 5 and 8.
+    pub fn data_bits(mut self, data_bits: u8) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    /// Set the parity.
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Set the number of stop bits, which must be 1 or 2.
+    pub fn stop_bits(mut self, stop_bits: u8) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Set the flow control.
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// Set the timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Try to open the serial port and create the Instrument interface.
+    ///
+    /// If the number of data bits or stop bits is invalid, an
+    /// [`InstrumentError::InvalidArgument`] error is returned.
+    pub fn open(self) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError> {
+        SerialInterface::full(self.to_serialport_builder()?)
+    }
+
+    /// Convert the settings into a [`serialport::SerialPortBuilder`].
+    fn to_serialport_builder(&self) -> Result<SerialPortBuilder, InstrumentError> {
+        let data_bits = match self.data_bits {
+            5 => DataBits::Five,
+            6 => DataBits::Six,
+            7 => DataBits::Seven,
+            8 => DataBits::Eight,
+            val => {
+                return Err(InstrumentError::InvalidArgument(format!(
+                    "Invalid number of data bits: {val}. Allowed values are 5, 6, 7, and 8."
+                )));
+            }
+        };
+        let stop_bits = match self.stop_bits {
+            1 => StopBits::One,
+            2 => StopBits::Two,
+            val => {
+                return Err(InstrumentError::InvalidArgument(format!(
+                    "Invalid number of stop bits: {val}. Allowed values are 1 and 2."
+                )));
+            }
+        };
+        Ok(serialport::new(&self.port, self.baud)
+            .data_bits(data_bits)
+            .parity(self.parity)
+            .stop_bits(stop_bits)
+            .flow_control(self.flow_control)
+            .timeout(self.timeout))
+    }
+}
+
+/// Description of a serial port that is available on the system.
+///
+/// See [`SerialInterface::list_ports`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialPortDescriptor {
+    /// Name of the port, e.g., `"/dev/ttyUSB0"` or `"COM3"`.
+    pub port_name: String,
+    /// USB vendor ID, if this is a USB serial port.
+    pub vid: Option<u16>,
+    /// USB product ID, if this is a USB serial port.
+    pub pid: Option<u16>,
+    /// Serial number, if this is a USB serial port that reports one.
+    pub serial_number: Option<String>,
+    /// Manufacturer, if this is a USB serial port that reports one.
+    pub manufacturer: Option<String>,
+}
+
+impl From<SerialPortInfo> for SerialPortDescriptor {
+    fn from(info: SerialPortInfo) -> Self {
+        let mut descriptor = SerialPortDescriptor {
+            port_name: info.port_name,
+            vid: None,
+            pid: None,
+            serial_number: None,
+            manufacturer: None,
+        };
+        if let SerialPortType::UsbPort(usb) = info.port_type {
+            descriptor.vid = Some(usb.vid);
+            descriptor.pid = Some(usb.pid);
+            descriptor.serial_number = usb.serial_number;
+            descriptor.manufacturer = usb.manufacturer;
+        }
+        descriptor
+    }
+}
+
+/// Find exactly one port that matches a given predicate.
+///
+/// # Arguments
+/// * `ports` - The ports to search through.
+/// * `description` - Description of what is searched for, used in the error messages.
+/// * `predicate` - Returns `true` for ports that match.
+fn find_port<'a, F>(
+    ports: &'a [SerialPortDescriptor],
+    description: &str,
+    predicate: F,
+) -> Result<&'a SerialPortDescriptor, InstrumentError>
+where
+    F: Fn(&SerialPortDescriptor) -> bool,
+{
+    let matches: Vec<&SerialPortDescriptor> = ports.iter().filter(|port| predicate(port)).collect();
+    match matches.as_slice() {
+        [port] => Ok(port),
+        [] => Err(InstrumentError::InvalidArgument(format!(
+            "No serial port with {description} found."
+        ))),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|port| port.port_name.as_str()).collect();
+            Err(InstrumentError::InvalidArgument(format!(
+                "Multiple serial ports with {description} found: {}",
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+/// Configuration for RS-485 half-duplex communication.
+///
+/// RS-485 adapters without automatic direction control need the RTS line to be asserted while
+/// sending. Furthermore, some instruments need some time to switch the bus direction before they
+/// reply. Set this configuration with [`Instrument::set_rs485`] on a serial [`Instrument`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use instrumentrs::{Rs485Config, SerialInterface};
+///
+/// let config = Rs485Config {
+///     rts_on_send: true,
+///     turnaround: Duration::from_millis(5),
+/// };
+/// let inst_interface = SerialInterface::simple("/dev/ttyUSB0", 9600)
+///     .unwrap()
+///     .with_rs485(config);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rs485Config {
+    /// Assert RTS while sending and clear it after the data was flushed.
+    pub rts_on_send: bool,
+    /// Time to wait after sending before the response is read.
+    pub turnaround: Duration,
+}
+
+impl Instrument<Box<dyn SerialPort>> {
+    /// Set an RS-485 configuration and return the [`Instrument`].
+    ///
+    /// See [`Instrument::set_rs485`] for details.
+    ///
+    /// # Arguments
+    /// * `config` - The RS-485 configuration.
+    pub fn with_rs485(mut self, config: Rs485Config) -> Self {
+        self.set_rs485(Some(config));
+        self
+    }
+
+    /// Set an RS-485 configuration for half-duplex communication.
+    ///
+    /// If `rts_on_send` is set, RTS is asserted for every write and cleared after the data was
+    /// flushed. Before the first read after a write, the `turnaround` delay is awaited.
+    ///
+    /// # Arguments
+    /// * `config` - The RS-485 configuration or `None` to disable it.
+    pub fn set_rs485(&mut self, config: Option<Rs485Config>) {
+        let config = config.unwrap_or_default();
+        let set_rts: fn(&mut Box<dyn SerialPort>, bool) -> std::io::Result<()> = set_rts;
+        self.set_half_duplex(config.rts_on_send.then_some(set_rts), config.turnaround);
+    }
+}
+
+/// Create the [`Instrument`] for a serial port, named after the port.
+fn instrument(port: Box<dyn SerialPort>, timeout: Duration) -> Instrument<Box<dyn SerialPort>> {
+    let name = port.name();
+    let mut inst = Instrument::new(port, timeout)
+        .with_port_timeout(set_timeout)
+        .with_port_write_timeout(set_timeout)
+        .with_port_check(is_available);
+    if let Some(name) = name {
+        inst.set_name(&name);
+    }
+    inst
+}
+
+/// Set the timeout of a serial port, which applies to reading and writing.
+fn set_timeout(port: &mut Box<dyn SerialPort>, timeout: Duration) -> std::io::Result<()> {
+    port.set_timeout(timeout)?;
+    Ok(())
+}
+
+/// Check if a serial port is still available by querying a control line.
+///
+/// This fails, e.g., if a USB to serial adapter was unplugged.
+fn is_available(port: &mut Box<dyn SerialPort>) -> bool {
+    port.read_clear_to_send().is_ok()
+}
+
+/// Set the level of the RTS line of a serial port.
+fn set_rts(port: &mut Box<dyn SerialPort>, level: bool) -> std::io::Result<()> {
+    port.write_request_to_send(level)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /// Create a descriptor of a USB serial port.
+    fn usb_port(name: &str, vid: u16, pid: u16, sn: &str) -> SerialPortDescriptor {
+        SerialPortDescriptor {
+            port_name: name.to_string(),
+            vid: Some(vid),
+            pid: Some(pid),
+            serial_number: Some(sn.to_string()),
+            manufacturer: None,
+        }
+    }
+
+    #[fixture]
+    fn ports() -> Vec<SerialPortDescriptor> {
+        vec![
+            SerialPortDescriptor {
+                port_name: "/dev/ttyS0".to_string(),
+                vid: None,
+                pid: None,
+                serial_number: None,
+                manufacturer: None,
+            },
+            usb_port("/dev/ttyUSB0", 0x0403, 0x6001, "A1"),
+            usb_port("/dev/ttyUSB1", 0x0403, 0x6001, "B2"),
+            usb_port("/dev/ttyUSB2", 0x067B, 0x2303, "C3"),
+        ]
+    }
+
+    #[rstest]
+    #[case("A1", "/dev/ttyUSB0")]
+    #[case("C3", "/dev/ttyUSB2")]
+    fn test_find_port_serial_number(
+        ports: Vec<SerialPortDescriptor>,
+        #[case] sn: &str,
+        #[case] exp: &str,
+    ) {
+        let port = find_port(&ports, "sn", |p| p.serial_number.as_deref() == Some(sn)).unwrap();
+        assert_eq!(exp, port.port_name);
+    }
+
+    #[rstest]
+    fn test_builder_default() {
+        let builder = SerialInterface::builder("/dev/ttyUSB0", 9600);
+        let exp = serialport::new("/dev/ttyUSB0", 9600).timeout(Duration::from_secs(3));
+        assert_eq!(exp, builder.to_serialport_builder().unwrap());
+    }
+
+    #[rstest]
+    fn test_builder_settings() {
+        let builder = SerialInterface::builder("COM3", 57600)
+            .parity(Parity::Odd)
+            .data_bits(7)
+            .stop_bits(2)
+            .flow_control(FlowControl::Hardware)
+            .timeout(Duration::from_millis(500));
+        let exp = serialport::new("COM3", 57600)
+            .parity(Parity::Odd)
+            .data_bits(DataBits::Seven)
+            .stop_bits(StopBits::Two)
+            .flow_control(FlowControl::Hardware)
+            .timeout(Duration::from_millis(500));
+        assert_eq!(exp, builder.to_serialport_builder().unwrap());
+    }
+
+    #[rstest]
+    #[case(4, 1)]
+    #[case(9, 1)]
+    #[case(8, 0)]
+    #[case(8, 3)]
+    fn test_builder_invalid(#[case] data_bits: u8, #[case] stop_bits: u8) {
+        let builder = SerialInterface::builder("COM3", 9600)
+            .data_bits(data_bits)
+            .stop_bits(stop_bits);
+        assert!(matches!(
+            builder.to_serialport_builder(),
+            Err(InstrumentError::InvalidArgument(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_find_port_vid_pid(ports: Vec<SerialPortDescriptor>) {
+        let port = find_port(&ports, "ids", |p| {
+            p.vid == Some(0x067B) && p.pid == Some(0x2303)
+        });
+        assert_eq!("/dev/ttyUSB2", port.unwrap().port_name);
+    }
+
+    #[rstest]
+    fn test_find_port_none(ports: Vec<SerialPortDescriptor>) {
+        match find_port(&ports, "serial number X", |p| {
+            p.serial_number.is_none() && p.vid.is_some()
+        }) {
+            Err(InstrumentError::InvalidArgument(msg)) => {
+                assert_eq!("No serial port with serial number X found.", msg)
+            }
+            _ => panic!("Expected an invalid argument error."),
+        }
+    }
+
+    #[rstest]
+    fn test_find_port_multiple(ports: Vec<SerialPortDescriptor>) {
+        match find_port(&ports, "VID:PID 0403:6001", |p| p.vid == Some(0x0403)) {
+            Err(InstrumentError::InvalidArgument(msg)) => assert_eq!(
+                "Multiple serial ports with VID:PID 0403:6001 found: /dev/ttyUSB0, /dev/ttyUSB1",
+                msg
+            ),
+            _ => panic!("Expected an invalid argument error."),
+        }
     }
 }
@@ -0,0 +1,236 @@
+//! This module provides a multiplexer for multiple instruments on one multi-drop bus.
+//!
+//! Multiple instruments on the same RS-485 segment, e.g., behind one serial to Ethernet converter,
+//! share a single interface. The [`SharedBus`] owns this interface and hands out [`BusHandle`]s,
+//! one for each instrument driver. Every handle implements [`InstrumentInterface`] and locks the
+//! bus for the full write and read transaction of a query, such that the frames of different
+//! instruments are never interleaved.
+
+#![cfg(feature = "std")]
+
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use crate::{InstrumentError, InstrumentInterface, lock_interface};
+
+/// A bus that is shared by multiple instruments.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use instrumentrs::{InstrumentInterface, SharedBus, TcpIpInterface};
+///
+/// let inst_interface = TcpIpInterface::simple("192.168.1.10:8000").unwrap();
+/// let bus = SharedBus::new(inst_interface);
+///
+/// // One handle per instrument, which can be passed to the instrument drivers.
+/// let mut gauge = bus.handle();
+/// let mut pump = bus.handle().with_post_transaction_delay(Duration::from_millis(10));
+///
+/// let pressure = gauge.query("PR1").unwrap();
+/// let voltage = pump.query("HV?").unwrap();
+/// ```
+pub struct SharedBus<T: InstrumentInterface> {
+    interface: Arc<Mutex<T>>,
+}
+
+impl<T: InstrumentInterface> SharedBus<T> {
+    /// Create a new shared bus that owns the given interface.
+    pub fn new(interface: T) -> Self {
+        SharedBus {
+            interface: Arc::new(Mutex::new(interface)),
+        }
+    }
+
+    /// Create a new handle to the bus.
+    ///
+    /// The handle starts with the terminator and the timeout that the interface currently has.
+    /// Changing them on the handle only affects the transactions of this handle.
+    pub fn handle(&self) -> BusHandle<T> {
+        let intf = self
+            .interface
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        BusHandle {
+            bus: Arc::clone(&self.interface),
+            terminator: intf.get_terminator_bytes().to_vec(),
+            timeout: intf.get_timeout(),
+            post_transaction_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl<T: InstrumentInterface> Clone for SharedBus<T> {
+    fn clone(&self) -> Self {
+        SharedBus {
+            interface: Arc::clone(&self.interface),
+        }
+    }
+}
+
+/// A handle to a [`SharedBus`] for one instrument.
+///
+/// All methods of the [`InstrumentInterface`] lock the bus for their full duration, i.e., a query
+/// writes the command and reads the response without any other handle accessing the bus in
+/// between. For sequences of multiple calls that must not be interrupted, e.g., sending a command
+/// and checking its acknowledgment, use [`BusHandle::transaction`].
+pub struct BusHandle<T: InstrumentInterface> {
+    bus: Arc<Mutex<T>>,
+    terminator: Vec<u8>,
+    timeout: Duration,
+    post_transaction_delay: Duration,
+}
+
+impl<T: InstrumentInterface> BusHandle<T> {
+    /// Set a delay after every transaction of this handle.
+    ///
+    /// The bus stays locked during the delay. This is useful for devices that require some
+    /// quiet time on the bus after they responded.
+    pub fn with_post_transaction_delay(mut self, delay: Duration) -> Self {
+        self.post_transaction_delay = delay;
+        self
+    }
+
+    /// Get the delay after every transaction of this handle.
+    pub fn get_post_transaction_delay(&self) -> Duration {
+        self.post_transaction_delay
+    }
+
+    /// Set the delay after every transaction of this handle.
+    pub fn set_post_transaction_delay(&mut self, delay: Duration) {
+        self.post_transaction_delay = delay;
+    }
+
+    /// Lock the bus and run a sequence of operations on the shared interface.
+    ///
+    /// The terminator and the timeout of this handle are applied to the interface before the
+    /// operations are run. After the operations, the post-transaction delay is waited before the
+    /// bus is released. If another thread panicked while using the bus, an
+    /// [`InstrumentError::InterfacePoisoned`] error is returned and the operations are not run.
+    ///
+    /// # Arguments
+    /// * `f` - The operations to run on the shared interface.
+    pub fn transaction<R>(
+        &mut self,
+        f: impl FnOnce(&mut T) -> Result<R, InstrumentError>,
+    ) -> Result<R, InstrumentError> {
+        let mut intf = lock_interface(&self.bus)?;
+        if intf.get_terminator_bytes() != self.terminator.as_slice() {
+            intf.set_terminator_bytes(&self.terminator);
+        }
+        if intf.get_timeout() != self.timeout {
+            intf.set_timeout(self.timeout);
+        }
+        let ret = f(&mut intf);
+        if !self.post_transaction_delay.is_zero() {
+            thread::sleep(self.post_transaction_delay);
+        }
+        ret
+    }
+}
+
+impl<T: InstrumentInterface> InstrumentInterface for BusHandle<T> {
+    fn check_acknowledgment(&mut self, ack: &str) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.check_acknowledgment(ack))
+    }
+
+    fn query_with_timeout(
+        &mut self,
+        cmd: &str,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        self.transaction(|intf| intf.query_with_timeout(cmd, timeout))
+    }
+
+    fn query_multiline(
+        &mut self,
+        cmd: &str,
+        nlines: usize,
+    ) -> Result<Vec<String>, InstrumentError> {
+        self.transaction(|intf| intf.query_multiline(cmd, nlines))
+    }
+
+    fn query_raw(&mut self, data: &[u8], response_len: usize) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.query_raw(data, response_len))
+    }
+
+    fn query_raw_until(&mut self, data: &[u8], delim: u8) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.query_raw_until(data, delim))
+    }
+
+    fn query_raw_until_byte(
+        &mut self,
+        data: &[u8],
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.query_raw_until_byte(data, delim, extra_bytes))
+    }
+
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        self.transaction(|intf| intf.drain_input())
+    }
+
+    /// Check if the bus is still usable, which is never the case if it is poisoned.
+    fn is_alive(&mut self) -> bool {
+        self.transaction(|intf| Ok(intf.is_alive()))
+            .unwrap_or(false)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.read_exact(buf))
+    }
+
+    fn read_until_byte(
+        &mut self,
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.read_until_byte(delim, extra_bytes))
+    }
+
+    fn read_until_terminator_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        self.transaction(|intf| intf.read_until_terminator_with_timeout(timeout))
+    }
+
+    fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.sendcmd(cmd))
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminator.as_slice()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.terminator = terminator.to_vec();
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.write_raw(data))
+    }
+
+    fn write_raw_chunked(
+        &mut self,
+        data: &[u8],
+        chunk_size: usize,
+        delay: Duration,
+    ) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.write_raw_chunked(data, chunk_size, delay))
+    }
+}
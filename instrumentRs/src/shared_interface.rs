@@ -0,0 +1,284 @@
+//! This module provides an interface that can be shared between an instrument and its channels.
+//!
+//! Instrument drivers usually hand out channels that talk to the same interface as the instrument
+//! itself, and drivers might be cloned to use them from multiple threads. The [`SharedInterface`]
+//! owns the interface behind a mutex, can be cloned cheaply, and implements
+//! [`InstrumentInterface`] by locking the interface for every call. Sequences of multiple calls
+//! that must not be interrupted by other clones are run with [`SharedInterface::transaction`].
+
+#![cfg(feature = "std")]
+
+use std::{
+    sync::{
+        Arc, Mutex, MutexGuard, OnceLock, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{InstrumentError, InstrumentInterface};
+
+/// Lock a mutex that holds an interface.
+///
+/// If another thread panicked while holding the lock, an [`InstrumentError::InterfacePoisoned`]
+/// error is returned instead of panicking as well.
+///
+/// # Arguments
+/// * `interface` - The mutex to lock.
+pub fn lock_interface<T: ?Sized>(
+    interface: &Mutex<T>,
+) -> Result<MutexGuard<'_, T>, InstrumentError> {
+    interface
+        .lock()
+        .map_err(|_| InstrumentError::InterfacePoisoned)
+}
+
+/// An interface that is shared between multiple owners, e.g., an instrument and its channels.
+///
+/// All clones of a [`SharedInterface`] use the same underlying interface. Every method of the
+/// [`InstrumentInterface`] locks the interface for its full duration, i.e., a query writes the
+/// command and reads the response without any other clone accessing the interface in between.
+///
+/// If a thread panics while it uses the interface, all further calls return an
+/// [`InstrumentError::InterfacePoisoned`] error instead of panicking as well.
+///
+/// Setting the terminator or the timeout on one clone applies to all clones.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{InstrumentInterface, SharedInterface, TcpIpInterface};
+///
+/// let inst_interface = TcpIpInterface::simple("192.168.1.10:8000").unwrap();
+/// let mut intf = SharedInterface::new(inst_interface);
+/// let mut channel_intf = intf.clone();
+///
+/// // A single query locks the interface until the response was read.
+/// let name = intf.query("*IDN?").unwrap();
+///
+/// // Multiple calls that must not be interrupted by other clones run in a transaction.
+/// channel_intf
+///     .transaction(|intf| {
+///         intf.sendcmd("OUT1 1")?;
+///         intf.check_acknowledgment("OK")
+///     })
+///     .unwrap();
+/// ```
+pub struct SharedInterface<T: InstrumentInterface> {
+    interface: Arc<Mutex<T>>,
+    terminators: Arc<Terminators>,
+}
+
+/// The terminators that were set on a [`SharedInterface`], shared by all of its clones.
+///
+/// The terminator must be returned by reference, which is not possible from behind the mutex.
+/// Instead, every distinct terminator is appended once to a list, such that references to
+/// previous terminators stay valid as long as any clone exists, and the index of the current one
+/// is stored next to it. Setting a terminator that is already in the list only changes the index,
+/// so the list never grows beyond the number of distinct terminators.
+struct Terminators {
+    first: TerminatorNode,
+    current: AtomicUsize,
+}
+
+/// A node in the list of [`Terminators`].
+struct TerminatorNode {
+    value: Vec<u8>,
+    next: OnceLock<Box<TerminatorNode>>,
+}
+
+impl TerminatorNode {
+    fn new(value: &[u8]) -> Self {
+        TerminatorNode {
+            value: value.to_vec(),
+            next: OnceLock::new(),
+        }
+    }
+}
+
+impl Terminators {
+    fn new(value: &[u8]) -> Self {
+        Terminators {
+            first: TerminatorNode::new(value),
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the terminator that was set last.
+    fn latest(&self) -> &[u8] {
+        let current = self.current.load(Ordering::Acquire);
+        self.nodes()
+            .nth(current)
+            .map_or(self.first.value.as_slice(), |node| node.value.as_slice())
+    }
+
+    /// Set a new terminator, reusing its node if it was set before.
+    fn set(&self, value: &[u8]) {
+        let mut index = 0;
+        let mut node = &self.first;
+        loop {
+            if node.value == value {
+                break;
+            }
+            index += 1;
+            // another clone might append at the same time, in which case we continue with its node
+            node = node
+                .next
+                .get_or_init(|| Box::new(TerminatorNode::new(value)));
+        }
+        self.current.store(index, Ordering::Release);
+    }
+
+    fn nodes(&self) -> impl Iterator<Item = &TerminatorNode> {
+        core::iter::successors(Some(&self.first), |node| {
+            node.next.get().map(|next| &**next)
+        })
+    }
+}
+
+impl<T: InstrumentInterface> SharedInterface<T> {
+    /// Create a new shared interface that owns the given interface.
+    pub fn new(interface: T) -> Self {
+        let terminators = Arc::new(Terminators::new(interface.get_terminator_bytes()));
+        SharedInterface {
+            interface: Arc::new(Mutex::new(interface)),
+            terminators,
+        }
+    }
+
+    /// Lock the interface and run a sequence of operations on it.
+    ///
+    /// No other clone of this [`SharedInterface`] can access the interface until the operations
+    /// are finished. If another thread panicked while using the interface, an
+    /// [`InstrumentError::InterfacePoisoned`] error is returned and the operations are not run.
+    ///
+    /// # Arguments
+    /// * `f` - The operations to run on the interface.
+    pub fn transaction<R>(
+        &self,
+        f: impl FnOnce(&mut T) -> Result<R, InstrumentError>,
+    ) -> Result<R, InstrumentError> {
+        let mut intf = lock_interface(&self.interface)?;
+        f(&mut intf)
+    }
+
+    /// Lock the interface to change or read its settings, even if it is poisoned.
+    fn settings(&self) -> MutexGuard<'_, T> {
+        self.interface
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: InstrumentInterface> Clone for SharedInterface<T> {
+    fn clone(&self) -> Self {
+        SharedInterface {
+            interface: Arc::clone(&self.interface),
+            terminators: Arc::clone(&self.terminators),
+        }
+    }
+}
+
+impl<T: InstrumentInterface> InstrumentInterface for SharedInterface<T> {
+    fn check_acknowledgment(&mut self, ack: &str) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.check_acknowledgment(ack))
+    }
+
+    fn query_with_timeout(
+        &mut self,
+        cmd: &str,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        self.transaction(|intf| intf.query_with_timeout(cmd, timeout))
+    }
+
+    fn query_multiline(
+        &mut self,
+        cmd: &str,
+        nlines: usize,
+    ) -> Result<Vec<String>, InstrumentError> {
+        self.transaction(|intf| intf.query_multiline(cmd, nlines))
+    }
+
+    fn query_raw(&mut self, data: &[u8], response_len: usize) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.query_raw(data, response_len))
+    }
+
+    fn query_raw_until(&mut self, data: &[u8], delim: u8) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.query_raw_until(data, delim))
+    }
+
+    fn query_raw_until_byte(
+        &mut self,
+        data: &[u8],
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.query_raw_until_byte(data, delim, extra_bytes))
+    }
+
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        self.transaction(|intf| intf.drain_input())
+    }
+
+    /// Check if the interface is still usable, which is never the case if it is poisoned.
+    fn is_alive(&mut self) -> bool {
+        self.transaction(|intf| Ok(intf.is_alive()))
+            .unwrap_or(false)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.read_exact(buf))
+    }
+
+    fn read_until_byte(
+        &mut self,
+        delim: u8,
+        extra_bytes: usize,
+    ) -> Result<Vec<u8>, InstrumentError> {
+        self.transaction(|intf| intf.read_until_byte(delim, extra_bytes))
+    }
+
+    fn read_until_terminator_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<String, InstrumentError> {
+        self.transaction(|intf| intf.read_until_terminator_with_timeout(timeout))
+    }
+
+    fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.sendcmd(cmd))
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminators.latest()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        // keep the lock while setting, such that the order matches the interface
+        let mut intf = self.settings();
+        intf.set_terminator_bytes(terminator);
+        self.terminators.set(terminator);
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.settings().get_timeout()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.settings().set_timeout(timeout);
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.write_raw(data))
+    }
+
+    fn write_raw_chunked(
+        &mut self,
+        data: &[u8],
+        chunk_size: usize,
+        delay: Duration,
+    ) -> Result<(), InstrumentError> {
+        self.transaction(|intf| intf.write_raw_chunked(data, chunk_size, delay))
+    }
+}
@@ -0,0 +1,178 @@
+//! This module provides stateful instrument simulators for testing purposes.
+//!
+//! The [`crate::LoopbackInterface`] replays a fixed script, which gets unwieldy for
+//! instruments with state, e.g., a cooler that relaxes toward its setpoint. A
+//! [`SimulatedInstrument`] instead implements the behavior of an instrument as a state machine that
+//! handles every command. The [`SimulatorInterface`] then provides the simulator as an
+//! [`InstrumentInterface`] to drivers, such that tests and examples run fully offline.
+//!
+//! Simulators for the following instruments are shipped:
+//!
+//! - [`Tpg36xSimulator`]: Pfeiffer TPG36x vacuum gauge controller.
+//! - [`CryoTelGtSimulator`]: Sunpower CryoTel GT cryocooler.
+//!
+//! This module is only available with the `"simulator"` feature.
+
+#![cfg(feature = "simulator")]
+
+mod cryotel_gt;
+mod tpg36x;
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{InstrumentError, InstrumentInterface};
+
+pub use cryotel_gt::CryoTelGtSimulator;
+pub use tpg36x::Tpg36xSimulator;
+
+/// An instrument that is simulated as a state machine.
+///
+/// The simulator gets all data that the host writes to the instrument, in the chunks the host
+/// writes them, and returns the data that the instrument sends back. Simulators must buffer
+/// incomplete commands themselves.
+///
+/// # Example
+///
+/// A simulated instrument that stores a value and returns it when queried:
+///
+/// ```
+/// use instrumentrs::{InstrumentInterface, SimulatedInstrument, SimulatorInterface};
+///
+/// #[derive(Default)]
+/// struct Register {
+///     value: String,
+/// }
+///
+/// impl SimulatedInstrument for Register {
+///     fn handle(&mut self, cmd: &[u8]) -> Vec<u8> {
+///         let cmd = String::from_utf8_lossy(cmd);
+///         match cmd.trim_end().strip_prefix("VAL ") {
+///             Some(value) => {
+///                 self.value = value.to_string();
+///                 Vec::new()
+///             }
+///             None => format!("{}\n", self.value).into_bytes(),
+///         }
+///     }
+/// }
+///
+/// let mut intf = SimulatorInterface::new(Register::default());
+/// intf.sendcmd("VAL 42").unwrap();
+/// assert_eq!("42", intf.query("VAL?").unwrap());
+/// ```
+pub trait SimulatedInstrument {
+    /// Handle data from the host and return the data that the instrument sends back.
+    fn handle(&mut self, cmd: &[u8]) -> Vec<u8>;
+}
+
+/// An [`InstrumentInterface`] that is connected to a [`SimulatedInstrument`].
+///
+/// Everything that is written to the interface is handed to the simulator, its responses are
+/// buffered until they are read. As the simulator answers immediately, reading more data than
+/// was sent back returns an [`InstrumentError::Timeout`] error without waiting.
+pub struct SimulatorInterface<S: SimulatedInstrument> {
+    simulator: S,
+    read_buf: VecDeque<u8>,
+    terminator: Vec<u8>,
+    timeout: Duration,
+}
+
+impl<S: SimulatedInstrument> SimulatorInterface<S> {
+    /// Create a new interface that is connected to the given simulator.
+    pub fn new(simulator: S) -> Self {
+        SimulatorInterface {
+            simulator,
+            read_buf: VecDeque::new(),
+            terminator: b"\n".to_vec(), // default terminator, as interfaces
+            timeout: Duration::from_secs(3),
+        }
+    }
+
+    /// Get a reference to the simulator, e.g., to check its state.
+    pub fn get_simulator(&self) -> &S {
+        &self.simulator
+    }
+
+    /// Get a mutable reference to the simulator, e.g., to change its state during a test.
+    pub fn get_simulator_mut(&mut self) -> &mut S {
+        &mut self.simulator
+    }
+
+    /// Consume the interface and return the simulator.
+    pub fn into_simulator(self) -> S {
+        self.simulator
+    }
+}
+
+impl<S: SimulatedInstrument> InstrumentInterface for SimulatorInterface<S> {
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        let drained = self.read_buf.len();
+        self.read_buf.clear();
+        Ok(drained)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        if self.read_buf.len() < buf.len() {
+            return Err(InstrumentError::Timeout(self.timeout));
+        }
+        let len = buf.len();
+        for (byte, val) in buf.iter_mut().zip(self.read_buf.drain(..len)) {
+            *byte = val;
+        }
+        Ok(())
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminator.as_slice()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.terminator = terminator.to_vec();
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        let response = self.simulator.handle(data);
+        self.read_buf.extend(response);
+        Ok(())
+    }
+}
+
+/// Buffer data from the host and split off complete commands that end with the terminator.
+#[derive(Debug, Clone, Default)]
+struct CommandBuffer {
+    buf: Vec<u8>,
+}
+
+impl CommandBuffer {
+    /// Append data from the host.
+    fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Remove the given prefix from the buffered data, returns `true` if it was present.
+    fn take_prefix(&mut self, prefix: &[u8]) -> bool {
+        let found = self.buf.starts_with(prefix);
+        if found {
+            self.buf.drain(..prefix.len());
+        }
+        found
+    }
+
+    /// Return the next complete command without the terminator, if any.
+    fn next(&mut self, terminator: &[u8]) -> Option<String> {
+        let end = self
+            .buf
+            .windows(terminator.len())
+            .position(|window| window == terminator)?;
+        let cmd: Vec<u8> = self.buf.drain(..end + terminator.len()).take(end).collect();
+        Some(String::from_utf8_lossy(&cmd).into_owned())
+    }
+}
@@ -0,0 +1,222 @@
+//! Simulator of a Sunpower CryoTel GT cryocooler.
+
+use std::collections::BTreeMap;
+
+use super::{CommandBuffer, SimulatedInstrument};
+
+/// Terminator of commands.
+const TERMINATOR: &[u8] = b"\r";
+
+/// Terminator of the lines that the cooler sends back.
+const LINE_TERMINATOR: &str = "\r\n";
+
+/// Parameters that are only stored by the simulator, with their default values.
+const PARAMETERS: [(&str, f64); 9] = [
+    ("SET PMAX", 240.0),
+    ("SET PMIN", 70.0),
+    ("SET PWOUT", 0.0),
+    ("SET TBAND", 0.5),
+    ("SET MODE", 2.0),
+    ("SET TSTATM", 0.0),
+    ("SET SSTOPM", 0.0),
+    ("SET KI", 1.0),
+    ("SET KP", 50.0),
+];
+
+/// Simulator of a Sunpower CryoTel GT cryocooler.
+///
+/// Commands end with `"\r"`. The cooler echoes every command and then sends the value, both as
+/// lines that end with `"\r\n"`. Setting a value returns the new value, just like querying it.
+/// Invalid commands are answered with the echo and `"Invalid command"`. The following commands
+/// are simulated:
+///
+/// - `TC`: Get the temperature of the cold head in K.
+/// - `SET TTARGET` and `SET TTARGET=<value>`: Get and set the target temperature in K.
+/// - `SET SSTOP` and `SET SSTOP=<0|1>`: Get and set if the cooler is stopped.
+/// - `SET <PMAX|PMIN|PWOUT|TBAND|MODE|TSTATM|SSTOPM|KI|KP>` and `SET <...>=<value>`: Get and set
+///   the other parameters. These values are stored, but do not change the behavior.
+/// - `P`: Get the commanded power in W.
+/// - `E`: Get the maximum, the minimum, and the commanded power in W, as three lines.
+/// - `ERROR`: Get the error flags as six binary digits, see [`CryoTelGtSimulator::set_errors`].
+///
+/// Every time the temperature is queried, it relaxes toward the target temperature by a fraction
+/// of the difference. If the cooler is stopped, it relaxes toward the ambient temperature of
+/// 295 K instead. The commanded power is proportional to the difference between temperature
+/// and target, limited by `PMIN` and `PMAX`, and zero if the cooler is stopped.
+///
+/// # Example
+///
+/// ```
+/// use instrumentrs::{CryoTelGtSimulator, InstrumentInterface, SimulatorInterface};
+///
+/// let mut intf = SimulatorInterface::new(CryoTelGtSimulator::new().with_relaxation(0.5));
+/// intf.set_terminator("\r\n");
+///
+/// let mut query = |cmd: &str| {
+///     intf.write(&format!("{cmd}\r")).unwrap();
+///     assert_eq!(cmd, intf.read_until_terminator().unwrap()); // echo
+///     intf.read_until_terminator().unwrap()
+/// };
+/// assert_eq!("100.00", query("SET TTARGET=100"));
+/// assert_eq!("197.50", query("TC"));
+/// assert_eq!("148.75", query("TC"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CryoTelGtSimulator {
+    input: CommandBuffer,
+    temperature: f64,
+    target: f64,
+    stopped: bool,
+    relaxation: f64,
+    parameters: BTreeMap<&'static str, f64>,
+    errors: u8,
+}
+
+impl CryoTelGtSimulator {
+    /// Ambient temperature in K, toward which a stopped cooler relaxes.
+    pub const AMBIENT_TEMPERATURE: f64 = 295.0;
+
+    /// Create a new running cooler at ambient temperature with a target temperature of 77 K.
+    ///
+    /// The temperature relaxes by 10% of the difference to the target per query.
+    pub fn new() -> Self {
+        CryoTelGtSimulator {
+            input: CommandBuffer::default(),
+            temperature: Self::AMBIENT_TEMPERATURE,
+            target: 77.0,
+            stopped: false,
+            relaxation: 0.1,
+            parameters: PARAMETERS.into_iter().collect(),
+            errors: 0,
+        }
+    }
+
+    /// Set the fraction of the difference to the target by which the temperature relaxes per
+    /// query.
+    ///
+    /// # Panics
+    /// Panics if the fraction is not between 0 and 1.
+    pub fn with_relaxation(mut self, relaxation: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&relaxation),
+            "The relaxation must be between 0 and 1."
+        );
+        self.relaxation = relaxation;
+        self
+    }
+
+    /// Set the current temperature of the cold head in K.
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Get the current temperature of the cold head in K.
+    pub fn get_temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Get the target temperature in K.
+    pub fn get_target(&self) -> f64 {
+        self.target
+    }
+
+    /// Check if the cooler is stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Get the value of a stored parameter, e.g., `"SET PMAX"`, or `None` if it does not exist.
+    pub fn get_parameter(&self, name: &str) -> Option<f64> {
+        self.parameters.get(name).copied()
+    }
+
+    /// Get the commanded power in W.
+    pub fn get_power(&self) -> f64 {
+        if self.stopped {
+            return 0.0;
+        }
+        let kp = self.parameters["SET KP"];
+        let pmin = self.parameters["SET PMIN"];
+        let pmax = self.parameters["SET PMAX"];
+        (kp * (self.temperature - self.target)).clamp(pmin, pmax.max(pmin))
+    }
+
+    /// Set the error flags that the cooler reports, e.g., to test error handling.
+    ///
+    /// Only the lower six bits are reported. They are sent back as binary digits, with the most
+    /// significant bit first.
+    pub fn set_errors(&mut self, errors: u8) {
+        self.errors = errors & 0b11_1111;
+    }
+
+    /// Execute a command and return its value, or `None` if the command is invalid.
+    fn execute(&mut self, cmd: &str) -> Option<String> {
+        let (name, value) = match cmd.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (cmd, None),
+        };
+        match (name, value) {
+            ("TC", None) => {
+                let equilibrium = if self.stopped {
+                    Self::AMBIENT_TEMPERATURE
+                } else {
+                    self.target
+                };
+                self.temperature += (equilibrium - self.temperature) * self.relaxation;
+                Some(format!("{:.2}", self.temperature))
+            }
+            ("SET TTARGET", value) => {
+                if let Some(value) = value {
+                    self.target = value.trim().parse().ok()?;
+                }
+                Some(format!("{:.2}", self.target))
+            }
+            ("SET SSTOP", value) => {
+                if let Some(value) = value {
+                    self.stopped = match value.trim() {
+                        "0" => false,
+                        "1" => true,
+                        _ => return None,
+                    };
+                }
+                Some(u8::from(self.stopped).to_string())
+            }
+            ("P", None) => Some(format!("{:.2}", self.get_power())),
+            ("E", None) => Some(format!(
+                "{:.2}{LINE_TERMINATOR}{:.2}{LINE_TERMINATOR}{:.2}",
+                self.parameters["SET PMAX"],
+                self.parameters["SET PMIN"],
+                self.get_power()
+            )),
+            ("ERROR", None) => Some(format!("{:06b}", self.errors)),
+            (name, value) => {
+                let parameter = self.parameters.get_mut(name)?;
+                if let Some(value) = value {
+                    *parameter = value.trim().parse().ok()?;
+                }
+                Some(format!("{parameter:.2}"))
+            }
+        }
+    }
+}
+
+impl Default for CryoTelGtSimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedInstrument for CryoTelGtSimulator {
+    fn handle(&mut self, cmd: &[u8]) -> Vec<u8> {
+        self.input.extend(cmd);
+        let mut out = String::new();
+        while let Some(cmd) = self.input.next(TERMINATOR) {
+            let value = self
+                .execute(&cmd)
+                .unwrap_or_else(|| "Invalid command".to_string());
+            out.push_str(&format!("{cmd}{LINE_TERMINATOR}{value}{LINE_TERMINATOR}"));
+        }
+        out.into_bytes()
+    }
+}
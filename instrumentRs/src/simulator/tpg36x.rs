@@ -0,0 +1,183 @@
+//! Simulator of a Pfeiffer TPG36x vacuum gauge controller.
+
+use super::{CommandBuffer, SimulatedInstrument};
+
+/// Terminator of commands and responses.
+const TERMINATOR: &[u8] = b"\r\n";
+/// Positive acknowledgment of a command.
+const ACK: &[u8] = b"\x06\r\n";
+/// Negative acknowledgment of a command.
+const NAK: &[u8] = b"\x15\r\n";
+/// Enquiry of the host to send the response of the last command.
+const ENQ: &[u8] = b"\x05";
+
+/// Conversion factors from mbar into the units 0 (mbar) to 4 (hPa) of the controller.
+const UNIT_FACTORS: [f64; 5] = [1.0, 0.750_062, 100.0, 750.062, 1.0];
+
+/// Unit code of the controller for voltages.
+const UNIT_VOLT: u8 = 5;
+
+/// Simulator of a Pfeiffer TPG362 vacuum gauge controller with two channels.
+///
+/// The simulator implements the ACK/ENQ protocol of the controller: Every command that ends with
+/// `"\r\n"` is acknowledged with `ACK` or, if it is invalid, with `NAK`. If the host then sends
+/// `ENQ`, the response of the command is returned. The following commands are simulated:
+///
+/// - `AYT`: Name, model, serial number, firmware, and hardware version.
+/// - `UNI` and `UNI,<unit>`: Get and set the pressure unit, see the manual for the codes.
+/// - `PR1` and `PR2`: Get the status and pressure of a channel in the current unit. If the unit is
+///   set to volts, the voltage of a full range gauge is returned.
+/// - `SEN` and `SEN,<status1>,<status2>`: Get and set the sensor status of the channels.
+///
+/// Pressures are stored in mbar and can be changed during a test with
+/// [`Tpg36xSimulator::set_pressure`].
+///
+/// # Example
+///
+/// ```
+/// use instrumentrs::{InstrumentInterface, SimulatorInterface, Tpg36xSimulator};
+///
+/// let mut intf = SimulatorInterface::new(Tpg36xSimulator::new());
+/// intf.set_terminator("\r\n");
+/// intf.get_simulator_mut().set_pressure(0, 1.2e-5);
+///
+/// intf.sendcmd("PR1").unwrap();
+/// intf.check_acknowledgment("\u{6}").unwrap();
+/// intf.write("\u{5}").unwrap();
+/// assert_eq!("0,1.2000E-03", intf.read_until_terminator().unwrap()); // in Pa
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tpg36xSimulator {
+    input: CommandBuffer,
+    pending: Option<String>,
+    unit: u8,
+    pressures: [f64; 2],
+    sensors_on: [bool; 2],
+}
+
+impl Tpg36xSimulator {
+    /// Create a new simulator that displays pressures in Pa and has both sensors switched on.
+    pub fn new() -> Self {
+        Tpg36xSimulator {
+            input: CommandBuffer::default(),
+            pending: None,
+            unit: 2,
+            pressures: [1.0e3, 1.0e3],
+            sensors_on: [true, true],
+        }
+    }
+
+    /// Get the pressure of the given zero-indexed channel in mbar.
+    ///
+    /// # Panics
+    /// Panics if the channel does not exist.
+    pub fn get_pressure(&self, channel: usize) -> f64 {
+        self.pressures[channel]
+    }
+
+    /// Set the pressure of the given zero-indexed channel in mbar.
+    ///
+    /// # Panics
+    /// Panics if the channel does not exist.
+    pub fn set_pressure(&mut self, channel: usize, pressure: f64) {
+        self.pressures[channel] = pressure;
+    }
+
+    /// Get the code of the pressure unit that is currently set.
+    pub fn get_unit(&self) -> u8 {
+        self.unit
+    }
+
+    /// Execute a command and return its response, or `None` if the command is invalid.
+    fn execute(&mut self, cmd: &str) -> Option<String> {
+        let (name, args) = cmd.split_once(',').unwrap_or((cmd, ""));
+        match (name, args) {
+            ("AYT", "") => Some("TPG362,PTG28290,44998061,010100,010100".to_string()),
+            ("UNI", "") => Some(self.unit.to_string()),
+            ("UNI", unit) => {
+                self.unit = unit.parse().ok().filter(|&unit| unit <= UNIT_VOLT)?;
+                Some(self.unit.to_string())
+            }
+            ("PR1", "") => Some(self.pressure_response(0)),
+            ("PR2", "") => Some(self.pressure_response(1)),
+            ("SEN", "") => Some(self.sensor_response()),
+            ("SEN", statuses) => {
+                let (first, second) = statuses.split_once(',')?;
+                for (idx, status) in [first, second].into_iter().enumerate() {
+                    match status {
+                        "0" => {}
+                        "1" => self.sensors_on[idx] = false,
+                        "2" => self.sensors_on[idx] = true,
+                        _ => return None,
+                    }
+                }
+                Some(self.sensor_response())
+            }
+            _ => None,
+        }
+    }
+
+    /// Format the status and pressure of a channel in the current unit.
+    fn pressure_response(&self, channel: usize) -> String {
+        if !self.sensors_on[channel] {
+            return "4,0.0000E+00".to_string(); // sensor off
+        }
+        let pressure = self.pressures[channel];
+        let value = match UNIT_FACTORS.get(usize::from(self.unit)) {
+            Some(factor) => pressure * factor,
+            // voltage of a full range gauge: p = 10^(1.667 U - 11.33) mbar
+            None => (pressure.log10() + 11.33) / 1.667,
+        };
+        format!("0,{}", format_exponential(value))
+    }
+
+    /// Format the sensor status of both channels.
+    fn sensor_response(&self) -> String {
+        let status = |on: bool| if on { "2" } else { "1" };
+        format!(
+            "{},{}",
+            status(self.sensors_on[0]),
+            status(self.sensors_on[1])
+        )
+    }
+}
+
+impl Default for Tpg36xSimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedInstrument for Tpg36xSimulator {
+    fn handle(&mut self, cmd: &[u8]) -> Vec<u8> {
+        self.input.extend(cmd);
+        let mut out = Vec::new();
+        loop {
+            if self.input.take_prefix(ENQ) {
+                if let Some(response) = self.pending.take() {
+                    out.extend(response.as_bytes());
+                    out.extend(TERMINATOR);
+                }
+                continue;
+            }
+            let Some(cmd) = self.input.next(TERMINATOR) else {
+                break;
+            };
+            self.pending = self.execute(&cmd);
+            out.extend(if self.pending.is_some() { ACK } else { NAK });
+        }
+        out
+    }
+}
+
+/// Format a value with four decimals and a signed two digit exponent, e.g., `1.2340E-05`.
+fn format_exponential(value: f64) -> String {
+    let formatted = format!("{value:.4E}");
+    let (mantissa, exponent) = formatted
+        .split_once('E')
+        .expect("Exponential format always contains an exponent.");
+    let exponent: i32 = exponent
+        .parse()
+        .expect("Exponential format always contains an integer exponent.");
+    format!("{mantissa}E{exponent:+03}")
+}
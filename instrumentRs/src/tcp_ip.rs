@@ -4,18 +4,23 @@
 //! [`std::net::TcpStream`] struct. As this is part of the standard library, this interface is
 //! always available as long as the standard library is available.
 
+#![cfg(feature = "std")]
+
 use std::{
     net::{TcpStream, ToSocketAddrs},
     time::Duration,
 };
 
-use crate::{Instrument, InstrumentError};
+use socket2::{SockRef, TcpKeepalive};
+
+use crate::{Instrument, InstrumentBuilder, InstrumentError, InstrumentInterface, TelnetFilter};
 
 /// A blocking TCP/IP implementation using [`std::net::TcpStream`].
 ///
 /// You have the possibility to create an instrument interface from a simple socket address, or to
 /// create a full featured TCP/IP interface with additional parameters and pass the `full` method an
-/// open [`TcpStream`].
+/// open [`TcpStream`]. For terminal servers that use the Telnet protocol, the `telnet` method
+/// creates an interface that filters the Telnet negotiation.
 ///
 /// # Returns
 /// Returns a [`Result`] containing an [`Instrument`] with the TCP/IP interface if successful,
@@ -37,19 +42,178 @@ impl TcpIpInterface {
         let timeout = Duration::from_secs(3);
         stream.set_write_timeout(Some(timeout))?;
         stream.set_read_timeout(Some(timeout))?;
-        Ok(Instrument::new(stream, timeout))
+        Ok(instrument(stream, timeout))
+    }
+
+    /// Try to create a new Instrument interface of a TCP/IP interface with custom settings.
+    ///
+    /// The connection is opened as for the `simple` method. The given closure then gets an
+    /// [`InstrumentBuilder`] with these settings, which allows to change, e.g., the terminator,
+    /// the timeouts, or the name before the interface is created.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use instrumentrs::TcpIpInterface;
+    ///
+    /// let inst_interface = TcpIpInterface::simple_with("192.168.1.10:8000", |builder| {
+    ///     builder.terminator("\r").timeout(Duration::from_secs(1))
+    /// })
+    /// .unwrap();
+    /// ```
+    ///
+    /// # Arguments
+    /// * `sock_addr` - Socket address.
+    /// * `f` - Closure that configures the builder.
+    pub fn simple_with<A, F>(sock_addr: A, f: F) -> Result<Instrument<TcpStream>, InstrumentError>
+    where
+        A: ToSocketAddrs,
+        F: FnOnce(InstrumentBuilder<TcpStream>) -> InstrumentBuilder<TcpStream>,
+    {
+        let inst = Self::simple(sock_addr)?;
+        Ok(f(InstrumentBuilder::from_instrument(inst)).build())
+    }
+
+    /// Try to create a new Instrument interface of a TCP/IP interface and run a handshake.
+    ///
+    /// The connection is opened as for the `simple` method. Right after, the `on_connect` closure
+    /// runs with the interface, e.g., to send a password or to switch the instrument to remote
+    /// mode. If the closure returns an error, the connection is closed and the error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use instrumentrs::TcpIpInterface;
+    ///
+    /// let inst_interface = TcpIpInterface::simple_on_connect("192.168.1.10:8000", |intf| {
+    ///     intf.sendcmd("PASSWORD secret")?;
+    ///     intf.check_acknowledgment("OK")
+    /// })
+    /// .unwrap();
+    /// ```
+    ///
+    /// # Arguments
+    /// * `sock_addr` - Socket address.
+    /// * `on_connect` - Closure that runs the handshake with the interface.
+    pub fn simple_on_connect<A, F>(
+        sock_addr: A,
+        on_connect: F,
+    ) -> Result<Instrument<TcpStream>, InstrumentError>
+    where
+        A: ToSocketAddrs,
+        F: FnOnce(&mut dyn InstrumentInterface) -> Result<(), InstrumentError>,
+    {
+        let mut inst = Self::simple(sock_addr)?;
+        on_connect(&mut inst)?;
+        Ok(inst)
     }
 
     /// Try to create a new Instrument interface from an open TCP/IP stream.
     ///
     /// This allows you to specify timeouts, etc. For the internal [`Instrument`] timeout, we will
     /// use the `read_timeout` of the [`TcpStream`]. If this is `None`, we will use a default
-    /// timeout of 3 seconds.
+    /// timeout of 3 seconds. The write timeout of the stream is set to the same timeout.
     ///
     /// # Arguments
     /// * `stream` - An already open [`TcpStream`].
     pub fn full(stream: TcpStream) -> Result<Instrument<TcpStream>, InstrumentError> {
         let timeout = stream.read_timeout()?.unwrap_or(Duration::from_secs(3));
-        Ok(Instrument::new(stream, timeout))
+        Ok(instrument(stream, timeout))
+    }
+
+    /// Try to create a new Instrument interface of a TCP/IP interface that uses Telnet.
+    ///
+    /// Some terminal servers inject Telnet negotiation sequences into the data stream. This
+    /// interface wraps the stream in a [`TelnetFilter`] that removes these sequences and escapes
+    /// `0xFF` bytes on writing. The timeout is set to 3 seconds for both reading and writing, as
+    /// for the `simple` method.
+    ///
+    /// # Arguments
+    /// * `sock_addr` - Socket address.
+    pub fn telnet<A: ToSocketAddrs>(
+        sock_addr: A,
+    ) -> Result<Instrument<TelnetFilter<TcpStream>>, InstrumentError> {
+        let stream = TcpStream::connect(sock_addr)?;
+        let timeout = Duration::from_secs(3);
+        stream.set_write_timeout(Some(timeout))?;
+        stream.set_read_timeout(Some(timeout))?;
+        let peer_addr = stream.peer_addr().ok();
+        let mut inst = Instrument::new(TelnetFilter::new(stream), timeout)
+            .with_port_timeout(|filter, timeout| set_read_timeout(filter.get_mut(), timeout))
+            .with_port_write_timeout(|filter, timeout| set_write_timeout(filter.get_mut(), timeout))
+            .with_port_check(|filter| is_connected(filter.get_mut()));
+        if let Some(addr) = peer_addr {
+            inst.set_name(&addr.to_string());
+        }
+        Ok(inst)
+    }
+}
+
+impl Instrument<TcpStream> {
+    /// Set the `TCP_NODELAY` option of the socket.
+    ///
+    /// If set, small packets are sent immediately instead of being combined, which reduces the
+    /// latency of short commands.
+    ///
+    /// # Arguments
+    /// * `nodelay` - If `true`, disable Nagle's algorithm.
+    pub fn set_nodelay(&mut self, nodelay: bool) -> Result<(), InstrumentError> {
+        self.get_ref().set_nodelay(nodelay)?;
+        Ok(())
+    }
+
+    /// Set the TCP keepalive of the socket.
+    ///
+    /// With keepalive enabled, the operating system detects connections that were silently
+    /// dropped, e.g., by a power-cycled instrument, even if no data is sent.
+    ///
+    /// # Arguments
+    /// * `keepalive` - Time the connection has to be idle before keepalive probes are sent, or
+    ///   `None` to disable keepalive.
+    pub fn set_keepalive(&mut self, keepalive: Option<Duration>) -> Result<(), InstrumentError> {
+        let sock = SockRef::from(self.get_ref());
+        match keepalive {
+            Some(time) => sock.set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?,
+            None => sock.set_keepalive(false)?,
+        }
+        Ok(())
+    }
+}
+
+/// Create the [`Instrument`] for a [`TcpStream`], named after the address of the peer.
+fn instrument(stream: TcpStream, timeout: Duration) -> Instrument<TcpStream> {
+    let peer_addr = stream.peer_addr().ok();
+    let mut inst = Instrument::new(stream, timeout)
+        .with_port_timeout(set_read_timeout)
+        .with_port_write_timeout(set_write_timeout)
+        .with_port_check(is_connected);
+    if let Some(addr) = peer_addr {
+        inst.set_name(&addr.to_string());
+    }
+    inst
+}
+
+/// Set the read timeout of a [`TcpStream`].
+fn set_read_timeout(stream: &mut TcpStream, timeout: Duration) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(timeout))
+}
+
+/// Set the write timeout of a [`TcpStream`].
+fn set_write_timeout(stream: &mut TcpStream, timeout: Duration) -> std::io::Result<()> {
+    stream.set_write_timeout(Some(timeout))
+}
+
+/// Check if the peer of a [`TcpStream`] is still connected without consuming any data.
+fn is_connected(stream: &mut TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
     }
+    let connected = match stream.peek(&mut [0u8]) {
+        Ok(0) => false, // the peer closed the connection
+        Ok(_) => true,
+        Err(e) => e.kind() == std::io::ErrorKind::WouldBlock,
+    };
+    stream.set_nonblocking(false).is_ok() && connected
 }
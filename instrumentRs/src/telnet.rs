@@ -0,0 +1,315 @@
+//! This module provides a filter for the Telnet protocol used by some terminal servers.
+//!
+//! Terminal servers, e.g., serial to Ethernet converters, might inject Telnet negotiation
+//! sequences into the data stream. These sequences start with the IAC (`0xFF`) byte and would
+//! otherwise end up in the responses of the instrument. The [`TelnetFilter`] removes them and
+//! refuses all options that the server offers or requests.
+
+#![cfg(feature = "std")]
+
+use std::io::{self, Read, Write};
+
+/// Interpret as command: starts every Telnet command.
+const IAC: u8 = 0xFF;
+/// Offer to enable an option.
+const WILL: u8 = 0xFB;
+/// Refuse to enable an option.
+const WONT: u8 = 0xFC;
+/// Request to enable an option.
+const DO: u8 = 0xFD;
+/// Request to disable an option.
+const DONT: u8 = 0xFE;
+/// Start of a subnegotiation.
+const SB: u8 = 0xFA;
+/// End of a subnegotiation.
+const SE: u8 = 0xF0;
+
+/// Maximum number of bytes that are read at once while negotiating.
+const NEGOTIATION_CHUNK_SIZE: usize = 64;
+
+/// State of the Telnet parser, which is kept between two reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Regular data.
+    Data,
+    /// An IAC byte was received.
+    Iac,
+    /// An option negotiation command was received, the option byte follows.
+    Negotiation(u8),
+    /// Inside a subnegotiation.
+    Sub,
+    /// An IAC byte was received inside a subnegotiation.
+    SubIac,
+}
+
+/// A stream wrapper that handles the Telnet protocol transparently.
+///
+/// On reading, all Telnet commands and subnegotiations are removed from the data and an escaped
+/// `0xFF` byte (`IAC IAC`) is returned as a single `0xFF` byte. Options that the server offers
+/// (`WILL`) or requests (`DO`) are refused with `DONT` or `WONT`, respectively. Sequences that are
+/// split over multiple reads are handled correctly. On writing, `0xFF` bytes are escaped.
+///
+/// You can get an [`crate::Instrument`] that uses this filter on a TCP/IP connection with
+/// [`crate::TcpIpInterface::telnet`].
+#[derive(Debug)]
+pub struct TelnetFilter<S: Read + Write> {
+    stream: S,
+    state: State,
+    /// Options that we offered, with the answer of the server if it was received.
+    offered: Vec<(u8, Option<bool>)>,
+}
+
+impl<S: Read + Write> TelnetFilter<S> {
+    /// Create a new Telnet filter that wraps the given stream.
+    pub fn new(stream: S) -> Self {
+        TelnetFilter {
+            stream,
+            state: State::Data,
+            offered: Vec::new(),
+        }
+    }
+
+    /// Get a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the wrapped stream.
+    ///
+    /// Reading from or writing to the stream directly bypasses the filter.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consume the filter and return the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Offer to enable an option with `WILL`.
+    ///
+    /// The answer of the server is processed while reading, see [`TelnetFilter::option_state`].
+    pub(crate) fn offer_option(&mut self, option: u8) -> io::Result<()> {
+        self.offered.retain(|(opt, _)| *opt != option);
+        self.offered.push((option, None));
+        self.stream.write_all(&[IAC, WILL, option])?;
+        self.stream.flush()
+    }
+
+    /// Get the answer of the server to an offered option.
+    ///
+    /// Returns `Some(true)` if the server accepted the option with `DO`, `Some(false)` if it
+    /// refused it with `DONT`, and `None` if no answer was received yet or the option was never
+    /// offered.
+    pub(crate) fn option_state(&self, option: u8) -> Option<bool> {
+        self.offered
+            .iter()
+            .find(|(opt, _)| *opt == option)
+            .and_then(|(_, state)| *state)
+    }
+
+    /// Read and process one chunk of data from the stream, e.g., to receive the answer to an
+    /// offered option.
+    ///
+    /// Data that is not part of a Telnet sequence is discarded.
+    pub(crate) fn process_negotiation(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; NEGOTIATION_CHUNK_SIZE];
+        let read = self.stream.read(&mut buf)?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut replies = Vec::new();
+        self.filter(&mut buf[..read], &mut replies);
+        self.send_replies(&replies)
+    }
+
+    /// Write replies to negotiations to the stream.
+    fn send_replies(&mut self, replies: &[u8]) -> io::Result<()> {
+        if replies.is_empty() {
+            return Ok(());
+        }
+        self.stream.write_all(replies)?;
+        self.stream.flush()
+    }
+
+    /// Handle an option negotiation command of the server and append the reply, if any.
+    fn negotiate(&mut self, cmd: u8, option: u8, replies: &mut Vec<u8>) {
+        let offered = self.offered.iter_mut().find(|(opt, _)| *opt == option);
+        match (cmd, offered) {
+            (DO, Some((_, state))) => *state = Some(true),
+            (DONT, Some((_, state))) => *state = Some(false),
+            (WILL, _) => replies.extend([IAC, DONT, option]),
+            (DO, None) => replies.extend([IAC, WONT, option]),
+            _ => {} // refusals need no reply
+        }
+    }
+
+    /// Remove all Telnet sequences from the buffer in place and return the number of data bytes.
+    ///
+    /// Options that were not offered are refused, replies are appended to `replies`.
+    fn filter(&mut self, buf: &mut [u8], replies: &mut Vec<u8>) -> usize {
+        let mut len = 0;
+        for idx in 0..buf.len() {
+            let byte = buf[idx];
+            self.state = match (self.state, byte) {
+                (State::Data, IAC) => State::Iac,
+                (State::Data, _) | (State::Iac, IAC) => {
+                    buf[len] = byte;
+                    len += 1;
+                    State::Data
+                }
+                (State::Iac, WILL | WONT | DO | DONT) => State::Negotiation(byte),
+                (State::Iac, SB) => State::Sub,
+                (State::Iac, _) => State::Data, // other commands have no arguments
+                (State::Negotiation(cmd), option) => {
+                    self.negotiate(cmd, option, replies);
+                    State::Data
+                }
+                (State::Sub, IAC) => State::SubIac,
+                (State::Sub, _) => State::Sub,
+                (State::SubIac, SE) => State::Data,
+                (State::SubIac, _) => State::Sub,
+            };
+        }
+        len
+    }
+}
+
+impl<S: Read + Write> Read for TelnetFilter<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let read = self.stream.read(buf)?;
+            if read == 0 {
+                return Ok(0);
+            }
+            let mut replies = Vec::new();
+            let len = self.filter(&mut buf[..read], &mut replies);
+            self.send_replies(&replies)?;
+            // if only negotiation was received, read again to wait for data
+            if len > 0 {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl<S: Read + Write> Write for TelnetFilter<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut escaped = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            if byte == IAC {
+                escaped.push(IAC);
+            }
+            escaped.push(byte);
+        }
+        self.stream.write_all(&escaped)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /// No operation command, which has no arguments.
+    const NOP: u8 = 0xF1;
+
+    /// A stream that returns canned chunks on reading and records all writes.
+    struct CannedStream {
+        chunks: Vec<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl CannedStream {
+        fn new(mut chunks: Vec<Vec<u8>>) -> Self {
+            chunks.reverse();
+            CannedStream {
+                chunks,
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for CannedStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.chunks.pop() {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    impl Write for CannedStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Read all data from the filter until the stream is exhausted.
+    fn read_all(filter: &mut TelnetFilter<CannedStream>) -> Vec<u8> {
+        let mut data = Vec::new();
+        filter.read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[rstest]
+    #[case(vec![b"resp\n".to_vec()], b"resp\n")]
+    #[case(vec![vec![IAC, WILL, 1, b'o', b'k', b'\n']], b"ok\n")]
+    #[case(vec![vec![b'o', IAC, NOP, b'k', b'\n']], b"ok\n")]
+    #[case(vec![vec![b'o', IAC, IAC, b'\n']], &[b'o', IAC, b'\n'])]
+    #[case(vec![vec![IAC, SB, 24, 1, IAC, IAC, IAC, SE, b'o', b'k']], b"ok")]
+    #[case(vec![vec![b'o'], vec![IAC], vec![DO], vec![1], b"k\n".to_vec()], b"ok\n")]
+    #[case(vec![vec![b'o', IAC, SB, 24], vec![1, IAC], vec![SE, b'k']], b"ok")]
+    fn test_filter_read(#[case] chunks: Vec<Vec<u8>>, #[case] exp: &[u8]) {
+        let mut filter = TelnetFilter::new(CannedStream::new(chunks));
+        assert_eq!(exp, read_all(&mut filter).as_slice());
+    }
+
+    #[rstest]
+    fn test_filter_replies() {
+        let chunks = vec![vec![IAC, WILL, 1, IAC, DO, 3, IAC, WONT, 5, IAC, DONT, 6]];
+        let mut filter = TelnetFilter::new(CannedStream::new(chunks));
+        assert!(read_all(&mut filter).is_empty());
+        assert_eq!(
+            vec![IAC, DONT, 1, IAC, WONT, 3],
+            filter.into_inner().written
+        );
+    }
+
+    #[rstest]
+    #[case(DO, Some(true))]
+    #[case(DONT, Some(false))]
+    fn test_filter_offer_option(#[case] answer: u8, #[case] exp: Option<bool>) {
+        let chunks = vec![vec![IAC, answer, 44]];
+        let mut filter = TelnetFilter::new(CannedStream::new(chunks));
+        filter.offer_option(44).unwrap();
+        assert_eq!(None, filter.option_state(44));
+        filter.process_negotiation().unwrap();
+        assert_eq!(exp, filter.option_state(44));
+        // answers to our own offer are not replied to
+        assert_eq!(vec![IAC, WILL, 44], filter.into_inner().written);
+    }
+
+    #[rstest]
+    fn test_filter_write_escapes() {
+        let mut filter = TelnetFilter::new(CannedStream::new(vec![]));
+        assert_eq!(3, filter.write(&[0x01, IAC, 0x02]).unwrap());
+        assert_eq!(vec![0x01, IAC, IAC, 0x02], filter.get_ref().written);
+    }
+}
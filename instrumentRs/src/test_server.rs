@@ -0,0 +1,227 @@
+//! This module provides a simulated instrument that listens on a local TCP port.
+//!
+//! The [`crate::LoopbackInterface`] tests drivers in-process. To test the full path through
+//! the [`crate::TcpIpInterface`], the [`SimulatedTcpInstrument`] binds a local port, accepts one
+//! connection, and answers every command with a user-defined closure.
+//!
+//! This module is only available with the `"test-server"` feature.
+
+#![cfg(feature = "test-server")]
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::InstrumentError;
+
+/// Interval in which the server thread checks if it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A builder for a [`SimulatedTcpInstrument`], see [`SimulatedTcpInstrument::builder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedTcpInstrumentBuilder {
+    terminator: Vec<u8>,
+    response_delay: Duration,
+}
+
+impl SimulatedTcpInstrumentBuilder {
+    /// Set the terminator of commands and responses, defaults to `"\n"`.
+    pub fn terminator(mut self, terminator: &str) -> Self {
+        self.terminator = terminator.as_bytes().to_vec();
+        self
+    }
+
+    /// Set a delay before every response is sent, defaults to no delay.
+    pub fn response_delay(mut self, delay: Duration) -> Self {
+        self.response_delay = delay;
+        self
+    }
+
+    /// Bind a local port and start the simulated instrument in a background thread.
+    ///
+    /// # Arguments
+    /// * `responder` - Closure that gets every command without the terminator and returns the
+    ///   response without the terminator, or `None` if the instrument should not respond.
+    pub fn spawn<F>(self, responder: F) -> Result<SimulatedTcpInstrument, InstrumentError>
+    where
+        F: Fn(&str) -> Option<String> + Send + 'static,
+    {
+        if self.terminator.is_empty() {
+            return Err(InstrumentError::InvalidArgument(
+                "The terminator must not be empty.".to_string(),
+            ));
+        }
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let server = Server {
+            terminator: self.terminator,
+            response_delay: self.response_delay,
+            stop: Arc::clone(&stop),
+            received: Arc::clone(&received),
+        };
+        let handle = thread::spawn(move || {
+            // errors end the simulation, the client sees them as a closed connection
+            let _ = server.run(listener, responder);
+        });
+        Ok(SimulatedTcpInstrument {
+            addr,
+            stop,
+            received,
+            handle: Some(handle),
+        })
+    }
+}
+
+/// A simulated instrument that answers commands on a local TCP port.
+///
+/// The instrument accepts a single connection. Every command that is terminated with the
+/// terminator is passed to the responder closure, and its response is sent back with the
+/// terminator appended. If the responder returns `None`, nothing is sent, which lets you test
+/// timeouts. The thread of the instrument stops when it is dropped.
+///
+/// # Example
+///
+/// ```
+/// use instrumentrs::{InstrumentInterface, SimulatedTcpInstrument, TcpIpInterface};
+///
+/// let sim = SimulatedTcpInstrument::spawn(|cmd| match cmd {
+///     "*IDN?" => Some("Simulated,1.0".to_string()),
+///     _ => None,
+/// })
+/// .unwrap();
+///
+/// let mut inst = TcpIpInterface::simple(sim.get_addr()).unwrap();
+/// assert_eq!("Simulated,1.0", inst.query("*IDN?").unwrap());
+/// ```
+pub struct SimulatedTcpInstrument {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<String>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SimulatedTcpInstrument {
+    /// Get a builder to configure the terminator and response delay.
+    pub fn builder() -> SimulatedTcpInstrumentBuilder {
+        SimulatedTcpInstrumentBuilder {
+            terminator: b"\n".to_vec(),
+            response_delay: Duration::ZERO,
+        }
+    }
+
+    /// Bind a local port and start a simulated instrument with the default settings.
+    ///
+    /// See [`SimulatedTcpInstrumentBuilder::spawn`] for details.
+    pub fn spawn<F>(responder: F) -> Result<Self, InstrumentError>
+    where
+        F: Fn(&str) -> Option<String> + Send + 'static,
+    {
+        Self::builder().spawn(responder)
+    }
+
+    /// Get the local address that the instrument listens on.
+    pub fn get_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get all commands that the instrument received so far, without terminators.
+    pub fn get_received(&self) -> Vec<String> {
+        self.received
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Drop for SimulatedTcpInstrument {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// The part of the simulated instrument that runs in the background thread.
+struct Server {
+    terminator: Vec<u8>,
+    response_delay: Duration,
+    stop: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<String>>>,
+}
+
+impl Server {
+    /// Accept one connection and answer commands until the instrument is stopped.
+    fn run<F>(&self, listener: TcpListener, responder: F) -> std::io::Result<()>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let Some(mut stream) = self.accept(&listener)? else {
+            return Ok(());
+        };
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !self.stop.load(Ordering::SeqCst) {
+            match stream.read(&mut chunk) {
+                Ok(0) => return Ok(()),
+                Ok(read) => buf.extend_from_slice(&chunk[..read]),
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue;
+                }
+                Err(err) => return Err(err),
+            }
+            while let Some(pos) = find(&buf, &self.terminator) {
+                let cmd = String::from_utf8_lossy(&buf[..pos]).to_string();
+                buf.drain(..pos + self.terminator.len());
+                let response = responder(&cmd);
+                self.received
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(cmd);
+                if let Some(response) = response {
+                    thread::sleep(self.response_delay);
+                    let mut data = response.into_bytes();
+                    data.extend_from_slice(&self.terminator);
+                    stream.write_all(&data)?;
+                    stream.flush()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for a connection, or return `None` if the instrument was stopped before.
+    fn accept(&self, listener: &TcpListener) -> std::io::Result<Option<TcpStream>> {
+        while !self.stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    return Ok(Some(stream));
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Find the position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,46 @@
+//! This module provides helpers to trace the traffic of instruments using the [`tracing`] crate.
+//!
+//! This module is only available when the `tracing` feature is enabled.
+
+#![cfg(feature = "tracing")]
+
+use std::fmt;
+
+/// Render bytes as hex values followed by their ASCII representation.
+///
+/// Non-printable characters are rendered as `.` in the ASCII representation, e.g., `b"ok\n"` is
+/// rendered as `6F 6B 0A |ok.|`.
+pub(crate) struct HexAscii<'a>(pub(crate) &'a [u8]);
+
+impl fmt::Display for HexAscii<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02X} ")?;
+        }
+        write!(f, "|")?;
+        for byte in self.0 {
+            let ch = if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '.'
+            };
+            write!(f, "{ch}")?;
+        }
+        write!(f, "|")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(b"ok\n", "6F 6B 0A |ok.|")]
+    #[case(b"a b", "61 20 62 |a b|")]
+    #[case(&[0x03, 0xFF], "03 FF |..|")]
+    #[case(b"", "||")]
+    fn test_hex_ascii(#[case] data: &[u8], #[case] exp: &str) {
+        assert_eq!(exp, HexAscii(data).to_string());
+    }
+}
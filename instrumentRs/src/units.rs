@@ -0,0 +1,48 @@
+//! Conversions between units that instruments report and the types of the [`measurements`] crate.
+//!
+//! Some units that vacuum gauges and pump controllers commonly use have no (or no exact)
+//! constructors in [`measurements::Pressure`]. The functions in this module convert from and to
+//! these units using the exact conversion factors, such that drivers do not have to hard-code
+//! them.
+//!
+//! This module is only available with the `"measurements"` feature.
+
+#![cfg(feature = "measurements")]
+
+use measurements::Pressure;
+
+/// Pascal per Torr, which is exactly 1/760 of a standard atmosphere.
+pub const PASCALS_PER_TORR: f64 = 101_325.0 / 760.0;
+
+/// Pascal per pound-force per square inch, using the exact international pound and inch.
+pub const PASCALS_PER_PSI: f64 = 0.453_592_37 * 9.806_65 / (0.0254 * 0.0254);
+
+/// Create a pressure from a value in Torr.
+pub fn pressure_from_torr(torr: f64) -> Pressure {
+    Pressure::from_pascals(torr * PASCALS_PER_TORR)
+}
+
+/// Create a pressure from a value in millitorr (also called micron).
+pub fn pressure_from_mtorr(mtorr: f64) -> Pressure {
+    Pressure::from_pascals(mtorr * PASCALS_PER_TORR / 1000.0)
+}
+
+/// Create a pressure from a value in pound-force per square inch.
+pub fn pressure_from_psi(psi: f64) -> Pressure {
+    Pressure::from_pascals(psi * PASCALS_PER_PSI)
+}
+
+/// Get the value of a pressure in Torr.
+pub fn pressure_as_torr(pressure: &Pressure) -> f64 {
+    pressure.as_pascals() / PASCALS_PER_TORR
+}
+
+/// Get the value of a pressure in millitorr (also called micron).
+pub fn pressure_as_mtorr(pressure: &Pressure) -> f64 {
+    pressure.as_pascals() / PASCALS_PER_TORR * 1000.0
+}
+
+/// Get the value of a pressure in pound-force per square inch.
+pub fn pressure_as_psi(pressure: &Pressure) -> f64 {
+    pressure.as_pascals() / PASCALS_PER_PSI
+}
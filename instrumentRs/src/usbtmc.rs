@@ -0,0 +1,510 @@
+//! This module provides an instrument interface for USB Test & Measurement Class (USBTMC) devices.
+//!
+//! This module is only available when the `usbtmc` feature is enabled. It uses the [`rusb`] crate
+//! (bindings to `libusb`) in order to talk to the device via its bulk endpoints. The framing of the
+//! USBTMC messages is done in this module, see the USBTMC specification (revision 1.0) for details.
+
+#![cfg(feature = "usbtmc")]
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use rusb::{
+    Device, DeviceHandle, Direction, GlobalContext, Recipient, RequestType, TransferType, devices,
+    request_type,
+};
+
+use crate::{InstrumentError, InstrumentInterface};
+
+/// Interface class code of USBTMC devices (application specific).
+const USBTMC_CLASS: u8 = 0xFE;
+/// Interface subclass code of USBTMC devices.
+const USBTMC_SUBCLASS: u8 = 0x03;
+
+/// Size of the header of every USBTMC bulk transfer.
+const HEADER_LEN: usize = 12;
+/// `MsgID` of a `DEV_DEP_MSG_OUT` bulk-out transfer.
+const DEV_DEP_MSG_OUT: u8 = 1;
+/// `MsgID` of a `REQUEST_DEV_DEP_MSG_IN` bulk-out transfer and of the bulk-in response.
+const REQUEST_DEV_DEP_MSG_IN: u8 = 2;
+/// Maximum number of bytes that we request from the device in one bulk-in transfer.
+const MAX_TRANSFER_SIZE: u32 = 64 * 1024;
+/// `bRequest` of the `INITIATE_ABORT_BULK_IN` control request.
+const INITIATE_ABORT_BULK_IN: u8 = 3;
+/// `bRequest` of the `CHECK_ABORT_BULK_IN_STATUS` control request.
+const CHECK_ABORT_BULK_IN_STATUS: u8 = 4;
+/// `USBTMC_status` of a successful control request.
+const STATUS_SUCCESS: u8 = 0x01;
+/// `USBTMC_status` of a control request that is still in progress.
+const STATUS_PENDING: u8 = 0x02;
+/// Interval at which the status of an abort is checked while the device is busy.
+const ABORT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A USBTMC interface using the [`rusb`] crate.
+///
+/// Devices can be opened by vendor and product ID, optionally in combination with a serial number
+/// in case multiple identical devices are connected.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{InstrumentInterface, UsbTmcInterface};
+///
+/// let mut inst_interface = UsbTmcInterface::open(0x0957, 0x1796).unwrap();
+/// println!("{}", inst_interface.query("*IDN?").unwrap());
+/// ```
+pub struct UsbTmcInterface {
+    handle: DeviceHandle<GlobalContext>,
+    interface_number: u8,
+    ep_bulk_in: u8,
+    ep_bulk_out: u8,
+    tag: u8,
+    read_buf: VecDeque<u8>,
+    terminator: Vec<u8>,
+    timeout: Duration,
+}
+
+impl UsbTmcInterface {
+    /// Open the first USBTMC device with the given vendor and product ID.
+    ///
+    /// The timeout is by default set to 3 seconds.
+    ///
+    /// # Arguments
+    /// * `vid` - USB vendor ID of the device.
+    /// * `pid` - USB product ID of the device.
+    pub fn open(vid: u16, pid: u16) -> Result<Self, InstrumentError> {
+        Self::find(vid, pid, None)
+    }
+
+    /// Open the USBTMC device with the given vendor ID, product ID, and serial number.
+    ///
+    /// The timeout is by default set to 3 seconds.
+    ///
+    /// # Arguments
+    /// * `vid` - USB vendor ID of the device.
+    /// * `pid` - USB product ID of the device.
+    /// * `serial_number` - Serial number of the device as reported in its USB descriptor.
+    pub fn open_with_serial_number(
+        vid: u16,
+        pid: u16,
+        serial_number: &str,
+    ) -> Result<Self, InstrumentError> {
+        Self::find(vid, pid, Some(serial_number))
+    }
+
+    /// Open the first USBTMC device that matches vendor ID, product ID, and serial number.
+    ///
+    /// If no serial number is given, it is not checked.
+    fn find(vid: u16, pid: u16, serial_number: Option<&str>) -> Result<Self, InstrumentError> {
+        for device in devices()?.iter() {
+            let desc = device.device_descriptor()?;
+            if desc.vendor_id() != vid || desc.product_id() != pid {
+                continue;
+            }
+            if let Some(sn) = serial_number {
+                let dev_sn = device
+                    .open()
+                    .and_then(|handle| handle.read_serial_number_string_ascii(&desc));
+                if dev_sn.ok().as_deref() != Some(sn) {
+                    continue;
+                }
+            }
+            if let Some(intf) = Self::from_device(&device)? {
+                return Ok(intf);
+            }
+        }
+        Err(InstrumentError::InvalidArgument(format!(
+            "No USBTMC device found with VID {vid:#06x}, PID {pid:#06x}, and serial number {serial_number:?}."
+        )))
+    }
+
+    /// Try to open a given USB device as USBTMC device.
+    ///
+    /// Returns `None` if the device does not have a USBTMC interface.
+    fn from_device(device: &Device<GlobalContext>) -> Result<Option<Self>, InstrumentError> {
+        let config = device.active_config_descriptor()?;
+        for interface in config.interfaces() {
+            for desc in interface.descriptors() {
+                if desc.class_code() != USBTMC_CLASS || desc.sub_class_code() != USBTMC_SUBCLASS {
+                    continue;
+                }
+                let mut ep_bulk_in = None;
+                let mut ep_bulk_out = None;
+                for ep in desc.endpoint_descriptors() {
+                    if ep.transfer_type() != TransferType::Bulk {
+                        continue;
+                    }
+                    match ep.direction() {
+                        Direction::In => ep_bulk_in = Some(ep.address()),
+                        Direction::Out => ep_bulk_out = Some(ep.address()),
+                    }
+                }
+                if let (Some(ep_bulk_in), Some(ep_bulk_out)) = (ep_bulk_in, ep_bulk_out) {
+                    let handle = device.open()?;
+                    // not supported on all platforms, in which case we simply try to claim
+                    let _ = handle.set_auto_detach_kernel_driver(true);
+                    handle.claim_interface(desc.interface_number())?;
+                    return Ok(Some(UsbTmcInterface {
+                        handle,
+                        interface_number: desc.interface_number(),
+                        ep_bulk_in,
+                        ep_bulk_out,
+                        tag: 0,
+                        read_buf: VecDeque::new(),
+                        terminator: b"\n".to_vec(),
+                        timeout: Duration::from_secs(3),
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the next `bTag` for a bulk-out transfer.
+    fn next_tag(&mut self) -> u8 {
+        self.tag = next_tag(self.tag);
+        self.tag
+    }
+
+    /// Request a message from the device and append its data to the read buffer.
+    ///
+    /// Transfers are requested until the device sets the end of message bit. If a bulk-in
+    /// transfer times out, it is aborted, such that the next transfer starts in sync with the
+    /// device.
+    fn fill_read_buf(&mut self) -> Result<(), InstrumentError> {
+        let (handle, ep_bulk_in, ep_bulk_out, timeout) = (
+            &self.handle,
+            self.ep_bulk_in,
+            self.ep_bulk_out,
+            self.timeout,
+        );
+        read_message(&mut self.tag, &mut self.read_buf, |tag| {
+            let request = request_dev_dep_msg_in(tag, MAX_TRANSFER_SIZE);
+            handle
+                .write_bulk(ep_bulk_out, &request, timeout)
+                .map_err(|e| transfer_error(e, timeout))?;
+
+            let mut buf = vec![0u8; HEADER_LEN + MAX_TRANSFER_SIZE as usize];
+            match handle.read_bulk(ep_bulk_in, &mut buf, timeout) {
+                Ok(len) => {
+                    buf.truncate(len);
+                    Ok(buf)
+                }
+                Err(rusb::Error::Timeout) => {
+                    abort_bulk_in(handle, ep_bulk_in, tag, timeout);
+                    Err(InstrumentError::Timeout(timeout))
+                }
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+}
+
+impl InstrumentInterface for UsbTmcInterface {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        while self.read_buf.len() < buf.len() {
+            self.fill_read_buf()?;
+        }
+        for byte in buf.iter_mut() {
+            // infallible, we ensured above that enough bytes are available
+            *byte = self.read_buf.pop_front().unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminator.as_slice()
+    }
+
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.terminator = terminator.to_vec();
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        let tag = self.next_tag();
+        let msg = dev_dep_msg_out(tag, data);
+        self.handle
+            .write_bulk(self.ep_bulk_out, &msg, self.timeout)
+            .map_err(|e| match e {
+                rusb::Error::Timeout => InstrumentError::TimeoutWrite(self.timeout),
+                e => e.into(),
+            })?;
+        Ok(())
+    }
+}
+
+impl Drop for UsbTmcInterface {
+    fn drop(&mut self) {
+        let _ = self.handle.release_interface(self.interface_number);
+    }
+}
+
+impl From<rusb::Error> for InstrumentError {
+    fn from(err: rusb::Error) -> Self {
+        let kind = match err {
+            rusb::Error::Timeout => std::io::ErrorKind::TimedOut,
+            rusb::Error::NoDevice | rusb::Error::NotFound => std::io::ErrorKind::NotFound,
+            rusb::Error::Access => std::io::ErrorKind::PermissionDenied,
+            rusb::Error::Busy => std::io::ErrorKind::ResourceBusy,
+            rusb::Error::Interrupted => std::io::ErrorKind::Interrupted,
+            rusb::Error::InvalidParam => std::io::ErrorKind::InvalidInput,
+            rusb::Error::NotSupported => std::io::ErrorKind::Unsupported,
+            rusb::Error::NoMem => std::io::ErrorKind::OutOfMemory,
+            _ => std::io::ErrorKind::Other,
+        };
+        InstrumentError::Io(std::io::Error::new(kind, err))
+    }
+}
+
+/// Convert an error of a bulk transfer, such that timeouts are reported as
+/// [`InstrumentError::Timeout`] with the timeout of the interface.
+fn transfer_error(err: rusb::Error, timeout: Duration) -> InstrumentError {
+    match err {
+        rusb::Error::Timeout => InstrumentError::Timeout(timeout),
+        err => err.into(),
+    }
+}
+
+/// Read a message that may span multiple bulk-in transfers and append its data to `out`.
+///
+/// The `transfer` function requests a bulk-in transfer with the given tag and returns it. Tags
+/// are taken from `tag`, which is updated. Transfers are requested until the end of message bit
+/// is set. If a transfer fails, the data of the previous transfers is kept in `out`.
+fn read_message(
+    tag: &mut u8,
+    out: &mut VecDeque<u8>,
+    mut transfer: impl FnMut(u8) -> Result<Vec<u8>, InstrumentError>,
+) -> Result<(), InstrumentError> {
+    loop {
+        *tag = next_tag(*tag);
+        let msg = transfer(*tag)?;
+        let (data, eom) = parse_dev_dep_msg_in(&msg, *tag)?;
+        out.extend(data);
+        if eom {
+            return Ok(());
+        }
+    }
+}
+
+/// Abort the bulk-in transfer with the given tag after it timed out.
+///
+/// This sends `INITIATE_ABORT_BULK_IN`, discards the data that the device still sends, and waits
+/// until `CHECK_ABORT_BULK_IN_STATUS` reports that the abort is done, at most for the timeout.
+/// The abort is best effort, errors are ignored.
+fn abort_bulk_in(handle: &DeviceHandle<GlobalContext>, ep_bulk_in: u8, tag: u8, timeout: Duration) {
+    let req_type = request_type(Direction::In, RequestType::Class, Recipient::Endpoint);
+    let mut resp = [0u8; 2];
+    match handle.read_control(
+        req_type,
+        INITIATE_ABORT_BULK_IN,
+        u16::from(tag),
+        u16::from(ep_bulk_in),
+        &mut resp,
+        timeout,
+    ) {
+        Ok(2) if resp[0] == STATUS_SUCCESS => {}
+        _ => return, // no transfer in progress or the abort failed
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut buf = vec![0u8; HEADER_LEN + MAX_TRANSFER_SIZE as usize];
+    while Instant::now() < deadline {
+        let mut status = [0u8; 8];
+        let Ok(8) = handle.read_control(
+            req_type,
+            CHECK_ABORT_BULK_IN_STATUS,
+            0,
+            u16::from(ep_bulk_in),
+            &mut status,
+            timeout,
+        ) else {
+            return;
+        };
+        if status[0] != STATUS_PENDING {
+            return;
+        }
+        // bmAbortBulkIn.D0: the device has data queued that must be read before it can finish
+        if status[1] & 0x01 == 0x01 {
+            let _ = handle.read_bulk(ep_bulk_in, &mut buf, timeout);
+        } else {
+            std::thread::sleep(ABORT_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Get the next `bTag` after the given one. Valid tags are in the range 1 to 255.
+fn next_tag(tag: u8) -> u8 {
+    tag.checked_add(1).unwrap_or(1)
+}
+
+/// Create the 12 byte bulk-out header for a given message ID and tag.
+///
+/// The message specific bytes 4 to 11 must be filled in by the caller.
+fn bulk_out_header(msg_id: u8, tag: u8) -> [u8; HEADER_LEN] {
+    let mut header = [0u8; HEADER_LEN];
+    header[0] = msg_id;
+    header[1] = tag;
+    header[2] = !tag;
+    header
+}
+
+/// Frame data into a `DEV_DEP_MSG_OUT` bulk-out transfer with the end of message bit set.
+///
+/// The transfer is padded with zeros to a multiple of four bytes.
+fn dev_dep_msg_out(tag: u8, data: &[u8]) -> Vec<u8> {
+    let mut header = bulk_out_header(DEV_DEP_MSG_OUT, tag);
+    header[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+    header[8] = 0x01; // EOM
+
+    let mut msg = header.to_vec();
+    msg.extend_from_slice(data);
+    msg.resize(msg.len().div_ceil(4) * 4, 0);
+    msg
+}
+
+/// Create a `REQUEST_DEV_DEP_MSG_IN` bulk-out transfer requesting at most `max_size` bytes.
+fn request_dev_dep_msg_in(tag: u8, max_size: u32) -> [u8; HEADER_LEN] {
+    let mut header = bulk_out_header(REQUEST_DEV_DEP_MSG_IN, tag);
+    header[4..8].copy_from_slice(&max_size.to_le_bytes());
+    header
+}
+
+/// Parse a `DEV_DEP_MSG_IN` bulk-in transfer that answers the request with the given tag.
+///
+/// Returns the data contained in the transfer and whether the end of message bit is set.
+fn parse_dev_dep_msg_in(msg: &[u8], tag: u8) -> Result<(&[u8], bool), InstrumentError> {
+    let invalid =
+        || InstrumentError::ResponseParseError(format!("Invalid USBTMC bulk-in transfer: {msg:?}"));
+    if msg.len() < HEADER_LEN || msg[0] != REQUEST_DEV_DEP_MSG_IN || msg[1] != tag || msg[2] != !tag
+    {
+        return Err(invalid());
+    }
+    let size = u32::from_le_bytes([msg[4], msg[5], msg[6], msg[7]]) as usize;
+    let eom = msg[8] & 0x01 == 0x01;
+    let data = msg.get(HEADER_LEN..HEADER_LEN + size).ok_or_else(invalid)?;
+    Ok((data, eom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(1, 2)]
+    #[case(254, 255)]
+    #[case(255, 1)]
+    fn test_next_tag(#[case] tag: u8, #[case] exp: u8) {
+        assert_eq!(exp, next_tag(tag));
+    }
+
+    /// The data must be padded to a multiple of four bytes.
+    #[rstest]
+    fn test_dev_dep_msg_out() {
+        let msg = dev_dep_msg_out(1, b"*IDN?\n");
+        let exp = [
+            0x01, 0x01, 0xFE, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, b'*', b'I',
+            b'D', b'N', b'?', b'\n', 0x00, 0x00,
+        ];
+        assert_eq!(exp.to_vec(), msg);
+    }
+
+    /// No padding is added if the data is already aligned.
+    #[rstest]
+    fn test_dev_dep_msg_out_aligned() {
+        let msg = dev_dep_msg_out(7, b"ABCD");
+        assert_eq!(16, msg.len());
+        assert_eq!(&msg[..3], &[0x01, 0x07, 0xF8]);
+        assert_eq!(&msg[12..], b"ABCD");
+    }
+
+    #[rstest]
+    fn test_request_dev_dep_msg_in() {
+        let msg = request_dev_dep_msg_in(2, 0x0102);
+        let exp = [
+            0x02, 0x02, 0xFD, 0x00, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(exp, msg);
+    }
+
+    /// Parse a response, ignoring the alignment bytes at the end.
+    #[rstest]
+    #[case(0x01, true)]
+    #[case(0x00, false)]
+    fn test_parse_dev_dep_msg_in(#[case] attr: u8, #[case] eom_exp: bool) {
+        let msg = [
+            0x02, 0x03, 0xFC, 0x00, 0x03, 0x00, 0x00, 0x00, attr, 0x00, 0x00, 0x00, b'a', b'b',
+            b'\n', 0x00,
+        ];
+        let (data, eom) = parse_dev_dep_msg_in(&msg, 3).unwrap();
+        assert_eq!(b"ab\n", data);
+        assert_eq!(eom_exp, eom);
+    }
+
+    /// Create a bulk-in transfer with the given tag, data, and end of message bit.
+    fn dev_dep_msg_in(tag: u8, data: &[u8], eom: bool) -> Vec<u8> {
+        let mut msg = bulk_out_header(REQUEST_DEV_DEP_MSG_IN, tag).to_vec();
+        msg[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        msg[8] = u8::from(eom);
+        msg.extend_from_slice(data);
+        msg
+    }
+
+    /// A message is reassembled from transfers until the end of message bit is set.
+    #[rstest]
+    fn test_read_message() {
+        let mut tag = 4;
+        let mut out = VecDeque::from(b"old".to_vec());
+        let mut chunks = vec![(&b"ab"[..], false), (b"cd", false), (b"\n", true)].into_iter();
+        read_message(&mut tag, &mut out, |tag| {
+            let (data, eom) = chunks.next().unwrap();
+            Ok(dev_dep_msg_in(tag, data, eom))
+        })
+        .unwrap();
+        assert_eq!(b"oldabcd\n", out.make_contiguous());
+        assert_eq!(7, tag);
+        assert!(chunks.next().is_none());
+    }
+
+    /// If a transfer fails, the data received so far is kept.
+    #[rstest]
+    fn test_read_message_timeout() {
+        let mut tag = 0;
+        let mut out = VecDeque::new();
+        let res = read_message(&mut tag, &mut out, |tag| match tag {
+            1 => Ok(dev_dep_msg_in(tag, b"ab", false)),
+            _ => Err(transfer_error(rusb::Error::Timeout, Duration::from_secs(1))),
+        });
+        assert!(matches!(res, Err(InstrumentError::Timeout(t)) if t == Duration::from_secs(1)));
+        assert_eq!(b"ab", out.make_contiguous());
+    }
+
+    /// Only timeouts of transfers are mapped to timeout errors.
+    #[rstest]
+    fn test_transfer_error() {
+        assert!(matches!(
+            transfer_error(rusb::Error::Pipe, Duration::from_secs(1)),
+            InstrumentError::Io(_)
+        ));
+    }
+
+    /// Wrong message IDs, tags, and truncated transfers must be rejected.
+    #[rstest]
+    #[case(vec![0x01, 0x03, 0xFC, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00])]
+    #[case(vec![0x02, 0x04, 0xFB, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00])]
+    #[case(vec![0x02, 0x03, 0xFC, 0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, b'a'])]
+    #[case(vec![0x02, 0x03, 0xFC])]
+    fn test_parse_dev_dep_msg_in_invalid(#[case] msg: Vec<u8>) {
+        assert!(parse_dev_dep_msg_in(&msg, 3).is_err());
+    }
+}
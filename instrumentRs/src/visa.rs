@@ -0,0 +1,343 @@
+//! This module provides an instrument interface that uses an installed VISA library.
+//!
+//! This module is only available when the `visa` feature is enabled. The VISA library (e.g.,
+//! NI-VISA, Keysight VISA, or R&S VISA) is loaded at runtime using the [`libloading`] crate, such
+//! that no VISA installation is required in order to compile `InstrumentRs`. Any VISA resource
+//! string, e.g., `"TCPIP0::192.168.1.10::INSTR"` or `"ASRL/dev/ttyUSB0::INSTR"`, can be opened.
+
+#![cfg(feature = "visa")]
+
+use std::{
+    collections::VecDeque,
+    ffi::{CString, c_char},
+    time::Duration,
+};
+
+use libloading::Library;
+
+use crate::{InstrumentError, InstrumentInterface};
+
+type ViStatus = i32;
+type ViSession = u32;
+type ViAttr = u32;
+type ViAttrState = usize;
+
+type ViOpenDefaultRm = unsafe extern "C" fn(*mut ViSession) -> ViStatus;
+type ViOpen = unsafe extern "C" fn(ViSession, *const c_char, u32, u32, *mut ViSession) -> ViStatus;
+type ViClose = unsafe extern "C" fn(ViSession) -> ViStatus;
+type ViRead = unsafe extern "C" fn(ViSession, *mut u8, u32, *mut u32) -> ViStatus;
+type ViWrite = unsafe extern "C" fn(ViSession, *const u8, u32, *mut u32) -> ViStatus;
+type ViSetAttribute = unsafe extern "C" fn(ViSession, ViAttr, ViAttrState) -> ViStatus;
+type ViStatusDesc = unsafe extern "C" fn(ViSession, ViStatus, *mut c_char) -> ViStatus;
+
+const VI_NULL: u32 = 0;
+const VI_TRUE: ViAttrState = 1;
+const VI_ERROR_TMO: ViStatus = 0xBFFF0015_u32 as ViStatus;
+const VI_ATTR_TMO_VALUE: ViAttr = 0x3FFF001A;
+const VI_ATTR_TERMCHAR: ViAttr = 0x3FFF0018;
+const VI_ATTR_TERMCHAR_EN: ViAttr = 0x3FFF0038;
+const VI_ATTR_ASRL_END_IN: ViAttr = 0x3FFF00B3;
+const VI_ASRL_END_TERMCHAR: ViAttrState = 2;
+
+/// Number of bytes that are requested from the VISA library in one read.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Default name of the VISA library on the current platform.
+#[cfg(target_os = "windows")]
+const DEFAULT_LIBRARY: &str = "visa64.dll";
+#[cfg(target_os = "macos")]
+const DEFAULT_LIBRARY: &str = "/Library/Frameworks/VISA.framework/VISA";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const DEFAULT_LIBRARY: &str = "libvisa.so";
+
+/// The functions of the VISA library that are used by the [`VisaInterface`].
+///
+/// The function pointers are only valid as long as the library is loaded, which is why the library
+/// is kept in the same struct.
+struct VisaLib {
+    _lib: Library,
+    open_default_rm: ViOpenDefaultRm,
+    open: ViOpen,
+    close: ViClose,
+    read: ViRead,
+    write: ViWrite,
+    set_attribute: ViSetAttribute,
+    status_desc: ViStatusDesc,
+}
+
+impl VisaLib {
+    /// Load the VISA library from the given path or name.
+    fn load(library: &str) -> Result<Self, InstrumentError> {
+        let load_err = |e: libloading::Error| {
+            InstrumentError::InvalidArgument(format!("Could not load VISA library {library}: {e}"))
+        };
+        // SAFETY: Loading the VISA library runs its initialization routines, which we have to
+        // trust. The symbols are looked up with the signatures defined in the VISA specification.
+        unsafe {
+            let lib = Library::new(library).map_err(load_err)?;
+            Ok(VisaLib {
+                open_default_rm: *lib.get(b"viOpenDefaultRM\0").map_err(load_err)?,
+                open: *lib.get(b"viOpen\0").map_err(load_err)?,
+                close: *lib.get(b"viClose\0").map_err(load_err)?,
+                read: *lib.get(b"viRead\0").map_err(load_err)?,
+                write: *lib.get(b"viWrite\0").map_err(load_err)?,
+                set_attribute: *lib.get(b"viSetAttribute\0").map_err(load_err)?,
+                status_desc: *lib.get(b"viStatusDesc\0").map_err(load_err)?,
+                _lib: lib,
+            })
+        }
+    }
+
+    /// Convert a VISA status into a result, querying the description of errors from the library.
+    fn check(&self, vi: ViSession, status: ViStatus) -> Result<(), InstrumentError> {
+        if status >= 0 {
+            return Ok(());
+        }
+        let mut desc = [0 as c_char; 256];
+        // SAFETY: The VISA specification requires a buffer of at least 256 bytes.
+        let desc = unsafe {
+            if (self.status_desc)(vi, status, desc.as_mut_ptr()) >= 0 {
+                std::ffi::CStr::from_ptr(desc.as_ptr())
+                    .to_string_lossy()
+                    .into_owned()
+            } else {
+                String::from("Unknown VISA error")
+            }
+        };
+        Err(InstrumentError::Visa {
+            status,
+            description: desc,
+        })
+    }
+}
+
+/// An interface to any instrument that can be reached through a VISA library.
+///
+/// The VISA library is loaded at runtime. By default, the platform specific default name of the
+/// library is used (`visa64.dll` on Windows, the VISA framework on macOS, and `libvisa.so` on
+/// other platforms). Use [`VisaInterface::open_with_library`] in order to specify the library
+/// path explicitly.
+///
+/// The terminator is mapped to the VISA termination character attribute, which means that only
+/// the last byte of the terminator is used by VISA to end a read.
+///
+/// # Example
+///
+/// ```no_run
+/// use instrumentrs::{InstrumentInterface, VisaInterface};
+///
+/// let mut inst_interface = VisaInterface::open("TCPIP0::192.168.1.10::INSTR").unwrap();
+/// println!("{}", inst_interface.query("*IDN?").unwrap());
+/// ```
+pub struct VisaInterface {
+    lib: VisaLib,
+    rm: ViSession,
+    vi: ViSession,
+    read_buf: VecDeque<u8>,
+    terminator: Vec<u8>,
+    timeout: Duration,
+}
+
+impl VisaInterface {
+    /// Open a VISA resource using the default VISA library of the platform.
+    ///
+    /// The timeout is by default set to 3 seconds.
+    ///
+    /// # Arguments
+    /// * `resource` - The VISA resource string, e.g., `"TCPIP0::192.168.1.10::INSTR"`.
+    pub fn open(resource: &str) -> Result<Self, InstrumentError> {
+        Self::open_with_library(DEFAULT_LIBRARY, resource)
+    }
+
+    /// Open a VISA resource using the VISA library at the given path.
+    ///
+    /// The timeout is by default set to 3 seconds.
+    ///
+    /// # Arguments
+    /// * `library` - Path or name of the VISA library.
+    /// * `resource` - The VISA resource string, e.g., `"TCPIP0::192.168.1.10::INSTR"`.
+    pub fn open_with_library(library: &str, resource: &str) -> Result<Self, InstrumentError> {
+        let lib = VisaLib::load(library)?;
+        let rsrc = CString::new(resource).map_err(|_| {
+            InstrumentError::InvalidArgument(format!("Invalid VISA resource string: {resource}"))
+        })?;
+
+        let mut rm: ViSession = 0;
+        let mut vi: ViSession = 0;
+        // SAFETY: The pointers are valid for the duration of the calls.
+        unsafe {
+            lib.check(VI_NULL, (lib.open_default_rm)(&mut rm))?;
+            let status = (lib.open)(rm, rsrc.as_ptr(), VI_NULL, VI_NULL, &mut vi);
+            if let Err(e) = lib.check(rm, status) {
+                (lib.close)(rm);
+                return Err(e);
+            }
+        }
+
+        let mut intf = VisaInterface {
+            lib,
+            rm,
+            vi,
+            read_buf: VecDeque::new(),
+            terminator: Vec::new(),
+            timeout: Duration::from_secs(3),
+        };
+        intf.set_attribute(VI_ATTR_TMO_VALUE, timeout_to_attr(intf.timeout))?;
+        intf.set_terminator_bytes(b"\n");
+        Ok(intf)
+    }
+
+    /// Set a VISA attribute of the opened resource.
+    fn set_attribute(&mut self, attr: ViAttr, value: ViAttrState) -> Result<(), InstrumentError> {
+        // SAFETY: The session is open as long as `self` exists.
+        let status = unsafe { (self.lib.set_attribute)(self.vi, attr, value) };
+        self.lib.check(self.vi, status)
+    }
+
+    /// Read the next chunk of data from the instrument into the read buffer.
+    fn fill_read_buf(&mut self) -> Result<(), InstrumentError> {
+        let mut buf = [0u8; READ_CHUNK_SIZE];
+        let mut ret_count = 0u32;
+        // SAFETY: The buffer is valid for `READ_CHUNK_SIZE` bytes.
+        let status = unsafe {
+            (self.lib.read)(
+                self.vi,
+                buf.as_mut_ptr(),
+                READ_CHUNK_SIZE as u32,
+                &mut ret_count,
+            )
+        };
+        if status == VI_ERROR_TMO {
+            return Err(InstrumentError::Timeout(self.timeout));
+        }
+        self.lib.check(self.vi, status)?;
+        self.read_buf.extend(&buf[..ret_count as usize]);
+        Ok(())
+    }
+}
+
+impl InstrumentInterface for VisaInterface {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        while self.read_buf.len() < buf.len() {
+            self.fill_read_buf()?;
+        }
+        for byte in buf.iter_mut() {
+            // infallible, we ensured above that enough bytes are available
+            *byte = self.read_buf.pop_front().unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    fn get_terminator_bytes(&self) -> &[u8] {
+        self.terminator.as_slice()
+    }
+
+    /// Set the terminator and map its last byte to the VISA termination character.
+    ///
+    /// Errors from the VISA library when setting the attributes are ignored, as not all resources
+    /// support all attributes (e.g., the serial end mode is only available for `ASRL` resources).
+    fn set_terminator_bytes(&mut self, terminator: &[u8]) {
+        self.terminator = terminator.to_vec();
+        if let Some(termchar) = termchar(terminator) {
+            let _ = self.set_attribute(VI_ATTR_TERMCHAR, termchar);
+            let _ = self.set_attribute(VI_ATTR_TERMCHAR_EN, VI_TRUE);
+            let _ = self.set_attribute(VI_ATTR_ASRL_END_IN, VI_ASRL_END_TERMCHAR);
+        }
+    }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Set the timeout and the VISA timeout attribute.
+    ///
+    /// Errors from the VISA library when setting the attribute are ignored.
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+        let _ = self.set_attribute(VI_ATTR_TMO_VALUE, timeout_to_attr(timeout));
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        let mut written = 0;
+        while written < data.len() {
+            let remaining = &data[written..];
+            let mut ret_count = 0u32;
+            // SAFETY: The buffer is valid for `remaining.len()` bytes.
+            let status = unsafe {
+                (self.lib.write)(
+                    self.vi,
+                    remaining.as_ptr(),
+                    remaining.len() as u32,
+                    &mut ret_count,
+                )
+            };
+            if status == VI_ERROR_TMO {
+                return Err(InstrumentError::TimeoutWrite(self.timeout));
+            }
+            self.lib.check(self.vi, status)?;
+            // a write without progress would otherwise be retried forever
+            if ret_count == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into());
+            }
+            written += ret_count as usize;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VisaInterface {
+    fn drop(&mut self) {
+        // SAFETY: Both sessions were opened in the constructor and are closed exactly once.
+        unsafe {
+            (self.lib.close)(self.vi);
+            (self.lib.close)(self.rm);
+        }
+    }
+}
+
+/// Get the VISA termination character for a given terminator, i.e., its last byte.
+fn termchar(terminator: &[u8]) -> Option<ViAttrState> {
+    terminator.last().map(|b| *b as ViAttrState)
+}
+
+/// Convert a timeout into the VISA timeout attribute value in milliseconds.
+fn timeout_to_attr(timeout: Duration) -> ViAttrState {
+    timeout.as_millis().try_into().unwrap_or(ViAttrState::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /// Drivers are generic over the [`InstrumentInterface`] and must accept the VISA interface.
+    #[rstest]
+    fn test_visa_interface_is_instrument_interface() {
+        fn assert_interface<T: InstrumentInterface>() {}
+        assert_interface::<VisaInterface>();
+    }
+
+    #[rstest]
+    #[case(b"\n", Some(0x0A))]
+    #[case(b"\r\n", Some(0x0A))]
+    #[case(b"\r", Some(0x0D))]
+    #[case(&[0x03], Some(0x03))]
+    #[case(b"", None)]
+    fn test_termchar(#[case] terminator: &[u8], #[case] exp: Option<ViAttrState>) {
+        assert_eq!(exp, termchar(terminator));
+    }
+
+    #[rstest]
+    #[case(Duration::from_secs(3), 3000)]
+    #[case(Duration::from_micros(1500), 1)]
+    fn test_timeout_to_attr(#[case] timeout: Duration, #[case] exp: ViAttrState) {
+        assert_eq!(exp, timeout_to_attr(timeout));
+    }
+
+    /// Loading a library that does not exist must return an error and not panic.
+    #[rstest]
+    fn test_open_missing_library() {
+        let res = VisaInterface::open_with_library("not-a-visa-library", "ASRL1::INSTR");
+        assert!(matches!(res, Err(InstrumentError::InvalidArgument(_))));
+    }
+}
@@ -0,0 +1,127 @@
+//! Tests for the asynchronous interfaces, only available with the `async` feature.
+
+#![cfg(feature = "async")]
+
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use instrumentrs::{
+    AsyncInstrument, AsyncInstrumentInterface, AsyncTcpIpInterface, InstrumentError,
+    InstrumentInterface, LoopbackInterface,
+};
+
+/// A function that creates a new `LoopbackInterface` with the given input and output vectors.
+fn crt_lbk(input: Vec<&str>, output: Vec<&str>) -> LoopbackInterface {
+    let input = input.iter().map(|s| s.to_string()).collect();
+    let output = output.iter().map(|s| s.to_string()).collect();
+    LoopbackInterface::new(input, output, "\n")
+}
+
+#[tokio::test]
+async fn loopback_query() {
+    let mut lbk = crt_lbk(vec!["cmd1", "cmd2"], vec!["resp1", "resp2"]);
+    assert_eq!(
+        AsyncInstrumentInterface::query(&mut lbk, "cmd1")
+            .await
+            .unwrap(),
+        "resp1"
+    );
+    assert_eq!(
+        AsyncInstrumentInterface::query(&mut lbk, "cmd2")
+            .await
+            .unwrap(),
+        "resp2"
+    );
+}
+
+/// Terminators that are not valid UTF-8 are used by the default implementations.
+#[tokio::test]
+async fn loopback_query_bytes_terminator() {
+    let mut lbk = LoopbackInterface::<Vec<u8>>::new(
+        vec![b"cmd\xff\x03".to_vec()],
+        vec![b"resp\xff\x03".to_vec()],
+        "",
+    );
+    InstrumentInterface::set_terminator_bytes(&mut lbk, &[0xff, 0x03]);
+    assert_eq!("", AsyncInstrumentInterface::get_terminator(&lbk));
+    assert_eq!(
+        "resp",
+        AsyncInstrumentInterface::query(&mut lbk, "cmd")
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn loopback_check_acknowledgment() {
+    let mut lbk = crt_lbk(vec!["cmd1"], vec!["ACK", "NACK"]);
+    AsyncInstrumentInterface::sendcmd(&mut lbk, "cmd1")
+        .await
+        .unwrap();
+    AsyncInstrumentInterface::check_acknowledgment(&mut lbk, "ACK")
+        .await
+        .unwrap();
+    assert!(
+        AsyncInstrumentInterface::check_acknowledgment(&mut lbk, "ACK")
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn instrument_write_read() {
+    let (port, mut remote) = tokio::io::duplex(64);
+    let mut inst = AsyncInstrument::new(port, Duration::from_secs(1));
+    inst.set_terminator("\r\n");
+    assert_eq!(inst.get_terminator(), "\r\n");
+
+    inst.sendcmd("CMD").await.unwrap();
+    let mut buf = [0u8; 5];
+    remote.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"CMD\r\n");
+
+    remote.write_all(b"resp\r\n").await.unwrap();
+    assert_eq!(inst.read_until_terminator().await.unwrap(), "resp");
+
+    remote.write_all(b" 1  2 \r\n 1  2 \r\n").await.unwrap();
+    assert_eq!(inst.read_until_terminator().await.unwrap(), " 1  2 ");
+    assert_eq!(inst.read_line_trimmed().await.unwrap(), "1  2");
+}
+
+/// The timeout is enforced even if the instrument does not send anything at all.
+#[tokio::test]
+async fn instrument_query_timeout() {
+    let (port, _remote) = tokio::io::duplex(64);
+    let timeout_exp = Duration::from_millis(10);
+    let mut inst = AsyncInstrument::new(port, timeout_exp);
+
+    match inst.query("QUERY").await {
+        Err(InstrumentError::TimeoutQuery { query, timeout, .. }) => {
+            assert_eq!("QUERY", query);
+            assert_eq!(timeout_exp, timeout);
+        }
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+}
+
+#[tokio::test]
+async fn tcp_ip_simple_query() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 6];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"*IDN?\n");
+        stream.write_all(b"MyInstrument\n").await.unwrap();
+    });
+
+    let mut inst = AsyncTcpIpInterface::simple(addr).await.unwrap();
+    assert_eq!(inst.query("*IDN?").await.unwrap(), "MyInstrument");
+    server.await.unwrap();
+}
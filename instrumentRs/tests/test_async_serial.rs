@@ -0,0 +1,51 @@
+//! Tests for the asynchronous serial interface, only available with the `serial-async` feature.
+//!
+//! The tests use a pseudo-terminal pair, which is only available on unix systems.
+
+#![cfg(all(feature = "serial-async", unix))]
+
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_serial::{SerialPort, SerialStream};
+
+use instrumentrs::{AsyncInstrumentInterface, AsyncSerialInterface};
+
+/// Create a pseudo-terminal pair and return the instrument side and the path of the host side.
+///
+/// The host side is returned as well, such that it stays open until the test opened it by path.
+fn pty_pair() -> (SerialStream, SerialStream, String) {
+    let (remote, host) = SerialStream::pair().expect("Failed to create pseudo-terminal pair");
+    let path = host.name().expect("Pseudo-terminal has no name");
+    (remote, host, path)
+}
+
+/// Query round trip through a pseudo-terminal opened with a full port configuration.
+#[tokio::test]
+async fn pty_full_query_round_trip() {
+    let (mut remote, host, path) = pty_pair();
+    let builder = tokio_serial::new(&path, 115_200).timeout(Duration::from_millis(500));
+    let mut inst = AsyncSerialInterface::full(builder).unwrap();
+    assert_eq!(Duration::from_millis(500), inst.get_timeout());
+    // The settings of the port are shared by all handles to it.
+    assert_eq!(115_200, host.baud_rate().unwrap());
+
+    let instrument = tokio::spawn(async move {
+        let mut buf = [0u8; 6];
+        remote.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"*IDN?\n");
+        remote.write_all(b"MyInstrument\n").await.unwrap();
+        remote
+    });
+
+    assert_eq!(inst.query("*IDN?").await.unwrap(), "MyInstrument");
+    let _remote = instrument.await.unwrap();
+}
+
+/// The simple port configuration uses the default timeout.
+#[tokio::test]
+async fn pty_simple_timeout() {
+    let (_remote, _host, path) = pty_pair();
+    let inst = AsyncSerialInterface::simple(&path, 9600).unwrap();
+    assert_eq!(Duration::from_secs(3), inst.get_timeout());
+}
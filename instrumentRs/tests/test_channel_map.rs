@@ -0,0 +1,91 @@
+//! Tests for the [`ChannelMap`] helper of instrument drivers.
+
+use rstest::*;
+
+use instrumentrs::{ChannelMap, InstrumentError};
+
+#[rstest]
+#[case(0)]
+#[case(3)]
+fn check_index_valid(#[case] idx: usize) {
+    let channels = ChannelMap::new(4);
+    assert_eq!(idx, channels.check_index(idx).unwrap());
+}
+
+#[rstest]
+#[case(4)]
+#[case(usize::MAX)]
+fn check_index_out_of_range(#[case] idx: usize) {
+    let channels = ChannelMap::new(4);
+    match channels.check_index(idx) {
+        Err(InstrumentError::ChannelIndexOutOfRange {
+            idx: idx_err,
+            nof_channels,
+        }) => {
+            assert_eq!(idx, idx_err);
+            assert_eq!(4, nof_channels);
+        }
+        _ => panic!("Expected ChannelIndexOutOfRange error"),
+    }
+}
+
+#[rstest]
+fn set_num_channels() {
+    let mut channels = ChannelMap::new(16);
+    channels.set_num_channels(6).unwrap();
+    assert_eq!(6, channels.get_num_channels());
+    assert!(channels.check_index(5).is_ok());
+    assert!(channels.check_index(6).is_err());
+}
+
+/// The number of channels of a one or two channel instrument can only be set to 1 or 2.
+#[rstest]
+#[case(0, false)]
+#[case(1, true)]
+#[case(2, true)]
+#[case(3, false)]
+fn set_num_channels_allowed_range(#[case] num: usize, #[case] valid: bool) {
+    let mut channels = ChannelMap::new(2).with_allowed_range(1..=2).unwrap();
+    match channels.set_num_channels(num) {
+        Ok(()) => {
+            assert!(valid);
+            assert_eq!(num, channels.get_num_channels());
+        }
+        Err(InstrumentError::IntValueOutOfRange { value, min, max }) => {
+            assert!(!valid);
+            assert_eq!((num as i64, 1, 2), (value, min, max));
+            assert_eq!(2, channels.get_num_channels());
+        }
+        Err(e) => panic!("Unexpected error: {e}"),
+    }
+}
+
+/// Without an allowed range, any number of channels except zero can be set.
+#[rstest]
+fn set_num_channels_default_range() {
+    let mut channels = ChannelMap::new(1);
+    match channels.set_num_channels(0) {
+        Err(InstrumentError::InvalidArgument(msg)) => {
+            assert_eq!("Number of channels must be greater than 0", msg);
+        }
+        res => panic!("Expected InvalidArgument error, but got: {res:?}"),
+    }
+    channels.set_num_channels(100).unwrap();
+}
+
+/// The number of channels of the map must be within the allowed range.
+#[rstest]
+fn with_allowed_range_invalid() {
+    match ChannelMap::new(3).with_allowed_range(1..=2) {
+        Err(InstrumentError::IntValueOutOfRange { value, min, max }) => {
+            assert_eq!((3, 1, 2), (value, min, max));
+        }
+        res => panic!("Expected IntValueOutOfRange error, but got: {res:?}"),
+    }
+}
+
+#[rstest]
+fn indices() {
+    let channels = ChannelMap::new(3);
+    assert_eq!(vec![0, 1, 2], channels.indices().collect::<Vec<_>>());
+}
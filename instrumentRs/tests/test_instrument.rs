@@ -1,13 +1,19 @@
 //! Tests for the [`Instrument`] interface itself.
 //!
 //! Note that many of the functionality of the [`InstrumentInterface`] trait is tested in the
-//! [`instrumentrs::LoopbackInterfaceString`] tests.
+//! [`instrumentrs::LoopbackInterface`] tests.
 
-use std::{collections::VecDeque, time::Duration};
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    io::{Read, Write},
+    rc::Rc,
+    time::Duration,
+};
 
 use rstest::*;
 
-use instrumentrs::{Instrument, InstrumentError, InstrumentInterface};
+use instrumentrs::{DEFAULT_MAX_RESPONSE_LEN, Instrument, InstrumentError, InstrumentInterface};
 
 /// Set up a empty instrument with default 3 second timeout.
 #[fixture]
@@ -32,6 +38,52 @@ fn test_instrument_terminator(mut empt_inst: Instrument<VecDeque<u8>>) {
     assert_eq!(empt_inst.get_terminator(), "\r\n");
 }
 
+#[rstest]
+fn test_instrument_terminator_bytes(mut empt_inst: Instrument<VecDeque<u8>>) {
+    assert_eq!(empt_inst.get_terminator_bytes(), b"\n");
+
+    empt_inst.set_terminator_bytes(&[0x03]);
+    assert_eq!(empt_inst.get_terminator_bytes(), &[0x03]);
+
+    empt_inst.set_terminator("\r\n");
+    assert_eq!(empt_inst.get_terminator_bytes(), b"\r\n");
+}
+
+/// Terminators that are not valid UTF-8 are returned lossy as a string.
+#[rstest]
+fn test_instrument_terminator_invalid_utf8(mut empt_inst: Instrument<VecDeque<u8>>) {
+    empt_inst.set_terminator_bytes(&[0xff, 0x03]);
+    assert_eq!("\u{fffd}\u{3}", empt_inst.get_terminator());
+    assert_eq!(&[0xff, 0x03], empt_inst.get_terminator_bytes());
+}
+
+#[rstest]
+#[case(&[0x03])]
+#[case(b"\r\n")]
+#[case(&[b'\r', 0x00])]
+fn test_instrument_query_terminator_bytes(
+    mut empt_inst: Instrument<VecDeque<u8>>,
+    #[case] terminator: &[u8],
+) {
+    empt_inst.set_terminator_bytes(terminator);
+
+    // `VecDeque` reads back what was written, so the query reads back the command itself.
+    assert_eq!("CMD", empt_inst.query("CMD").unwrap());
+}
+
+#[rstest]
+fn test_instrument_read_until_terminator_keeps_whitespace(mut empt_inst: Instrument<VecDeque<u8>>) {
+    empt_inst.set_terminator("\r\n");
+    empt_inst.write_raw(b"  padded  name \t\r\n").unwrap();
+    assert_eq!(
+        "  padded  name \t",
+        empt_inst.read_until_terminator().unwrap()
+    );
+
+    empt_inst.write_raw(b"  padded  name \t\r\n").unwrap();
+    assert_eq!("padded  name", empt_inst.read_line_trimmed().unwrap());
+}
+
 #[rstest]
 fn test_instrument_timeout(empt_inst: Instrument<VecDeque<u8>>) {
     assert_eq!(empt_inst.get_timeout(), std::time::Duration::from_secs(3));
@@ -65,10 +117,861 @@ fn test_instrument_query_timeout(mut no_term_inst: Instrument<VecDeque<u8>>) {
     let query_exp = "QUERY";
 
     match no_term_inst.query(query_exp) {
-        Err(InstrumentError::TimeoutQuery { query, timeout }) => {
+        Err(InstrumentError::TimeoutQuery {
+            query,
+            timeout,
+            partial,
+        }) => {
             assert_eq!(query_exp, query);
             assert_eq!(timeout_exp, timeout);
+            assert!(partial.is_empty());
+        }
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+}
+
+/// A response that is already buffered is returned even if the timeout is zero.
+#[rstest]
+fn test_instrument_read_buffered_zero_timeout() {
+    let mut inst = Instrument::new(VecDeque::from(b"a\nb\n".to_vec()), Duration::from_secs(1));
+    assert_eq!("a", inst.read_until_terminator().unwrap());
+    assert_eq!(
+        "b",
+        inst.read_until_terminator_with_timeout(Duration::ZERO)
+            .unwrap()
+    );
+}
+
+/// Only a trailing fragment without terminator is returned in a timeout error.
+#[rstest]
+fn test_instrument_read_buffered_line_and_fragment() {
+    let mut inst = Instrument::new(VecDeque::from(b"x\na\nb".to_vec()), Duration::from_secs(1));
+    assert_eq!("x", inst.read_until_terminator().unwrap());
+    assert_eq!(
+        "a",
+        inst.read_until_terminator_with_timeout(Duration::ZERO)
+            .unwrap()
+    );
+    match inst.read_until_terminator_with_timeout(Duration::ZERO) {
+        Err(InstrumentError::TimeoutPartial { partial, .. }) => assert_eq!(b"b", &partial[..]),
+        res => panic!("Expected partial timeout error, but got: {res:?}"),
+    }
+}
+
+#[rstest]
+fn test_instrument_read_until_terminator_with_timeout() {
+    let mut inst = Instrument::new(VecDeque::from(b"resp\n".to_vec()), Duration::from_secs(0));
+
+    // The override allows reading, while the default timeout of zero would not.
+    assert_eq!(
+        "resp",
+        inst.read_until_terminator_with_timeout(Duration::from_secs(3))
+            .unwrap()
+    );
+    assert_eq!(Duration::from_secs(0), inst.get_timeout());
+
+    inst.write_raw(b"resp\n").unwrap();
+    match inst.read_until_terminator() {
+        Err(InstrumentError::Timeout(timeout)) => {
+            assert_eq!(Duration::from_secs(0), timeout);
+        }
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+}
+
+#[rstest]
+fn test_instrument_query_with_timeout(mut empt_inst: Instrument<VecDeque<u8>>) {
+    let timeout_exp = Duration::from_secs(0);
+
+    match empt_inst.query_with_timeout("QUERY", timeout_exp) {
+        Err(InstrumentError::TimeoutQuery { timeout, .. }) => {
+            assert_eq!(timeout_exp, timeout);
+        }
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+    assert_eq!(Duration::from_secs(3), empt_inst.get_timeout());
+}
+
+/// A port that returns at most `chunk_size` bytes per read and counts the number of reads.
+struct ChunkedPort {
+    data: VecDeque<u8>,
+    chunk_size: usize,
+    nof_reads: Rc<Cell<usize>>,
+}
+
+impl ChunkedPort {
+    fn new(data: &[u8], chunk_size: usize) -> Self {
+        Self {
+            data: VecDeque::from(data.to_vec()),
+            chunk_size,
+            nof_reads: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+impl Read for ChunkedPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.nof_reads.set(self.nof_reads.get() + 1);
+        let len = buf.len().min(self.chunk_size);
+        self.data.read(&mut buf[..len])
+    }
+}
+
+impl Write for ChunkedPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+#[case(1)]
+#[case(2)]
+#[case(3)]
+#[case(1024)]
+fn test_instrument_read_until_terminator_chunks(#[case] chunk_size: usize) {
+    let port = ChunkedPort::new(b"first\r\nsecond\r\nrest", chunk_size);
+    let mut inst = Instrument::new(port, Duration::from_secs(3));
+    inst.set_terminator("\r\n");
+
+    assert_eq!("first", inst.read_until_terminator().unwrap());
+    assert_eq!("second", inst.read_until_terminator().unwrap());
+
+    // Leftover bytes after the terminator are still available.
+    let mut buf = [0u8; 4];
+    inst.read_exact(&mut buf).unwrap();
+    assert_eq!(b"rest", &buf);
+}
+
+#[rstest]
+fn test_instrument_read_until_terminator_long_response() {
+    let mut data = "a".repeat(10_000).into_bytes();
+    data.extend(b"\nnext\n");
+    let port = ChunkedPort::new(&data, usize::MAX);
+    let nof_reads = port.nof_reads.clone();
+    let mut inst = Instrument::new(port, Duration::from_secs(3));
+
+    assert_eq!("a".repeat(10_000), inst.read_until_terminator().unwrap());
+    assert_eq!("next", inst.read_until_terminator().unwrap());
+
+    // The port is read in chunks and not byte by byte.
+    assert!(nof_reads.get() < 20);
+}
+
+#[rstest]
+#[case(b"\x02data\x03\x12\x34", 2, b"\x02data\x03\x12\x34")]
+#[case(b"\x02data\x03\x03\x03", 2, b"\x02data\x03\x03\x03")]
+#[case(b"\x02data\x03\x03\x03", 0, b"\x02data\x03")]
+fn test_instrument_read_until_byte(
+    mut empt_inst: Instrument<VecDeque<u8>>,
+    #[case] data: &[u8],
+    #[case] extra_bytes: usize,
+    #[case] frame_exp: &[u8],
+) {
+    empt_inst.write_raw(data).unwrap();
+    assert_eq!(
+        frame_exp,
+        empt_inst.read_until_byte(0x03, extra_bytes).unwrap()
+    );
+}
+
+#[rstest]
+fn test_instrument_read_until_byte_frames(mut empt_inst: Instrument<VecDeque<u8>>) {
+    // The checksum of the first frame contains the delimiter.
+    empt_inst
+        .write_raw(b"\x02a\x03\x03\x01\x02b\x03\xAA\xBB")
+        .unwrap();
+    assert_eq!(
+        b"\x02a\x03\x03\x01",
+        empt_inst.read_until_byte(0x03, 2).unwrap().as_slice()
+    );
+    assert_eq!(
+        b"\x02b\x03\xAA\xBB",
+        empt_inst.read_until_byte(0x03, 2).unwrap().as_slice()
+    );
+}
+
+#[rstest]
+fn test_instrument_read_until_byte_timeout(mut no_term_inst: Instrument<VecDeque<u8>>) {
+    match no_term_inst.read_until_byte(0x03, 2) {
+        Err(InstrumentError::Timeout(timeout)) => {
+            assert_eq!(Duration::from_secs(0), timeout);
         }
         _ => panic!("Expected timeout error, but got a different result."),
     }
 }
+
+#[rstest]
+fn test_instrument_query_raw(mut empt_inst: Instrument<VecDeque<u8>>) {
+    // `VecDeque` reads back what was written, so the query reads back the command itself.
+    assert_eq!(
+        b"\x01\x02",
+        empt_inst.query_raw(b"\x01\x02\x03", 2).unwrap().as_slice()
+    );
+    assert_eq!(
+        b"\x03\x04\x05",
+        empt_inst
+            .query_raw_until(b"\x04\x05\x06", 0x05)
+            .unwrap()
+            .as_slice()
+    );
+}
+
+#[rstest]
+fn test_instrument_query_raw_timeout(mut no_term_inst: Instrument<VecDeque<u8>>) {
+    match no_term_inst.query_raw(b"QUERY", 2) {
+        Err(InstrumentError::TimeoutQuery { query, timeout, .. }) => {
+            assert_eq!("QUERY", query);
+            assert_eq!(Duration::from_secs(0), timeout);
+        }
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+    match no_term_inst.query_raw_until(b"QUERY", 0x03) {
+        Err(InstrumentError::TimeoutQuery { query, timeout, .. }) => {
+            assert_eq!("QUERY", query);
+            assert_eq!(Duration::from_secs(0), timeout);
+        }
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+}
+
+#[rstest]
+fn test_instrument_drain_input(mut empt_inst: Instrument<VecDeque<u8>>) {
+    empt_inst.write_raw(b"stale\ndata").unwrap();
+    assert_eq!("stale", empt_inst.read_until_terminator().unwrap());
+
+    // Buffered and not yet buffered data is drained.
+    empt_inst.write_raw(b" and more").unwrap();
+    assert_eq!(13, empt_inst.drain_input().unwrap());
+    assert_eq!(0, empt_inst.drain_input().unwrap());
+
+    empt_inst.write_raw(b"resp\n").unwrap();
+    assert_eq!("resp", empt_inst.read_until_terminator().unwrap());
+}
+
+#[rstest]
+fn test_instrument_set_timeout(mut empt_inst: Instrument<VecDeque<u8>>) {
+    empt_inst.write_raw(b"resp\n").unwrap();
+    empt_inst.set_timeout(Duration::from_secs(0));
+    assert_eq!(Duration::from_secs(0), empt_inst.get_timeout());
+
+    match empt_inst.read_until_terminator() {
+        Err(InstrumentError::Timeout(timeout)) => {
+            assert_eq!(Duration::from_secs(0), timeout);
+        }
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+}
+
+#[rstest]
+fn test_instrument_inter_command_delay(empt_inst: Instrument<VecDeque<u8>>) {
+    let delay = Duration::from_millis(50);
+    let mut inst = empt_inst.with_inter_command_delay(delay);
+    assert_eq!(Some(delay), inst.get_inter_command_delay());
+
+    // The first write is not delayed, the following ones are paced.
+    let tic = std::time::Instant::now();
+    inst.write_raw(b"first").unwrap();
+    inst.sendcmd("second").unwrap();
+    assert_eq!("firstsecond", inst.query("third").unwrap());
+    assert!(tic.elapsed() >= 2 * delay);
+
+    // Without a delay, writes are not paced.
+    inst.set_inter_command_delay(None);
+    let tic = std::time::Instant::now();
+    inst.write_raw(b"first").unwrap();
+    inst.write_raw(b"second").unwrap();
+    assert!(tic.elapsed() < delay);
+}
+
+#[rstest]
+fn test_instrument_invalid_utf8_lossy(mut empt_inst: Instrument<VecDeque<u8>>) {
+    empt_inst.write_raw(b"re\xFFsp\nnext\n").unwrap();
+    assert_eq!("re\u{FFFD}sp", empt_inst.read_until_terminator().unwrap());
+    assert_eq!("next", empt_inst.read_until_terminator().unwrap());
+}
+
+#[rstest]
+fn test_instrument_invalid_utf8_strict(mut empt_inst: Instrument<VecDeque<u8>>) {
+    empt_inst.set_strict_utf8(true);
+    empt_inst.write_raw(b"re\xFFsp\nnext\n").unwrap();
+    match empt_inst.read_until_terminator() {
+        Err(InstrumentError::InvalidData(data)) => assert_eq!(b"re\xFFsp", data.as_slice()),
+        _ => panic!("Expected invalid data error, but got a different result."),
+    }
+    assert_eq!("next", empt_inst.read_until_terminator().unwrap());
+}
+
+/// A port that never has data available and would always block.
+struct WouldBlockPort;
+
+impl Read for WouldBlockPort {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::WouldBlock.into())
+    }
+}
+
+impl Write for WouldBlockPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+fn test_instrument_timeout_would_block() {
+    let timeout = Duration::from_millis(50);
+    let mut inst = Instrument::new(WouldBlockPort, timeout);
+
+    let tic = std::time::Instant::now();
+    match inst.read_until_terminator() {
+        Err(InstrumentError::Timeout(tout)) => assert_eq!(timeout, tout),
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+    assert!(tic.elapsed() >= timeout);
+
+    let mut buf = [0u8; 1];
+    match inst.read_exact(&mut buf) {
+        Err(InstrumentError::Timeout(tout)) => assert_eq!(timeout, tout),
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+}
+
+/// A `VecDeque` backed port that would block instead of returning EOF when empty.
+///
+/// Data written to the port is discarded.
+struct StallingPort(VecDeque<u8>);
+
+impl StallingPort {
+    fn new(data: &[u8]) -> Self {
+        Self(VecDeque::from(data.to_vec()))
+    }
+}
+
+impl Read for StallingPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
+        self.0.read(buf)
+    }
+}
+
+impl Write for StallingPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+fn test_instrument_read_until_terminator_timeout_partial() {
+    let timeout_exp = Duration::from_millis(10);
+    let mut inst = Instrument::new(StallingPort::new(b"resp\r"), timeout_exp);
+
+    match inst.read_until_terminator() {
+        Err(InstrumentError::TimeoutPartial { timeout, partial }) => {
+            assert_eq!(timeout_exp, timeout);
+            assert_eq!(b"resp\r", partial.as_slice());
+        }
+        _ => panic!("Expected timeout error with partial data, but got a different result."),
+    }
+
+    // The partial data is consumed and not returned again.
+    match inst.read_until_terminator() {
+        Err(InstrumentError::Timeout(timeout)) => assert_eq!(timeout_exp, timeout),
+        _ => panic!("Expected timeout error without partial data, but got a different result."),
+    }
+}
+
+#[rstest]
+fn test_instrument_timeout_partial_display() {
+    let err = InstrumentError::TimeoutPartial {
+        timeout: Duration::from_secs(1),
+        partial: b"resp\r".to_vec(),
+    };
+    assert!(
+        err.to_string()
+            .ends_with(r#"Partial response received: "resp\r""#)
+    );
+
+    let err = InstrumentError::TimeoutQuery {
+        query: "QUERY".to_string(),
+        timeout: Duration::from_secs(1),
+        partial: Vec::new(),
+    };
+    assert!(!err.to_string().contains("Partial response"));
+}
+
+#[rstest]
+#[case(b"resp\n", "resp", true)]
+#[case(b"resp\r", "resp\r", false)]
+#[case(b"", "", false)]
+fn test_instrument_read_until_terminator_lossy(
+    #[case] data: &[u8],
+    #[case] exp: &str,
+    #[case] exp_terminated: bool,
+) {
+    let mut inst = Instrument::new(StallingPort::new(data), Duration::from_millis(10));
+    assert_eq!(
+        (exp.to_string(), exp_terminated),
+        inst.read_until_terminator_lossy().unwrap()
+    );
+}
+
+#[rstest]
+fn test_instrument_query_multiline_timeout() {
+    let timeout_exp = Duration::from_millis(10);
+    let port = StallingPort::new(b"line 1\r\nline 2\r\nline");
+    let mut inst = Instrument::new(port, timeout_exp);
+    inst.set_terminator("\r\n");
+
+    // The third line is not terminated.
+    match inst.query_multiline("LIST", 4) {
+        Err(InstrumentError::TimeoutQueryLine {
+            query,
+            line,
+            timeout,
+            partial,
+        }) => {
+            assert_eq!("LIST", query);
+            assert_eq!(2, line);
+            assert_eq!(timeout_exp, timeout);
+            assert_eq!(b"line", partial.as_slice());
+        }
+        _ => panic!("Expected timeout error on line three, but got a different result."),
+    }
+}
+
+#[rstest]
+#[case(true, b"QUERY\nresp\n", Ok("resp"))]
+#[case(true, b"QUERX\nresp\n", Err("QUERX"))]
+#[case(false, b"resp\n", Ok("resp"))]
+fn test_instrument_echo(#[case] echo: bool, #[case] data: &[u8], #[case] exp: Result<&str, &str>) {
+    let mut inst = Instrument::new(StallingPort::new(data), Duration::from_millis(10));
+    inst.set_echo(echo);
+    assert_eq!(echo, inst.get_echo());
+
+    match (inst.query("QUERY"), exp) {
+        (Ok(resp), Ok(exp)) => assert_eq!(exp, resp),
+        (Err(InstrumentError::NotAcknowledged(resp)), Err(exp)) => assert_eq!(exp, resp),
+        (res, _) => panic!("Unexpected result: {res:?}"),
+    }
+}
+
+/// A port that records the data of every flushed write.
+#[derive(Default)]
+struct WriteRecordingPort {
+    pending: Vec<u8>,
+    writes: Rc<std::cell::RefCell<Vec<Vec<u8>>>>,
+}
+
+impl Read for WriteRecordingPort {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for WriteRecordingPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writes
+            .borrow_mut()
+            .push(std::mem::take(&mut self.pending));
+        Ok(())
+    }
+}
+
+#[rstest]
+fn test_instrument_write_raw_chunked() {
+    let port = WriteRecordingPort::default();
+    let writes = Rc::clone(&port.writes);
+    let mut inst = Instrument::new(port, Duration::from_millis(10));
+
+    let delay = Duration::from_millis(20);
+    let tic = std::time::Instant::now();
+    inst.write_raw_chunked(b"abcdefgh", 3, delay).unwrap();
+    assert!(tic.elapsed() >= 2 * delay);
+    assert_eq!(
+        vec![b"abc".to_vec(), b"def".to_vec(), b"gh".to_vec()],
+        *writes.borrow()
+    );
+
+    // Writing without chunking writes everything at once.
+    writes.borrow_mut().clear();
+    inst.write_raw(b"abcdefgh").unwrap();
+    assert_eq!(vec![b"abcdefgh".to_vec()], *writes.borrow());
+}
+
+#[rstest]
+fn test_instrument_write_chunking_sendcmd() {
+    let port = WriteRecordingPort::default();
+    let writes = Rc::clone(&port.writes);
+    let chunking = (4, Duration::ZERO);
+    let mut inst =
+        Instrument::new(port, Duration::from_millis(10)).with_write_chunking(4, Duration::ZERO);
+    assert_eq!(Some(chunking), inst.get_write_chunking());
+
+    inst.sendcmd("LONGCMD").unwrap();
+    assert_eq!(vec![b"LONG".to_vec(), b"CMD\n".to_vec()], *writes.borrow());
+
+    inst.set_write_chunking(None);
+    assert_eq!(None, inst.get_write_chunking());
+    writes.borrow_mut().clear();
+    inst.sendcmd("LONGCMD").unwrap();
+    assert_eq!(vec![b"LONGCMD\n".to_vec()], *writes.borrow());
+}
+
+#[rstest]
+fn test_instrument_write_chunking_zero_chunk_size(mut empt_inst: Instrument<VecDeque<u8>>) {
+    assert!(matches!(
+        empt_inst.write_raw_chunked(b"data", 0, Duration::ZERO),
+        Err(InstrumentError::InvalidArgument(_))
+    ));
+    empt_inst.set_write_chunking(Some((0, Duration::ZERO)));
+    assert!(matches!(
+        empt_inst.sendcmd("CMD"),
+        Err(InstrumentError::InvalidArgument(_))
+    ));
+}
+
+/// A port that accepts at most one byte per write and would block on every other write.
+///
+/// If `stuck`, every write would block. The write timeouts that are set are recorded.
+#[derive(Default)]
+struct SlowWritePort {
+    written: Vec<u8>,
+    stuck: bool,
+    block_next: bool,
+    write_timeouts: Rc<std::cell::RefCell<Vec<Duration>>>,
+}
+
+impl Read for SlowWritePort {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for SlowWritePort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.block_next = !self.block_next;
+        if self.stuck || !self.block_next {
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
+        self.written.push(buf[0]);
+        Ok(1)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+fn test_instrument_write_would_block() {
+    let mut inst = Instrument::new(SlowWritePort::default(), Duration::from_secs(1));
+    inst.sendcmd("CMD").unwrap();
+    assert_eq!(b"CMD\n", inst.get_ref().written.as_slice());
+}
+
+#[rstest]
+fn test_instrument_write_timeout() {
+    let timeout_exp = Duration::from_millis(20);
+    let port = SlowWritePort {
+        stuck: true,
+        ..Default::default()
+    };
+    let mut inst = Instrument::new(port, timeout_exp);
+
+    let tic = std::time::Instant::now();
+    match inst.sendcmd("CMD") {
+        Err(InstrumentError::TimeoutWrite(timeout)) => assert_eq!(timeout_exp, timeout),
+        res => panic!("Expected a write timeout, but got: {res:?}"),
+    }
+    assert!(tic.elapsed() >= timeout_exp);
+    assert!(
+        InstrumentError::TimeoutWrite(timeout_exp)
+            .to_string()
+            .contains("writing")
+    );
+}
+
+#[rstest]
+fn test_instrument_port_write_timeout() {
+    let port = SlowWritePort::default();
+    let write_timeouts = Rc::clone(&port.write_timeouts);
+    let mut inst =
+        Instrument::new(port, Duration::from_secs(1)).with_port_write_timeout(|port, timeout| {
+            port.write_timeouts.borrow_mut().push(timeout);
+            Ok(())
+        });
+    assert_eq!(vec![Duration::from_secs(1)], *write_timeouts.borrow());
+
+    inst.set_timeout(Duration::from_secs(2));
+    assert_eq!(
+        Some(&Duration::from_secs(2)),
+        write_timeouts.borrow().last()
+    );
+
+    // Before every write, the remaining time is set, and the timeout is reset afterwards.
+    write_timeouts.borrow_mut().clear();
+    inst.write_raw(b"a").unwrap();
+    let timeouts = write_timeouts.borrow();
+    assert!(timeouts.len() > 2);
+    assert!(
+        timeouts[..timeouts.len() - 1]
+            .iter()
+            .all(|timeout| *timeout <= Duration::from_secs(2))
+    );
+    assert_eq!(Some(&Duration::from_secs(2)), timeouts.last());
+}
+
+/// A port that records the timeouts that are set on it.
+#[derive(Default)]
+struct TimeoutPort {
+    data: VecDeque<u8>,
+    read_timeouts: Vec<Duration>,
+    write_timeouts: Vec<Duration>,
+}
+
+impl Read for TimeoutPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.data.read(buf)
+    }
+}
+
+impl Write for TimeoutPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+fn test_instrument_read_with_timeout_port_timeouts() {
+    let port = TimeoutPort {
+        data: VecDeque::from(b"resp\n".to_vec()),
+        ..Default::default()
+    };
+    let mut inst = Instrument::new(port, Duration::from_secs(1))
+        .with_port_timeout(|port, timeout| {
+            port.read_timeouts.push(timeout);
+            Ok(())
+        })
+        .with_port_write_timeout(|port, timeout| {
+            port.write_timeouts.push(timeout);
+            Ok(())
+        });
+    inst.get_mut().read_timeouts.clear();
+    inst.get_mut().write_timeouts.clear();
+
+    // Only the read timeout of the port is changed, and restored afterwards.
+    assert_eq!(
+        "resp",
+        inst.read_until_terminator_with_timeout(Duration::from_secs(3))
+            .unwrap()
+    );
+    let port = inst.get_ref();
+    assert!(port.write_timeouts.is_empty());
+    assert_eq!(Some(&Duration::from_secs(1)), port.read_timeouts.last());
+    assert!(
+        port.read_timeouts
+            .iter()
+            .all(|timeout| *timeout <= Duration::from_secs(3))
+    );
+    assert_eq!(Duration::from_secs(1), inst.get_timeout());
+}
+
+#[rstest]
+fn test_instrument_query_raw_until_byte(mut empt_inst: Instrument<VecDeque<u8>>) {
+    // The extra byte after the delimiter is read even though it is the delimiter itself.
+    assert_eq!(
+        b"\x02\x03\x03",
+        empt_inst
+            .query_raw_until_byte(b"\x02\x03\x03\x04", 0x03, 1)
+            .unwrap()
+            .as_slice()
+    );
+}
+
+#[rstest]
+#[case(b"", b"")]
+#[case(b"\x01", b"\x01")]
+#[case(b"\x01\x02", b"\x01\x02")]
+fn test_instrument_query_raw_short_response(#[case] data: &[u8], #[case] partial_exp: &[u8]) {
+    let timeout_exp = Duration::from_millis(10);
+    let mut inst = Instrument::new(StallingPort::new(data), timeout_exp);
+
+    match inst.query_raw(b"QUERY", 3) {
+        Err(InstrumentError::TimeoutQuery {
+            query,
+            timeout,
+            partial,
+        }) => {
+            assert_eq!("QUERY", query);
+            assert_eq!(timeout_exp, timeout);
+            assert_eq!(partial_exp, partial.as_slice());
+        }
+        res => panic!("Expected timeout error, but got: {res:?}"),
+    }
+}
+
+#[rstest]
+#[case(b"\x01\x02", b"\x01\x02")]
+#[case(b"\x01\x03", b"\x01\x03")]
+fn test_instrument_query_raw_until_byte_short_response(
+    #[case] data: &[u8],
+    #[case] partial_exp: &[u8],
+) {
+    let timeout_exp = Duration::from_millis(10);
+    let mut inst = Instrument::new(StallingPort::new(data), timeout_exp);
+
+    // Either the delimiter or the extra bytes after it are missing.
+    match inst.query_raw_until_byte(b"QUERY", 0x03, 2) {
+        Err(InstrumentError::TimeoutQuery {
+            query,
+            timeout,
+            partial,
+        }) => {
+            assert_eq!("QUERY", query);
+            assert_eq!(timeout_exp, timeout);
+            assert_eq!(partial_exp, partial.as_slice());
+        }
+        res => panic!("Expected timeout error, but got: {res:?}"),
+    }
+}
+
+#[rstest]
+fn test_instrument_builder() {
+    let inst = Instrument::builder(VecDeque::<u8>::new()).build();
+    assert_eq!("\n", inst.get_terminator());
+    assert_eq!(Duration::from_secs(3), inst.get_timeout());
+    assert_eq!(Duration::from_secs(3), inst.get_write_timeout());
+    assert_eq!(None, inst.get_name());
+
+    let mut inst = Instrument::builder(VecDeque::<u8>::new())
+        .terminator("\r")
+        .timeout(Duration::from_secs(1))
+        .write_timeout(Duration::from_millis(100))
+        .max_response_len(16)
+        .name("tpg-roughing")
+        .build();
+    assert_eq!("\r", inst.get_terminator());
+    assert_eq!(Duration::from_secs(1), inst.get_timeout());
+    assert_eq!(Duration::from_millis(100), inst.get_write_timeout());
+    assert_eq!(16, inst.get_max_response_len());
+    assert_eq!(Some("tpg-roughing"), inst.get_name());
+
+    // A separate write timeout is kept when the timeout changes.
+    inst.set_timeout(Duration::from_secs(2));
+    assert_eq!(Duration::from_millis(100), inst.get_write_timeout());
+    inst.set_write_timeout(None);
+    assert_eq!(Duration::from_secs(2), inst.get_write_timeout());
+}
+
+#[rstest]
+fn test_instrument_write_timeout_separate() {
+    let port = SlowWritePort {
+        stuck: true,
+        ..Default::default()
+    };
+    let mut inst = Instrument::builder(port)
+        .write_timeout(Duration::from_millis(10))
+        .build();
+    match inst.sendcmd("CMD") {
+        Err(InstrumentError::TimeoutWrite(timeout)) => {
+            assert_eq!(Duration::from_millis(10), timeout)
+        }
+        res => panic!("Expected a write timeout, but got: {res:?}"),
+    }
+}
+
+/// A port that endlessly streams the given byte without a terminator.
+struct StreamingPort(u8);
+
+impl Read for StreamingPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        buf.fill(self.0);
+        Ok(buf.len())
+    }
+}
+
+impl Write for StreamingPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+fn test_instrument_response_too_long() {
+    let mut inst = Instrument::new(StreamingPort(b'x'), Duration::from_secs(5));
+    assert_eq!(DEFAULT_MAX_RESPONSE_LEN, inst.get_max_response_len());
+
+    // The read is aborted long before the timeout.
+    let tic = std::time::Instant::now();
+    match inst.query("STREAM?") {
+        Err(InstrumentError::ResponseTooLong { limit, got }) => {
+            assert_eq!(DEFAULT_MAX_RESPONSE_LEN, limit);
+            assert_eq!(vec![b'x'; DEFAULT_MAX_RESPONSE_LEN], got);
+        }
+        res => panic!("Expected response too long error, but got: {res:?}"),
+    }
+    assert!(tic.elapsed() < Duration::from_secs(1));
+
+    inst.set_max_response_len(10);
+    match inst.read_until_terminator() {
+        Err(InstrumentError::ResponseTooLong { limit, got }) => {
+            assert_eq!(10, limit);
+            assert_eq!(b"xxxxxxxxxx", got.as_slice());
+        }
+        res => panic!("Expected response too long error, but got: {res:?}"),
+    }
+
+    // A limit for a single call replaces the limit of the interface.
+    match inst.read_until_terminator_with_limit(20) {
+        Err(InstrumentError::ResponseTooLong { limit, got }) => {
+            assert_eq!(20, limit);
+            assert_eq!(20, got.len());
+        }
+        res => panic!("Expected response too long error, but got: {res:?}"),
+    }
+}
+
+#[rstest]
+#[case(4, Ok("resp"))]
+#[case(3, Err(b"res".as_slice()))]
+fn test_instrument_max_response_len(#[case] max_len: usize, #[case] exp: Result<&str, &[u8]>) {
+    let mut inst = Instrument::new(StallingPort::new(b"resp\r\n"), Duration::from_millis(10))
+        .with_max_response_len(max_len);
+    inst.set_terminator("\r\n");
+
+    // The limit applies to the response without the terminator.
+    match (inst.read_until_terminator(), exp) {
+        (Ok(resp), Ok(exp)) => assert_eq!(exp, resp),
+        (Err(InstrumentError::ResponseTooLong { limit, got }), Err(exp)) => {
+            assert_eq!(max_len, limit);
+            assert_eq!(exp, got.as_slice());
+        }
+        (res, _) => panic!("Unexpected result: {res:?}"),
+    }
+}
@@ -4,7 +4,7 @@ use std::{collections::VecDeque, io::Read, io::Write, time::Duration};
 
 use rstest::*;
 
-use instrumentrs::{InstrumentError, InstrumentInterface};
+use instrumentrs::{Clock, InstrumentError, InstrumentInterface};
 
 struct TestInstrument<P: Read + Write> {
     _port: P,
@@ -40,3 +40,114 @@ fn test_default_get_terminator(inst: TestInstrument<VecDeque<u8>>) {
 fn test_default_get_timeout(inst: TestInstrument<VecDeque<u8>>) {
     assert_eq!(inst.get_timeout(), Duration::from_secs(3));
 }
+
+#[rstest]
+fn test_default_get_terminator_bytes(mut inst: TestInstrument<VecDeque<u8>>) {
+    assert_eq!(inst.get_terminator_bytes(), b"\n");
+
+    // Default setters do nothing.
+    inst.set_terminator_bytes(&[0x03]);
+    assert_eq!(inst.get_terminator_bytes(), b"\n");
+}
+
+#[rstest]
+fn test_default_is_alive(mut inst: TestInstrument<VecDeque<u8>>) {
+    assert!(inst.is_alive());
+}
+
+#[rstest]
+fn test_default_write_raw_chunked(mut inst: TestInstrument<VecDeque<u8>>) {
+    inst.write_raw_chunked(b"data", 3, Duration::ZERO).unwrap();
+    assert!(matches!(
+        inst.write_raw_chunked(b"data", 0, Duration::ZERO),
+        Err(InstrumentError::InvalidArgument(_))
+    ));
+}
+
+/// A clock that advances by one millisecond every time it is read.
+#[derive(Default)]
+struct TickingClock(std::cell::Cell<u64>);
+
+impl Clock for TickingClock {
+    fn now(&self) -> Duration {
+        self.0.set(self.0.get() + 1);
+        Duration::from_millis(self.0.get())
+    }
+}
+
+/// An interface that never receives a terminator and measures time with its own clock.
+#[derive(Default)]
+struct ClockedInstrument {
+    clock: TickingClock,
+}
+
+impl InstrumentInterface for ClockedInstrument {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        buf.fill(b'x');
+        Ok(())
+    }
+
+    fn write_raw(&mut self, _data: &[u8]) -> Result<(), InstrumentError> {
+        Ok(())
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        &self.clock
+    }
+}
+
+#[rstest]
+fn test_custom_clock() {
+    let mut inst = ClockedInstrument::default();
+
+    // Every byte advances the clock, such that the timeout is reached after a few bytes.
+    match inst.read_until_terminator_with_timeout(Duration::from_millis(10)) {
+        Err(InstrumentError::TimeoutPartial { partial, .. }) => {
+            assert!(!partial.is_empty());
+            assert!(partial.len() <= 10);
+        }
+        res => panic!("Expected timeout error, but got: {res:?}"),
+    }
+
+    // The default sleep of a clock spins until the clock advanced far enough.
+    let tic = inst.clock().now();
+    inst.clock().sleep(Duration::from_millis(5));
+    assert!(inst.clock().elapsed(tic) >= Duration::from_millis(5));
+}
+
+/// An interface that receives some data and then times out.
+struct StalledInstrument {
+    data: VecDeque<u8>,
+}
+
+impl InstrumentInterface for StalledInstrument {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        for byte in buf.iter_mut() {
+            *byte = self
+                .data
+                .pop_front()
+                .ok_or(InstrumentError::Timeout(Duration::from_millis(1)))?;
+        }
+        Ok(())
+    }
+
+    fn write_raw(&mut self, _data: &[u8]) -> Result<(), InstrumentError> {
+        Ok(())
+    }
+}
+
+/// Data that was received before the read timed out is kept in the error.
+#[rstest]
+fn test_read_until_terminator_with_timeout_partial() {
+    let mut inst = StalledInstrument {
+        data: b"part".iter().copied().collect(),
+    };
+    match inst.read_until_terminator_with_timeout(Duration::from_secs(3)) {
+        Err(InstrumentError::TimeoutPartial { partial, .. }) => assert_eq!(b"part", &partial[..]),
+        res => panic!("Expected partial timeout error, but got: {res:?}"),
+    }
+    assert!(matches!(
+        inst.read_until_terminator_with_timeout(Duration::from_secs(3)),
+        Err(InstrumentError::Timeout(_))
+    ));
+}
@@ -0,0 +1,174 @@
+//! Tests for building interfaces from an [`InterfaceConfig`].
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    time::Duration,
+};
+
+use rstest::*;
+
+#[cfg(feature = "serial")]
+use instrumentrs::ConfigParity;
+#[cfg(any(feature = "serde", feature = "serial"))]
+use instrumentrs::InstrumentError;
+use instrumentrs::{InstrumentInterface, InterfaceConfig};
+
+#[rstest]
+fn build_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = InterfaceConfig::Tcp {
+        addr: listener.local_addr().unwrap().to_string(),
+        timeout: Some(Duration::from_millis(500)),
+    };
+    let mut intf = config.build().unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    assert_eq!(Duration::from_millis(500), intf.get_timeout());
+
+    stream.write_all(b"resp\n").unwrap();
+    assert_eq!("resp", intf.query("cmd").unwrap());
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(b"cmd\n", &buf);
+}
+
+#[rstest]
+fn build_tcp_default_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = InterfaceConfig::Tcp {
+        addr: listener.local_addr().unwrap().to_string(),
+        timeout: None,
+    };
+    let intf = config.build().unwrap();
+    assert_eq!(Duration::from_secs(3), intf.get_timeout());
+}
+
+#[cfg(feature = "serde")]
+#[rstest]
+fn from_toml_build_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let toml = format!(
+        "type = \"tcp\"\naddr = \"{}\"\ntimeout = 0.25\n",
+        listener.local_addr().unwrap()
+    );
+    let config = InterfaceConfig::from_toml(&toml).unwrap();
+    let mut intf = config.build().unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    assert_eq!(Duration::from_millis(250), intf.get_timeout());
+
+    stream.write_all(b"resp\n").unwrap();
+    assert_eq!("resp", intf.query("cmd").unwrap());
+}
+
+/// A deployment file that describes multiple instruments.
+#[cfg(feature = "serde")]
+#[rstest]
+fn from_toml_deployment_file() {
+    use std::collections::BTreeMap;
+
+    let toml = r#"
+        [tpg]
+        type = "tcp"
+        addr = "192.168.1.10:8000"
+
+        [pump]
+        type = "tcp"
+        addr = "192.168.1.11:8000"
+        timeout = 2
+    "#;
+    let configs: BTreeMap<String, InterfaceConfig> = toml::from_str(toml).unwrap();
+    assert_eq!(
+        InterfaceConfig::Tcp {
+            addr: "192.168.1.10:8000".to_string(),
+            timeout: None,
+        },
+        configs["tpg"]
+    );
+    assert_eq!(
+        InterfaceConfig::Tcp {
+            addr: "192.168.1.11:8000".to_string(),
+            timeout: Some(Duration::from_secs(2)),
+        },
+        configs["pump"]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[rstest]
+#[case("type = \"tcp\"\naddr = \"localhost:1\"\nbaud = 9600\n", "baud")]
+#[case("type = \"usb\"\naddr = \"localhost:1\"\n", "usb")]
+#[case("addr = \"localhost:1\"\n", "type")]
+#[case("type = \"tcp\"\n", "addr")]
+#[case("type = \"tcp\"\naddr = \"localhost:1\"\ntimeout = -1.0\n", "timeout")]
+fn from_toml_invalid(#[case] toml: &str, #[case] reason: &str) {
+    match InterfaceConfig::from_toml(toml) {
+        Err(InstrumentError::InvalidArgument(msg)) => {
+            assert!(msg.contains(reason), "{reason} not in: {msg}");
+        }
+        res => panic!("Expected InvalidArgument error, got {res:?}"),
+    }
+}
+
+#[cfg(feature = "serde")]
+#[rstest]
+fn toml_round_trip() {
+    let config = InterfaceConfig::Tcp {
+        addr: "192.168.1.10:8000".to_string(),
+        timeout: Some(Duration::from_millis(1500)),
+    };
+    let toml = toml::to_string(&config).unwrap();
+    assert_eq!(config, InterfaceConfig::from_toml(&toml).unwrap());
+}
+
+#[cfg(feature = "serial")]
+#[rstest]
+#[case(9, 1, "data bits")]
+#[case(8, 3, "stop bits")]
+fn build_serial_invalid(#[case] data_bits: u8, #[case] stop_bits: u8, #[case] reason: &str) {
+    let config = InterfaceConfig::Serial {
+        port: "/dev/does-not-exist".to_string(),
+        baud: 9600,
+        data_bits,
+        parity: ConfigParity::Even,
+        stop_bits,
+        timeout: None,
+    };
+    match config.build() {
+        Err(InstrumentError::InvalidArgument(msg)) => assert!(msg.contains(reason)),
+        Err(err) => panic!("Expected InvalidArgument error, got {err}"),
+        Ok(_) => panic!("Expected InvalidArgument error"),
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "serial"))]
+#[rstest]
+fn from_toml_serial_defaults() {
+    let toml = "type = \"serial\"\nport = \"/dev/ttyUSB1\"\nbaud = 9600\n";
+    assert_eq!(
+        InterfaceConfig::Serial {
+            port: "/dev/ttyUSB1".to_string(),
+            baud: 9600,
+            data_bits: 8,
+            parity: ConfigParity::None,
+            stop_bits: 1,
+            timeout: None,
+        },
+        InterfaceConfig::from_toml(toml).unwrap()
+    );
+}
+
+/// Invalid parities are rejected when the configuration is parsed.
+#[cfg(all(feature = "serde", feature = "serial"))]
+#[rstest]
+#[case("mark")]
+#[case("Odd")]
+fn from_toml_serial_invalid_parity(#[case] parity: &str) {
+    let toml =
+        format!("type = \"serial\"\nport = \"/dev/ttyUSB1\"\nbaud = 9600\nparity = \"{parity}\"\n");
+    match InterfaceConfig::from_toml(&toml) {
+        Err(InstrumentError::InvalidArgument(msg)) => {
+            assert!(msg.contains(parity), "{parity} not in: {msg}");
+        }
+        res => panic!("Expected InvalidArgument error, got {res:?}"),
+    }
+}
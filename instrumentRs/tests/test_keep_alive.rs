@@ -0,0 +1,127 @@
+//! Tests for the [`KeepAlive`] helper.
+
+use std::{
+    sync::{Arc, Mutex, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rstest::*;
+
+use instrumentrs::{InstrumentError, KeepAlive, lock_interface};
+
+const INTERVAL: Duration = Duration::from_millis(20);
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Beats are sent at the given interval.
+#[rstest]
+fn keep_alive_cadence() {
+    let (tx, rx) = mpsc::channel();
+    let keep_alive = KeepAlive::new(
+        (),
+        INTERVAL,
+        move |_| {
+            let _ = tx.send(Instant::now());
+            Ok(())
+        },
+        |_, _| panic!("No beat should be missed"),
+    );
+
+    let beats: Vec<Instant> = (0..5)
+        .map(|_| rx.recv_timeout(RECV_TIMEOUT).unwrap())
+        .collect();
+    for pair in beats.windows(2) {
+        let dt = pair[1] - pair[0];
+        assert!(
+            dt >= INTERVAL - Duration::from_millis(2),
+            "beats too fast: {dt:?}"
+        );
+        assert!(dt < RECV_TIMEOUT);
+    }
+    assert_eq!(0, keep_alive.missed_beats());
+    assert!(keep_alive.stop().is_some());
+}
+
+/// Beats wait while another holder of the shared interface is in a transaction.
+#[rstest]
+fn keep_alive_pauses_during_transaction() {
+    let interface = Arc::new(Mutex::new(Vec::<Instant>::new()));
+    let keep_alive = KeepAlive::new(
+        Arc::clone(&interface),
+        INTERVAL,
+        |intf| {
+            lock_interface(intf)?.push(Instant::now());
+            Ok(())
+        },
+        |_, _| {},
+    );
+
+    // hold the interface for several intervals
+    let (start, end) = {
+        let _guard = interface.lock().unwrap();
+        let start = Instant::now();
+        thread::sleep(INTERVAL * 5);
+        (start, Instant::now())
+    };
+    thread::sleep(INTERVAL * 3);
+    keep_alive.stop().unwrap();
+
+    let beats = interface.lock().unwrap();
+    assert!(beats.iter().all(|beat| *beat <= start || *beat >= end));
+    assert!(beats.iter().any(|beat| *beat >= end));
+}
+
+/// Failed beats are reported to the callback with the number of consecutive misses.
+#[rstest]
+fn keep_alive_missed_beats() {
+    let (tx, rx) = mpsc::channel();
+    let keep_alive = KeepAlive::new(
+        0usize,
+        Duration::from_millis(1),
+        |cnt| {
+            *cnt += 1;
+            // miss the beats 2, 3, and 5
+            match *cnt {
+                2 | 3 | 5 => Err(InstrumentError::Timeout(INTERVAL)),
+                _ => Ok(()),
+            }
+        },
+        move |err, consecutive| {
+            assert!(matches!(err, InstrumentError::Timeout(_)));
+            let _ = tx.send(consecutive);
+        },
+    );
+
+    let missed: Vec<usize> = (0..3)
+        .map(|_| rx.recv_timeout(RECV_TIMEOUT).unwrap())
+        .collect();
+    assert_eq!(vec![1, 2, 1], missed);
+    assert_eq!(3, keep_alive.missed_beats());
+    assert!(keep_alive.stop().unwrap() >= 5);
+}
+
+/// Dropping the keep-alive stops the thread, even with a long interval.
+#[rstest]
+fn keep_alive_stops_on_drop() {
+    let (tx, rx) = mpsc::channel();
+    let keep_alive = KeepAlive::new(
+        (),
+        Duration::from_secs(3600),
+        move |_| {
+            let _ = tx.send(());
+            Ok(())
+        },
+        |_, _| {},
+    );
+    rx.recv_timeout(RECV_TIMEOUT).unwrap();
+
+    let start = Instant::now();
+    drop(keep_alive);
+    assert!(start.elapsed() < RECV_TIMEOUT);
+
+    // the thread has exited and dropped the closure with the sender
+    assert_eq!(
+        Err(mpsc::RecvTimeoutError::Disconnected),
+        rx.recv_timeout(RECV_TIMEOUT)
+    );
+}
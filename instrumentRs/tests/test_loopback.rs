@@ -1,19 +1,24 @@
-//! Test cases for the LoopbackInterfaceStr.
+//! Test cases for the LoopbackInterface.
+
+use std::time::{Duration, Instant};
 
 use rstest::*;
 
-use instrumentrs::{InstrumentInterface, LoopbackInterfaceString};
+use instrumentrs::{
+    Instrument, InstrumentError, InstrumentInterface, LoopbackCommand, LoopbackEntry,
+    LoopbackInterface,
+};
 
-/// A function that creates a new `LoopbackInterfaceString` with the given input and output vectors.
-fn crt_lbk(input: Vec<&str>, output: Vec<&str>) -> LoopbackInterfaceString {
+/// A function that creates a new `LoopbackInterface` with the given input and output vectors.
+fn crt_lbk(input: Vec<&str>, output: Vec<&str>) -> LoopbackInterface {
     let input = input.iter().map(|s| s.to_string()).collect();
     let output = output.iter().map(|s| s.to_string()).collect();
-    LoopbackInterfaceString::new(input, output, "\n")
+    LoopbackInterface::new(input, output, "\n")
 }
 
 /// Create a loopback interface that contains no commands.
 #[fixture]
-fn emp_lbk() -> LoopbackInterfaceString {
+fn emp_lbk() -> LoopbackInterface {
     crt_lbk(vec![], vec![])
 }
 
@@ -32,12 +37,34 @@ fn check_acknowledgment_fail() {
     assert!(lbk.check_acknowledgment("ACK").is_err());
 }
 
+/// Check acknowledgment with a predicate that tolerates trailing whitespace.
+#[rstest]
+#[case("ACK", true)]
+#[case("ACK \t", true)]
+#[case("NACK", false)]
+fn check_acknowledgment_with(#[case] resp: &str, #[case] exp: bool) {
+    let mut lbk = crt_lbk(vec![], vec![resp]);
+    let res = lbk.check_acknowledgment_with(|response| response.trim_end() == "ACK");
+    assert_eq!(exp, res.is_ok());
+}
+
+/// Check acknowledgment with a prefix and return the full response if it does not match.
+#[rstest]
+fn check_acknowledgment_prefix() {
+    let mut lbk = crt_lbk(vec![], vec!["OK 42", "ERR 3"]);
+    lbk.check_acknowledgment_prefix("OK").unwrap();
+    match lbk.check_acknowledgment_prefix("OK") {
+        Err(InstrumentError::NotAcknowledged(response)) => assert_eq!("ERR 3", response),
+        _ => panic!("Expected not acknowledged error, but got a different result."),
+    }
+}
+
 /// Ensure `finalize` method passes if an empty loopback interface is used.
 ///
 /// This routine calls the finalize method manually, however, it is not necessary to do so as it is
-/// implemented in the `Drop` trait for `LoopbackInterfaceString`.
+/// implemented in the `Drop` trait for `LoopbackInterface`.
 #[rstest]
-fn finalize_test(mut emp_lbk: LoopbackInterfaceString) {
+fn finalize_test(mut emp_lbk: LoopbackInterface) {
     emp_lbk.finalize();
 }
 
@@ -76,3 +103,410 @@ fn query() {
     let resp2 = lbk.query("cmd2").unwrap();
     assert_eq!(resp2, "resp2");
 }
+
+#[rstest]
+fn query_multiline() {
+    let mut lbk = crt_lbk(vec!["LIST"], vec!["line 1", "line 2", "line 3"]);
+    assert_eq!(
+        vec!["line 1", "line 2", "line 3"],
+        lbk.query_multiline("LIST", 3).unwrap()
+    );
+}
+
+/// Drain the rest of a partially read response.
+#[rstest]
+fn drain_input() {
+    let mut lbk = crt_lbk(vec![], vec!["stale", "resp"]);
+    let mut buf = [0u8; 2];
+    lbk.read_exact(&mut buf).unwrap();
+
+    // Remaining: "ale\n"
+    assert_eq!(4, lbk.drain_input().unwrap());
+    assert_eq!("resp", lbk.read_until_terminator().unwrap());
+}
+
+/// A function that creates a new unordered `LoopbackInterface` from the given exchanges.
+fn crt_lbk_unordered(exchanges: Vec<(&str, Option<&str>)>) -> LoopbackInterface {
+    let exchanges = exchanges
+        .iter()
+        .map(|(cmd, resp)| (cmd.to_string(), resp.map(|r| r.to_string())))
+        .collect();
+    LoopbackInterface::unordered(exchanges, "\n")
+}
+
+/// Commands are matched in any order and return the response paired with them.
+#[rstest]
+fn unordered() {
+    let mut lbk = crt_lbk_unordered(vec![
+        ("cmd1", None),
+        ("cmd2?", Some("resp2")),
+        ("cmd3?", Some("resp3")),
+    ]);
+    assert_eq!("resp3", lbk.query("cmd3?").unwrap());
+    lbk.sendcmd("cmd1").unwrap();
+    assert_eq!("resp2", lbk.query("cmd2?").unwrap());
+}
+
+/// Matched commands are removed, so sending the same command twice panics.
+#[rstest]
+#[should_panic(expected = "Expected one of sendcmd [\"cmd2\"], got b\"cmd1\\n\"")]
+fn unordered_mismatch() {
+    let mut lbk = crt_lbk_unordered(vec![("cmd1", None), ("cmd2", None)]);
+    lbk.sendcmd("cmd1").unwrap();
+    lbk.sendcmd("cmd1").unwrap();
+}
+
+/// Ensure `finalize` panics if unordered commands or their responses are left over.
+#[rstest]
+#[case(vec![("cmd", None)], false)]
+#[case(vec![("cmd?", Some("resp"))], true)]
+#[should_panic]
+fn unordered_finalize_panic(#[case] exchanges: Vec<(&str, Option<&str>)>, #[case] send: bool) {
+    let mut lbk = crt_lbk_unordered(exchanges);
+    if send {
+        lbk.sendcmd("cmd?").unwrap();
+    }
+}
+
+/// Exact commands and patterns can be mixed in one script.
+#[rstest]
+fn patterns() {
+    let from_host = vec![
+        "*IDN?".into(),
+        LoopbackCommand::glob("SET TTARGET=*"),
+        LoopbackCommand::glob("TC?"),
+        LoopbackCommand::glob("SET ?=*").respond_with(|caps| format!("{0}={1}", caps[0], caps[1])),
+        "TC".into(),
+    ];
+    let from_inst = vec!["CRYOTEL".into(), "77.00".into()];
+    let mut lbk = LoopbackInterface::<String>::with_commands(from_host, from_inst, "\n");
+
+    assert_eq!("CRYOTEL", lbk.query("*IDN?").unwrap());
+    lbk.sendcmd("SET TTARGET=100.000").unwrap();
+    lbk.sendcmd("TC?").unwrap();
+    assert_eq!("P=2.5", lbk.query("SET P=2.5").unwrap());
+    assert_eq!("77.00", lbk.query("TC").unwrap());
+}
+
+/// Mismatches of patterns show the expected pattern and the received command.
+#[rstest]
+#[should_panic(
+    expected = "Expected sendcmd #0 matching 'glob SET TTARGET=*', got b\"SET TSTATM=1\\n\""
+)]
+fn patterns_mismatch() {
+    let from_host = vec![LoopbackCommand::glob("SET TTARGET=*")];
+    let mut lbk = LoopbackInterface::<String>::with_commands(from_host, vec![], "\n");
+    lbk.sendcmd("SET TSTATM=1").unwrap();
+}
+
+/// Regular expressions must match the full command and capture their groups.
+#[cfg(feature = "regex")]
+#[rstest]
+fn patterns_regex() {
+    let from_host = vec![
+        LoopbackCommand::regex(r"SET TTARGET=\d+\.\d+"),
+        LoopbackCommand::regex(r"PR([1-6])").respond_with(|caps| format!("0,{0}.0E-5", caps[0])),
+    ];
+    let mut lbk = LoopbackInterface::<String>::with_commands(from_host, vec![], "\n");
+
+    lbk.sendcmd("SET TTARGET=100.00").unwrap();
+    assert_eq!("0,3.0E-5", lbk.query("PR3").unwrap());
+}
+
+/// Regular expressions do not match parts of a command.
+#[cfg(feature = "regex")]
+#[rstest]
+#[should_panic(expected = "Expected sendcmd #0 matching 'regex PR[1-6]'")]
+fn patterns_regex_mismatch() {
+    let from_host = vec![LoopbackCommand::regex("PR[1-6]")];
+    let mut lbk = LoopbackInterface::<String>::with_commands(from_host, vec![], "\n");
+    lbk.sendcmd("PR12").unwrap();
+}
+
+/// A tiny simulated CryoTel that echoes `SET` commands and returns stored values on queries.
+#[rstest]
+fn responder_simulated_cryotel() {
+    let mut target = 0.0;
+    let mut lbk = crt_lbk(vec!["*IDN?"], vec!["CRYOTEL"]).with_responder(move |cmd| {
+        if let Some(value) = cmd.strip_prefix("SET TTARGET=") {
+            target = value.parse().ok()?;
+            return Some(format!("{target:.2}"));
+        }
+        (cmd == "SET TTARGET").then(|| format!("{target:.2}"))
+    });
+
+    assert_eq!("0.00", lbk.query("SET TTARGET").unwrap());
+    assert_eq!("80.00", lbk.query("SET TTARGET=80").unwrap());
+    assert_eq!("80.00", lbk.query("SET TTARGET").unwrap());
+    // commands that the responder does not answer follow the script
+    assert_eq!("CRYOTEL", lbk.query("*IDN?").unwrap());
+}
+
+/// Commands that the responder does not answer must still match the script.
+#[rstest]
+#[should_panic(expected = "No more commands were expected from host to instrument, got command #0")]
+fn responder_falls_back_to_script() {
+    let mut lbk = emp_lbk().with_responder(|_| None);
+    lbk.sendcmd("cmd").unwrap();
+}
+
+/// A query to an instrument that does not respond times out.
+#[rstest]
+fn no_response() {
+    let from_inst = vec![LoopbackEntry::no_response()];
+    let mut lbk = LoopbackInterface::<String>::with_commands(vec!["TC".into()], from_inst, "\n")
+        .with_timeout(Duration::from_millis(20));
+
+    let tic = Instant::now();
+    match lbk.query("TC") {
+        Err(InstrumentError::TimeoutQuery { query, timeout, .. }) => {
+            assert_eq!("TC", query);
+            assert_eq!(Duration::from_millis(20), timeout);
+        }
+        _ => panic!("Expected query timeout error, but got a different result."),
+    }
+    assert!(tic.elapsed() >= Duration::from_millis(20));
+}
+
+/// A delayed response shorter than the timeout is received after the delay.
+#[rstest]
+fn delayed_response() {
+    let from_inst = vec![LoopbackEntry::delayed("77.00", Duration::from_millis(50))];
+    let mut lbk = LoopbackInterface::<String>::with_commands(vec!["TC".into()], from_inst, "\n")
+        .with_timeout(Duration::from_millis(500));
+
+    let tic = Instant::now();
+    assert_eq!("77.00", lbk.query("TC").unwrap());
+    assert!(tic.elapsed() >= Duration::from_millis(50));
+}
+
+/// A response delayed longer than the timeout arrives at the next read.
+#[rstest]
+fn delayed_response_timeout() {
+    let from_inst = vec![LoopbackEntry::delayed("77.00", Duration::from_millis(30))];
+    let mut lbk = LoopbackInterface::<String>::with_commands(vec![], from_inst, "\n");
+    lbk.set_timeout(Duration::from_millis(20));
+
+    assert!(matches!(
+        lbk.read_until_terminator(),
+        Err(InstrumentError::Timeout(_))
+    ));
+    assert_eq!("77.00", lbk.read_until_terminator().unwrap());
+}
+
+/// The timeout is returned as set.
+#[rstest]
+fn timeout_get_set() {
+    let mut lbk = crt_lbk(vec![], vec![]).with_timeout(Duration::from_millis(20));
+    assert_eq!(Duration::from_millis(20), lbk.get_timeout());
+
+    lbk.set_timeout(Duration::ZERO);
+    assert_eq!(Duration::ZERO, lbk.get_timeout());
+}
+
+/// An exhausted loopback with empty reads allowed times out after the timeout of the interface.
+#[rstest]
+#[case(Duration::ZERO)]
+#[case(Duration::from_millis(10))]
+fn allow_empty_reads_timeout(#[case] timeout: Duration) {
+    let mut lbk = crt_lbk(vec!["TC"], vec!["77.00"]).allow_empty_reads();
+
+    assert_eq!("77.00", lbk.query("TC").unwrap());
+    lbk.set_timeout(timeout);
+    let tic = Instant::now();
+    match lbk.read_until_terminator() {
+        Err(InstrumentError::Timeout(to)) => assert_eq!(timeout, to),
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+    let elapsed = tic.elapsed();
+    assert!(elapsed >= timeout);
+    assert!(elapsed < Duration::from_millis(500));
+}
+
+/// Reading from an exhausted loopback panics by default.
+#[rstest]
+#[should_panic(expected = "No more commands were expected from instrument to host.")]
+fn empty_read_panics() {
+    let mut lbk = crt_lbk(vec![], vec![]).with_timeout(Duration::from_millis(10));
+    let _ = lbk.read_until_terminator();
+}
+
+/// Injected errors are returned the given number of times before the script continues.
+#[rstest]
+fn failing_entries() {
+    let from_host = vec![
+        LoopbackCommand::exact("TC")
+            .failing(2, || InstrumentError::Timeout(Duration::from_secs(1))),
+    ];
+    let from_inst = vec![LoopbackEntry::from("77.00").failing(1, || {
+        std::io::Error::from(std::io::ErrorKind::BrokenPipe).into()
+    })];
+    let mut lbk = LoopbackInterface::<String>::with_commands(from_host, from_inst, "\n");
+
+    for _ in 0..2 {
+        assert!(matches!(
+            lbk.sendcmd("TC"),
+            Err(InstrumentError::Timeout(_))
+        ));
+    }
+    lbk.sendcmd("TC").unwrap();
+    match lbk.read_until_terminator() {
+        Err(InstrumentError::Io(err)) => assert_eq!(std::io::ErrorKind::BrokenPipe, err.kind()),
+        _ => panic!("Expected I/O error, but got a different result."),
+    }
+    assert_eq!("77.00", lbk.read_until_terminator().unwrap());
+}
+
+/// Mismatches show the index of the command in the script and where the bytes differ.
+///
+/// The script has leftover commands when the mismatch panics. Dropping the loopback interface
+/// must not panic again, as this would abort the test instead of reporting the mismatch.
+#[rstest]
+#[should_panic(
+    expected = "Expected sendcmd #1 'SET TTARGET=100.00\n', got 'Ok(\"SET TTARGET=100.0\\n\")'\n  \
+    expected: b\"SET TTARGET=100.00\\n\"\n  \
+    received: b\"SET TTARGET=100.0\\n\"\n  \
+    first difference at byte 17: expected 0x30, received 0x0a"
+)]
+fn mismatch_message() {
+    let mut lbk = crt_lbk(vec!["*IDN?", "SET TTARGET=100.00", "TC"], vec!["CRYOTEL"]);
+    lbk.query("*IDN?").unwrap();
+    lbk.sendcmd("SET TTARGET=100.0").unwrap();
+}
+
+/// Leftover commands report how many commands of each script were consumed.
+#[rstest]
+#[should_panic(
+    expected = "Leftover expected commands found from host to instrument: TC (consumed 1 \
+    of 2 commands from host to instrument and 1 of 1 commands from instrument to host)"
+)]
+fn finalize_consumed_message() {
+    let mut lbk = crt_lbk(vec!["*IDN?", "TC"], vec!["CRYOTEL"]);
+    lbk.query("*IDN?").unwrap();
+}
+
+/// Responses fragmented inside the terminator are reassembled by the buffered instrument.
+#[rstest]
+#[case(1)]
+#[case(3)]
+#[case(6)]
+fn fragment_responses(#[case] max_len: usize) {
+    let lbk = LoopbackInterface::new(
+        vec!["TC".to_string(), "TC".to_string()],
+        vec!["77.00".to_string(), "78.00".to_string()],
+        "\r\n",
+    )
+    .fragment_responses(max_len);
+    let mut inst = Instrument::new(lbk, Duration::from_secs(1));
+    inst.set_terminator("\r\n");
+
+    assert_eq!("77.00", inst.query("TC").unwrap());
+    assert_eq!("78.00", inst.query("TC").unwrap());
+}
+
+/// Frames with a checksum after the end byte are reassembled if the fragments split them.
+#[rstest]
+#[case(1)]
+#[case(4)]
+fn fragment_responses_frame(#[case] max_len: usize) {
+    let lbk = LoopbackInterface::new(vec![], vec!["\u{2}8000\u{3}A7".to_string()], "")
+        .fragment_responses(max_len);
+    let mut inst = Instrument::new(lbk, Duration::from_secs(1));
+
+    assert_eq!(
+        b"\x028000\x03A7".to_vec(),
+        inst.read_until_byte(0x03, 2).unwrap()
+    );
+}
+
+/// A loopback interface with byte payloads sends and receives binary data.
+#[rstest]
+fn bytes_payload() {
+    let mut lbk: LoopbackInterface<Vec<u8>> =
+        LoopbackInterface::new(vec![vec![0x01, 0xff]], vec![vec![0x80, 0x00, 0xfe]], "");
+
+    lbk.write_raw(&[0x01, 0xff]).unwrap();
+    let mut buf = [0u8; 3];
+    lbk.read_exact(&mut buf).unwrap();
+    assert_eq!([0x80, 0x00, 0xfe], buf);
+}
+
+/// Unordered byte exchanges return the response paired with the matched command.
+#[rstest]
+fn bytes_payload_unordered() {
+    let exchanges = vec![(vec![0x02], None), (vec![0x01], Some(vec![0xaa]))];
+    let mut lbk = LoopbackInterface::unordered(exchanges, "");
+
+    lbk.write_raw(&[0x01]).unwrap();
+    lbk.write_raw(&[0x02]).unwrap();
+    let mut buf = [0u8];
+    lbk.read_exact(&mut buf).unwrap();
+    assert_eq!([0xaa], buf);
+}
+
+/// Mismatched commands are reported the same way for string and byte payloads.
+#[rstest]
+#[case::string(LoopbackInterface::<String>::new(vec!["TC".into()], vec![], "\n"))]
+#[case::bytes(LoopbackInterface::<Vec<u8>>::new(vec![b"TC".to_vec()], vec![], "\n"))]
+#[should_panic(
+    expected = "Expected sendcmd #0 'TC\n', got 'Ok(\"TX\\n\")'\n  expected: b\"TC\\n\"\n  \
+    received: b\"TX\\n\"\n  first difference at byte 1: expected 0x43, received 0x58"
+)]
+fn payload_mismatch(#[case] mut lbk: impl InstrumentInterface) {
+    lbk.sendcmd("TX").unwrap();
+}
+
+/// Leftover commands are reported the same way for string and byte payloads.
+#[rstest]
+#[case::string(LoopbackInterface::<String>::new(vec![], vec!["77.00".into()], "\n"))]
+#[case::bytes(LoopbackInterface::<Vec<u8>>::new(vec![], vec![b"77.00".to_vec()], "\n"))]
+#[should_panic(
+    expected = "Leftover expected commands found from instrument to host: 77.00 (consumed \
+    0 of 0 commands from host to instrument and 0 of 1 commands from instrument to host)"
+)]
+fn payload_finalize(#[case] lbk: impl InstrumentInterface) {
+    drop(lbk);
+}
+
+/// Leftover binary responses that are not valid UTF-8 are reported as escaped bytes.
+#[rstest]
+#[should_panic(
+    expected = "Leftover expected commands found from instrument to host: b\"\\xff\\x00\""
+)]
+fn bytes_payload_finalize_escaped() {
+    let _lbk = LoopbackInterface::new(vec![], vec![vec![0xff, 0x00]], "");
+}
+
+/// In strict mode, the configured terminator must match the expected one.
+#[rstest]
+fn strict_terminator() {
+    let mut lbk = LoopbackInterface::new(vec!["TC".to_string()], vec!["77.00".to_string()], "\r")
+        .strict_terminator();
+    lbk.set_terminator("\r");
+
+    assert_eq!("77.00", lbk.query("TC").unwrap());
+}
+
+/// In strict mode, a driver that does not configure the expected terminator fails.
+#[rstest]
+#[should_panic(
+    expected = "The driver configured the terminator b\"\\n\", but the script expects \
+    b\"\\r\"."
+)]
+fn strict_terminator_mismatch() {
+    let mut lbk = LoopbackInterface::new(vec!["TC".to_string()], vec![], "\r").strict_terminator();
+
+    lbk.write("TC\r").unwrap();
+}
+
+/// In strict mode, responses are terminated with the configured terminator.
+#[rstest]
+fn strict_terminator_responses() {
+    let mut lbk =
+        LoopbackInterface::new(vec![], vec!["77.00".to_string()], "\r\n").strict_terminator();
+    lbk.set_terminator("\r");
+
+    let mut buf = [0u8; 6];
+    lbk.read_exact(&mut buf).unwrap();
+    assert_eq!(b"77.00\r", &buf);
+}
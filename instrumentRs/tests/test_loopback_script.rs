@@ -0,0 +1,144 @@
+//! Tests for building loopback interfaces with a [`LoopbackScript`].
+
+use rstest::*;
+
+use instrumentrs::{InstrumentInterface, LoopbackCommand, LoopbackEntry, LoopbackScript};
+
+const ENQ: &str = "\u{5}";
+const ACK: &str = "\u{6}";
+
+/// Commands and queries are interleaved in the order they are added.
+#[rstest]
+fn cmd_and_query() {
+    let mut lbk = LoopbackScript::new("\n")
+        .cmd("OUT 1")
+        .query("OUT?", "1")
+        .query("*IDN?", "MyInstrument")
+        .build();
+
+    lbk.sendcmd("OUT 1").unwrap();
+    assert_eq!("1", lbk.query("OUT?").unwrap());
+    assert_eq!("MyInstrument", lbk.query("*IDN?").unwrap());
+}
+
+/// Acknowledged commands and enquired responses of a Pfeiffer controller.
+#[rstest]
+fn pfeiffer_ack_enq() {
+    let mut lbk = LoopbackScript::new("\r\n")
+        .pfeiffer_ack_enq()
+        .ack_then("UNI", "2")
+        .ack("UNI,1")
+        .build();
+    lbk.set_terminator("\r\n");
+
+    lbk.sendcmd("UNI").unwrap();
+    lbk.check_acknowledgment(ACK).unwrap();
+    lbk.write(ENQ).unwrap();
+    assert_eq!("2", lbk.read_until_terminator().unwrap());
+
+    lbk.sendcmd("UNI,1").unwrap();
+    lbk.check_acknowledgment(ACK).unwrap();
+}
+
+/// Custom acknowledgments and enquiries are used instead of the Pfeiffer ones.
+#[rstest]
+fn custom_ack_enq() {
+    let mut lbk = LoopbackScript::new("\n")
+        .with_ack_enq("OK", "?")
+        .ack_then("VAL", "42")
+        .build();
+
+    assert_eq!("OK", lbk.query("VAL").unwrap());
+    lbk.write("?").unwrap();
+    assert_eq!("42", lbk.read_until_terminator().unwrap());
+}
+
+/// Patterns and loopback entries can be added to the script as well.
+#[rstest]
+fn expect_and_respond() {
+    let mut lbk = LoopbackScript::new("\n")
+        .expect(LoopbackCommand::glob("SET TTARGET=*"))
+        .respond(LoopbackEntry::response("100.00"))
+        .build();
+
+    assert_eq!("100.00", lbk.query("SET TTARGET=100.000").unwrap());
+}
+
+/// Acknowledgments must be configured before they are used.
+#[rstest]
+#[should_panic(expected = "Acknowledgment and enquiry must be configured")]
+fn ack_without_configuration() {
+    let _ = LoopbackScript::new("\n").ack("UNI,1");
+}
+
+/// A polling loop queries the same command over and over until a different command arrives.
+#[rstest]
+fn forever_polling_loop() {
+    let mut lbk = LoopbackScript::new("\n")
+        .query("*IDN?", "CryoTel GT")
+        .query("TC", "120.00")
+        .forever()
+        .cmd("SET SSTOP=1")
+        .build();
+
+    assert_eq!("CryoTel GT", lbk.query("*IDN?").unwrap());
+    for _ in 0..100 {
+        assert_eq!("120.00", lbk.query("TC").unwrap());
+    }
+    lbk.sendcmd("SET SSTOP=1").unwrap();
+}
+
+/// A command that is expected forever can also not be sent at all.
+#[rstest]
+fn forever_not_sent() {
+    let mut lbk = LoopbackScript::new("\n")
+        .query("TC", "120.00")
+        .forever()
+        .cmd("SET SSTOP=1")
+        .build();
+
+    lbk.sendcmd("SET SSTOP=1").unwrap();
+}
+
+/// A command that is expected a number of times must be sent exactly that often.
+#[rstest]
+fn times_exact_count() {
+    let mut lbk = LoopbackScript::new("\n")
+        .query("TC", "120.00")
+        .times(3)
+        .cmd("SET SSTOP=1")
+        .build();
+
+    for _ in 0..3 {
+        assert_eq!("120.00", lbk.query("TC").unwrap());
+    }
+    lbk.sendcmd("SET SSTOP=1").unwrap();
+}
+
+/// Sending a repeated command too few times fails.
+#[rstest]
+#[should_panic(expected = "Expected sendcmd #0 'TC\n'")]
+fn times_too_few() {
+    let mut lbk = LoopbackScript::new("\n")
+        .query("TC", "120.00")
+        .times(3)
+        .cmd("SET SSTOP=1")
+        .build();
+
+    for _ in 0..2 {
+        assert_eq!("120.00", lbk.query("TC").unwrap());
+    }
+    lbk.sendcmd("SET SSTOP=1").unwrap();
+}
+
+/// Not sending all repetitions is reported when the loopback is dropped.
+#[rstest]
+#[should_panic(expected = "Leftover expected commands found from host to instrument: TC")]
+fn times_leftover() {
+    let mut lbk = LoopbackScript::new("\n")
+        .query("TC", "120.00")
+        .times(2)
+        .build();
+
+    assert_eq!("120.00", lbk.query("TC").unwrap());
+}
@@ -0,0 +1,252 @@
+//! Tests for the Modbus RTU and Modbus TCP clients.
+
+use std::collections::VecDeque;
+
+use rstest::*;
+
+use instrumentrs::{
+    InstrumentError, InstrumentInterface, ModbusClient, ModbusException, ModbusRtuClient,
+    ModbusTcpClient,
+};
+
+/// A byte interface that expects request frames and answers with response frames.
+struct ByteScript {
+    requests: VecDeque<Vec<u8>>,
+    response: Vec<u8>,
+}
+
+impl ByteScript {
+    fn new(request: &[u8], response: &[u8]) -> Self {
+        Self::with_requests(&[request], response)
+    }
+
+    /// Expect multiple request frames, the responses to all of them are read from `response`.
+    fn with_requests(requests: &[&[u8]], response: &[u8]) -> Self {
+        ByteScript {
+            requests: requests.iter().map(|req| req.to_vec()).collect(),
+            response: response.to_vec(),
+        }
+    }
+}
+
+impl InstrumentInterface for ByteScript {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        assert!(
+            buf.len() <= self.response.len(),
+            "Read beyond the response."
+        );
+        let rest = self.response.split_off(buf.len());
+        buf.copy_from_slice(&self.response);
+        self.response = rest;
+        Ok(())
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        assert_eq!(self.requests.pop_front().as_deref(), Some(data));
+        Ok(())
+    }
+}
+
+impl Drop for ByteScript {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            assert!(self.response.is_empty(), "Response was not fully read.");
+        }
+    }
+}
+
+/// Create an RTU client for unit 0x11 with the given request and response frames.
+fn rtu(request: &[u8], response: &[u8]) -> ModbusRtuClient<ByteScript> {
+    ModbusRtuClient::new(ByteScript::new(request, response), 0x11)
+}
+
+/// Create a TCP client for unit 0x01 with the given request and response frames.
+fn tcp(request: &[u8], response: &[u8]) -> ModbusTcpClient<ByteScript> {
+    ModbusTcpClient::new(ByteScript::new(request, response), 0x01)
+}
+
+#[rstest]
+fn rtu_read_holding_registers() {
+    let mut client = rtu(
+        &[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x76, 0x87],
+        &[
+            0x11, 0x03, 0x06, 0xAE, 0x41, 0x56, 0x52, 0x43, 0x40, 0x49, 0xAD,
+        ],
+    );
+    assert_eq!(
+        vec![0xAE41, 0x5652, 0x4340],
+        client.read_holding_registers(0x006B, 3).unwrap()
+    );
+}
+
+#[rstest]
+fn rtu_read_input_registers() {
+    let mut client = rtu(
+        &[0x11, 0x04, 0x00, 0x08, 0x00, 0x01, 0xB2, 0x98],
+        &[0x11, 0x04, 0x02, 0x00, 0x0A, 0xF8, 0xF4],
+    );
+    assert_eq!(
+        vec![0x000A],
+        client.read_input_registers(0x0008, 1).unwrap()
+    );
+}
+
+#[rstest]
+fn rtu_write_single_register() {
+    let frame = [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B];
+    let mut client = rtu(&frame, &frame);
+    client.write_single_register(0x0001, 0x0003).unwrap();
+}
+
+#[rstest]
+fn rtu_write_multiple_registers() {
+    let mut client = rtu(
+        &[
+            0x11, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02, 0xC6, 0xF0,
+        ],
+        &[0x11, 0x10, 0x00, 0x01, 0x00, 0x02, 0x12, 0x98],
+    );
+    client
+        .write_multiple_registers(0x0001, &[0x000A, 0x0102])
+        .unwrap();
+}
+
+#[rstest]
+fn rtu_exception() {
+    let mut client = rtu(
+        &[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x76, 0x87],
+        &[0x11, 0x83, 0x02, 0xC1, 0x34],
+    );
+    match client.read_holding_registers(0x006B, 3) {
+        Err(InstrumentError::Modbus {
+            function,
+            exception,
+        }) => {
+            assert_eq!(0x03, function);
+            assert_eq!(ModbusException::IllegalDataAddress, exception);
+        }
+        res => panic!("Expected a Modbus exception, got {res:?}"),
+    }
+}
+
+/// A response with a corrupted checksum is rejected.
+#[rstest]
+fn rtu_crc_error() {
+    let mut client = rtu(
+        &[0x11, 0x04, 0x00, 0x08, 0x00, 0x01, 0xB2, 0x98],
+        &[0x11, 0x04, 0x02, 0x00, 0x0A, 0xF8, 0xF5],
+    );
+    assert!(matches!(
+        client.read_input_registers(0x0008, 1),
+        Err(InstrumentError::ResponseParseError(_))
+    ));
+}
+
+/// A response from a different unit is rejected.
+#[rstest]
+fn rtu_wrong_unit() {
+    let request = [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B];
+    let response = [0x12, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0xA8];
+    let mut client = rtu(&request, &response);
+    let err = client.write_single_register(0x0001, 0x0003).unwrap_err();
+    assert!(!err.to_string().contains("CRC"));
+}
+
+#[rstest]
+#[case(0)]
+#[case(126)]
+fn read_invalid_count(#[case] count: u16) {
+    let mut client = rtu(&[], &[]);
+    assert!(matches!(
+        client.read_holding_registers(0x0000, count),
+        Err(InstrumentError::IntValueOutOfRange { .. })
+    ));
+}
+
+#[rstest]
+fn tcp_read_holding_registers() {
+    let mut client = tcp(
+        &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x6B, 0x00, 0x02,
+        ],
+        &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x07, 0x01, 0x03, 0x04, 0x02, 0x2B, 0x00, 0x64,
+        ],
+    );
+    assert_eq!(
+        vec![0x022B, 0x0064],
+        client.read_holding_registers(0x006B, 2).unwrap()
+    );
+}
+
+#[rstest]
+fn tcp_write_multiple_registers() {
+    let mut client = tcp(
+        &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x0B, 0x01, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00,
+            0x0A, 0x01, 0x02,
+        ],
+        &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x10, 0x00, 0x01, 0x00, 0x02,
+        ],
+    );
+    client
+        .write_multiple_registers(0x0001, &[0x000A, 0x0102])
+        .unwrap();
+}
+
+#[rstest]
+fn tcp_exception() {
+    let mut client = tcp(
+        &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x01, 0x00, 0x03,
+        ],
+        &[0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x86, 0x06],
+    );
+    assert!(matches!(
+        client.write_single_register(0x0001, 0x0003),
+        Err(InstrumentError::Modbus {
+            function: 0x06,
+            exception: ModbusException::ServerDeviceBusy,
+        })
+    ));
+}
+
+/// A response with a different transaction ID is rejected.
+#[rstest]
+fn tcp_wrong_transaction_id() {
+    let mut client = tcp(
+        &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x01, 0x00, 0x03,
+        ],
+        &[0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0x01, 0x86, 0x06],
+    );
+    let res = client.write_single_register(0x0001, 0x0003);
+    assert!(matches!(res, Err(InstrumentError::ResponseParseError(_))));
+}
+
+/// The rest of a response with a different transaction ID is discarded, such that the next
+/// exchange reads its own response.
+#[rstest]
+fn tcp_wrong_transaction_id_then_valid() {
+    let script = ByteScript::with_requests(
+        &[
+            &[
+                0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x01, 0x00, 0x03,
+            ],
+            &[
+                0x00, 0x02, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x01, 0x00, 0x03,
+            ],
+        ],
+        &[
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x01, 0x00, 0x03, 0x00, 0x02,
+            0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x01, 0x00, 0x03,
+        ],
+    );
+    let mut client = ModbusTcpClient::new(script, 0x01);
+    assert!(matches!(
+        client.write_single_register(0x0001, 0x0003),
+        Err(InstrumentError::ResponseParseError(_))
+    ));
+    client.write_single_register(0x0001, 0x0003).unwrap();
+}
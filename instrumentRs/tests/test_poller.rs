@@ -0,0 +1,128 @@
+//! Tests for the background [`Poller`].
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use rstest::*;
+
+use instrumentrs::{InstrumentError, PollEvent, Poller};
+
+const INTERVAL: Duration = Duration::from_millis(5);
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Poll a counter that increments on every read.
+#[rstest]
+fn poller_latest_value() {
+    let poller = Poller::new(0usize, INTERVAL, |cnt| {
+        *cnt += 1;
+        Ok(*cnt)
+    });
+    let rx = poller.subscribe();
+    for _ in 0..3 {
+        rx.recv_timeout(RECV_TIMEOUT).unwrap();
+    }
+
+    let (time, value) = poller.latest().unwrap();
+    assert!(value >= 3);
+    assert!(time.elapsed() < RECV_TIMEOUT);
+    assert_eq!(0, poller.consecutive_errors());
+
+    let cnt = poller.stop().unwrap();
+    assert!(cnt >= value);
+}
+
+/// Subscribers receive all values in order.
+#[rstest]
+fn poller_subscribe() {
+    let poller = Poller::new(0usize, INTERVAL, |cnt| {
+        *cnt += 1;
+        Ok(*cnt)
+    });
+    let rx = poller.subscribe();
+    let mut last = 0;
+    for _ in 0..5 {
+        match rx.recv_timeout(RECV_TIMEOUT).unwrap() {
+            PollEvent::Value(_, value) => {
+                if last > 0 {
+                    assert_eq!(last + 1, value);
+                }
+                last = value;
+            }
+            PollEvent::Error { .. } => panic!("Expected a value"),
+        }
+    }
+}
+
+/// Errors are counted until the next successful read and sent to subscribers.
+#[rstest]
+fn poller_consecutive_errors() {
+    // the first three reads fail, then all succeed
+    let poller = Poller::new(0usize, INTERVAL, |cnt| {
+        *cnt += 1;
+        if *cnt <= 3 {
+            Err(InstrumentError::InvalidArgument("nope".to_string()))
+        } else {
+            Ok(*cnt)
+        }
+    });
+    let rx = poller.subscribe();
+
+    let mut errors = Vec::new();
+    loop {
+        match rx.recv_timeout(RECV_TIMEOUT).unwrap() {
+            PollEvent::Error { error, consecutive } => {
+                assert!(matches!(*error, InstrumentError::InvalidArgument(_)));
+                errors.push(consecutive);
+            }
+            PollEvent::Value(_, value) => {
+                assert_eq!(4, value);
+                break;
+            }
+        }
+    }
+    assert!(poller.latest().is_some());
+    assert_eq!(0, poller.consecutive_errors());
+
+    // the subscription might have started after the first errors were read
+    assert!(!errors.is_empty());
+    assert_eq!(3, *errors.last().unwrap());
+}
+
+/// Without a successful read, there is no latest value.
+#[rstest]
+fn poller_only_errors() {
+    let poller: Poller<(), usize> =
+        Poller::new((), INTERVAL, |_| Err(InstrumentError::Timeout(INTERVAL)));
+    let rx = poller.subscribe();
+    for _ in 0..3 {
+        rx.recv_timeout(RECV_TIMEOUT).unwrap();
+    }
+    assert!(poller.latest().is_none());
+    assert!(poller.consecutive_errors() >= 3);
+}
+
+/// Dropping the poller stops the thread, even with a long interval.
+#[rstest]
+fn poller_stops_on_drop() {
+    let reads = Arc::new(AtomicUsize::new(0));
+    let reads_thread = Arc::clone(&reads);
+    let poller = Poller::new((), Duration::from_secs(3600), move |_| {
+        Ok(reads_thread.fetch_add(1, Ordering::SeqCst))
+    });
+    let rx = poller.subscribe();
+    drop(poller);
+
+    // the thread has exited and dropped its sender, the channel is disconnected
+    let _ = rx.recv_timeout(RECV_TIMEOUT);
+    assert!(rx.recv_timeout(RECV_TIMEOUT).is_err());
+    let reads_after_drop = reads.load(Ordering::SeqCst);
+    thread::sleep(INTERVAL * 4);
+    assert_eq!(reads_after_drop, reads.load(Ordering::SeqCst));
+    assert!(reads_after_drop <= 1);
+}
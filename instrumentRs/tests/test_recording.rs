@@ -0,0 +1,106 @@
+//! Tests for recording the traffic of an interface with the [`RecordingInterface`].
+
+#![cfg(feature = "recording")]
+
+use std::{collections::VecDeque, time::Duration};
+
+use rstest::*;
+
+use instrumentrs::{
+    Direction, Instrument, InstrumentInterface, LoopbackInterface, RecordingInterface, Transcript,
+};
+
+/// Create a recording interface around a loopback interface with the given commands.
+fn crt_rec(host2inst: Vec<&str>, inst2host: Vec<&str>) -> RecordingInterface<LoopbackInterface> {
+    let host2inst = host2inst.iter().map(|s| s.to_string()).collect();
+    let inst2host = inst2host.iter().map(|s| s.to_string()).collect();
+    RecordingInterface::new(LoopbackInterface::new(host2inst, inst2host, "\n"))
+}
+
+/// Get the directions and data of all entries of a transcript.
+fn entries(transcript: &Transcript) -> Vec<(Direction, &[u8])> {
+    transcript
+        .entries
+        .iter()
+        .map(|e| (e.direction, e.data.as_slice()))
+        .collect()
+}
+
+/// Queries are recorded including their terminators, reads are combined into one entry.
+#[rstest]
+fn record_queries() {
+    let mut rec = crt_rec(vec!["cmd1", "cmd2"], vec!["resp1", "resp2"]);
+    assert_eq!("resp1", rec.query("cmd1").unwrap());
+    assert_eq!("resp2", rec.query("cmd2").unwrap());
+
+    let exp: Vec<(Direction, &[u8])> = vec![
+        (Direction::Write, b"cmd1\n"),
+        (Direction::Read, b"resp1\n"),
+        (Direction::Write, b"cmd2\n"),
+        (Direction::Read, b"resp2\n"),
+    ];
+    assert_eq!(exp, entries(rec.transcript()));
+
+    let times: Vec<f64> = rec.transcript().entries.iter().map(|e| e.time).collect();
+    assert!(times.windows(2).all(|w| w[0] <= w[1]));
+}
+
+/// Raw bytes that are not valid UTF-8 are recorded and serialized as bytes.
+#[rstest]
+fn record_raw_bytes() {
+    let port: VecDeque<u8> = VecDeque::new();
+    let mut rec = RecordingInterface::new(Instrument::new(port, Duration::from_secs(1)));
+    assert_eq!(vec![0xFF, 0x01], rec.query_raw(&[0xFF, 0x01], 2).unwrap());
+
+    let exp: Vec<(Direction, &[u8])> = vec![
+        (Direction::Write, &[0xFF, 0x01]),
+        (Direction::Read, &[0xFF, 0x01]),
+    ];
+    assert_eq!(exp, entries(rec.transcript()));
+
+    let json = rec.transcript().to_json().unwrap();
+    assert!(json.contains("\"bytes\""));
+    assert!(!json.contains("\"text\""));
+}
+
+/// The JSON transcript follows the documented schema and can be read back.
+#[rstest]
+fn save_json() {
+    let mut rec = crt_rec(vec!["*IDN?"], vec!["MyInstrument"]);
+    rec.query("*IDN?").unwrap();
+
+    let path = std::env::temp_dir().join("instrumentrs_test_recording.json");
+    rec.save_json(&path).unwrap();
+    let json = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(json.contains("\"direction\": \"write\""));
+    assert!(json.contains("\"text\": \"*IDN?\\n\""));
+    assert!(json.contains("\"direction\": \"read\""));
+    assert!(json.contains("\"text\": \"MyInstrument\\n\""));
+    assert_eq!(rec.transcript(), &Transcript::from_json(&json).unwrap());
+}
+
+/// The TOML transcript follows the documented schema and can be read back.
+#[rstest]
+fn save_toml() {
+    let mut rec = crt_rec(vec!["*IDN?"], vec!["MyInstrument"]);
+    rec.query("*IDN?").unwrap();
+
+    let path = std::env::temp_dir().join("instrumentrs_test_recording.toml");
+    rec.save_toml(&path).unwrap();
+    let toml = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(2, toml.matches("[[entries]]").count());
+    assert!(toml.contains("direction = \"write\""));
+    assert!(toml.contains("direction = \"read\""));
+    assert_eq!(rec.transcript(), &Transcript::from_toml(&toml).unwrap());
+}
+
+/// Invalid transcripts return an error.
+#[rstest]
+fn invalid_transcript() {
+    assert!(Transcript::from_json("{\"entries\": [{\"time\": 0.0}]}").is_err());
+    assert!(Transcript::from_toml("entries = 1").is_err());
+}
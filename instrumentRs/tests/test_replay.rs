@@ -0,0 +1,102 @@
+//! Tests for replaying recorded transcripts with the [`ReplayInterface`].
+
+#![cfg(feature = "recording")]
+
+use rstest::*;
+
+use instrumentrs::{
+    Direction, InstrumentError, InstrumentInterface, LoopbackInterface, RecordingInterface,
+    ReplayInterface, Transcript, TranscriptEntry,
+};
+
+/// Create a transcript from a list of entries without timing information.
+fn crt_transcript(entries: Vec<(Direction, &[u8])>) -> Transcript {
+    let entries = entries
+        .into_iter()
+        .map(|(direction, data)| TranscriptEntry {
+            time: 0.0,
+            direction,
+            data: data.to_vec(),
+        })
+        .collect();
+    Transcript { entries }
+}
+
+/// Transcript of a query for the name of an instrument.
+#[fixture]
+fn idn() -> Transcript {
+    crt_transcript(vec![
+        (Direction::Write, b"*IDN?\n"),
+        (Direction::Read, b"MyInstrument\n"),
+    ])
+}
+
+/// Record a session, save it, and replay it from the file.
+#[rstest]
+#[case("json")]
+#[case("toml")]
+fn replay_recorded_session(#[case] ext: &str) {
+    let host2inst = vec!["*IDN?".to_string(), "TEMP?".to_string()];
+    let inst2host = vec!["MyInstrument".to_string(), "273.15".to_string()];
+    let mut rec = RecordingInterface::new(LoopbackInterface::new(host2inst, inst2host, "\n"));
+    rec.query("*IDN?").unwrap();
+    rec.query("TEMP?").unwrap();
+
+    let path = std::env::temp_dir().join(format!("instrumentrs_test_replay.{ext}"));
+    match ext {
+        "json" => rec.save_json(&path).unwrap(),
+        _ => rec.save_toml(&path).unwrap(),
+    }
+    let mut replay = ReplayInterface::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!("MyInstrument", replay.query("*IDN?").unwrap());
+    assert_eq!("273.15", replay.query("TEMP?").unwrap());
+}
+
+/// Replay a transcript with raw bytes.
+#[rstest]
+fn replay_bytes() {
+    let transcript = crt_transcript(vec![
+        (Direction::Write, &[0x02, 0xFF]),
+        (Direction::Read, &[0x06, 0xFE, 0x03]),
+    ]);
+    let mut replay = ReplayInterface::new(transcript);
+    assert_eq!(
+        vec![0x06, 0xFE, 0x03],
+        replay.query_raw_until(&[0x02, 0xFF], 0x03).unwrap()
+    );
+}
+
+/// Data written that does not match the transcript panics.
+#[rstest]
+#[should_panic(expected = "expected: \"*IDN?\\n\"")]
+fn replay_mismatch(idn: Transcript) {
+    let mut replay = ReplayInterface::new(idn);
+    let _ = replay.query("*IDX?");
+}
+
+/// Reading when a write is expected panics.
+#[rstest]
+#[should_panic(expected = "but a Read was requested")]
+fn replay_wrong_direction(idn: Transcript) {
+    let mut replay = ReplayInterface::new(idn);
+    let _ = replay.read_until_terminator();
+}
+
+/// Unused entries of the transcript panic when the interface is dropped.
+#[rstest]
+#[should_panic(expected = "Leftover entry 1")]
+fn replay_leftover(idn: Transcript) {
+    let mut replay = ReplayInterface::new(idn);
+    replay.sendcmd("*IDN?").unwrap();
+}
+
+/// Files without a known extension are rejected.
+#[rstest]
+fn replay_from_file_invalid_extension() {
+    assert!(matches!(
+        ReplayInterface::from_file("transcript.txt"),
+        Err(InstrumentError::InvalidArgument(_))
+    ));
+}
@@ -0,0 +1,143 @@
+//! Tests for retrying queries with a [`RetryPolicy`].
+
+use std::{collections::VecDeque, time::Duration};
+
+use rstest::*;
+
+use instrumentrs::{
+    Backoff, InstrumentError, InstrumentInterface, LoopbackCommand, LoopbackEntry,
+    LoopbackInterface, RetryPolicy,
+};
+
+/// An interface that wraps a loopback interface and fails the first `failures` reads.
+struct FlakyInterface {
+    lbk: LoopbackInterface,
+    failures: usize,
+}
+
+impl FlakyInterface {
+    fn new(host2inst: Vec<&str>, inst2host: Vec<&str>, failures: usize) -> Self {
+        let host2inst = host2inst.iter().map(|s| s.to_string()).collect();
+        let inst2host = inst2host.iter().map(|s| s.to_string()).collect();
+        Self {
+            lbk: LoopbackInterface::new(host2inst, inst2host, "\n"),
+            failures,
+        }
+    }
+}
+
+impl InstrumentInterface for FlakyInterface {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into());
+        }
+        self.lbk.read_exact(buf)
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        self.lbk.write_raw(data)
+    }
+}
+
+/// An interface that wraps a loopback interface and receives a late reply to the first query.
+///
+/// The first read times out, then the late reply arrives and stays in the input until drained.
+struct LateInterface {
+    lbk: LoopbackInterface,
+    late_reply: Option<&'static [u8]>,
+    input: VecDeque<u8>,
+}
+
+impl InstrumentInterface for LateInterface {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        if let Some(reply) = self.late_reply.take() {
+            self.input.extend(reply);
+            return Err(InstrumentError::Timeout(Duration::ZERO));
+        }
+        if self.input.is_empty() {
+            return self.lbk.read_exact(buf);
+        }
+        for byte in buf.iter_mut() {
+            *byte = self.input.pop_front().unwrap();
+        }
+        Ok(())
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        self.lbk.write_raw(data)
+    }
+
+    fn drain_input(&mut self) -> Result<usize, InstrumentError> {
+        let drained = self.input.len();
+        self.input.clear();
+        Ok(drained)
+    }
+}
+
+/// A fast retry policy for testing.
+#[fixture]
+fn policy() -> RetryPolicy {
+    RetryPolicy::new(3).with_backoff(Backoff::Fixed(Duration::from_millis(1)))
+}
+
+/// The first attempt fails, the second one succeeds.
+#[rstest]
+fn query_with_retry_success_on_second_attempt(policy: RetryPolicy) {
+    let mut intf = FlakyInterface::new(vec!["cmd", "cmd"], vec!["resp"], 1);
+    assert_eq!("resp", intf.query_with_retry("cmd", &policy).unwrap());
+}
+
+/// All attempts fail, the error contains the number of attempts.
+#[rstest]
+fn query_with_retry_exhausted(policy: RetryPolicy) {
+    let mut intf = FlakyInterface::new(vec!["cmd", "cmd", "cmd"], vec![], 3);
+    match intf.query_with_retry("cmd", &policy) {
+        Err(InstrumentError::RetriesExhausted { attempts, source }) => {
+            assert_eq!(3, attempts);
+            assert!(matches!(*source, InstrumentError::Io(_)));
+        }
+        _ => panic!("Expected exhausted retries, but got a different result."),
+    }
+}
+
+/// Errors that should not be retried are returned immediately.
+#[rstest]
+fn query_with_retry_not_retried(policy: RetryPolicy) {
+    let mut intf = FlakyInterface::new(vec!["cmd"], vec![], 1);
+    let policy = policy.with_retry_on(|err| matches!(err, InstrumentError::Timeout(_)));
+    assert!(matches!(
+        intf.query_with_retry("cmd", &policy),
+        Err(InstrumentError::Io(_))
+    ));
+}
+
+/// Recover from I/O errors that the loopback interface injects into the reads and writes.
+#[rstest]
+fn query_with_retry_injected_errors(policy: RetryPolicy) {
+    let io_error = || std::io::Error::from(std::io::ErrorKind::ConnectionReset).into();
+    let from_host = vec![
+        LoopbackCommand::exact("cmd").failing(1, io_error),
+        "cmd".into(),
+    ];
+    let from_inst = vec![LoopbackEntry::from("resp").failing(1, io_error)];
+    let mut lbk = LoopbackInterface::<String>::with_commands(from_host, from_inst, "\n");
+
+    // write fails, read fails, then the query succeeds
+    assert_eq!("resp", lbk.query_with_retry("cmd", &policy).unwrap());
+}
+
+/// A late reply to a failed attempt is drained and not taken as the reply to the retry.
+#[rstest]
+fn query_with_retry_late_reply(policy: RetryPolicy) {
+    let mut intf = LateInterface {
+        lbk: LoopbackInterface::new(
+            vec!["cmd".to_string(), "cmd".to_string()],
+            vec!["resp".to_string()],
+            "\n",
+        ),
+        late_reply: Some(b"stale\n"),
+        input: VecDeque::new(),
+    };
+    assert_eq!("resp", intf.query_with_retry("cmd", &policy).unwrap());
+}
@@ -0,0 +1,169 @@
+//! Tests for the [`Rfc2217Interface`] using a local TCP listener as the RFC 2217 server.
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use rstest::*;
+
+use instrumentrs::{
+    Instrument, InstrumentError, InstrumentInterface, Rfc2217Config, Rfc2217Interface,
+    Rfc2217Parity, Rfc2217Port, Rfc2217StopBits,
+};
+
+/// Telnet IAC WILL COM-PORT-OPTION, sent by the client.
+const WILL_COM_PORT: [u8; 3] = [255, 251, 44];
+
+/// Read exactly `len` bytes from the stream.
+fn read_bytes(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; len];
+    stream.read_exact(&mut buf).unwrap();
+    buf
+}
+
+/// Connect to a server that accepts the COM-PORT-OPTION and return the client and the server.
+fn connect(config: &Rfc2217Config) -> (Instrument<Rfc2217Port>, TcpStream, Vec<u8>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        assert_eq!(WILL_COM_PORT.to_vec(), read_bytes(&mut stream, 3));
+        stream.write_all(&[255, 253, 44]).unwrap(); // IAC DO COM-PORT-OPTION
+        stream
+    });
+    let inst = Rfc2217Interface::simple(addr, config).unwrap();
+    let mut stream = server.join().unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+
+    // baud rate (4 bytes), data size, parity, stop size, and control (1 byte each)
+    let settings = read_bytes(&mut stream, 10 + 4 * 7);
+    (inst, stream, settings)
+}
+
+/// The settings are sent as COM-PORT-OPTION subnegotiations as defined in RFC 2217.
+#[rstest]
+fn negotiation_sequence() {
+    let config = Rfc2217Config {
+        baud: 57600,
+        data_bits: 7,
+        parity: Rfc2217Parity::Odd,
+        stop_bits: Rfc2217StopBits::One,
+    };
+    let (_inst, _stream, settings) = connect(&config);
+
+    let exp: Vec<u8> = [
+        vec![255, 250, 44, 1, 0x00, 0x00, 0xE1, 0x00, 255, 240], // SET-BAUDRATE 57600
+        vec![255, 250, 44, 2, 7, 255, 240],                      // SET-DATASIZE 7
+        vec![255, 250, 44, 3, 2, 255, 240],                      // SET-PARITY ODD
+        vec![255, 250, 44, 4, 1, 255, 240],                      // SET-STOPSIZE 1
+        vec![255, 250, 44, 5, 1, 255, 240],                      // SET-CONTROL no flow control
+    ]
+    .concat();
+    assert_eq!(exp, settings);
+}
+
+/// A baud rate that contains 0xFF is escaped.
+#[rstest]
+fn negotiation_escapes_baud_rate() {
+    let config = Rfc2217Config {
+        baud: 0x01FF,
+        ..Default::default()
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_bytes(&mut stream, 3);
+        stream.write_all(&[255, 253, 44]).unwrap();
+        let mut settings = read_bytes(&mut stream, 11 + 4 * 7);
+        settings.truncate(11);
+        settings
+    });
+    let _inst = Rfc2217Interface::simple(addr, &config).unwrap();
+    assert_eq!(
+        vec![255, 250, 44, 1, 0x00, 0x00, 0x01, 0xFF, 0xFF, 255, 240],
+        server.join().unwrap()
+    );
+}
+
+/// After the negotiation, the interface behaves like a normal interface and ignores the
+/// notifications of the server.
+#[rstest]
+fn query_after_negotiation() {
+    let (mut inst, mut stream, _) = connect(&Rfc2217Config::default());
+    // server confirms the baud rate, then the instrument responds
+    stream
+        .write_all(&[255, 250, 44, 101, 0, 0, 0x25, 0x80, 255, 240])
+        .unwrap();
+    stream.write_all(b"resp\n").unwrap();
+    assert_eq!("resp", inst.query("cmd").unwrap());
+    assert_eq!(b"cmd\n".to_vec(), read_bytes(&mut stream, 4));
+}
+
+/// RTS and DTR are controlled with SET-CONTROL commands.
+#[rstest]
+fn control_lines() {
+    let (mut inst, mut stream, _) = connect(&Rfc2217Config::default());
+    inst.set_rts(true).unwrap();
+    inst.set_rts(false).unwrap();
+    inst.set_dtr(true).unwrap();
+    inst.set_dtr(false).unwrap();
+
+    let exp: Vec<u8> = [11, 12, 8, 9]
+        .into_iter()
+        .flat_map(|val| [255, 250, 44, 5, val, 255, 240])
+        .collect();
+    assert_eq!(exp, read_bytes(&mut stream, exp.len()));
+}
+
+/// With RTS on send, RTS is set before and cleared after every write.
+#[rstest]
+fn rts_on_send() {
+    let (mut inst, mut stream, _) = connect(&Rfc2217Config::default());
+    inst.set_rts_on_send(Some(Duration::from_millis(1)));
+    inst.sendcmd("cmd").unwrap();
+
+    let exp: Vec<u8> = [
+        vec![255, 250, 44, 5, 11, 255, 240],
+        b"cmd\n".to_vec(),
+        vec![255, 250, 44, 5, 12, 255, 240],
+    ]
+    .concat();
+    assert_eq!(exp, read_bytes(&mut stream, exp.len()));
+}
+
+/// A server that refuses the COM-PORT-OPTION returns an error.
+#[rstest]
+fn server_refuses_option() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_bytes(&mut stream, 3);
+        stream.write_all(&[255, 254, 44]).unwrap(); // IAC DONT COM-PORT-OPTION
+        stream
+    });
+    let res = Rfc2217Interface::simple(addr, &Rfc2217Config::default());
+    let _stream = server.join().unwrap();
+    assert!(matches!(res, Err(InstrumentError::Io(_))));
+}
+
+/// Invalid data bits are rejected.
+#[rstest]
+fn invalid_data_bits() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = Rfc2217Config {
+        data_bits: 9,
+        ..Default::default()
+    };
+    assert!(matches!(
+        Rfc2217Interface::simple(addr, &config),
+        Err(InstrumentError::InvalidArgument(_))
+    ));
+}
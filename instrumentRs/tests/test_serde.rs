@@ -0,0 +1,83 @@
+//! Tests for serializing errors and settings, only available with the `serde` feature.
+
+#![cfg(feature = "serde")]
+
+use std::{fmt::Debug, io, time::Duration};
+
+use rstest::*;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+
+use instrumentrs::{
+    Backoff, InstrumentError, ModbusException, Rfc2217Config, Rfc2217Parity, Rfc2217StopBits,
+};
+
+/// Serialize a value to JSON and back.
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: T) {
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(value, serde_json::from_str::<T>(&json).unwrap());
+}
+
+#[rstest]
+#[case(ModbusException::IllegalDataAddress)]
+#[case(ModbusException::Other(0x42))]
+fn modbus_exception_round_trip(#[case] exception: ModbusException) {
+    round_trip(exception);
+}
+
+#[rstest]
+#[case(Backoff::Fixed(Duration::from_millis(100)))]
+#[case(Backoff::Exponential {
+    initial: Duration::from_millis(50),
+    max: Duration::from_secs(2),
+})]
+fn backoff_round_trip(#[case] backoff: Backoff) {
+    round_trip(backoff);
+}
+
+#[rstest]
+fn rfc2217_config_round_trip() {
+    round_trip(Rfc2217Config::default());
+    round_trip(Rfc2217Config {
+        baud: 115200,
+        data_bits: 7,
+        parity: Rfc2217Parity::Even,
+        stop_bits: Rfc2217StopBits::Two,
+    });
+}
+
+#[rstest]
+#[case(
+    InstrumentError::NotAcknowledged("ERR".to_string()),
+    "NotAcknowledged",
+)]
+#[case(InstrumentError::Timeout(Duration::from_secs(1)), "Timeout")]
+#[case(
+    InstrumentError::Io(io::Error::new(io::ErrorKind::BrokenPipe, "cable unplugged")),
+    "Io"
+)]
+#[case(
+    InstrumentError::RetriesExhausted {
+        attempts: 3,
+        source: Box::new(InstrumentError::InterfacePoisoned),
+    },
+    "RetriesExhausted",
+)]
+#[case(
+    InstrumentError::Modbus {
+        function: 0x03,
+        exception: ModbusException::IllegalFunction,
+    },
+    "Modbus",
+)]
+fn instrument_error_serialize(#[case] err: InstrumentError, #[case] kind: &str) {
+    let exp = json!({"kind": kind, "message": err.to_string()});
+    assert_eq!(exp, serde_json::to_value(&err).unwrap());
+}
+
+#[rstest]
+fn instrument_error_serialize_io_message() {
+    let err = InstrumentError::Io(io::Error::new(io::ErrorKind::BrokenPipe, "cable unplugged"));
+    let value = serde_json::to_value(&err).unwrap();
+    assert_eq!("cable unplugged", value["message"]);
+}
@@ -0,0 +1,230 @@
+//! Tests for serial instruments using a mock serial port.
+
+#![cfg(feature = "serial")]
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rstest::*;
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use instrumentrs::{Instrument, InstrumentInterface, Rs485Config};
+
+/// An event that happened on the mock serial port.
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Rts(bool),
+    Write(Vec<u8>),
+    Flush,
+    Read,
+}
+
+/// Shared list of events with their timestamps.
+type Events = Arc<Mutex<Vec<(Instant, Event)>>>;
+
+/// A mock serial port that records all events with a timestamp.
+#[derive(Debug, Default)]
+struct MockPort {
+    events: Events,
+    response: VecDeque<u8>,
+}
+
+impl MockPort {
+    fn record(&self, event: Event) {
+        self.events.lock().unwrap().push((Instant::now(), event));
+    }
+}
+
+impl Read for MockPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.record(Event::Read);
+        self.response.read(buf)
+    }
+}
+
+impl Write for MockPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.record(Event::Write(buf.to_vec()));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.record(Event::Flush);
+        Ok(())
+    }
+}
+
+impl SerialPort for MockPort {
+    fn name(&self) -> Option<String> {
+        Some("mock".to_string())
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(9600)
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(DataBits::Eight)
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(FlowControl::None)
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(Parity::None)
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(StopBits::One)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(3)
+    }
+
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, _data_bits: DataBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, _flow_control: FlowControl) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_parity(&mut self, _parity: Parity) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, _stop_bits: StopBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.record(Event::Rts(level));
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, _level: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Ok(self.response.len() as u32)
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+
+    fn clear(&self, _buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Err(serialport::Error::new(
+            serialport::ErrorKind::Unknown,
+            "Mock port cannot be cloned",
+        ))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
+
+/// Create a serial instrument with a mock port that responds with the given data.
+fn mock_inst(response: &[u8]) -> (Instrument<Box<dyn SerialPort>>, Events) {
+    let port = MockPort {
+        response: VecDeque::from(response.to_vec()),
+        ..Default::default()
+    };
+    let events = port.events.clone();
+    let port: Box<dyn SerialPort> = Box::new(port);
+    (Instrument::new(port, Duration::from_secs(3)), events)
+}
+
+#[rstest]
+fn test_rs485_sequence() {
+    let turnaround = Duration::from_millis(20);
+    let (inst, events) = mock_inst(b"resp\n");
+    let mut inst = inst.with_rs485(Rs485Config {
+        rts_on_send: true,
+        turnaround,
+    });
+
+    assert_eq!("resp", inst.query("cmd").unwrap());
+
+    let events = events.lock().unwrap();
+    let sequence: Vec<Event> = events.iter().map(|(_, ev)| ev.clone()).collect();
+    assert_eq!(
+        vec![
+            Event::Rts(true),
+            Event::Write(b"cmd\n".to_vec()),
+            Event::Flush,
+            Event::Rts(false),
+            Event::Read,
+        ],
+        sequence
+    );
+    // The response is read after the turnaround delay.
+    assert!(events[4].0 - events[3].0 >= turnaround);
+}
+
+#[rstest]
+fn test_rs485_turnaround_only() {
+    let (inst, events) = mock_inst(b"resp\n");
+    let mut inst = inst.with_rs485(Rs485Config {
+        rts_on_send: false,
+        turnaround: Duration::from_millis(1),
+    });
+
+    assert_eq!("resp", inst.query("cmd").unwrap());
+    let events = events.lock().unwrap();
+    assert!(!events.iter().any(|(_, ev)| matches!(ev, Event::Rts(_))));
+}
+
+#[rstest]
+fn test_rs485_disabled() {
+    let (inst, events) = mock_inst(b"resp\n");
+    let mut inst = inst.with_rs485(Rs485Config {
+        rts_on_send: true,
+        turnaround: Duration::ZERO,
+    });
+    inst.set_rs485(None);
+
+    assert_eq!("resp", inst.query("cmd").unwrap());
+    let events = events.lock().unwrap();
+    assert!(!events.iter().any(|(_, ev)| matches!(ev, Event::Rts(_))));
+}
@@ -0,0 +1,154 @@
+//! Tests for sharing one interface between multiple instruments with a [`SharedBus`].
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rstest::*;
+
+use instrumentrs::{Instrument, InstrumentError, InstrumentInterface, SharedBus};
+
+/// An echo interface that detects if a write happens while a response is still pending.
+struct EchoBus {
+    pending: VecDeque<u8>,
+    interleaved: Arc<AtomicBool>,
+}
+
+impl EchoBus {
+    fn new(interleaved: Arc<AtomicBool>) -> Self {
+        EchoBus {
+            pending: VecDeque::new(),
+            interleaved,
+        }
+    }
+}
+
+impl InstrumentInterface for EchoBus {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        for byte in buf.iter_mut() {
+            *byte = self
+                .pending
+                .pop_front()
+                .ok_or(InstrumentError::Timeout(Duration::ZERO))?;
+        }
+        Ok(())
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        if !self.pending.is_empty() {
+            self.interleaved.store(true, Ordering::SeqCst);
+        }
+        self.pending.extend(data);
+        // give other threads the chance to write in between
+        thread::sleep(Duration::from_micros(100));
+        Ok(())
+    }
+}
+
+/// Two threads that query through their own handles never interleave their frames.
+#[rstest]
+fn no_interleaving() {
+    let interleaved = Arc::new(AtomicBool::new(false));
+    let bus = SharedBus::new(EchoBus::new(Arc::clone(&interleaved)));
+
+    let threads: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|name| {
+            let mut handle = bus.handle();
+            thread::spawn(move || {
+                for it in 0..50 {
+                    let cmd = format!("{name}{it}");
+                    assert_eq!(cmd, handle.query(&cmd).unwrap());
+                }
+            })
+        })
+        .collect();
+    for th in threads {
+        th.join().unwrap();
+    }
+
+    assert!(!interleaved.load(Ordering::SeqCst));
+}
+
+/// Multiple calls in a transaction are not interrupted by other handles.
+#[rstest]
+fn transaction() {
+    let interleaved = Arc::new(AtomicBool::new(false));
+    let bus = SharedBus::new(EchoBus::new(Arc::clone(&interleaved)));
+    let mut handle = bus.handle();
+
+    handle
+        .transaction(|intf| {
+            intf.sendcmd("ACK")?;
+            intf.check_acknowledgment("ACK")
+        })
+        .unwrap();
+    assert!(!interleaved.load(Ordering::SeqCst));
+}
+
+/// Every handle uses its own terminator and timeout.
+#[rstest]
+fn handle_settings() {
+    let port: VecDeque<u8> = VecDeque::new();
+    let bus = SharedBus::new(Instrument::new(port, Duration::from_secs(1)));
+    let mut hdl1 = bus.handle();
+    let mut hdl2 = bus.handle();
+    hdl1.set_terminator("\r");
+    hdl1.set_timeout(Duration::from_millis(200));
+
+    assert_eq!("\n", hdl2.get_terminator());
+    assert_eq!(Duration::from_secs(1), hdl2.get_timeout());
+
+    assert_eq!("cmd1", hdl1.query("cmd1").unwrap());
+    assert_eq!("cmd2", hdl2.query("cmd2").unwrap());
+    hdl1.transaction(|intf| {
+        assert_eq!("\r", intf.get_terminator());
+        assert_eq!(Duration::from_millis(200), intf.get_timeout());
+        Ok(())
+    })
+    .unwrap();
+}
+
+/// The bus stays locked for the post-transaction delay of a handle.
+#[rstest]
+fn post_transaction_delay() {
+    let delay = Duration::from_millis(20);
+    let interleaved = Arc::new(AtomicBool::new(false));
+    let bus = SharedBus::new(EchoBus::new(interleaved));
+    let mut hdl1 = bus.handle().with_post_transaction_delay(delay);
+    let mut hdl2 = bus.handle();
+    assert_eq!(delay, hdl1.get_post_transaction_delay());
+    assert_eq!(Duration::ZERO, hdl2.get_post_transaction_delay());
+
+    let tic = Instant::now();
+    hdl1.query("cmd1").unwrap();
+    hdl1.query("cmd2").unwrap();
+    hdl2.query("cmd3").unwrap();
+    assert!(tic.elapsed() >= 2 * delay);
+}
+
+/// A panic while one handle uses the bus results in errors for all other handles.
+#[rstest]
+fn poisoned_bus() {
+    let bus = SharedBus::new(EchoBus::new(Arc::new(AtomicBool::new(false))));
+    let mut hdl1 = bus.handle();
+    let mut hdl2 = bus.handle();
+
+    let res = thread::spawn(move || {
+        hdl1.transaction(|_| -> Result<(), InstrumentError> { panic!("driver bug") })
+    })
+    .join();
+    assert!(res.is_err());
+
+    assert!(matches!(
+        hdl2.query("cmd"),
+        Err(InstrumentError::InterfacePoisoned)
+    ));
+    assert!(!hdl2.is_alive());
+}
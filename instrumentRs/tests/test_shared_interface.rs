@@ -0,0 +1,204 @@
+//! Tests for sharing one interface between multiple owners with a [`SharedInterface`].
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use rstest::*;
+
+use instrumentrs::{
+    Instrument, InstrumentError, InstrumentInterface, SharedInterface, lock_interface,
+};
+
+/// A loopback interface that detects if a write happens while a response is still pending.
+struct EchoLoopback {
+    pending: VecDeque<u8>,
+    interleaved: Arc<AtomicBool>,
+}
+
+impl EchoLoopback {
+    fn new(interleaved: Arc<AtomicBool>) -> Self {
+        EchoLoopback {
+            pending: VecDeque::new(),
+            interleaved,
+        }
+    }
+}
+
+impl InstrumentInterface for EchoLoopback {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), InstrumentError> {
+        for byte in buf.iter_mut() {
+            *byte = self
+                .pending
+                .pop_front()
+                .ok_or(InstrumentError::Timeout(Duration::ZERO))?;
+        }
+        Ok(())
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), InstrumentError> {
+        if !self.pending.is_empty() {
+            self.interleaved.store(true, Ordering::SeqCst);
+        }
+        self.pending.extend(data);
+        // give other threads the chance to write in between
+        thread::sleep(Duration::from_micros(100));
+        Ok(())
+    }
+}
+
+/// Transactions of two threads that share one interface never interleave.
+#[rstest]
+fn transactions_do_not_interleave() {
+    let interleaved = Arc::new(AtomicBool::new(false));
+    let intf = SharedInterface::new(EchoLoopback::new(Arc::clone(&interleaved)));
+
+    let threads: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|name| {
+            let intf = intf.clone();
+            thread::spawn(move || {
+                for it in 0..50 {
+                    let cmd = format!("{name}{it}");
+                    intf.transaction(|intf| {
+                        intf.sendcmd(&cmd)?;
+                        intf.check_acknowledgment(&cmd)
+                    })
+                    .unwrap();
+                }
+            })
+        })
+        .collect();
+    for th in threads {
+        th.join().unwrap();
+    }
+
+    assert!(!interleaved.load(Ordering::SeqCst));
+}
+
+/// Queries of two threads that share one interface never interleave.
+#[rstest]
+fn queries_do_not_interleave() {
+    let interleaved = Arc::new(AtomicBool::new(false));
+    let intf = SharedInterface::new(EchoLoopback::new(Arc::clone(&interleaved)));
+
+    let threads: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|name| {
+            let mut intf = intf.clone();
+            thread::spawn(move || {
+                for it in 0..50 {
+                    let cmd = format!("{name}{it}");
+                    assert_eq!(cmd, intf.query(&cmd).unwrap());
+                }
+            })
+        })
+        .collect();
+    for th in threads {
+        th.join().unwrap();
+    }
+
+    assert!(!interleaved.load(Ordering::SeqCst));
+}
+
+/// Settings are applied to the shared interface.
+#[rstest]
+fn settings() {
+    let port: VecDeque<u8> = VecDeque::new();
+    let mut intf = SharedInterface::new(Instrument::new(port, Duration::from_secs(1)));
+    intf.set_terminator("\r");
+    intf.set_timeout(Duration::from_millis(200));
+
+    let other = intf.clone();
+    assert_eq!("\r", other.get_terminator());
+    assert_eq!(Duration::from_millis(200), other.get_timeout());
+    other
+        .transaction(|intf| {
+            assert_eq!("\r", intf.get_terminator());
+            Ok(())
+        })
+        .unwrap();
+}
+
+/// Setting the terminator on one clone applies to all other clones.
+#[rstest]
+fn terminator_shared_by_clones() {
+    let port = VecDeque::from(b"resp\r".to_vec());
+    let mut intf = SharedInterface::new(Instrument::new(port, Duration::from_secs(1)));
+    let mut other = intf.clone();
+    let term = intf.get_terminator_bytes();
+    other.set_terminator("\r");
+
+    assert_eq!("\n", String::from_utf8_lossy(term));
+    assert_eq!("\r", intf.get_terminator());
+    assert_eq!("resp", intf.read_until_terminator().unwrap());
+    assert_eq!("\r", intf.clone().get_terminator());
+}
+
+/// Setting the same terminators over and over again keeps returning the latest one.
+#[rstest]
+fn terminator_set_repeatedly() {
+    let port: VecDeque<u8> = VecDeque::new();
+    let mut intf = SharedInterface::new(Instrument::new(port, Duration::from_secs(1)));
+    let other = intf.clone();
+    let first = intf.get_terminator_bytes().to_vec();
+    for _ in 0..1000 {
+        intf.set_terminator("\r\n");
+        assert_eq!("\r\n", other.get_terminator());
+        intf.set_terminator("\r");
+        assert_eq!("\r", other.get_terminator());
+        intf.set_terminator_bytes(&first);
+        assert_eq!(first, other.get_terminator_bytes());
+    }
+}
+
+/// A panic in one thread results in errors instead of panics in all other threads.
+#[rstest]
+fn poisoned_interface() {
+    let port: VecDeque<u8> = VecDeque::new();
+    let mut intf = SharedInterface::new(Instrument::new(port, Duration::from_secs(3)));
+    let other = intf.clone();
+
+    let res = thread::spawn(move || {
+        other.transaction(|_| -> Result<(), InstrumentError> { panic!("driver bug") })
+    })
+    .join();
+    assert!(res.is_err());
+
+    assert!(matches!(
+        intf.query("cmd"),
+        Err(InstrumentError::InterfacePoisoned)
+    ));
+    assert!(!intf.is_alive());
+    // settings can still be read and changed
+    intf.set_timeout(Duration::from_secs(1));
+    assert_eq!(Duration::from_secs(1), intf.get_timeout());
+}
+
+/// Locking a poisoned mutex returns an error.
+#[rstest]
+fn lock_interface_poisoned() {
+    let mutex = Arc::new(Mutex::new(EchoLoopback::new(Arc::new(AtomicBool::new(
+        false,
+    )))));
+    assert!(lock_interface(&mutex).is_ok());
+
+    let other = Arc::clone(&mutex);
+    let res = thread::spawn(move || {
+        let _intf = other.lock().unwrap();
+        panic!("driver bug");
+    })
+    .join();
+    assert!(res.is_err());
+
+    assert!(matches!(
+        lock_interface(&mutex),
+        Err(InstrumentError::InterfacePoisoned)
+    ));
+}
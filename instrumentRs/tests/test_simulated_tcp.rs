@@ -0,0 +1,105 @@
+//! End-to-end tests of the [`TcpIpInterface`] against a [`SimulatedTcpInstrument`], only
+//! available with the `test-server` feature.
+
+#![cfg(feature = "test-server")]
+
+use std::time::{Duration, Instant};
+
+use rstest::*;
+
+use instrumentrs::{InstrumentError, InstrumentInterface, SimulatedTcpInstrument, TcpIpInterface};
+
+/// A simple instrument that knows two commands and ignores all others.
+fn responder(cmd: &str) -> Option<String> {
+    match cmd {
+        "*IDN?" => Some("Simulated,1.0".to_string()),
+        "TEMP?" => Some("4.2".to_string()),
+        _ => None,
+    }
+}
+
+#[rstest]
+fn simple_query() {
+    let sim = SimulatedTcpInstrument::spawn(responder).unwrap();
+    let mut inst = TcpIpInterface::simple(sim.get_addr()).unwrap();
+
+    assert_eq!("Simulated,1.0", inst.query("*IDN?").unwrap());
+    assert_eq!("4.2", inst.query("TEMP?").unwrap());
+    inst.sendcmd("OUT 1").unwrap();
+    assert_eq!("4.2", inst.query("TEMP?").unwrap());
+
+    assert_eq!(vec!["*IDN?", "TEMP?", "OUT 1", "TEMP?"], sim.get_received());
+}
+
+#[rstest]
+fn simple_query_timeout() {
+    let sim = SimulatedTcpInstrument::spawn(responder).unwrap();
+    let mut inst = TcpIpInterface::simple(sim.get_addr()).unwrap();
+    let timeout = Duration::from_millis(50);
+    inst.set_timeout(timeout);
+
+    let tic = Instant::now();
+    match inst.query("UNKNOWN?") {
+        Err(InstrumentError::TimeoutQuery {
+            query,
+            timeout: tout,
+            partial,
+        }) => {
+            assert_eq!("UNKNOWN?", query);
+            assert_eq!(timeout, tout);
+            assert!(partial.is_empty());
+        }
+        res => panic!("Expected TimeoutQuery error, got {res:?}"),
+    }
+    assert!(tic.elapsed() < Duration::from_secs(1));
+
+    // the interface is still usable after the timeout
+    assert_eq!("4.2", inst.query("TEMP?").unwrap());
+}
+
+#[rstest]
+fn response_delay() {
+    let sim = SimulatedTcpInstrument::builder()
+        .response_delay(Duration::from_millis(200))
+        .spawn(responder)
+        .unwrap();
+    let mut inst = TcpIpInterface::simple(sim.get_addr()).unwrap();
+
+    inst.set_timeout(Duration::from_millis(50));
+    assert!(inst.query("TEMP?").is_err());
+
+    // the late response is still in the input buffer and discarded
+    std::thread::sleep(Duration::from_millis(250));
+    inst.drain_input().unwrap();
+    inst.set_timeout(Duration::from_secs(1));
+    assert_eq!("4.2", inst.query("TEMP?").unwrap());
+}
+
+#[rstest]
+fn custom_terminator() {
+    let sim = SimulatedTcpInstrument::builder()
+        .terminator("\r\n")
+        .spawn(|cmd| Some(format!("echo {cmd}")))
+        .unwrap();
+    let mut inst = TcpIpInterface::simple(sim.get_addr()).unwrap();
+    inst.set_terminator("\r\n");
+
+    assert_eq!("echo cmd", inst.query("cmd").unwrap());
+}
+
+#[rstest]
+fn empty_terminator() {
+    let res = SimulatedTcpInstrument::builder()
+        .terminator("")
+        .spawn(responder);
+    assert!(matches!(res, Err(InstrumentError::InvalidArgument(_))));
+}
+
+/// Dropping the instrument stops its thread, even if no client ever connected.
+#[rstest]
+fn drop_without_connection() {
+    let sim = SimulatedTcpInstrument::spawn(responder).unwrap();
+    let tic = Instant::now();
+    drop(sim);
+    assert!(tic.elapsed() < Duration::from_secs(1));
+}
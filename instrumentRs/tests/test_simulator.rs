@@ -0,0 +1,170 @@
+//! Tests for the [`SimulatorInterface`] and the shipped simulators, only available with the
+//! `simulator` feature.
+
+#![cfg(feature = "simulator")]
+
+use rstest::*;
+
+use instrumentrs::{
+    CryoTelGtSimulator, InstrumentError, InstrumentInterface, SimulatorInterface, Tpg36xSimulator,
+};
+
+const ENQ: &str = "\u{5}";
+const ACK: &str = "\u{6}";
+const NAK: &str = "\u{15}";
+
+/// Create an interface to a simulated TPG36x with the terminator the driver uses.
+#[fixture]
+fn tpg36x() -> SimulatorInterface<Tpg36xSimulator> {
+    let mut intf = SimulatorInterface::new(Tpg36xSimulator::new());
+    intf.set_terminator("\r\n");
+    intf
+}
+
+/// Send a command to the TPG36x, check the acknowledgment, and enquire the response.
+fn tpg36x_query(intf: &mut SimulatorInterface<Tpg36xSimulator>, cmd: &str) -> String {
+    intf.sendcmd(cmd).unwrap();
+    intf.check_acknowledgment(ACK).unwrap();
+    intf.write(ENQ).unwrap();
+    intf.read_until_terminator().unwrap()
+}
+
+/// Create an interface to a simulated CryoTel GT with the terminator of its responses.
+#[fixture]
+fn cryotel() -> SimulatorInterface<CryoTelGtSimulator> {
+    let mut intf = SimulatorInterface::new(CryoTelGtSimulator::new().with_relaxation(0.5));
+    intf.set_terminator("\r\n");
+    intf
+}
+
+/// Send a command to the CryoTel GT, check the echo, and return the value.
+fn cryotel_query(intf: &mut SimulatorInterface<CryoTelGtSimulator>, cmd: &str) -> String {
+    intf.write(&format!("{cmd}\r")).unwrap();
+    assert_eq!(cmd, intf.read_until_terminator().unwrap());
+    intf.read_until_terminator().unwrap()
+}
+
+/// Reading more data than the simulator sent times out immediately.
+#[rstest]
+fn simulator_interface_timeout(mut tpg36x: SimulatorInterface<Tpg36xSimulator>) {
+    assert!(matches!(
+        tpg36x.read_until_terminator(),
+        Err(InstrumentError::Timeout(_))
+    ));
+}
+
+/// The TPG36x acknowledges valid and rejects invalid commands.
+#[rstest]
+fn tpg36x_acknowledgment(mut tpg36x: SimulatorInterface<Tpg36xSimulator>) {
+    assert_eq!(
+        "TPG362,PTG28290,44998061,010100,010100",
+        tpg36x_query(&mut tpg36x, "AYT")
+    );
+    tpg36x.sendcmd("XYZ").unwrap();
+    assert_eq!(NAK, tpg36x.read_until_terminator().unwrap());
+    // ENQ after an invalid command returns nothing
+    tpg36x.write(ENQ).unwrap();
+    assert!(tpg36x.read_until_terminator().is_err());
+}
+
+/// Pressures are returned in the current unit.
+#[rstest]
+#[case("0", "0,1.0000E-05")]
+#[case("1", "0,7.5006E-06")]
+#[case("2", "0,1.0000E-03")]
+#[case("3", "0,7.5006E-03")]
+#[case("5", "0,3.7972E+00")]
+fn tpg36x_pressure_unit(
+    mut tpg36x: SimulatorInterface<Tpg36xSimulator>,
+    #[case] unit: &str,
+    #[case] exp: &str,
+) {
+    tpg36x.get_simulator_mut().set_pressure(1, 1.0e-5);
+    tpg36x_query(&mut tpg36x, &format!("UNI,{unit}"));
+    assert_eq!(unit, tpg36x_query(&mut tpg36x, "UNI"));
+    assert_eq!(exp, tpg36x_query(&mut tpg36x, "PR2"));
+}
+
+/// Switched off sensors report their status instead of a pressure.
+#[rstest]
+fn tpg36x_sensor_status(mut tpg36x: SimulatorInterface<Tpg36xSimulator>) {
+    assert_eq!("2,2", tpg36x_query(&mut tpg36x, "SEN"));
+    tpg36x_query(&mut tpg36x, "SEN,1,0");
+    assert_eq!("1,2", tpg36x_query(&mut tpg36x, "SEN"));
+    assert_eq!("4,0.0000E+00", tpg36x_query(&mut tpg36x, "PR1"));
+}
+
+/// Commands that are written in chunks are buffered until they are complete.
+#[rstest]
+fn tpg36x_chunked_command(mut tpg36x: SimulatorInterface<Tpg36xSimulator>) {
+    tpg36x.write("UN").unwrap();
+    tpg36x.write("I\r").unwrap();
+    assert!(tpg36x.read_until_terminator().is_err());
+    tpg36x.write("\n").unwrap();
+    tpg36x.check_acknowledgment(ACK).unwrap();
+}
+
+/// The temperature of the CryoTel GT relaxes toward the target, or ambient if it is stopped.
+#[rstest]
+fn cryotel_relaxation(mut cryotel: SimulatorInterface<CryoTelGtSimulator>) {
+    assert_eq!("77.00", cryotel_query(&mut cryotel, "SET TTARGET"));
+    assert_eq!("95.00", cryotel_query(&mut cryotel, "SET TTARGET=95"));
+    assert_eq!("195.00", cryotel_query(&mut cryotel, "TC"));
+    assert_eq!("145.00", cryotel_query(&mut cryotel, "TC"));
+
+    assert_eq!("1", cryotel_query(&mut cryotel, "SET SSTOP=1"));
+    assert_eq!("220.00", cryotel_query(&mut cryotel, "TC"));
+    assert!(cryotel.get_simulator().is_stopped());
+}
+
+/// All other parameters of the CryoTel GT are stored.
+#[rstest]
+#[case("SET PMAX", "240.00", "200.00")]
+#[case("SET PMIN", "70.00", "60.00")]
+#[case("SET TBAND", "0.50", "1.00")]
+#[case("SET KP", "50.00", "40.00")]
+fn cryotel_parameters(
+    mut cryotel: SimulatorInterface<CryoTelGtSimulator>,
+    #[case] name: &str,
+    #[case] default: &str,
+    #[case] value: &str,
+) {
+    assert_eq!(default, cryotel_query(&mut cryotel, name));
+    assert_eq!(
+        value,
+        cryotel_query(&mut cryotel, &format!("{name}={value}"))
+    );
+    assert_eq!(value, cryotel_query(&mut cryotel, name));
+}
+
+/// The CryoTel GT cools down with decreasing power, then reports an injected error.
+#[rstest]
+fn cryotel_cooldown_then_error(mut cryotel: SimulatorInterface<CryoTelGtSimulator>) {
+    cryotel_query(&mut cryotel, "SET TTARGET=80");
+    assert_eq!("240.00", cryotel_query(&mut cryotel, "P"));
+    for _ in 0..10 {
+        cryotel_query(&mut cryotel, "TC");
+    }
+    assert_eq!("80.10", cryotel_query(&mut cryotel, "TC"));
+    assert_eq!("70.00", cryotel_query(&mut cryotel, "P"));
+    assert_eq!("240.00", cryotel_query(&mut cryotel, "E"));
+    assert_eq!("70.00", cryotel.read_until_terminator().unwrap());
+    assert_eq!("70.00", cryotel.read_until_terminator().unwrap());
+    assert_eq!("000000", cryotel_query(&mut cryotel, "ERROR"));
+
+    cryotel.get_simulator_mut().set_errors(0b10_0001);
+    assert_eq!("100001", cryotel_query(&mut cryotel, "ERROR"));
+    cryotel_query(&mut cryotel, "SET SSTOP=1");
+    assert_eq!("0.00", cryotel_query(&mut cryotel, "P"));
+}
+
+/// Invalid commands are echoed and rejected.
+#[rstest]
+#[case("XYZ")]
+#[case("SET SSTOP=2")]
+#[case("SET TTARGET=cold")]
+#[case("SET KP=fast")]
+#[case("TC=5")]
+fn cryotel_invalid_command(mut cryotel: SimulatorInterface<CryoTelGtSimulator>, #[case] cmd: &str) {
+    assert_eq!("Invalid command", cryotel_query(&mut cryotel, cmd));
+}
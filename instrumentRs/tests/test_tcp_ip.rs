@@ -0,0 +1,232 @@
+//! Tests for the [`TcpIpInterface`] using a local TCP listener as the instrument.
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use rstest::*;
+
+use instrumentrs::{Instrument, InstrumentError, InstrumentInterface, TcpIpInterface};
+
+/// Create a connected pair of an instrument interface and the stream of the "instrument".
+#[fixture]
+fn tcp_pair() -> (Instrument<TcpStream>, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let inst = TcpIpInterface::simple(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    (inst, stream)
+}
+
+#[rstest]
+fn test_tcp_ip_query(tcp_pair: (Instrument<TcpStream>, TcpStream)) {
+    let (mut inst, mut stream) = tcp_pair;
+    stream.write_all(b"resp\n").unwrap();
+    assert_eq!("resp", inst.query("cmd").unwrap());
+
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(b"cmd\n", &buf);
+}
+
+#[rstest]
+fn test_tcp_ip_drain_input(tcp_pair: (Instrument<TcpStream>, TcpStream)) {
+    let (mut inst, mut stream) = tcp_pair;
+    stream.write_all(b"stale").unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+
+    // Draining must not wait for the full timeout of the interface.
+    let tic = Instant::now();
+    assert_eq!(5, inst.drain_input().unwrap());
+    assert!(tic.elapsed() < Duration::from_secs(1));
+
+    // The timeout of the port is restored after draining.
+    stream.write_all(b"resp\n").unwrap();
+    assert_eq!("resp", inst.read_until_terminator().unwrap());
+}
+
+#[rstest]
+fn test_tcp_ip_set_timeout(tcp_pair: (Instrument<TcpStream>, TcpStream)) {
+    let (mut inst, _stream) = tcp_pair;
+    inst.set_timeout(Duration::from_millis(50));
+
+    // The port does not block for the default timeout of 3 seconds anymore.
+    let tic = Instant::now();
+    assert!(inst.read_until_terminator().is_err());
+    assert!(tic.elapsed() < Duration::from_secs(1));
+}
+
+#[rstest]
+fn test_tcp_ip_read_until_terminator_with_timeout(tcp_pair: (Instrument<TcpStream>, TcpStream)) {
+    let (mut inst, mut stream) = tcp_pair;
+    inst.set_timeout(Duration::from_millis(50));
+
+    // The response arrives after the timeout of the interface, but within the override.
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        stream.write_all(b"resp\n").unwrap();
+        stream
+    });
+    let resp = inst.read_until_terminator_with_timeout(Duration::from_secs(3));
+    assert_eq!("resp", resp.unwrap());
+    assert_eq!(Duration::from_millis(50), inst.get_timeout());
+    let _stream = handle.join().unwrap();
+}
+
+#[rstest]
+fn test_tcp_ip_is_alive(tcp_pair: (Instrument<TcpStream>, TcpStream)) {
+    let (mut inst, mut stream) = tcp_pair;
+    assert!(inst.is_alive());
+
+    // Pending data is not consumed by the check.
+    stream.write_all(b"resp\n").unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(inst.is_alive());
+    assert_eq!("resp", inst.read_until_terminator().unwrap());
+}
+
+#[rstest]
+fn test_tcp_ip_is_alive_closed_peer(tcp_pair: (Instrument<TcpStream>, TcpStream)) {
+    let (mut inst, stream) = tcp_pair;
+    drop(stream);
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(!inst.is_alive());
+}
+
+#[rstest]
+fn test_tcp_ip_timeout_enforced_without_port_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (_peer, _) = listener.accept().unwrap();
+
+    // The stream itself has no read timeout and would block forever.
+    assert_eq!(None, stream.read_timeout().unwrap());
+    let timeout = Duration::from_millis(50);
+    let mut inst = Instrument::new(stream, timeout)
+        .with_port_timeout(|stream, timeout| stream.set_read_timeout(Some(timeout)));
+
+    let tic = Instant::now();
+    match inst.query("cmd") {
+        Err(InstrumentError::TimeoutQuery { timeout: tout, .. }) => assert_eq!(timeout, tout),
+        _ => panic!("Expected timeout error, but got a different result."),
+    }
+    assert!(tic.elapsed() < Duration::from_secs(1));
+}
+
+#[rstest]
+fn test_tcp_ip_telnet() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut inst = TcpIpInterface::telnet(listener.local_addr().unwrap()).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    // server offers echo (1) in the middle of the response
+    stream
+        .write_all(&[b'r', b'e', 0xFF, 0xFB, 0x01, b's', b'p', b'\n'])
+        .unwrap();
+    inst.write_raw(&[b'c', 0xFF, b'\n']).unwrap();
+    assert_eq!("resp", inst.read_until_terminator().unwrap());
+
+    // command with escaped 0xFF, followed by the refusal of the offer
+    let mut buf = [0u8; 7];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!([b'c', 0xFF, 0xFF, b'\n', 0xFF, 0xFE, 0x01], buf);
+}
+
+#[rstest]
+fn test_tcp_ip_port_access(tcp_pair: (Instrument<TcpStream>, TcpStream)) {
+    let (mut inst, mut stream) = tcp_pair;
+
+    inst.get_mut().set_nodelay(true).unwrap();
+    assert!(inst.get_ref().nodelay().unwrap());
+    inst.set_nodelay(false).unwrap();
+    assert!(!inst.get_ref().nodelay().unwrap());
+    inst.set_keepalive(Some(Duration::from_secs(30))).unwrap();
+    inst.set_keepalive(None).unwrap();
+
+    // The port is still connected after it is returned.
+    let mut port = inst.into_inner();
+    port.write_all(b"cmd\n").unwrap();
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(b"cmd\n", &buf);
+}
+
+#[rstest]
+fn test_tcp_ip_simple_with() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut inst = TcpIpInterface::simple_with(listener.local_addr().unwrap(), |builder| {
+        builder
+            .terminator("\r")
+            .timeout(Duration::from_millis(500))
+            .write_timeout(Duration::from_millis(200))
+            .name("tpg-roughing")
+    })
+    .unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    assert_eq!("\r", inst.get_terminator());
+    assert_eq!(Duration::from_millis(500), inst.get_timeout());
+    assert_eq!(Duration::from_millis(200), inst.get_write_timeout());
+    assert_eq!(Some("tpg-roughing"), inst.get_name());
+
+    // The timeouts are applied to the socket.
+    assert_eq!(
+        Some(Duration::from_millis(500)),
+        inst.get_ref().read_timeout().unwrap()
+    );
+    assert_eq!(
+        Some(Duration::from_millis(200)),
+        inst.get_ref().write_timeout().unwrap()
+    );
+
+    stream.write_all(b"resp\r").unwrap();
+    assert_eq!("resp", inst.query("cmd").unwrap());
+}
+
+#[rstest]
+fn test_tcp_ip_simple_on_connect() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // The instrument answers the password with a prompt and then echoes every command.
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        let mut received = Vec::new();
+        let mut line = String::new();
+        while std::io::BufRead::read_line(&mut reader, &mut line).unwrap() > 0 {
+            let cmd = line.trim_end().to_string();
+            let resp = if cmd == "PASS secret" {
+                "OK".to_string()
+            } else {
+                cmd.clone()
+            };
+            stream.write_all(format!("{resp}\n").as_bytes()).unwrap();
+            received.push(cmd);
+            line.clear();
+        }
+        received
+    });
+
+    let mut inst = TcpIpInterface::simple_on_connect(addr, |intf| {
+        intf.sendcmd("PASS secret")?;
+        intf.check_acknowledgment("OK")
+    })
+    .unwrap();
+    assert_eq!("cmd", inst.query("cmd").unwrap());
+    drop(inst);
+
+    // The handshake ran exactly once, before any other command.
+    assert_eq!(vec!["PASS secret", "cmd"], server.join().unwrap());
+}
+
+#[rstest]
+fn test_tcp_ip_simple_on_connect_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let res = TcpIpInterface::simple_on_connect(listener.local_addr().unwrap(), |_| {
+        Err(InstrumentError::NotAcknowledged("denied".to_string()))
+    });
+    assert!(matches!(res, Err(InstrumentError::NotAcknowledged(_))));
+}
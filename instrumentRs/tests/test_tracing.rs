@@ -0,0 +1,89 @@
+//! Tests for tracing the traffic of an [`Instrument`].
+
+#![cfg(feature = "tracing")]
+
+use std::{
+    collections::VecDeque,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rstest::*;
+use tracing_subscriber::fmt::MakeWriter;
+
+use instrumentrs::{Instrument, InstrumentInterface};
+
+/// A writer that collects all formatted events in a shared buffer.
+#[derive(Clone, Default)]
+struct EventBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl EventBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for EventBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for EventBuffer {
+    type Writer = EventBuffer;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Run a closure with a subscriber that collects all events and return the events.
+fn collect_events<F: FnOnce()>(f: F) -> String {
+    let buffer = EventBuffer::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_writer(buffer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, f);
+    buffer.contents()
+}
+
+#[rstest]
+fn test_trace_query() {
+    let events = collect_events(|| {
+        let mut inst = Instrument::new(VecDeque::new(), Duration::from_secs(3));
+        inst.set_name("my_inst");
+        // `VecDeque` reads back what was written, so the query reads back the command itself.
+        assert_eq!("ok", inst.query("ok").unwrap());
+    });
+
+    assert!(events.contains("interface=\"my_inst\""));
+    assert!(events.contains("direction=\"write\" data=6F 6B 0A |ok.|"));
+    assert!(events.contains("direction=\"read\" data=6F 6B 0A |ok.|"));
+}
+
+#[rstest]
+fn test_trace_read_exact() {
+    let events = collect_events(|| {
+        let mut inst = Instrument::new(VecDeque::from(vec![0x03, 0xFF]), Duration::from_secs(3));
+        let mut buf = [0u8; 2];
+        inst.read_exact(&mut buf).unwrap();
+    });
+
+    assert!(events.contains("direction=\"read\" data=03 FF |..|"));
+}
+
+#[rstest]
+fn test_trace_timeout() {
+    let events = collect_events(|| {
+        let mut inst = Instrument::new(VecDeque::from(b"resp".to_vec()), Duration::from_secs(0));
+        assert!(inst.read_until_terminator().is_err());
+    });
+
+    assert!(events.contains("timeout while waiting for terminator"));
+}
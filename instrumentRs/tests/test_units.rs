@@ -0,0 +1,61 @@
+//! Tests for the unit conversions, only available with the `measurements` feature.
+
+#![cfg(feature = "measurements")]
+
+use measurements::Pressure;
+use rstest::*;
+
+use instrumentrs::units::*;
+
+/// Check that two values agree to a relative precision of a few machine epsilons.
+fn assert_close(exp: f64, val: f64) {
+    let tol = 4.0 * f64::EPSILON * exp.abs();
+    assert!((exp - val).abs() <= tol, "expected {exp}, got {val}");
+}
+
+#[rstest]
+#[case(1.0, 133.322_368_421_052_63)]
+#[case(760.0, 101_325.0)]
+#[case(1.0e-9, 1.333_223_684_210_526_3e-7)]
+fn torr_to_pascal(#[case] torr: f64, #[case] pascal: f64) {
+    assert_close(pascal, pressure_from_torr(torr).as_pascals());
+}
+
+#[rstest]
+#[case(1.0, 0.133_322_368_421_052_63)]
+#[case(760_000.0, 101_325.0)]
+fn mtorr_to_pascal(#[case] mtorr: f64, #[case] pascal: f64) {
+    assert_close(pascal, pressure_from_mtorr(mtorr).as_pascals());
+}
+
+#[rstest]
+#[case(1.0, 6_894.757_293_168_361)]
+#[case(14.695_948_775_513_45, 101_325.0)]
+fn psi_to_pascal(#[case] psi: f64, #[case] pascal: f64) {
+    assert_close(pascal, pressure_from_psi(psi).as_pascals());
+}
+
+#[rstest]
+fn mtorr_is_thousandth_torr() {
+    let torr = pressure_from_torr(1.0e-3);
+    let mtorr = pressure_from_mtorr(1.0);
+    assert_close(torr.as_pascals(), mtorr.as_pascals());
+}
+
+/// Converting a value back and forth must return the same value to machine precision.
+#[rstest]
+fn round_trip(#[values(0.0, 1.0e-12, 3.7e-9, 1.0e-3, 0.5, 1.0, 760.0, 1.0e5, -2.5)] value: f64) {
+    assert_close(value, pressure_as_torr(&pressure_from_torr(value)));
+    assert_close(value, pressure_as_mtorr(&pressure_from_mtorr(value)));
+    assert_close(value, pressure_as_psi(&pressure_from_psi(value)));
+}
+
+/// Converting a pressure to Torr and back must return the same pressure to machine precision.
+#[rstest]
+fn round_trip_pressure(#[values(1.0e-7, 1.0, 101_325.0)] pascal: f64) {
+    let pressure = Pressure::from_pascals(pascal);
+    let torr = pressure_from_torr(pressure_as_torr(&pressure));
+    assert_close(pascal, torr.as_pascals());
+    let psi = pressure_from_psi(pressure_as_psi(&pressure));
+    assert_close(pascal, psi.as_pascals());
+}
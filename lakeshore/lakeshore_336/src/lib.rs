@@ -34,12 +34,13 @@ use std::{
     time::Duration,
 };
 
-use instrumentrs::{Instrument, InstrumentError, InstrumentInterface, SerialInterface};
+use instrumentrs::{
+    Instrument, InstrumentError, InstrumentInterface, Parity, SerialInterface, SerialPort,
+    lock_interface,
+};
 
 use measurements::Temperature;
 
-use serialport::SerialPort;
-
 /// A SerialInterface for the Lakeshore336.
 ///
 /// Builds an InstrumentRs SerialInterface with the correct parity, stop bits, and data bits for
@@ -57,13 +58,12 @@ impl SerialInterfaceLakeshore {
     /// Arguments:
     /// * `port` - The name of the serial port, e.g., `"/dev/ttyUSB0"` or `"COM3"`.
     pub fn simple(port: &str) -> Result<Instrument<Box<dyn SerialPort>>, InstrumentError> {
-        let timeout = Duration::from_secs(3);
-        let port = serialport::new(port, 57600)
-            .timeout(timeout)
-            .parity(serialport::Parity::Odd)
-            .data_bits(serialport::DataBits::Seven)
-            .stop_bits(serialport::StopBits::One);
-        SerialInterface::full(port)
+        SerialInterface::builder(port, 57600)
+            .parity(Parity::Odd)
+            .data_bits(7)
+            .stop_bits(1)
+            .timeout(Duration::from_secs(3))
+            .open()
     }
 }
 
@@ -116,15 +116,15 @@ impl<T: InstrumentInterface> Lakeshore336<T> {
 
     /// Send a command to the instrument.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         intf.sendcmd(cmd)
     }
 
     /// Query the instrument with a command and return the response as a String.
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
         self.sendcmd(cmd)?;
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
-        intf.read_until_terminator()
+        let mut intf = lock_interface(&self.interface)?;
+        intf.read_line_trimmed()
     }
 }
 
@@ -189,15 +189,15 @@ impl<T: InstrumentInterface> Channel<T> {
 
     /// Send a command for this instrument to an interface.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         intf.sendcmd(format!("{}{}", cmd, self.idx_mapper()).as_str())
     }
 
     /// Query the instrument with a command and return the response as a String.
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
         self.sendcmd(cmd)?;
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
-        intf.read_until_terminator()
+        let mut intf = lock_interface(&self.interface)?;
+        intf.read_line_trimmed()
     }
 }
 
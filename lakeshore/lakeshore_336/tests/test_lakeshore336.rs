@@ -2,20 +2,18 @@
 
 use rstest::*;
 
-use instrumentrs::LoopbackInterfaceString;
+use instrumentrs::LoopbackInterface;
+use instrumentrs_test_utils::loopback;
 
 use lakeshore_336::*;
 
 // Type alias for the loopback interface with the Lakeshore336 driver.
-type Lakeshore336Lbk = Lakeshore336<LoopbackInterfaceString>;
+type Lakeshore336Lbk = Lakeshore336<LoopbackInterface>;
 
 /// Function that creates a new Lakeshore336 instance with the given input
 /// and output commands.
 fn crt_inst(host2inst: Vec<&str>, inst2host: Vec<&str>) -> Lakeshore336Lbk {
-    let term = "\n";
-    let h2i: Vec<String> = host2inst.iter().map(|s| s.to_string()).collect();
-    let i2h: Vec<String> = inst2host.iter().map(|s| s.to_string()).collect();
-    let interface = LoopbackInterfaceString::new(h2i, i2h, term);
+    let interface = loopback(&host2inst, &inst2host, "\n");
     Lakeshore336::try_new(interface).unwrap()
 }
 
@@ -28,6 +26,14 @@ fn emp_inst() -> Lakeshore336Lbk {
 #[rstest]
 fn test_initialization(_emp_inst: Lakeshore336Lbk) {}
 
+/// The driver communicates with the terminator that the Lakeshore336 expects.
+#[rstest]
+fn test_terminator() {
+    let interface = loopback(&["*IDN?"], &["Lakeshore,336,12345678,1.0"], "\n").strict_terminator();
+    let mut inst = Lakeshore336::try_new(interface).unwrap();
+    assert_eq!("Lakeshore,336,12345678,1.0", inst.get_name().unwrap());
+}
+
 /// Get the name from the instrument.
 #[rstest]
 fn test_get_name() {
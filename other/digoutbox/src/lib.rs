@@ -26,15 +26,13 @@
 
 #![deny(warnings, missing_docs)]
 
-use std::{
-    fmt::Display,
-    sync::{Arc, Mutex},
-};
+use std::fmt::Display;
 
-use instrumentrs::{InstrumentError, InstrumentInterface};
+use instrumentrs::{ChannelMap, InstrumentError, InstrumentInterface, SharedInterface};
 
 /// Enum representing the current interlock state of the device.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InterlockStatus {
     /// Status that is returned when the box is ready for operation (interlock not triggered).
     Ready,
@@ -65,6 +63,7 @@ impl From<&str> for InterlockStatus {
 
 /// Enum representing the current software lockout state of the device.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SoftwareControlStatus {
     /// Status when software can be used to operate the device
     Ready,
@@ -112,16 +111,16 @@ impl From<&str> for SoftwareControlStatus {
 ///
 /// This would print the name, hardware, and software version of the instrument to `stdout`.
 pub struct DigOutBox<T: InstrumentInterface> {
-    interface: Arc<Mutex<T>>,
-    num_channels: usize,
+    interface: SharedInterface<T>,
+    channels: ChannelMap,
 }
 
 impl<T: InstrumentInterface> DigOutBox<T> {
     /// Create a new DigOutBox instance with the given instrument interface.
     pub fn new(interface: T) -> Self {
         DigOutBox {
-            interface: Arc::new(Mutex::new(interface)),
-            num_channels: 16, // Default for the standard DigOutBox
+            interface: SharedInterface::new(interface),
+            channels: ChannelMap::new(16), // Default for the standard DigOutBox
         }
     }
 
@@ -129,13 +128,10 @@ impl<T: InstrumentInterface> DigOutBox<T> {
     ///
     /// Please note that channels are zero-indexed.
     pub fn get_channel(&mut self, idx: usize) -> Result<Channel<T>, InstrumentError> {
-        if idx >= self.num_channels {
-            return Err(InstrumentError::ChannelIndexOutOfRange {
-                idx,
-                nof_channels: self.num_channels,
-            });
-        }
-        Ok(Channel::new(idx, Arc::clone(&self.interface)))
+        Ok(Channel::new(
+            self.channels.check_index(idx)?,
+            self.interface.clone(),
+        ))
     }
 
     /// Turn all channels off.
@@ -162,18 +158,14 @@ impl<T: InstrumentInterface> DigOutBox<T> {
 
     /// Query the name, hard, and firmware version of the device as a string.
     pub fn get_name(&mut self) -> Result<String, InstrumentError> {
-        Ok(self.query("*IDN?")?.trim().to_string())
+        self.query("*IDN?")
     }
 
     /// Set the number of channels for the DigOutBox.
+    ///
+    /// The number of channels must be greater than 0.
     pub fn set_num_channels(&mut self, num: usize) -> Result<(), InstrumentError> {
-        if num == 0 {
-            return Err(InstrumentError::InvalidArgument(
-                "Number of channels must be greater than 0".to_string(),
-            ));
-        }
-        self.num_channels = num;
-        Ok(())
+        self.channels.set_num_channels(num)
     }
 
     /// Get the current software control status of the instrument.
@@ -186,21 +178,15 @@ impl<T: InstrumentInterface> DigOutBox<T> {
 
     /// Send a command to the instrument.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        {
-            self.interface
-                .lock()
-                .expect("Mutext should not be poisoned")
-                .sendcmd(cmd)?;
-        }
-        Ok(())
+        self.interface.sendcmd(cmd)
     }
 
     /// Query the instrument with a command and return the response as a String.
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
-        self.interface
-            .lock()
-            .expect("Mutex should not be poisoned")
-            .query(cmd)
+        self.interface.transaction(|intf| {
+            intf.sendcmd(cmd)?;
+            intf.read_line_trimmed()
+        })
     }
 }
 
@@ -208,7 +194,7 @@ impl<T: InstrumentInterface> Clone for DigOutBox<T> {
     fn clone(&self) -> Self {
         Self {
             interface: self.interface.clone(),
-            num_channels: self.num_channels,
+            channels: self.channels.clone(),
         }
     }
 }
@@ -220,7 +206,7 @@ impl<T: InstrumentInterface> Clone for DigOutBox<T> {
 /// Implementation of an individual channel and commands that go to it.
 pub struct Channel<T: InstrumentInterface> {
     idx: usize,
-    interface: Arc<Mutex<T>>,
+    interface: SharedInterface<T>,
 }
 
 impl<T: InstrumentInterface> Channel<T> {
@@ -244,7 +230,7 @@ impl<T: InstrumentInterface> Channel<T> {
     /// Get a new channel for the given instrument interface.
     ///
     /// This function can only be called from inside of the `DigOutBox` struct.
-    fn new(idx: usize, interface: Arc<Mutex<T>>) -> Self {
+    fn new(idx: usize, interface: SharedInterface<T>) -> Self {
         Channel { idx, interface }
     }
 
@@ -257,13 +243,8 @@ impl<T: InstrumentInterface> Channel<T> {
     /// - `cmd`: Command to send to the channel
     /// - `value`: Argument to send along with this command.
     fn sendcmd(&mut self, cmd: &str, value: &str) -> Result<(), InstrumentError> {
-        {
-            self.interface
-                .lock()
-                .expect("Mutex should not be poisoned")
-                .sendcmd(&format!("{cmd}{0} {value}", self.idx))?;
-        }
-        Ok(())
+        self.interface
+            .sendcmd(&format!("{cmd}{0} {value}", self.idx))
     }
 
     /// Send a query to this channel of the instrument.
@@ -274,10 +255,11 @@ impl<T: InstrumentInterface> Channel<T> {
     /// # Arguments:
     /// - `cmd`: Command to send to the channel
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
-        self.interface
-            .lock()
-            .expect("Mutex should not be poisoned")
-            .query(&format!("{cmd}{0}?", self.idx))
+        let cmd = format!("{cmd}{0}?", self.idx);
+        self.interface.transaction(|intf| {
+            intf.sendcmd(&cmd)?;
+            intf.read_line_trimmed()
+        })
     }
 }
 
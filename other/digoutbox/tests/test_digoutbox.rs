@@ -1,31 +1,36 @@
+use std::time::Duration;
+
 use digoutbox::*;
-use instrumentrs::{InstrumentError, LoopbackInterfaceString};
+use instrumentrs::{InstrumentError, LoopbackInterface, LoopbackScript, Poller};
+use instrumentrs_test_utils::script;
 use rstest::*;
 
-/// Create a new loopback instrument from the given input string slices.
-fn crt_inst(host2inst: Vec<&str>, inst2host: Vec<&str>) -> DigOutBox<LoopbackInterfaceString> {
-    let term = "\n";
-    let h2i: Vec<String> = host2inst.iter().map(|s| s.to_string()).collect();
-    let i2h: Vec<String> = inst2host.iter().map(|s| s.to_string()).collect();
-    let interface = LoopbackInterfaceString::new(h2i, i2h, term);
-    DigOutBox::new(interface)
-}
-
 /// Create an empty loopback interface for the DigOutBox instrument.
 #[fixture]
-fn emp_inst() -> DigOutBox<LoopbackInterfaceString> {
-    crt_inst(vec![], vec![])
+fn emp_inst(script: LoopbackScript) -> DigOutBox<LoopbackInterface> {
+    DigOutBox::new(script.build())
 }
 
 #[rstest]
-pub fn test_all_off() {
-    let mut inst = crt_inst(vec!["ALLOFF"], vec![]);
+pub fn test_all_off(script: LoopbackScript) {
+    let mut inst = DigOutBox::new(script.cmd("ALLOFF").build());
 
     inst.all_off().unwrap();
 }
+
+/// The driver communicates with the terminator that the DigOutBox expects.
+#[rstest]
+fn test_terminator(script: LoopbackScript) {
+    let script = script.query("ALLDO?", "1,0,1,0,1,0,1,0,1,0,1,0,1,0,1,0");
+    let mut inst = DigOutBox::new(script.build().strict_terminator());
+
+    assert_eq!(16, inst.get_all_outputs().unwrap().len());
+}
+
 #[rstest]
-fn test_get_all_outputs() {
-    let mut inst = crt_inst(vec!["ALLDO?"], vec!["1,0,1,0,1,0,1,0,1,0,1,0,1,0,1,0"]);
+fn test_get_all_outputs(script: LoopbackScript) {
+    let script = script.query("ALLDO?", "1,0,1,0,1,0,1,0,1,0,1,0,1,0,1,0");
+    let mut inst = DigOutBox::new(script.build());
 
     assert_eq!(
         inst.get_all_outputs().unwrap(),
@@ -37,8 +42,9 @@ fn test_get_all_outputs() {
 }
 
 #[rstest]
-fn test_get_interlock_status() {
-    let mut inst = crt_inst(vec!["INTERLOCKS?", "INTERLOCKS?"], vec!["0", "1"]);
+fn test_get_interlock_status(script: LoopbackScript) {
+    let script = script.query("INTERLOCKS?", "0").query("INTERLOCKS?", "1");
+    let mut inst = DigOutBox::new(script.build());
 
     let interlock_status = inst.get_interlock_status().unwrap();
     assert_eq!(interlock_status, InterlockStatus::Ready);
@@ -50,15 +56,16 @@ fn test_get_interlock_status() {
 }
 
 #[rstest]
-fn test_get_name() {
-    let mut inst = crt_inst(vec!["*IDN?"], vec!["Inst Name"]);
+fn test_get_name(script: LoopbackScript) {
+    let mut inst = DigOutBox::new(script.query("*IDN?", "Inst Name").build());
 
     assert_eq!(inst.get_name().unwrap(), "Inst Name");
 }
 
 #[rstest]
-fn test_get_software_control_status() {
-    let mut inst = crt_inst(vec!["SWL?", "SWL?"], vec!["0", "1"]);
+fn test_get_software_control_status(script: LoopbackScript) {
+    let script = script.query("SWL?", "0").query("SWL?", "1");
+    let mut inst = DigOutBox::new(script.build());
 
     let scs = inst.get_software_control_status().unwrap();
     assert_eq!(scs, SoftwareControlStatus::Ready);
@@ -71,7 +78,7 @@ fn test_get_software_control_status() {
 
 // Tests for the channels
 #[rstest]
-fn test_get_channel(mut emp_inst: DigOutBox<LoopbackInterfaceString>) {
+fn test_get_channel(mut emp_inst: DigOutBox<LoopbackInterface>) {
     // Get a channel and check if it is created correctly
     let _ = emp_inst.get_channel(0).unwrap();
 
@@ -88,11 +95,23 @@ fn test_get_channel(mut emp_inst: DigOutBox<LoopbackInterfaceString>) {
     emp_inst.set_num_channels(6).unwrap();
     // Try to get a channel that is out of range
     assert!(emp_inst.get_channel(6).is_err());
+
+    // A box without channels is not valid
+    assert!(matches!(
+        emp_inst.set_num_channels(0),
+        Err(InstrumentError::InvalidArgument(_))
+    ));
+    assert!(emp_inst.get_channel(5).is_ok());
 }
 
 #[rstest]
-fn test_channel_output() {
-    let mut inst = crt_inst(vec!["DO0 1", "DO0?", "DO1 0", "DO1?"], vec!["1", "0"]);
+fn test_channel_output(script: LoopbackScript) {
+    let script = script
+        .cmd("DO0 1")
+        .query("DO0?", "1")
+        .cmd("DO1 0")
+        .query("DO1?", "0");
+    let mut inst = DigOutBox::new(script.build());
 
     let mut ch0 = inst.get_channel(0).unwrap();
     ch0.set_output(true).unwrap();
@@ -102,3 +121,55 @@ fn test_channel_output() {
     ch1.set_output(false).unwrap();
     assert!(!ch1.get_output().unwrap());
 }
+
+/// Poll the output of a channel in the background.
+#[rstest]
+fn test_channel_poller(script: LoopbackScript) {
+    let script = script
+        .query("DO3?", "0")
+        .query("DO3?", "1")
+        .query("DO3?", "1");
+    let mut inst = DigOutBox::new(script.build());
+    let ch = inst.get_channel(3).unwrap();
+
+    // only three responses are available, so fail afterwards without touching the interface
+    let mut reads = 0;
+    let poller = Poller::new(ch, Duration::from_millis(5), move |ch| {
+        reads += 1;
+        if reads > 3 {
+            return Err(InstrumentError::InvalidArgument("done".to_string()));
+        }
+        ch.get_output()
+    });
+    let rx = poller.subscribe();
+    while poller.consecutive_errors() == 0 {
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    let (_, output) = poller.latest().unwrap();
+    assert!(output);
+    assert!(poller.stop().is_some());
+}
+
+/// Two threads toggling different channels interleave their commands in any order.
+#[rstest]
+fn test_channels_from_threads() {
+    let exchanges = ["DO0 1", "DO0 0", "DO1 1", "DO1 0"]
+        .iter()
+        .map(|cmd| (cmd.to_string(), None))
+        .collect();
+    let mut inst = DigOutBox::new(LoopbackInterface::unordered(exchanges, "\n"));
+
+    let handles: Vec<_> = (0..2)
+        .map(|idx| {
+            let mut ch = inst.get_channel(idx).unwrap();
+            std::thread::spawn(move || {
+                ch.set_output(true).unwrap();
+                ch.set_output(false).unwrap();
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
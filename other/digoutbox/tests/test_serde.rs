@@ -0,0 +1,22 @@
+//! Tests for serializing the status types, only available with the `serde` feature.
+
+#![cfg(feature = "serde")]
+
+use digoutbox::*;
+use rstest::*;
+
+#[rstest]
+#[case(InterlockStatus::Ready)]
+#[case(InterlockStatus::Interlocked)]
+fn test_interlock_status_round_trip(#[case] status: InterlockStatus) {
+    let json = serde_json::to_string(&status).unwrap();
+    assert_eq!(status, serde_json::from_str(&json).unwrap());
+}
+
+#[rstest]
+#[case(SoftwareControlStatus::Ready)]
+#[case(SoftwareControlStatus::LockedOut)]
+fn test_software_control_status_round_trip(#[case] status: SoftwareControlStatus) {
+    let json = serde_json::to_string(&status).unwrap();
+    assert_eq!(status, serde_json::from_str(&json).unwrap());
+}
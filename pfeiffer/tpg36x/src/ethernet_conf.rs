@@ -6,6 +6,7 @@ use instrumentrs::InstrumentError;
 
 /// An enum for the DHCP configuration.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DhcpConfig {
     /// Static DHCP configuration
     Static,
@@ -52,6 +53,7 @@ impl TryFrom<&str> for DhcpConfig {
 ///
 /// All IPs must be defined as IPv4 addresses, as this is the only supported protocol.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EthernetConfig {
     /// The DHCP configuration.
     pub dhcp_conf: DhcpConfig,
@@ -37,9 +37,9 @@ pub use ethernet_conf::{DhcpConfig, EthernetConfig};
 pub use status::SensorStatus;
 pub use units::{PressureUnit, Tpg36xMeasurement};
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 
-use instrumentrs::{InstrumentError, InstrumentInterface};
+use instrumentrs::{ChannelMap, InstrumentError, InstrumentInterface, lock_interface};
 
 use status::PressMsrDatStat;
 
@@ -66,7 +66,7 @@ use status::PressMsrDatStat;
 pub struct Tpg36x<T: InstrumentInterface> {
     interface: Arc<Mutex<T>>,
     unit: Arc<Mutex<PressureUnit>>,
-    num_channels: usize,
+    channels: ChannelMap,
 }
 
 impl<T: InstrumentInterface> Tpg36x<T> {
@@ -85,7 +85,8 @@ impl<T: InstrumentInterface> Tpg36x<T> {
         let mut instrument = Tpg36x {
             interface,
             unit: Arc::new(Mutex::new(PressureUnit::default())),
-            num_channels: 2, // Default for the TPG362 model, can be changed later
+            // Default for the TPG362 model, can be changed later to 1 for the TPG361
+            channels: ChannelMap::new(2).with_allowed_range(1..=2)?,
         };
         instrument.update_unit()?;
         Ok(instrument)
@@ -95,14 +96,8 @@ impl<T: InstrumentInterface> Tpg36x<T> {
     ///
     /// Please note that channels are zero-indexed.
     pub fn get_channel(&mut self, idx: usize) -> Result<Channel<T>, InstrumentError> {
-        if idx >= self.num_channels {
-            return Err(InstrumentError::ChannelIndexOutOfRange {
-                idx,
-                nof_channels: self.num_channels,
-            });
-        }
         Ok(Channel::new(
-            idx,
+            self.channels.check_index(idx)?,
             Arc::clone(&self.interface),
             Arc::clone(&self.unit),
         ))
@@ -141,17 +136,10 @@ impl<T: InstrumentInterface> Tpg36x<T> {
     }
 
     /// Set the number of channels for the TPG36x.
+    ///
+    /// Only 1 (TPG361) or 2 (TPG362) channels are valid.
     pub fn set_num_channels(&mut self, num: usize) -> Result<(), InstrumentError> {
-        if !(1..3).contains(&num) {
-            let num: i64 = num.try_into().unwrap_or(i64::MAX);
-            return Err(InstrumentError::IntValueOutOfRange {
-                value: num,
-                min: 1,
-                max: 2,
-            });
-        }
-        self.num_channels = num;
-        Ok(())
+        self.channels.set_num_channels(num)
     }
 
     /// Get the MAC address of the instrument.
@@ -168,7 +156,7 @@ impl<T: InstrumentInterface> Tpg36x<T> {
     /// This updates the internally kept unit and returns a copy of it.
     pub fn get_unit(&mut self) -> Result<PressureUnit, InstrumentError> {
         self.update_unit()?;
-        let unit = self.unit.lock().expect("Mutex should not be poisoned");
+        let unit = self.unit.lock().unwrap_or_else(PoisonError::into_inner);
         Ok(*unit)
     }
 
@@ -182,7 +170,7 @@ impl<T: InstrumentInterface> Tpg36x<T> {
     pub fn set_unit(&mut self, unit: PressureUnit) -> Result<(), InstrumentError> {
         self.sendcmd(&format!("UNI,{}", unit.as_str()))?;
         {
-            let mut current_unit = self.unit.lock().expect("Mutex should not be poisoned");
+            let mut current_unit = self.unit.lock().unwrap_or_else(PoisonError::into_inner);
             *current_unit = unit;
         }
         Ok(())
@@ -192,7 +180,7 @@ impl<T: InstrumentInterface> Tpg36x<T> {
     pub fn update_unit(&mut self) -> Result<(), InstrumentError> {
         let response = self.query("UNI")?;
         {
-            let mut unit = self.unit.lock().expect("Mutex should not be poisoned");
+            let mut unit = self.unit.lock().unwrap_or_else(PoisonError::into_inner);
             *unit = PressureUnit::from_cmd_str(response.as_str())?;
         }
         Ok(())
@@ -200,16 +188,16 @@ impl<T: InstrumentInterface> Tpg36x<T> {
 
     /// Send a command to the instrument.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         intf.sendcmd(cmd)?;
-        intf.check_acknowledgment("\u{6}") // check for "ACK"
+        intf.check_acknowledgment_prefix("\u{6}") // check for "ACK"
     }
 
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
         self.sendcmd(cmd)?;
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         intf.write("\u{5}")?; // send "ENQ"
-        intf.read_until_terminator()
+        intf.read_line_trimmed()
     }
 }
 
@@ -218,7 +206,7 @@ impl<T: InstrumentInterface> Clone for Tpg36x<T> {
         Self {
             interface: self.interface.clone(),
             unit: self.unit.clone(),
-            num_channels: self.num_channels,
+            channels: self.channels.clone(),
         }
     }
 }
@@ -245,7 +233,6 @@ impl<T: InstrumentInterface> Channel<T> {
     /// struct prior to calling this function!
     pub fn get_pressure(&mut self) -> Result<Tpg36xMeasurement, InstrumentError> {
         let resp = self.query(&format!("PR{}", self.idx + 1))?;
-        println!("Response: {resp}");
         let parts = resp.split(',').collect::<Vec<&str>>();
         if parts.len() != 2 {
             return Err(InstrumentError::ResponseParseError(resp));
@@ -260,7 +247,7 @@ impl<T: InstrumentInterface> Channel<T> {
             .parse::<f64>()
             .map_err(|_| InstrumentError::ResponseParseError(resp.to_string()))?;
         let ret_val = {
-            let unit = self.unit.lock().expect("Mutex should not be poisoned");
+            let unit = self.unit.lock().unwrap_or_else(PoisonError::into_inner);
             units::from_value_unit(val, &unit)
         };
         Ok(ret_val)
@@ -313,17 +300,17 @@ impl<T: InstrumentInterface> Channel<T> {
 
     /// Send a command for this instrument to an interface.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         intf.sendcmd(cmd)?;
-        intf.check_acknowledgment("\u{6}") // check for "ACK"
+        intf.check_acknowledgment_prefix("\u{6}") // check for "ACK"
     }
 
     /// Query the instrument with a command and return the response as a String.
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
         self.sendcmd(cmd)?;
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         intf.write("\u{5}")?; // send "ENQ"
-        intf.read_until_terminator()
+        intf.read_line_trimmed()
     }
 }
 
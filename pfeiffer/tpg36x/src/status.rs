@@ -48,6 +48,7 @@ impl Display for PressMsrDatStat {
 
 /// Status that can be sent to the an individual sensor to change its state.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SensorStatus {
     /// Set: leave the sensor in its current state / Get: Sensor cannot be changed.
     NoChange,
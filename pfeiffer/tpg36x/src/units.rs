@@ -2,6 +2,7 @@
 
 use std::fmt::Display;
 
+use instrumentrs::units::{pressure_from_mtorr, pressure_from_torr};
 use measurements::{Pressure, Voltage};
 
 /// Since the TPG36x can return either a pressure or a voltage measurement, we return an enum for
@@ -25,6 +26,7 @@ impl Display for Tpg36xMeasurement {
 
 /// All the units the TPG36x can be configured to use.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PressureUnit {
     /// Millibar
     #[allow(non_camel_case_types)] // could stand for Mega otherwise
@@ -90,9 +92,9 @@ impl Display for PressureUnit {
 pub(crate) fn from_value_unit(value: f64, unit: &PressureUnit) -> Tpg36xMeasurement {
     match unit {
         PressureUnit::mBar => Tpg36xMeasurement::Pressure(Pressure::from_millibars(value)),
-        PressureUnit::Torr => Tpg36xMeasurement::Pressure(Pressure::from_torrs(value)),
+        PressureUnit::Torr => Tpg36xMeasurement::Pressure(pressure_from_torr(value)),
         PressureUnit::Pa => Tpg36xMeasurement::Pressure(Pressure::from_pascals(value)),
-        PressureUnit::mTorr => Tpg36xMeasurement::Pressure(Pressure::from_millitorrs(value)),
+        PressureUnit::mTorr => Tpg36xMeasurement::Pressure(pressure_from_mtorr(value)),
         PressureUnit::hPa => Tpg36xMeasurement::Pressure(Pressure::from_pascals(value * 100.0)),
         PressureUnit::V => Tpg36xMeasurement::Voltage(Voltage::from_volts(value)),
     }
@@ -106,9 +108,9 @@ mod test {
 
     #[rstest]
     #[case(1000.0, PressureUnit::mBar, Pressure::from_millibars(1000.0))]
-    #[case(1000.0, PressureUnit::Torr, Pressure::from_pascals(1000.0 * 133.3224))]
+    #[case(1000.0, PressureUnit::Torr, Pressure::from_pascals(1000.0 * 101_325.0 / 760.0))]
     #[case(1000.0, PressureUnit::Pa, Pressure::from_pascals(1000.0))]
-    #[case(1000.0, PressureUnit::mTorr, Pressure::from_pascals(1000.0 * 133322.4))]
+    #[case(1000.0, PressureUnit::mTorr, Pressure::from_pascals(101_325.0 / 760.0))]
     #[case(1000.0, PressureUnit::hPa, Pressure::from_pascals(1000.0 * 100.0))]
     fn test_from_value_unit_pressure(
         #[case] value: f64,
@@ -0,0 +1,42 @@
+//! Tests for serializing the configuration types, only available with the `serde` feature.
+
+#![cfg(feature = "serde")]
+
+use std::net::Ipv4Addr;
+
+use rstest::*;
+
+use pfeiffer_tpg36x::{DhcpConfig, EthernetConfig, PressureUnit, SensorStatus};
+
+#[rstest]
+#[case(EthernetConfig::new_dynamic())]
+#[case(EthernetConfig::new_static(
+    Ipv4Addr::new(192, 168, 1, 10),
+    Ipv4Addr::new(255, 255, 255, 0),
+    Ipv4Addr::new(192, 168, 1, 1),
+))]
+fn test_ethernet_config_round_trip(#[case] config: EthernetConfig) {
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(config, serde_json::from_str(&json).unwrap());
+}
+
+#[rstest]
+fn test_dhcp_config_round_trip() {
+    let json = serde_json::to_string(&DhcpConfig::Static).unwrap();
+    assert_eq!(DhcpConfig::Static, serde_json::from_str(&json).unwrap());
+}
+
+#[rstest]
+#[case(PressureUnit::mBar)]
+#[case(PressureUnit::mTorr)]
+#[case(PressureUnit::V)]
+fn test_pressure_unit_round_trip(#[case] unit: PressureUnit) {
+    let json = serde_json::to_string(&unit).unwrap();
+    assert_eq!(unit, serde_json::from_str(&json).unwrap());
+}
+
+#[rstest]
+fn test_sensor_status_round_trip() {
+    let json = serde_json::to_string(&SensorStatus::On).unwrap();
+    assert_eq!(SensorStatus::On, serde_json::from_str(&json).unwrap());
+}
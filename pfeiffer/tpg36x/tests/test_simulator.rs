@@ -0,0 +1,58 @@
+//! Tests for the Pfeiffer TPG36x driver against the simulated controller of `instrumentrs`.
+
+use measurements::Pressure;
+use rstest::*;
+
+use instrumentrs::{SimulatorInterface, Tpg36xSimulator};
+use instrumentrs_test_utils::assert_pressure_eq;
+
+use pfeiffer_tpg36x::{PressureUnit, SensorStatus, Tpg36x, Tpg36xMeasurement};
+
+type Tpg36xSim = Tpg36x<SimulatorInterface<Tpg36xSimulator>>;
+
+/// Create a TPG36x instrument that is connected to a simulated controller.
+#[fixture]
+fn sim_inst() -> Tpg36xSim {
+    let mut simulator = Tpg36xSimulator::new();
+    simulator.set_pressure(0, 1.2e-5);
+    simulator.set_pressure(1, 2.3e-3);
+    Tpg36x::try_new(SimulatorInterface::new(simulator)).unwrap()
+}
+
+/// Read the pressures of both channels in the unit that the controller reports.
+#[rstest]
+#[case(0, 1.2e-3)]
+#[case(1, 2.3e-1)]
+fn test_get_pressure(mut sim_inst: Tpg36xSim, #[case] channel: usize, #[case] pascals: f64) {
+    let mut ch = sim_inst.get_channel(channel).unwrap();
+    match ch.get_pressure().unwrap() {
+        Tpg36xMeasurement::Pressure(pressure) => {
+            assert_pressure_eq(Pressure::from_pascals(pascals), pressure);
+        }
+        _ => panic!("Expect a pressure and not voltage measurement."),
+    }
+}
+
+/// Change the unit and read the pressure in the new unit.
+#[rstest]
+fn test_set_unit(mut sim_inst: Tpg36xSim) {
+    sim_inst.set_unit(PressureUnit::mBar).unwrap();
+    assert_eq!(PressureUnit::mBar, sim_inst.get_unit().unwrap());
+
+    let mut ch = sim_inst.get_channel(0).unwrap();
+    match ch.get_pressure().unwrap() {
+        Tpg36xMeasurement::Pressure(pressure) => {
+            assert_pressure_eq(Pressure::from_pascals(1.2e-3), pressure);
+        }
+        _ => panic!("Expect a pressure and not voltage measurement."),
+    }
+}
+
+/// Switch a sensor off, after which its pressure cannot be read anymore.
+#[rstest]
+fn test_sensor_off(mut sim_inst: Tpg36xSim) {
+    let mut ch = sim_inst.get_channel(1).unwrap();
+    ch.set_status(SensorStatus::Off).unwrap();
+    assert_eq!(SensorStatus::Off, ch.get_status().unwrap());
+    assert!(ch.get_pressure().is_err());
+}
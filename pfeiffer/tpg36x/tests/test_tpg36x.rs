@@ -2,50 +2,37 @@
 
 use std::net::Ipv4Addr;
 
-use measurements::{Measurement, test_utils::almost_eq};
 use rstest::*;
 
-use instrumentrs::LoopbackInterfaceString;
+use instrumentrs::{LoopbackInterface, LoopbackScript};
+use instrumentrs_test_utils::{ACK, ENQ, assert_pressure_eq, pfeiffer_ack_enq, pfeiffer_script};
 
 use pfeiffer_tpg36x::{
     DhcpConfig, EthernetConfig, PressureUnit, SensorStatus, Tpg36x, Tpg36xMeasurement,
 };
 
-type Tpg36Lbk = Tpg36x<LoopbackInterfaceString>;
-
-const ENQ: &str = "\u{5}";
-const ACK: &str = "\u{6}";
+type Tpg36Lbk = Tpg36x<LoopbackInterface>;
 
 /// Function that takes input, output `Vec<&str>` and prepares the TPG36x instrument with this loopback
 /// interface.
 ///
 /// Note that it will automatically fill the input and output vectors with the unit query that is
 /// performed when creating a new instrument instance. The unit is by default set to "Pa".
-/// Furthermore, we will add the terminator to every command from (`host2inst` and `inst2host`),
-/// except for the `ENQ`.
+/// Furthermore, the terminator is added to every command (`host2inst` and `inst2host`), except for
+/// the `ENQ`, see `pfeiffer_ack_enq`.
 fn crt_inst(host2inst: Vec<&str>, inst2host: Vec<&str>) -> Tpg36Lbk {
-    let term = "\r\n";
-    let mut inp = vec![format!("UNI{term}"), ENQ.to_string()];
-    let mut out = vec![format!("{ACK}{term}"), format!("2{term}")];
-    host2inst.iter().for_each(|s| {
-        if *s != ENQ {
-            inp.push(format!("{s}{term}"));
-        } else {
-            inp.push(s.to_string());
-        }
-    });
-    inst2host
-        .iter()
-        .for_each(|s| out.push(format!("{s}{term}")));
+    let inp = [&["UNI", ENQ][..], &host2inst].concat();
+    let out = [&[ACK, "2"][..], &inst2host].concat();
+    let (inp, out) = pfeiffer_ack_enq(&inp, &out, "\r\n");
 
     // initialize the interface with empty terminator, as we set it manually above!
-    let interface = LoopbackInterfaceString::new(inp, out, "");
+    let interface = LoopbackInterface::new(inp, out, "");
     Tpg36x::try_new(interface).unwrap()
 }
 
 /// A fixture to create an empty TPG36x loopback interface.
 #[fixture]
-fn emp_tpg36x() -> Tpg36x<LoopbackInterfaceString> {
+fn emp_tpg36x() -> Tpg36x<LoopbackInterface> {
     crt_inst(vec![], vec![])
 }
 
@@ -53,6 +40,16 @@ fn emp_tpg36x() -> Tpg36x<LoopbackInterfaceString> {
 #[rstest]
 fn test_initialization(_emp_tpg36x: Tpg36Lbk) {}
 
+/// The driver configures the terminator that the TPG36x expects.
+#[rstest]
+fn test_terminator(pfeiffer_script: LoopbackScript) {
+    let interface = pfeiffer_script
+        .ack_then("UNI", "2")
+        .build()
+        .strict_terminator();
+    let _ = Tpg36x::try_new(interface).unwrap();
+}
+
 /// By default, instrument is set for TPG362, but can be configured as TPG361
 #[rstest]
 fn test_get_channel(mut emp_tpg36x: Tpg36Lbk) {
@@ -168,7 +165,7 @@ fn test_get_pressure(#[case] channel: usize, #[case] pressure: f64) {
 
     let exp = measurements::Pressure::from_pascals(pressure);
     if let Tpg36xMeasurement::Pressure(pressure) = val {
-        almost_eq(exp.as_base_units(), pressure.as_base_units());
+        assert_pressure_eq(exp, pressure);
     } else {
         panic!("Expect a pressure and not voltage measurement.");
     }
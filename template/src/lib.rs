@@ -11,9 +11,9 @@
 //TODO: Uncomment the following line to enable warnings and missing docs
 //#![deny(warnings, missing_docs)]
 
-use std::{fmt::Display, sync::{Arc, Mutex}};
+use std::{fmt::Display, sync::{Arc, Mutex, PoisonError}};
 
-use instrumentrs::{InstrumentError, InstrumentInterface};
+use instrumentrs::{InstrumentError, InstrumentInterface, lock_interface};
 
 {% if units -%}
 /// Units that are available on the {{ device }}.
@@ -126,13 +126,13 @@ impl<T: InstrumentInterface> {{ device | upper_camel_case }}<T> {
 
     /// Send a command to the instrument.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         todo!();
     }
 
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
         self.sendcmd(cmd)?;
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         todo!();
     }
     {% if units %}
@@ -141,7 +141,7 @@ impl<T: InstrumentInterface> {{ device | upper_camel_case }}<T> {
     /// This updates the internally kept unit and returns a copy of it.
     pub fn get_unit(&mut self) -> Result<Unit, InstrumentError> {
         self.update_unit()?;
-        let unit = self.unit.lock().expect("Mutex should not be poisoned");
+        let unit = self.unit.lock().unwrap_or_else(PoisonError::into_inner);
         Ok(*unit)
     }
 
@@ -207,14 +207,14 @@ impl<T: InstrumentInterface> Channel<T> {
 
     /// Send a command for this instrument to an interface.
     fn sendcmd(&mut self, cmd: &str) -> Result<(), InstrumentError> {
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         todo!();
     }
 
     /// Query the instrument with a command and return the response as a String.
     fn query(&mut self, cmd: &str) -> Result<String, InstrumentError> {
         self.sendcmd(cmd)?;
-        let mut intf = self.interface.lock().expect("Mutex should not be poisoned");
+        let mut intf = lock_interface(&self.interface)?;
         todo!();
     }
 }
@@ -0,0 +1,34 @@
+//! Assertions that compare measured quantities of the [`measurements`] crate.
+
+use measurements::{Pressure, Temperature, test_utils::almost_eq};
+
+/// Assert that two pressures are almost equal, compared in pascals.
+///
+/// The pressures are compared with [`almost_eq`], i.e., their relative difference must be small.
+///
+/// # Panics
+/// Panics if the pressures are not almost equal.
+#[track_caller]
+pub fn assert_pressure_eq(expected: Pressure, actual: Pressure) {
+    let (expected, actual) = (expected.as_pascals(), actual.as_pascals());
+    assert!(
+        almost_eq(expected, actual),
+        "Expected a pressure of {expected} Pa, got {actual} Pa."
+    );
+}
+
+/// Assert that two temperatures are almost equal, compared in kelvin.
+///
+/// The temperatures are compared with [`almost_eq`], i.e., their relative difference must be
+/// small.
+///
+/// # Panics
+/// Panics if the temperatures are not almost equal.
+#[track_caller]
+pub fn assert_temperature_eq(expected: Temperature, actual: Temperature) {
+    let (expected, actual) = (expected.as_kelvin(), actual.as_kelvin());
+    assert!(
+        almost_eq(expected, actual),
+        "Expected a temperature of {expected} K, got {actual} K."
+    );
+}
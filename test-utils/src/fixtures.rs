@@ -0,0 +1,33 @@
+//! Fixtures and functions that create loopback interfaces for driver tests.
+
+use instrumentrs::{LoopbackInterface, LoopbackScript};
+use rstest::fixture;
+
+/// Create an empty loopback script, by default with a newline as terminator.
+///
+/// Use `#[with("\r\n")]` on the argument of a test to change the terminator.
+#[fixture]
+pub fn script(#[default("\n")] terminator: &str) -> LoopbackScript {
+    LoopbackScript::new(terminator)
+}
+
+/// Create an empty loopback script for Pfeiffer gauge controllers.
+///
+/// The script uses `"\r\n"` as terminator and acknowledges commands with `ACK` and `ENQ`, see
+/// [`LoopbackScript::pfeiffer_ack_enq`].
+#[fixture]
+pub fn pfeiffer_script() -> LoopbackScript {
+    LoopbackScript::new("\r\n").pfeiffer_ack_enq()
+}
+
+/// Create a loopback interface from the commands from host to instrument and back.
+///
+/// # Arguments
+/// * `host2inst` - Commands from host to instrument.
+/// * `inst2host` - Responses from instrument to host.
+/// * `terminator` - The terminator that is appended to every command and response.
+pub fn loopback(host2inst: &[&str], inst2host: &[&str], terminator: &str) -> LoopbackInterface {
+    let host2inst = host2inst.iter().map(|cmd| cmd.to_string()).collect();
+    let inst2host = inst2host.iter().map(|resp| resp.to_string()).collect();
+    LoopbackInterface::new(host2inst, inst2host, terminator)
+}
@@ -0,0 +1,82 @@
+//! Checksums and frames of instrument protocols.
+
+/// The acknowledgment (`ACK`) that Pfeiffer gauge controllers respond to commands with.
+pub const ACK: &str = "\u{6}";
+
+/// The enquiry (`ENQ`) that requests the response of a command from Pfeiffer gauge controllers.
+pub const ENQ: &str = "\u{5}";
+
+/// Append the checksum of the Agilent serial protocol, e.g., of the 4UHV ion pump controller.
+///
+/// A frame starts with `STX` (`0x02`) and ends with `ETX` (`0x03`). The checksum is the XOR of all
+/// bytes after the `STX` up to and including the `ETX`, appended as two uppercase hexadecimal
+/// digits.
+///
+/// ```
+/// use instrumentrs_test_utils::add_crc;
+///
+/// // read window 205 of the controller at address 0
+/// assert_eq!(b"\x02\x802050\x0384".to_vec(), add_crc(b"\x02\x802050\x03"));
+/// ```
+///
+/// # Panics
+/// Panics if the frame does not start with `STX`.
+pub fn add_crc(frame: &[u8]) -> Vec<u8> {
+    let Some((0x02, data)) = frame.split_first() else {
+        panic!("Agilent frames must start with STX, got {frame:02X?}.");
+    };
+    let crc = data.iter().fold(0, |crc, byte| crc ^ byte);
+    let mut frame = frame.to_vec();
+    frame.extend(format!("{crc:02X}").bytes());
+    frame
+}
+
+/// Append the checksum of the Pfeiffer vacuum protocol, e.g., of the OmniControl.
+///
+/// The checksum is the sum of all characters of the telegram modulo 256, appended as three
+/// decimal digits. The terminator (`\r`) is not part of the telegram and added by the loopback
+/// interface.
+///
+/// ```
+/// use instrumentrs_test_utils::add_checksum;
+///
+/// // query parameter 309 of the device at address 1
+/// assert_eq!("0010030902=?107", add_checksum("0010030902=?"));
+/// ```
+pub fn add_checksum(telegram: &str) -> String {
+    let checksum = telegram.bytes().map(u32::from).sum::<u32>() % 256;
+    format!("{telegram}{checksum:03}")
+}
+
+/// Add the terminator to the commands and responses of a Pfeiffer gauge controller.
+///
+/// Pfeiffer gauge controllers, e.g., the TPG36x, acknowledge every command with [`ACK`]. The host
+/// then requests the response with [`ENQ`], which is sent without terminator. The terminator is
+/// appended to all other commands and all responses, such that the loopback interface must be
+/// created with an empty terminator.
+///
+/// ```
+/// use instrumentrs_test_utils::{ACK, ENQ, pfeiffer_ack_enq};
+///
+/// let (host2inst, inst2host) = pfeiffer_ack_enq(&["PR1", ENQ], &[ACK, "0,1.0E-3"], "\r\n");
+/// assert_eq!(vec!["PR1\r\n", ENQ], host2inst);
+/// assert_eq!(vec!["\u{6}\r\n", "0,1.0E-3\r\n"], inst2host);
+/// ```
+pub fn pfeiffer_ack_enq(
+    host2inst: &[&str],
+    inst2host: &[&str],
+    terminator: &str,
+) -> (Vec<String>, Vec<String>) {
+    let host2inst = host2inst
+        .iter()
+        .map(|cmd| match *cmd {
+            ENQ => cmd.to_string(),
+            cmd => format!("{cmd}{terminator}"),
+        })
+        .collect();
+    let inst2host = inst2host
+        .iter()
+        .map(|resp| format!("{resp}{terminator}"))
+        .collect();
+    (host2inst, inst2host)
+}
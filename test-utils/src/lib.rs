@@ -0,0 +1,36 @@
+//! Shared helpers for the tests of instrument drivers that are built with `instrumentRs`.
+//!
+//! Driver tests often need the same helpers: checksums and frames of the protocol of the
+//! instrument, fixtures that create loopback interfaces, and assertions that compare measured
+//! quantities. This crate is their single, tested home, such that every driver uses the same
+//! implementation.
+//!
+//! - The [`add_crc`], [`add_checksum`], and [`pfeiffer_ack_enq`] functions build the frames of
+//!   Agilent, Pfeiffer vacuum, and Pfeiffer gauge controller protocols.
+//! - The [`script`] and [`pfeiffer_script`] fixtures and the [`loopback`] function create the
+//!   loopback interfaces that drivers are tested with.
+//! - With the `"measurements"` feature, `assert_pressure_eq` and `assert_temperature_eq` compare
+//!   pressures and temperatures of the `measurements` crate.
+//!
+//! # Usage
+//!
+//! This crate is not published. Add it as a development dependency with a path only, such that
+//! it is stripped from the manifest when the driver is published and never ends up in the
+//! dependency tree of a release:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! instrumentrs-test-utils = { path = "../../test-utils", features = ["measurements"] }
+//! ```
+
+mod fixtures;
+mod frames;
+
+#[cfg(feature = "measurements")]
+mod assertions;
+
+pub use fixtures::{loopback, pfeiffer_script, script};
+pub use frames::{ACK, ENQ, add_checksum, add_crc, pfeiffer_ack_enq};
+
+#[cfg(feature = "measurements")]
+pub use assertions::{assert_pressure_eq, assert_temperature_eq};
@@ -0,0 +1,72 @@
+//! Tests for the shared helpers of driver tests.
+
+use rstest::*;
+
+use instrumentrs::{InstrumentInterface, LoopbackScript};
+use instrumentrs_test_utils::*;
+
+/// Checksums of Agilent frames are the XOR of all bytes after `STX`.
+#[rstest]
+#[case(b"\x02\x802050\x03", b"84")]
+#[case(b"\x02\x80\x03", b"83")]
+#[case(b"\x02\x03", b"03")]
+fn agilent_crc(#[case] frame: &[u8], #[case] crc: &[u8]) {
+    assert_eq!([frame, crc].concat(), add_crc(frame));
+}
+
+/// Agilent frames must start with `STX`.
+#[rstest]
+#[should_panic(expected = "Agilent frames must start with STX")]
+fn agilent_crc_no_stx() {
+    add_crc(b"\x802050\x03");
+}
+
+/// Checksums of the Pfeiffer vacuum protocol are the sum of all characters modulo 256.
+#[rstest]
+#[case("0010030902=?", "0010030902=?107")]
+#[case("0011001006111111", "0011001006111111015")]
+#[case("", "000")]
+fn pfeiffer_checksum(#[case] telegram: &str, #[case] exp: &str) {
+    assert_eq!(exp, add_checksum(telegram));
+}
+
+/// The terminator is added to all commands except for `ENQ` and to all responses.
+#[rstest]
+fn pfeiffer_ack_enq_frames() {
+    let (host2inst, inst2host) = pfeiffer_ack_enq(&["UNI,1", "UNI", ENQ], &[ACK, ACK, "1"], "\r\n");
+    assert_eq!(vec!["UNI,1\r\n", "UNI\r\n", ENQ], host2inst);
+    assert_eq!(vec!["\u{6}\r\n", "\u{6}\r\n", "1\r\n"], inst2host);
+}
+
+/// The script fixture uses a newline as terminator by default.
+#[rstest]
+fn script_fixture(script: LoopbackScript) {
+    let mut lbk = script.query("*IDN?", "MyInstrument").build();
+    assert_eq!("MyInstrument", lbk.query("*IDN?").unwrap());
+}
+
+/// The terminator of the script fixture can be changed.
+#[rstest]
+fn script_fixture_terminator(#[with("\r")] script: LoopbackScript) {
+    let mut lbk = script.cmd("OUT 1").build();
+    lbk.write("OUT 1\r").unwrap();
+}
+
+/// The Pfeiffer script fixture acknowledges commands and enquires responses.
+#[rstest]
+fn pfeiffer_script_fixture(pfeiffer_script: LoopbackScript) {
+    let mut lbk = pfeiffer_script.ack_then("PR1", "0,1.0E-3").build();
+    lbk.set_terminator("\r\n");
+
+    lbk.sendcmd("PR1").unwrap();
+    lbk.check_acknowledgment(ACK).unwrap();
+    lbk.write(ENQ).unwrap();
+    assert_eq!("0,1.0E-3", lbk.read_until_terminator().unwrap());
+}
+
+/// The loopback interface expects the given commands and returns the given responses.
+#[rstest]
+fn loopback_interface() {
+    let mut lbk = loopback(&["TC"], &["77.00"], "\n");
+    assert_eq!("77.00", lbk.query("TC").unwrap());
+}